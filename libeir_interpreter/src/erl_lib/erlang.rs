@@ -1,15 +1,17 @@
 use libeir_intern::Symbol;
-use libeir_util_number::bigint_to_double;
+use libeir_util_binary::{BitCarrier, BitSlice, BitVec};
+use libeir_util_number::{bigint_to_double, float_to_string};
 
+use super::io::write_term;
 use crate::module::{NativeModule, NativeReturn};
-use crate::process::ProcessContext;
+use crate::process::{ProcessContext, ProcessStatus};
 use crate::vm::VMState;
 
 use crate::term::ListIteratorItem;
 use crate::term::Term;
 use crate::term::{ErlEq, ErlExactEq, ErlOrd};
 
-use ::num_traits::Signed;
+use ::num_traits::{Signed, Zero};
 
 use std::rc::Rc;
 
@@ -147,6 +149,22 @@ fn div(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeRe
     }
 }
 
+fn int_div(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    if args.len() != 2 {
+        panic!();
+    }
+
+    match (&*args[0], &*args[1]) {
+        (Term::Integer(i1), Term::Integer(i2)) if !i2.is_zero() => NativeReturn::Return {
+            term: Term::Integer(i1.clone() / i2).into(),
+        },
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarith").into(),
+        },
+    }
+}
+
 fn is_list(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
     if args.len() != 1 {
         panic!();
@@ -435,14 +453,42 @@ fn is_function(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) ->
 //    monitor_ref
 //}
 //
-//fn spawn_1(vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
-//    assert!(args.len() == 1);
-//    let fun_term = &*args[0];
-//
-//    let new_pid = base_spawn_term(vm, fun_term, vec![]);
-//    NativeReturn::Return { term: Term::Pid(new_pid) }
-//}
-//
+fn spawn_1(vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    match &*args[0] {
+        Term::CapturedFunction { .. } | Term::BoundLambda { .. } => {
+            let new_pid = vm.spawn(args[0].clone(), &[]);
+            NativeReturn::Return {
+                term: Term::Pid(new_pid).into(),
+            }
+        }
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
+/// Spawns and links in one step. The new process has not run yet when
+/// the link is made, so it can't exit before being linked.
+fn spawn_link_1(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    match &*args[0] {
+        Term::CapturedFunction { .. } | Term::BoundLambda { .. } => {
+            let new_pid = vm.spawn(args[0].clone(), &[]);
+            let linked = vm.link(proc, new_pid);
+            debug_assert!(linked);
+            NativeReturn::Return {
+                term: Term::Pid(new_pid).into(),
+            }
+        }
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
 //fn spawn_monitor_1(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
 //    assert!(args.len() == 1);
 //    let fun_term = &*args[0];
@@ -493,12 +539,115 @@ fn is_binary(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> Na
         Term::BinarySlice { .. } => NativeReturn::Return {
             term: Term::new_bool(true).into(),
         },
+        Term::AppendBinary { .. } => NativeReturn::Return {
+            term: Term::new_bool(true).into(),
+        },
         _ => NativeReturn::Return {
             term: Term::new_bool(false).into(),
         },
     }
 }
 
+/// The buffer, bit offset and bit length of a binary term. Append
+/// binaries are copied out, their buffer may be extended in place.
+fn binary_view(term: &Term) -> Option<(Rc<BitVec>, usize, usize)> {
+    match term {
+        Term::Binary(buf) => Some((buf.clone(), 0, buf.bit_len())),
+        Term::BinarySlice {
+            buf,
+            bit_offset,
+            bit_length,
+        } => Some((buf.clone(), *bit_offset, *bit_length)),
+        Term::AppendBinary { buf, bit_length } => {
+            let mut bin = BitVec::new();
+            bin.push(BitSlice::with_offset_length(&*buf.borrow(), 0, *bit_length));
+            Some((bin.into(), 0, *bit_length))
+        }
+        _ => None,
+    }
+}
+
+fn byte_size(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    if let Some((_, _, bit_length)) = binary_view(&args[0]) {
+        NativeReturn::Return {
+            term: Term::new_usize((bit_length + 7) / 8).into(),
+        }
+    } else {
+        NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        }
+    }
+}
+
+fn bit_size(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    if let Some((_, _, bit_length)) = binary_view(&args[0]) {
+        NativeReturn::Return {
+            term: Term::new_usize(bit_length).into(),
+        }
+    } else {
+        NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        }
+    }
+}
+
+fn binary_part(bin: &Term, start: &Term, length: &Term) -> NativeReturn {
+    let badarg = || NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+    };
+
+    let (buf, bit_offset, bit_length) = match binary_view(bin) {
+        Some(view) if view.2 % 8 == 0 => view,
+        _ => return badarg(),
+    };
+    let (start, length) = match (start.as_i64(), length.as_i64()) {
+        (Some(start), Some(length)) => (start, length),
+        _ => return badarg(),
+    };
+
+    // A negative length selects the bytes before `start`.
+    let (start, length) = if length < 0 {
+        (start + length, -length)
+    } else {
+        (start, length)
+    };
+    if start < 0 || start + length > (bit_length / 8) as i64 {
+        return badarg();
+    }
+
+    NativeReturn::Return {
+        term: Term::BinarySlice {
+            buf,
+            bit_offset: bit_offset + start as usize * 8,
+            bit_length: length as usize * 8,
+        }
+        .into(),
+    }
+}
+
+fn binary_part_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+    match &*args[1] {
+        Term::Tuple(pos_len) if pos_len.len() == 2 => {
+            binary_part(&args[0], &pos_len[0], &pos_len[1])
+        }
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
+fn binary_part_3(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 3);
+    binary_part(&args[0], &args[1], &args[2])
+}
+
 fn atom_to_list(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
     assert!(args.len() == 1);
     let a1 = &*args[0];
@@ -518,6 +667,32 @@ fn atom_to_list(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) ->
     }
 }
 
+/// Only the `[short]` option is supported, which formats the same
+/// way as `~p`.
+fn float_to_list_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let short = Term::as_list(&args[1])
+        .map(|opts| opts.len() == 1 && opts[0].as_atom() == Some(Symbol::intern("short")))
+        .unwrap_or(false);
+
+    match &*args[0] {
+        Term::Float(flt) if short => {
+            let chars: Vec<_> = float_to_string(flt.0)
+                .chars()
+                .map(|c| Term::new_i64(c as i64).into())
+                .collect();
+            NativeReturn::Return {
+                term: Term::slice_to_list(&chars, Term::Nil.into()),
+            }
+        }
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
 fn less_than_or_equal(
     _vm: &VMState,
     _proc: &mut ProcessContext,
@@ -587,21 +762,26 @@ fn setelement(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> N
 }
 fn element(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
     assert!(args.len() == 2);
+    let badarg = |arg| NativeReturn::ThrowArg {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+        arg,
+    };
     let idx = if let Some(num) = args[0].as_usize() {
         num
     } else {
-        panic!()
+        return badarg(0);
     };
     if let Term::Tuple(vals) = &*args[1] {
         if idx == 0 || idx > vals.len() {
-            panic!()
+            badarg(0)
         } else {
             NativeReturn::Return {
                 term: vals[idx - 1].clone(),
             }
         }
     } else {
-        panic!()
+        badarg(1)
     }
 }
 
@@ -612,18 +792,321 @@ fn erl_self(_vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> Nati
     }
 }
 
-//fn process_flag(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
-//    assert!(args.len() == 2);
-//    if args[0].erl_eq(&Term::new_atom("trap_exit")) {
-//        let mut mailboxes = vm.mailboxes.borrow_mut();
-//        let mailbox = &mut mailboxes.get_mut(&proc.pid).unwrap();
-//        let old_trap_exits = mailbox.get_trap_exits();
-//        mailbox.set_trap_exits(args[1].as_boolean().unwrap());
-//        NativeReturn::Return { term: Term::new_bool(old_trap_exits).into() }
-//    } else {
-//        unimplemented!()
-//    }
-//}
+fn pid_to_list(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    if let Term::Pid(pid) = &*args[0] {
+        let chars: Vec<_> = pid
+            .to_string()
+            .chars()
+            .map(|c| Term::new_i64(c as i64).into())
+            .collect();
+        NativeReturn::Return {
+            term: Term::slice_to_list(&chars, Term::Nil.into()),
+        }
+    } else {
+        NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        }
+    }
+}
+
+fn send(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+    let to = match &*args[0] {
+        Term::Pid(pid) => Some(*pid),
+        Term::Atom(name) => vm.whereis(*name),
+        _ => None,
+    };
+    if let Some(pid) = to {
+        vm.send(proc, pid, args[1].clone());
+        NativeReturn::Return {
+            term: args[1].clone(),
+        }
+    } else {
+        NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        }
+    }
+}
+
+fn link(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    let pid = if let Term::Pid(pid) = &*args[0] {
+        *pid
+    } else {
+        return NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        };
+    };
+
+    if !vm.link(proc, pid) {
+        // Linking to a dead process immediately delivers the exit signal.
+        if proc.mailbox.get_trap_exits() {
+            let message = Term::Tuple(vec![
+                Term::new_atom("EXIT").into(),
+                args[0].clone(),
+                Term::new_atom("noproc").into(),
+            ]);
+            proc.deliver(message.into());
+        } else {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("noproc").into(),
+            };
+        }
+    }
+
+    NativeReturn::Return {
+        term: Term::new_bool(true).into(),
+    }
+}
+
+fn unlink(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    if let Term::Pid(pid) = &*args[0] {
+        vm.unlink(proc, *pid);
+        NativeReturn::Return {
+            term: Term::new_bool(true).into(),
+        }
+    } else {
+        NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        }
+    }
+}
+
+fn register(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+    match (&*args[0], &*args[1]) {
+        (Term::Atom(name), Term::Pid(pid))
+            if *name != Symbol::intern("undefined") && vm.register(proc, *name, *pid) =>
+        {
+            NativeReturn::Return {
+                term: Term::new_bool(true).into(),
+            }
+        }
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
+fn unregister(vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    match &*args[0] {
+        Term::Atom(name) if vm.unregister(*name) => NativeReturn::Return {
+            term: Term::new_bool(true).into(),
+        },
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
+fn whereis(vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    if let Term::Atom(name) = &*args[0] {
+        let term = match vm.whereis(*name) {
+            Some(pid) => Term::Pid(pid),
+            None => Term::new_atom("undefined"),
+        };
+        NativeReturn::Return { term: term.into() }
+    } else {
+        NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        }
+    }
+}
+
+fn send_after_3(vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 3);
+    match (args[0].as_i64(), &*args[1]) {
+        (Some(time), Term::Pid(to)) if time >= 0 => {
+            let reference = vm.start_timer(time as u64, *to, args[2].clone());
+            NativeReturn::Return {
+                term: Term::Reference(reference).into(),
+            }
+        }
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
+/// Returns the milliseconds left on the timer, or `false` if it already
+/// fired or was cancelled.
+fn cancel_timer_1(vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    if let Term::Reference(reference) = &*args[0] {
+        let term = match vm.cancel_timer(*reference) {
+            Some(left) => Term::new_i64(left as i64),
+            None => Term::new_bool(false),
+        };
+        NativeReturn::Return { term: term.into() }
+    } else {
+        NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        }
+    }
+}
+
+fn error_1(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: args[0].clone(),
+    }
+}
+
+/// `error(Reason, Args)`, the arguments end up in the top frame of the
+/// trace. `none` as `Args` behaves like `error/1`.
+fn error_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+    if args[1].as_atom() == Some(Symbol::intern("none")) {
+        return NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: args[0].clone(),
+        };
+    }
+    if Term::as_list(&args[1]).is_none() {
+        return NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        };
+    }
+    NativeReturn::ThrowWithArgs {
+        typ: Term::new_atom("error").into(),
+        reason: args[0].clone(),
+        args: args[1].clone(),
+    }
+}
+
+fn exit_1(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    NativeReturn::Throw {
+        typ: Term::new_atom("exit").into(),
+        reason: args[0].clone(),
+    }
+}
+
+fn throw_1(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    NativeReturn::Throw {
+        typ: Term::new_atom("throw").into(),
+        reason: args[0].clone(),
+    }
+}
+
+/// Writes the term followed by a newline to the output, `~p` style.
+fn display_1(vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    let mut out = String::new();
+    write_term(&mut out, &args[0], true);
+    out.push('\n');
+    vm.write_output(&out);
+    NativeReturn::Return {
+        term: Term::new_bool(true).into(),
+    }
+}
+
+fn process_flag(_vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+    match (args[0].erl_eq(&Term::new_atom("trap_exit")), args[1].as_boolean()) {
+        (true, Some(trap_exits)) => {
+            let old_trap_exits = proc.mailbox.get_trap_exits();
+            proc.mailbox.set_trap_exits(trap_exits);
+            NativeReturn::Return {
+                term: Term::new_bool(old_trap_exits).into(),
+            }
+        }
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
+/// Supports the `message_queue_len`, `status` and `current_function`
+/// items. Unknown items and dead processes give `undefined`.
+fn process_info_2(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+    let (pid, item) = match (&*args[0], &*args[1]) {
+        (Term::Pid(pid), Term::Atom(item)) => (*pid, *item),
+        _ => {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("badarg").into(),
+            }
+        }
+    };
+
+    let value = vm
+        .with_process(proc, pid, |process| {
+            if !process.is_alive() {
+                return None;
+            }
+            match item.as_str().get() {
+                "message_queue_len" => Some(Term::new_i64(process.mailbox.len() as i64).into()),
+                "status" => {
+                    let status = match process.status {
+                        ProcessStatus::Runnable(_) => "runnable",
+                        ProcessStatus::Waiting(_) => "waiting",
+                        ProcessStatus::Running => "running",
+                        ProcessStatus::Exited(_) => unreachable!(),
+                    };
+                    Some(Term::new_atom(status).into())
+                }
+                "current_function" => {
+                    let ident = match &process.status {
+                        // The running process is the one calling us
+                        ProcessStatus::Running => {
+                            return Some(
+                                Term::Tuple(vec![
+                                    Term::new_atom("erlang").into(),
+                                    Term::new_atom("process_info").into(),
+                                    Term::new_i64(2).into(),
+                                ])
+                                .into(),
+                            )
+                        }
+                        ProcessStatus::Runnable(call) | ProcessStatus::Waiting(call) => {
+                            match &*call.fun {
+                                Term::CapturedFunction { ident } => ident.clone(),
+                                Term::BoundLambda { ident, .. } => ident.clone(),
+                                _ => return Some(Term::new_atom("undefined").into()),
+                            }
+                        }
+                        ProcessStatus::Exited(_) => unreachable!(),
+                    };
+                    Some(
+                        Term::Tuple(vec![
+                            Term::Atom(ident.module.name).into(),
+                            Term::Atom(ident.name.name).into(),
+                            Term::new_i64(ident.arity as i64).into(),
+                        ])
+                        .into(),
+                    )
+                }
+                _ => None,
+            }
+        })
+        .flatten();
+
+    let term = match value {
+        Some(value) => Term::Tuple(vec![args[1].clone(), value]),
+        None => Term::new_atom("undefined"),
+    };
+    NativeReturn::Return { term: term.into() }
+}
 
 fn put(_vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
     assert!(args.len() == 2);
@@ -652,6 +1135,18 @@ fn get(_vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeRet
     }
 }
 
+fn get_0(_vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 0);
+    let entries: Vec<Rc<Term>> = proc
+        .dict
+        .iter()
+        .map(|(key, val)| Term::Tuple(vec![key.clone(), val.clone()]).into())
+        .collect();
+    NativeReturn::Return {
+        term: Term::slice_to_list(&entries, Term::Nil.into()),
+    }
+}
+
 fn erase(_vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
     assert!(args.len() == 1);
     let idx = proc
@@ -742,6 +1237,7 @@ pub fn make_erlang() -> NativeModule {
     module.add_fun(Symbol::intern("-"), 2, Box::new(sub));
     module.add_fun(Symbol::intern("*"), 2, Box::new(mul));
     module.add_fun(Symbol::intern("/"), 2, Box::new(div));
+    module.add_fun(Symbol::intern("div"), 2, Box::new(int_div));
     module.add_fun(Symbol::intern("abs"), 1, Box::new(abs));
     //module.add_fun(Symbol::intern("++"), 2, Box::new(list_append));
     module.add_fun(Symbol::intern("--"), 2, Box::new(list_subtract));
@@ -765,19 +1261,42 @@ pub fn make_erlang() -> NativeModule {
     module.add_fun(Symbol::intern("is_function"), 2, Box::new(is_function));
     //module.add_fun(Symbol::intern("spawn_monitor"), 1, Box::new(spawn_monitor_1));
     module.add_fun(Symbol::intern("not"), 1, Box::new(not));
+    module.add_fun(Symbol::intern("byte_size"), 1, Box::new(byte_size));
+    module.add_fun(Symbol::intern("bit_size"), 1, Box::new(bit_size));
+    module.add_fun(Symbol::intern("binary_part"), 2, Box::new(binary_part_2));
+    module.add_fun(Symbol::intern("binary_part"), 3, Box::new(binary_part_3));
     module.add_fun(Symbol::intern("atom_to_list"), 1, Box::new(atom_to_list));
+    module.add_fun(Symbol::intern("float_to_list"), 2, Box::new(float_to_list_2));
     module.add_fun(Symbol::intern("setelement"), 3, Box::new(setelement));
     module.add_fun(Symbol::intern("element"), 2, Box::new(element));
     module.add_fun(Symbol::intern("length"), 1, Box::new(length));
     module.add_fun(Symbol::intern("self"), 0, Box::new(erl_self));
+    module.add_fun(Symbol::intern("pid_to_list"), 1, Box::new(pid_to_list));
     module.add_fun(Symbol::intern("put"), 2, Box::new(put));
+    module.add_fun(Symbol::intern("get"), 0, Box::new(get_0));
     module.add_fun(Symbol::intern("get"), 1, Box::new(get));
     module.add_fun(Symbol::intern("erase"), 1, Box::new(erase));
     module.add_fun(Symbol::intern("hd"), 1, Box::new(hd));
     module.add_fun(Symbol::intern("tl"), 1, Box::new(tl));
     module.add_fun(Symbol::intern("map_size"), 1, Box::new(map_size));
-    //module.add_fun(Symbol::intern("spawn"), 1, Box::new(spawn_1));
+    module.add_fun(Symbol::intern("spawn"), 1, Box::new(spawn_1));
+    module.add_fun(Symbol::intern("spawn_link"), 1, Box::new(spawn_link_1));
     //module.add_fun(Symbol::intern("monitor"), 2, Box::new(monitor_2));
-    //module.add_fun(Symbol::intern("process_flag"), 2, Box::new(process_flag));
+    module.add_fun(Symbol::intern("process_flag"), 2, Box::new(process_flag));
+    module.add_fun(Symbol::intern("process_info"), 2, Box::new(process_info_2));
+    module.add_fun(Symbol::intern("!"), 2, Box::new(send));
+    module.add_fun(Symbol::intern("send"), 2, Box::new(send));
+    module.add_fun(Symbol::intern("link"), 1, Box::new(link));
+    module.add_fun(Symbol::intern("unlink"), 1, Box::new(unlink));
+    module.add_fun(Symbol::intern("register"), 2, Box::new(register));
+    module.add_fun(Symbol::intern("unregister"), 1, Box::new(unregister));
+    module.add_fun(Symbol::intern("whereis"), 1, Box::new(whereis));
+    module.add_fun(Symbol::intern("send_after"), 3, Box::new(send_after_3));
+    module.add_fun(Symbol::intern("cancel_timer"), 1, Box::new(cancel_timer_1));
+    module.add_fun(Symbol::intern("error"), 1, Box::new(error_1));
+    module.add_fun(Symbol::intern("error"), 2, Box::new(error_2));
+    module.add_fun(Symbol::intern("exit"), 1, Box::new(exit_1));
+    module.add_fun(Symbol::intern("throw"), 1, Box::new(throw_1));
+    module.add_fun(Symbol::intern("display"), 1, Box::new(display_1));
     module
 }
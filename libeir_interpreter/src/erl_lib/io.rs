@@ -0,0 +1,255 @@
+use std::rc::Rc;
+
+use crate::module::{NativeModule, NativeReturn};
+use crate::process::ProcessContext;
+use crate::term::Term;
+use crate::vm::VMState;
+
+use libeir_intern::Symbol;
+use libeir_ir::write_atom;
+use libeir_util_binary::{BitCarrier, BitSlice, BitVec};
+use libeir_util_number::float_to_string;
+
+fn badarg() -> NativeReturn {
+    NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+    }
+}
+
+/// Characters `~p` prints inside a string literal, and how.
+fn escape_char(chr: i64) -> Option<String> {
+    match chr {
+        0x22 => Some("\\\"".to_string()),
+        0x5c => Some("\\\\".to_string()),
+        0x0a => Some("\\n".to_string()),
+        0x0d => Some("\\r".to_string()),
+        0x09 => Some("\\t".to_string()),
+        0x0b => Some("\\v".to_string()),
+        0x08 => Some("\\b".to_string()),
+        0x0c => Some("\\f".to_string()),
+        0x1b => Some("\\e".to_string()),
+        0x20..=0x7e => Some((chr as u8 as char).to_string()),
+        _ => None,
+    }
+}
+
+/// The contents of a string literal for `term`, if it is a non-empty
+/// proper list of printable characters.
+fn printable_list(term: &Rc<Term>) -> Option<String> {
+    let list = Term::as_list(term)?;
+    if list.is_empty() {
+        return None;
+    }
+    let mut out = String::new();
+    for elem in list.iter() {
+        out.push_str(&escape_char(elem.as_i64()?)?);
+    }
+    Some(out)
+}
+
+/// Copies any of the binary term variants into a single buffer.
+fn binary_bits(term: &Term) -> Option<BitVec> {
+    let mut bin = BitVec::new();
+    match term {
+        Term::Binary(buf) => return Some((**buf).clone()),
+        Term::BinarySlice {
+            buf,
+            bit_offset,
+            bit_length,
+        } => bin.push(BitSlice::with_offset_length(
+            &**buf,
+            *bit_offset,
+            *bit_length,
+        )),
+        Term::AppendBinary { buf, bit_length } => {
+            bin.push(BitSlice::with_offset_length(&*buf.borrow(), 0, *bit_length))
+        }
+        _ => return None,
+    }
+    Some(bin)
+}
+
+fn write_binary(out: &mut String, bin: &BitVec, pretty: bool) {
+    let bytes = bin.bit_len() / 8;
+    let rem_bits = bin.bit_len() % 8;
+
+    let printable = (0..bytes)
+        .map(|n| escape_char(bin.get(n).unwrap() as i64))
+        .collect::<Option<Vec<_>>>();
+    match printable {
+        Some(chars) if pretty && bytes > 0 && rem_bits == 0 => {
+            out.push_str("<<\"");
+            out.extend(chars);
+            out.push_str("\">>");
+        }
+        _ => {
+            out.push_str("<<");
+            let mut elems: Vec<String> = (0..bytes)
+                .map(|n| bin.get(n).unwrap().to_string())
+                .collect();
+            if rem_bits != 0 {
+                let last = bin.get(bytes).unwrap() >> (8 - rem_bits);
+                elems.push(format!("{}:{}", last, rem_bits));
+            }
+            out.push_str(&elems.join(","));
+            out.push_str(">>");
+        }
+    }
+}
+
+/// Writes `term` the way `~w` does, or `~p` when `pretty` is set. The
+/// only difference made is that `~p` prints printable lists and binaries
+/// as strings, no line breaking is done.
+pub(crate) fn write_term(out: &mut String, term: &Rc<Term>, pretty: bool) {
+    match &**term {
+        Term::Nil => out.push_str("[]"),
+        Term::Integer(int) => out.push_str(&int.to_string()),
+        Term::Float(flt) => out.push_str(&float_to_string(flt.0)),
+        Term::Atom(atom) => write_atom(out, &atom.as_str()).unwrap(),
+        Term::Tuple(elems) => {
+            out.push('{');
+            for (idx, elem) in elems.iter().enumerate() {
+                if idx != 0 {
+                    out.push(',');
+                }
+                write_term(out, elem, pretty);
+            }
+            out.push('}');
+        }
+        Term::ListCell(_, _) => {
+            if let Some(string) = printable_list(term).filter(|_| pretty) {
+                out.push('"');
+                out.push_str(&string);
+                out.push('"');
+                return;
+            }
+
+            let (head, tail) = Term::as_inproper_list(term);
+            out.push('[');
+            for (idx, elem) in head.iter().enumerate() {
+                if idx != 0 {
+                    out.push(',');
+                }
+                write_term(out, elem, pretty);
+            }
+            match &*tail {
+                Term::Nil => (),
+                _ => {
+                    out.push('|');
+                    write_term(out, &tail, pretty);
+                }
+            }
+            out.push(']');
+        }
+        Term::Map(map) => {
+            out.push_str("#{");
+            for (idx, (key, value)) in map.iter().enumerate() {
+                if idx != 0 {
+                    out.push(',');
+                }
+                write_term(out, key, pretty);
+                out.push_str(" => ");
+                write_term(out, value, pretty);
+            }
+            out.push('}');
+        }
+        Term::Pid(pid) => out.push_str(&pid.to_string()),
+        Term::Reference(reference) => out.push_str(&format!("#Ref<0.0.0.{}>", reference.0)),
+        Term::Binary(_) | Term::BinarySlice { .. } | Term::AppendBinary { .. } => {
+            write_binary(out, &binary_bits(term).unwrap(), pretty)
+        }
+        Term::CapturedFunction { ident } => out.push_str(&format!(
+            "fun {}:{}/{}",
+            ident.module, ident.name, ident.arity
+        )),
+        Term::BoundLambda { ident, .. } => out.push_str(&format!("#Fun<{}>", ident)),
+        Term::ValueList(_) | Term::ReturnOk | Term::ReturnThrow => unreachable!(),
+    }
+}
+
+/// The characters of an iolist-like string argument, as accepted by `~s`
+/// and for the format string itself.
+fn chardata(term: &Rc<Term>) -> Option<String> {
+    match &**term {
+        Term::Atom(atom) => Some(atom.as_str().to_string()),
+        Term::Binary(_) | Term::BinarySlice { .. } | Term::AppendBinary { .. } => {
+            let bin = binary_bits(term)?;
+            let bytes = bin.try_as_byte_aligned_slice()?;
+            Some(bytes.iter().map(|b| *b as char).collect())
+        }
+        _ => {
+            let mut out = String::new();
+            for elem in Term::as_list(term)? {
+                match &*elem {
+                    Term::Integer(_) => out.push(std::char::from_u32(elem.as_i64()? as u32)?),
+                    _ => out.push_str(&chardata(&elem)?),
+                }
+            }
+            Some(out)
+        }
+    }
+}
+
+/// Expands the control sequences in `format`. Supports `~p`, `~w`, `~s`,
+/// `~b`, `~n` and `~~`.
+fn format(format: &str, args: &[Rc<Term>]) -> Option<String> {
+    let mut out = String::new();
+    let mut args = args.iter();
+
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'p' => write_term(&mut out, args.next()?, true),
+            'w' => write_term(&mut out, args.next()?, false),
+            's' => out.push_str(&chardata(args.next()?)?),
+            'b' => match &**args.next()? {
+                Term::Integer(int) => out.push_str(&int.to_string()),
+                _ => return None,
+            },
+            'n' => out.push('\n'),
+            '~' => out.push('~'),
+            _ => return None,
+        }
+    }
+
+    if args.next().is_some() {
+        return None;
+    }
+    Some(out)
+}
+
+fn format_1(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+    format_2(vm, proc, &[args[0].clone(), Term::Nil.into()])
+}
+
+fn format_2(vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let string = chardata(&args[0]);
+    let fmt_args = Term::as_list(&args[1]);
+    match (string, fmt_args) {
+        (Some(string), Some(fmt_args)) => match format(&string, &fmt_args) {
+            Some(formatted) => {
+                vm.write_output(&formatted);
+                NativeReturn::Return {
+                    term: Term::new_atom("ok").into(),
+                }
+            }
+            None => badarg(),
+        },
+        _ => badarg(),
+    }
+}
+
+pub fn make_io() -> NativeModule {
+    let mut module = NativeModule::new(Symbol::intern("io"));
+    module.add_fun(Symbol::intern("format"), 1, Box::new(format_1));
+    module.add_fun(Symbol::intern("format"), 2, Box::new(format_2));
+    module
+}
@@ -1,32 +1,44 @@
+use std::cmp::Ordering;
 use std::rc::Rc;
 
 use crate::module::{NativeModule, NativeReturn};
 use crate::process::ProcessContext;
-use crate::term::{ErlEq, Term};
+use crate::term::{ErlEq, ErlExactEq, ErlOrd, ListIteratorItem, Term};
 use crate::vm::VMState;
 
 use libeir_intern::Symbol;
 
-//fn member_list(item: &Term, list: &Term) -> NativeReturn {
-//    if let Term::Nil = list {
-//        NativeReturn::Return { term: Term::new_bool(false).into() }
-//    } else if let Term::List(ref head, ref tail) = list {
-//        for l_item in head {
-//            if item.erl_exact_eq(l_item) {
-//                return NativeReturn::Return { term: Term::new_bool(true).into() };
-//            }
-//        }
-//        member_list(item, tail)
-//    } else {
-//        NativeReturn::Throw
-//    }
-//}
-//
-//fn member(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
-//    assert!(args.len() == 2);
-//    member_list(&args[0], &args[1])
-//}
-//
+use num_traits::cast::ToPrimitive;
+
+fn member_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    for item in Term::list_iter(&args[1]) {
+        match item {
+            ListIteratorItem::Elem(elem) => {
+                if args[0].erl_exact_eq(&*elem) {
+                    return NativeReturn::Return {
+                        term: Term::new_bool(true).into(),
+                    };
+                }
+            }
+            ListIteratorItem::Tail(tail) => {
+                if let Term::Nil = &*tail {
+                    break;
+                }
+                return NativeReturn::Throw {
+                    typ: Term::new_atom("error").into(),
+                    reason: Term::new_atom("badarg").into(),
+                };
+            }
+        }
+    }
+
+    NativeReturn::Return {
+        term: Term::new_bool(false).into(),
+    }
+}
+
 fn reverse_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
     assert!(args.len() == 2);
 
@@ -44,6 +56,555 @@ fn reverse_1(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> Nati
     reverse_2(vm, proc, &[args[0].clone(), Term::Nil.into()])
 }
 
+fn zip_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let badarg = NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+    };
+
+    let (l1, l2) = match (Term::as_list(&args[0]), Term::as_list(&args[1])) {
+        (Some(l1), Some(l2)) => (l1, l2),
+        _ => return badarg,
+    };
+    if l1.len() != l2.len() {
+        return badarg;
+    }
+
+    let zipped: Vec<Rc<Term>> = l1
+        .into_iter()
+        .zip(l2.into_iter())
+        .map(|(a, b)| Term::Tuple(vec![a, b]).into())
+        .collect();
+    NativeReturn::Return {
+        term: Term::slice_to_list(&zipped, Term::Nil.into()),
+    }
+}
+
+fn unzip_1(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+
+    let badarg = NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+    };
+
+    let list = match Term::as_list(&args[0]) {
+        Some(list) => list,
+        None => return badarg,
+    };
+
+    let mut l1 = Vec::with_capacity(list.len());
+    let mut l2 = Vec::with_capacity(list.len());
+    for item in list.iter() {
+        match item.as_tuple() {
+            Some([a, b]) => {
+                l1.push(a.clone());
+                l2.push(b.clone());
+            }
+            _ => return badarg,
+        }
+    }
+
+    NativeReturn::Return {
+        term: Term::Tuple(vec![
+            Term::slice_to_list(&l1, Term::Nil.into()),
+            Term::slice_to_list(&l2, Term::Nil.into()),
+        ])
+        .into(),
+    }
+}
+
+fn nth_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let n = match &*args[0] {
+        Term::Integer(int) => int.to_usize(),
+        _ => None,
+    };
+    let list = Term::as_list(&args[1]);
+
+    match (n, list) {
+        (Some(n), Some(list)) if n >= 1 && n <= list.len() => NativeReturn::Return {
+            term: list[n - 1].clone(),
+        },
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("function_clause").into(),
+        },
+    }
+}
+
+fn duplicate_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let n = match &*args[0] {
+        Term::Integer(int) => int.to_usize(),
+        _ => None,
+    };
+
+    match n {
+        Some(n) => {
+            let list = vec![args[1].clone(); n];
+            NativeReturn::Return {
+                term: Term::slice_to_list(&list, Term::Nil.into()),
+            }
+        }
+        None => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("badarg").into(),
+        },
+    }
+}
+
+fn seq_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    // Like the BEAM, `To` may be one less than `From` for an empty list.
+    let bounds = match (args[0].as_i64(), args[1].as_i64()) {
+        (Some(from), Some(to)) if from <= to + 1 => Some((from, to)),
+        _ => None,
+    };
+
+    match bounds {
+        Some((from, to)) => {
+            let list: Vec<Rc<Term>> = (from..=to).map(|n| Term::new_i64(n).into()).collect();
+            NativeReturn::Return {
+                term: Term::slice_to_list(&list, Term::Nil.into()),
+            }
+        }
+        None => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("function_clause").into(),
+        },
+    }
+}
+
+fn last_1(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+
+    let list = Term::as_list(&args[0]);
+    match list.as_ref().and_then(|list| list.last()) {
+        Some(last) => NativeReturn::Return { term: last.clone() },
+        None => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("function_clause").into(),
+        },
+    }
+}
+
+fn droplast_1(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+
+    match Term::as_list(&args[0]) {
+        Some(mut list) if !list.is_empty() => {
+            list.pop();
+            NativeReturn::Return {
+                term: Term::slice_to_list(&list, Term::Nil.into()),
+            }
+        }
+        _ => NativeReturn::Throw {
+            typ: Term::new_atom("error").into(),
+            reason: Term::new_atom("function_clause").into(),
+        },
+    }
+}
+
+fn call_fun(
+    vm: &VMState,
+    proc: &mut ProcessContext,
+    fun: &Rc<Term>,
+    args: &[Rc<Term>],
+) -> Result<Rc<Term>, NativeReturn> {
+    vm.call_term(proc, fun.clone(), args)
+        .map_err(|(typ, reason, trace)| NativeReturn::Rethrow { typ, reason, trace })
+}
+
+fn map_2(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let list = match Term::as_list(&args[1]) {
+        Some(list) => list,
+        None => {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("function_clause").into(),
+            }
+        }
+    };
+
+    let mut mapped = Vec::with_capacity(list.len());
+    for item in list.iter() {
+        match call_fun(vm, proc, &args[0], &[item.clone()]) {
+            Ok(item) => mapped.push(item),
+            Err(ret) => return ret,
+        }
+    }
+
+    NativeReturn::Return {
+        term: Term::slice_to_list(&mapped, Term::Nil.into()),
+    }
+}
+
+fn foldl_3(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 3);
+
+    let list = match Term::as_list(&args[2]) {
+        Some(list) => list,
+        None => {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("function_clause").into(),
+            }
+        }
+    };
+
+    let mut acc = args[1].clone();
+    for item in list.iter() {
+        acc = match call_fun(vm, proc, &args[0], &[item.clone(), acc]) {
+            Ok(acc) => acc,
+            Err(ret) => return ret,
+        };
+    }
+
+    NativeReturn::Return { term: acc }
+}
+
+fn foldr_3(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 3);
+
+    let list = match Term::as_list(&args[2]) {
+        Some(list) => list,
+        None => {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("function_clause").into(),
+            }
+        }
+    };
+
+    let mut acc = args[1].clone();
+    for item in list.iter().rev() {
+        acc = match call_fun(vm, proc, &args[0], &[item.clone(), acc]) {
+            Ok(acc) => acc,
+            Err(ret) => return ret,
+        };
+    }
+
+    NativeReturn::Return { term: acc }
+}
+
+fn mapfoldl_3(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 3);
+
+    let badarg = NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+    };
+
+    let list = match Term::as_list(&args[2]) {
+        Some(list) => list,
+        None => {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("function_clause").into(),
+            }
+        }
+    };
+
+    let mut mapped = Vec::with_capacity(list.len());
+    let mut acc = args[1].clone();
+    for item in list.iter() {
+        let res = match call_fun(vm, proc, &args[0], &[item.clone(), acc]) {
+            Ok(res) => res,
+            Err(ret) => return ret,
+        };
+        match res.as_tuple() {
+            Some([item, new_acc]) => {
+                mapped.push(item.clone());
+                acc = new_acc.clone();
+            }
+            _ => return badarg,
+        }
+    }
+
+    NativeReturn::Return {
+        term: Term::Tuple(vec![Term::slice_to_list(&mapped, Term::Nil.into()), acc]).into(),
+    }
+}
+
+/// Applies the predicate to every element of the list in order, stopping
+/// early once `stop_on` is returned. The results are passed to `visit`.
+fn walk_predicate<F>(
+    vm: &VMState,
+    proc: &mut ProcessContext,
+    pred: &Rc<Term>,
+    list: &Rc<Term>,
+    mut visit: F,
+) -> Result<(), NativeReturn>
+where
+    F: FnMut(&Rc<Term>, bool) -> bool,
+{
+    let badarg = || NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+    };
+
+    let list = Term::as_list(list).ok_or_else(badarg)?;
+    for item in list.iter() {
+        let res = call_fun(vm, proc, pred, &[item.clone()])?;
+        let res = res.as_boolean().ok_or_else(badarg)?;
+        if !visit(item, res) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn filter_2(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let mut filtered = Vec::new();
+    let res = walk_predicate(vm, proc, &args[0], &args[1], |item, keep| {
+        if keep {
+            filtered.push(item.clone());
+        }
+        true
+    });
+
+    match res {
+        Ok(()) => NativeReturn::Return {
+            term: Term::slice_to_list(&filtered, Term::Nil.into()),
+        },
+        Err(ret) => ret,
+    }
+}
+
+fn partition_2(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let mut satisfying = Vec::new();
+    let mut not_satisfying = Vec::new();
+    let res = walk_predicate(vm, proc, &args[0], &args[1], |item, res| {
+        if res {
+            satisfying.push(item.clone());
+        } else {
+            not_satisfying.push(item.clone());
+        }
+        true
+    });
+
+    match res {
+        Ok(()) => NativeReturn::Return {
+            term: Term::Tuple(vec![
+                Term::slice_to_list(&satisfying, Term::Nil.into()),
+                Term::slice_to_list(&not_satisfying, Term::Nil.into()),
+            ])
+            .into(),
+        },
+        Err(ret) => ret,
+    }
+}
+
+fn all_2(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let mut all = true;
+    let res = walk_predicate(vm, proc, &args[0], &args[1], |_item, res| {
+        all = res;
+        res
+    });
+
+    match res {
+        Ok(()) => NativeReturn::Return {
+            term: Term::new_bool(all).into(),
+        },
+        Err(ret) => ret,
+    }
+}
+
+fn any_2(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let mut any = false;
+    let res = walk_predicate(vm, proc, &args[0], &args[1], |_item, res| {
+        any = res;
+        !res
+    });
+
+    match res {
+        Ok(()) => NativeReturn::Return {
+            term: Term::new_bool(any).into(),
+        },
+        Err(ret) => ret,
+    }
+}
+
+/// The element at the 1-based position `pos` of a tuple.
+fn key_element(term: &Term, pos: usize) -> Option<&Rc<Term>> {
+    term.as_tuple()
+        .and_then(|elems| elems.get(pos.checked_sub(1)?))
+}
+
+/// Parses the position and list arguments shared by the `key*`
+/// functions.
+fn key_args(pos: &Term, list: &Rc<Term>) -> Option<(usize, Vec<Rc<Term>>)> {
+    let pos = match pos {
+        Term::Integer(int) => int.to_usize().filter(|pos| *pos >= 1)?,
+        _ => return None,
+    };
+    let list = Term::as_list(list)?;
+    Some((pos, list))
+}
+
+fn keysort_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let badarg = NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+    };
+
+    let (pos, mut list) = match key_args(&args[0], &args[1]) {
+        Some(res) => res,
+        None => return badarg,
+    };
+    if !list.iter().all(|item| key_element(item, pos).is_some()) {
+        return badarg;
+    }
+
+    // `sort_by` is stable, elements with equal keys keep their order
+    list.sort_by(|a, b| {
+        let a = key_element(a, pos).unwrap();
+        let b = key_element(b, pos).unwrap();
+        a.erl_ord(&**b)
+    });
+
+    NativeReturn::Return {
+        term: Term::slice_to_list(&list, Term::Nil.into()),
+    }
+}
+
+fn usort_1(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 1);
+
+    let mut list = match Term::as_list(&args[0]) {
+        Some(list) => list,
+        None => {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("badarg").into(),
+            }
+        }
+    };
+
+    list.sort_by(|a, b| a.erl_ord(&**b));
+    list.dedup_by(|a, b| a.erl_eq(&**b));
+
+    NativeReturn::Return {
+        term: Term::slice_to_list(&list, Term::Nil.into()),
+    }
+}
+
+/// Merges two sorted lists, dropping elements of the second list that
+/// are equal to one in the first.
+fn umerge_2(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 2);
+
+    let (left, right) = match (Term::as_list(&args[0]), Term::as_list(&args[1])) {
+        (Some(left), Some(right)) => (left, right),
+        _ => {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("badarg").into(),
+            }
+        }
+    };
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    loop {
+        let ord = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) => l.erl_ord(&**r),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        match ord {
+            Ordering::Less => merged.push(left.next().unwrap()),
+            Ordering::Greater => merged.push(right.next().unwrap()),
+            Ordering::Equal => {
+                merged.push(left.next().unwrap());
+                right.next();
+            }
+        }
+    }
+
+    NativeReturn::Return {
+        term: Term::slice_to_list(&merged, Term::Nil.into()),
+    }
+}
+
+fn keydelete_3(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 3);
+
+    let (pos, mut list) = match key_args(&args[1], &args[2]) {
+        Some(res) => res,
+        None => {
+            return NativeReturn::Throw {
+                typ: Term::new_atom("error").into(),
+                reason: Term::new_atom("badarg").into(),
+            }
+        }
+    };
+
+    let found = list.iter().position(|item| {
+        key_element(item, pos)
+            .map(|elem| elem.erl_eq(&*args[0]))
+            .unwrap_or(false)
+    });
+    if let Some(idx) = found {
+        list.remove(idx);
+    }
+
+    NativeReturn::Return {
+        term: Term::slice_to_list(&list, Term::Nil.into()),
+    }
+}
+
+fn keyreplace_4(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
+    assert!(args.len() == 4);
+
+    let badarg = NativeReturn::Throw {
+        typ: Term::new_atom("error").into(),
+        reason: Term::new_atom("badarg").into(),
+    };
+
+    let (pos, mut list) = match key_args(&args[1], &args[2]) {
+        Some(res) => res,
+        None => return badarg,
+    };
+    if args[3].as_tuple().is_none() {
+        return badarg;
+    }
+
+    let found = list.iter().position(|item| {
+        key_element(item, pos)
+            .map(|elem| elem.erl_eq(&*args[0]))
+            .unwrap_or(false)
+    });
+    if let Some(idx) = found {
+        list[idx] = args[3].clone();
+    }
+
+    NativeReturn::Return {
+        term: Term::slice_to_list(&list, Term::Nil.into()),
+    }
+}
+
 //fn keyfind(_vm: &VMState, _proc: &mut ProcessContext, args: &[Rc<Term>]) -> NativeReturn {
 //    assert!(args.len() == 3);
 //    let key = &*args[0];
@@ -72,9 +633,29 @@ fn reverse_1(vm: &VMState, proc: &mut ProcessContext, args: &[Rc<Term>]) -> Nati
 
 pub fn make_lists() -> NativeModule {
     let mut module = NativeModule::new(Symbol::intern("lists"));
-    //module.add_fun(Symbol::intern("member"), 2, Box::new(member));
+    module.add_fun(Symbol::intern("member"), 2, Box::new(member_2));
     module.add_fun(Symbol::intern("reverse"), 1, Box::new(reverse_1));
     module.add_fun(Symbol::intern("reverse"), 2, Box::new(reverse_2));
+    module.add_fun(Symbol::intern("zip"), 2, Box::new(zip_2));
+    module.add_fun(Symbol::intern("unzip"), 1, Box::new(unzip_1));
+    module.add_fun(Symbol::intern("nth"), 2, Box::new(nth_2));
+    module.add_fun(Symbol::intern("duplicate"), 2, Box::new(duplicate_2));
+    module.add_fun(Symbol::intern("last"), 1, Box::new(last_1));
+    module.add_fun(Symbol::intern("droplast"), 1, Box::new(droplast_1));
+    module.add_fun(Symbol::intern("seq"), 2, Box::new(seq_2));
+    module.add_fun(Symbol::intern("map"), 2, Box::new(map_2));
+    module.add_fun(Symbol::intern("foldl"), 3, Box::new(foldl_3));
+    module.add_fun(Symbol::intern("foldr"), 3, Box::new(foldr_3));
+    module.add_fun(Symbol::intern("mapfoldl"), 3, Box::new(mapfoldl_3));
+    module.add_fun(Symbol::intern("filter"), 2, Box::new(filter_2));
+    module.add_fun(Symbol::intern("partition"), 2, Box::new(partition_2));
+    module.add_fun(Symbol::intern("all"), 2, Box::new(all_2));
+    module.add_fun(Symbol::intern("any"), 2, Box::new(any_2));
+    module.add_fun(Symbol::intern("keysort"), 2, Box::new(keysort_2));
+    module.add_fun(Symbol::intern("usort"), 1, Box::new(usort_1));
+    module.add_fun(Symbol::intern("umerge"), 2, Box::new(umerge_2));
+    module.add_fun(Symbol::intern("keydelete"), 3, Box::new(keydelete_3));
+    module.add_fun(Symbol::intern("keyreplace"), 4, Box::new(keyreplace_4));
     //module.add_fun(Symbol::intern("keyfind"), 3, Box::new(keyfind));
     module
 }
@@ -15,3 +15,6 @@ pub use self::math::make_math;
 
 mod maps;
 pub use self::maps::make_maps;
+
+mod io;
+pub use self::io::make_io;
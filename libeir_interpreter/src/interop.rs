@@ -0,0 +1,118 @@
+//! Conversion between interpreter terms and a plain Rust representation,
+//! for host code that needs to build arguments or inspect results.
+
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+
+use libeir_util_binary::{BitSlice, BitVec};
+
+use crate::term::{MapTerm, Term};
+
+/// A term in a form that doesn't depend on interpreter internals.
+///
+/// Only data terms are covered. Pids, references and funs have no
+/// meaning outside of the `VMState` they were created in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RustTerm {
+    Int(BigInt),
+    Float(f64),
+    Atom(String),
+    Tuple(Vec<RustTerm>),
+    /// A proper list, the empty list is `List(vec![])`.
+    List(Vec<RustTerm>),
+    /// A byte aligned binary.
+    Binary(Vec<u8>),
+    /// Map entries, in term order.
+    Map(Vec<(RustTerm, RustTerm)>),
+}
+
+impl Term {
+    /// Converts the term to a `RustTerm`. Returns `None` if the term, or
+    /// any term nested in it, can't be represented: pids, references,
+    /// funs, improper lists and bitstrings that are not a whole number
+    /// of bytes.
+    pub fn to_rust(&self) -> Option<RustTerm> {
+        match self {
+            Term::Nil => Some(RustTerm::List(Vec::new())),
+            Term::Integer(int) => Some(RustTerm::Int(int.clone())),
+            Term::Float(float) => Some(RustTerm::Float(float.0)),
+            Term::Atom(atom) => Some(RustTerm::Atom(atom.as_str().to_string())),
+            Term::Tuple(elems) => elems
+                .iter()
+                .map(|elem| elem.to_rust())
+                .collect::<Option<_>>()
+                .map(RustTerm::Tuple),
+            Term::ListCell(_, _) => {
+                let mut elems = Vec::new();
+                let mut cell = self;
+                loop {
+                    match cell {
+                        Term::ListCell(head, tail) => {
+                            elems.push(head.to_rust()?);
+                            cell = &**tail;
+                        }
+                        Term::Nil => return Some(RustTerm::List(elems)),
+                        _ => return None,
+                    }
+                }
+            }
+            Term::Map(map) => map
+                .iter()
+                .map(|(key, val)| Some((key.to_rust()?, val.to_rust()?)))
+                .collect::<Option<_>>()
+                .map(RustTerm::Map),
+            Term::Binary(_) | Term::BinarySlice { .. } | Term::AppendBinary { .. } => {
+                let bin = match self {
+                    Term::Binary(buf) => (**buf).clone(),
+                    Term::BinarySlice {
+                        buf,
+                        bit_offset,
+                        bit_length,
+                    } => {
+                        let mut bin = BitVec::new();
+                        bin.push(BitSlice::with_offset_length(
+                            &**buf,
+                            *bit_offset,
+                            *bit_length,
+                        ));
+                        bin
+                    }
+                    Term::AppendBinary { buf, bit_length } => {
+                        let mut bin = BitVec::new();
+                        bin.push(BitSlice::with_offset_length(&*buf.borrow(), 0, *bit_length));
+                        bin
+                    }
+                    _ => unreachable!(),
+                };
+                bin.try_as_byte_aligned_slice()
+                    .map(|bytes| RustTerm::Binary(bytes.to_vec()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds an interpreter term from a `RustTerm`.
+    pub fn from_rust(term: &RustTerm) -> Rc<Term> {
+        match term {
+            RustTerm::Int(int) => Term::Integer(int.clone()).into(),
+            RustTerm::Float(float) => Term::Float((*float).into()).into(),
+            RustTerm::Atom(atom) => Term::new_atom(atom).into(),
+            RustTerm::Tuple(elems) => {
+                Term::Tuple(elems.iter().map(Term::from_rust).collect()).into()
+            }
+            RustTerm::List(elems) => {
+                let elems: Vec<_> = elems.iter().map(Term::from_rust).collect();
+                Term::slice_to_list(&elems, Term::Nil.into())
+            }
+            RustTerm::Binary(bytes) => Term::Binary(Rc::new(bytes.clone().into())).into(),
+            RustTerm::Map(entries) => {
+                let mut map = MapTerm::new();
+                for (key, val) in entries {
+                    map.insert(Term::from_rust(key), Term::from_rust(val));
+                }
+                Term::Map(map).into()
+            }
+        }
+    }
+}
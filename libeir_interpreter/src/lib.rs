@@ -9,11 +9,16 @@ pub use term::{ErlEq, ErlExactEq, ErlOrd, Pid, Reference, Term, TermType};
 
 pub mod erl_lib;
 
+mod interop;
+pub use interop::RustTerm;
+
 mod vm;
-pub use vm::{VMState, WatchType};
+pub use vm::{Exception, VMState, WatchType};
 
 mod process;
 
+mod mailbox;
+
 mod module;
 
 //mod trace;
@@ -1,16 +1,31 @@
-use ::term::{ Term, Pid };
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use crate::term::Term;
 
 #[derive(Debug)]
 pub struct Mailbox {
     trap_exits: bool,
-    messages: Vec<(Pid, Term)>,
+    messages: VecDeque<Rc<Term>>,
+
+    /// Index of the next message `receive_wait` will look at.
+    cursor: usize,
+    /// Virtual time at which the current receive times out, `None` is
+    /// infinity.
+    deadline: Option<u64>,
+    /// Set when `receive_wait` ran out of messages, the process must
+    /// yield until a message arrives.
+    waiting: bool,
 }
 
 impl Mailbox {
     pub fn new() -> Self {
         Mailbox {
             trap_exits: false,
-            messages: vec![],
+            messages: VecDeque::new(),
+            cursor: 0,
+            deadline: None,
+            waiting: false,
         }
     }
     pub fn get_trap_exits(&self) -> bool {
@@ -19,4 +34,54 @@ impl Mailbox {
     pub fn set_trap_exits(&mut self, val: bool) {
         self.trap_exits = val;
     }
+
+    pub fn push(&mut self, message: Rc<Term>) {
+        self.messages.push_back(message);
+    }
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn receive_start(&mut self, deadline: Option<u64>) {
+        self.cursor = 0;
+        self.deadline = deadline;
+    }
+
+    /// Fetches the next message under the mailbox pointer.
+    pub fn receive_next(&mut self) -> Option<Rc<Term>> {
+        let message = self.messages.get(self.cursor).cloned();
+        if message.is_some() {
+            self.cursor += 1;
+        }
+        message
+    }
+
+    /// Removes the last message returned by `receive_next`.
+    pub fn receive_done(&mut self) {
+        assert!(self.cursor > 0);
+        self.messages.remove(self.cursor - 1);
+        self.cursor = 0;
+        self.deadline = None;
+    }
+
+    /// Whether the current receive should take its timeout branch
+    /// now that the mailbox is exhausted.
+    pub fn receive_should_timeout(&self, now: u64) -> bool {
+        self.deadline.map(|d| d <= now).unwrap_or(false)
+    }
+    /// Finishes the current receive through its timeout branch.
+    pub fn receive_timeout(&mut self) {
+        self.cursor = 0;
+        self.deadline = None;
+    }
+    pub fn receive_deadline(&self) -> Option<u64> {
+        self.deadline
+    }
+
+    pub fn set_waiting(&mut self) {
+        self.waiting = true;
+    }
+    pub fn take_waiting(&mut self) -> bool {
+        std::mem::replace(&mut self.waiting, false)
+    }
 }
@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
@@ -10,6 +11,27 @@ use libeir_ir::{Function, FunctionIdent, LiveValues, Module};
 pub enum NativeReturn {
     Return { term: Rc<Term> },
     Throw { typ: Rc<Term>, reason: Rc<Term> },
+    /// Like `Throw`, but caused by the argument at index `arg`. The
+    /// trace points at the source of that argument when known.
+    ThrowArg {
+        typ: Rc<Term>,
+        reason: Rc<Term>,
+        arg: usize,
+    },
+    /// Like `Throw`, but the top frame of the trace is the calling
+    /// function with the argument list `args` in place of its arity.
+    ThrowWithArgs {
+        typ: Rc<Term>,
+        reason: Rc<Term>,
+        args: Rc<Term>,
+    },
+    /// Passes on an exception raised by a function the native function
+    /// called, keeping its `trace`.
+    Rethrow {
+        typ: Rc<Term>,
+        reason: Rc<Term>,
+        trace: Rc<Term>,
+    },
 }
 
 pub struct NativeModule {
@@ -46,31 +68,59 @@ pub struct ErlangFunction {
     pub live: LiveValues,
 }
 
+/// Why `ErlangModule::function` could not provide a function.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FunctionError {
+    /// The module does not define the function.
+    Undefined,
+    /// Lowering the lazily declared function failed.
+    LowerFailed,
+}
+
 pub struct ErlangModule {
     pub name: Symbol,
-    pub functions: HashMap<FunctionIdent, ErlangFunction>,
+    module: RefCell<Module>,
+    functions: RefCell<HashMap<FunctionIdent, Rc<ErlangFunction>>>,
 }
 
 impl ErlangModule {
     pub fn from_eir(module: Module) -> Self {
-        let functions = module
-            .index_iter()
-            .map(|idx| {
-                let fun_def = &module[idx];
-                let fun = fun_def.function();
-                let nfun = ErlangFunction {
-                    live: fun.live_values(),
-                    fun: fun.clone(),
-                };
-                (fun.ident().clone(), nfun)
-            })
-            .collect();
-
         ErlangModule {
             name: module.name().name,
-            functions,
+            module: RefCell::new(module),
+            functions: RefCell::new(HashMap::new()),
         }
     }
+
+    /// Checks whether the module defines `ident`, without lowering it.
+    pub fn has_function(&self, ident: &FunctionIdent) -> bool {
+        self.module.borrow().ident_index(ident).is_some()
+    }
+
+    /// Gets the function `ident`. A lazily declared function is lowered
+    /// the first time it is requested. If that fails, every request for
+    /// it returns `FunctionError::LowerFailed`, see `Module::lower_function`.
+    pub fn function(&self, ident: &FunctionIdent) -> Result<Rc<ErlangFunction>, FunctionError> {
+        if let Some(fun) = self.functions.borrow().get(ident) {
+            return Ok(fun.clone());
+        }
+
+        let mut module = self.module.borrow_mut();
+        if module.ident_index(ident).is_none() {
+            return Err(FunctionError::Undefined);
+        }
+        let fun = module
+            .lower_function(ident)
+            .map_err(|()| FunctionError::LowerFailed)?
+            .function();
+        let nfun = Rc::new(ErlangFunction {
+            live: fun.live_values(),
+            fun: fun.clone(),
+        });
+        self.functions.borrow_mut().insert(*ident, nfun.clone());
+
+        Ok(nfun)
+    }
 }
 
 pub enum ModuleType {
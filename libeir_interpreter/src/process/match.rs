@@ -1,7 +1,11 @@
+use std::rc::Rc;
+
+use num_traits::ToPrimitive;
+
 use libeir_ir::{BasicType, BinaryEntrySpecifier, Block, Endianness, MatchKind};
 
 use libeir_util_binary::BitCarrier;
-use libeir_util_binary::{carrier_to_integer, BitSlice, BitVec, Endian};
+use libeir_util_binary::{carrier_to_integer, BitSlice, Endian};
 
 use crate::module::ErlangFunction;
 use crate::term::ErlExactEq;
@@ -19,7 +23,9 @@ pub fn match_op(
 
     let branches_elems = Term::as_value_list(&exec.make_term(fun, reads[0]));
 
-    let unpack_term = exec.make_term(fun, reads[1]);
+    // Binary matching reads the bits directly, so appendable binaries
+    // are copied out of their shared buffer first.
+    let unpack_term = Term::snapshot_binary(&exec.make_term(fun, reads[1]));
 
     for (idx, kind) in branches.iter().enumerate() {
         let branch_args = Term::as_value_list(&exec.make_term(fun, reads[idx + 2]));
@@ -85,104 +91,14 @@ pub fn match_op(
                     _ => unreachable!(),
                 }
             }
-            MatchKind::Binary(BinaryEntrySpecifier::Integer {
-                unit,
-                endianness,
-                signed,
-            }) => {
-                let size = branch_args[0].as_usize().unwrap();
-                let bit_len = (*unit as usize) * size;
-
-                let ret = match &*unpack_term {
-                    Term::Binary(bin) => {
-                        if (bin.len() * 8) < bit_len {
-                            continue;
-                        }
-
-                        let int_slice = BitSlice::with_offset_length(&**bin, 0, bit_len);
-                        let endian = match *endianness {
-                            Endianness::Big => Endian::Big,
-                            Endianness::Little => Endian::Little,
-                            Endianness::Native => Endian::Big,
-                        };
-                        let int = carrier_to_integer(int_slice, *signed, endian);
-
-                        TermCall {
-                            fun: branches_elems[idx].clone(),
-                            args: vec![
-                                Term::Integer(int).into(),
-                                Term::BinarySlice {
-                                    buf: bin.clone(),
-                                    bit_offset: bit_len,
-                                    bit_length: bin.bit_len() - bit_len,
-                                }
-                                .into(),
-                            ],
-                        }
-                    }
-                    Term::BinarySlice {
-                        buf,
-                        bit_offset,
-                        bit_length,
-                    } => {
-                        if *bit_length < bit_len {
-                            continue;
-                        }
-
-                        let int_slice = BitSlice::with_offset_length(&**buf, *bit_offset, bit_len);
-                        let endian = match *endianness {
-                            Endianness::Big => Endian::Big,
-                            Endianness::Little => Endian::Little,
-                            Endianness::Native => Endian::Big,
-                        };
-                        let int = carrier_to_integer(int_slice, *signed, endian);
-
-                        TermCall {
-                            fun: branches_elems[idx].clone(),
-                            args: vec![
-                                Term::Integer(int).into(),
-                                Term::BinarySlice {
-                                    buf: buf.clone(),
-                                    bit_offset: *bit_offset + bit_len,
-                                    bit_length: *bit_length - bit_len,
-                                }
-                                .into(),
-                            ],
-                        }
-                    }
-                    _ => continue,
-                };
-                return ret;
-            }
-            MatchKind::Binary(BinaryEntrySpecifier::Bytes { unit: 8 }) => match &*unpack_term {
-                Term::Binary(bin) => {
-                    if bin.bit_len() % 8 != 0 {
-                        continue;
-                    }
-
-                    return TermCall {
-                        fun: branches_elems[idx].clone(),
-                        args: vec![
-                            unpack_term.clone(),
-                            Term::Binary(BitVec::new().into()).into(),
-                        ],
-                    };
-                }
-                Term::BinarySlice { bit_length, .. } => {
-                    if *bit_length % 8 != 0 {
-                        continue;
-                    }
-
+            MatchKind::Binary(specifier) => {
+                if let Some(args) = match_binary(specifier, &unpack_term, &branch_args) {
                     return TermCall {
                         fun: branches_elems[idx].clone(),
-                        args: vec![
-                            unpack_term.clone(),
-                            Term::Binary(BitVec::new().into()).into(),
-                        ],
+                        args,
                     };
                 }
-                _ => (),
-            },
+            }
             MatchKind::Wildcard => {
                 assert!(branch_args.len() == 0);
                 return TermCall {
@@ -196,3 +112,97 @@ pub fn match_op(
 
     panic!()
 }
+
+fn to_endian(endianness: Endianness) -> Endian {
+    match endianness {
+        Endianness::Big => Endian::Big,
+        Endianness::Little => Endian::Little,
+        Endianness::Native => Endian::Big,
+    }
+}
+
+/// Matches a single binary entry at the start of `term`. The size, if
+/// any, is the first branch argument, in units of the specifier. It can
+/// be any runtime value, usually one bound by an earlier entry.
+///
+/// Returns the matched value and the remaining binary.
+fn match_binary(
+    specifier: &BinaryEntrySpecifier,
+    term: &Term,
+    branch_args: &[Rc<Term>],
+) -> Option<Vec<Rc<Term>>> {
+    let (buf, bit_offset, bit_length) = match term {
+        Term::Binary(buf) => (buf.clone(), 0, buf.bit_len()),
+        Term::BinarySlice {
+            buf,
+            bit_offset,
+            bit_length,
+        } => (buf.clone(), *bit_offset, *bit_length),
+        _ => return None,
+    };
+
+    let size = match branch_args.get(0) {
+        Some(size) => Some(size.as_integer()?.to_usize()?),
+        None => None,
+    };
+
+    let (value, entry_len) = match *specifier {
+        BinaryEntrySpecifier::Integer {
+            unit,
+            endianness,
+            signed,
+        } => {
+            let entry_len = unit as usize * size?;
+            if bit_length < entry_len {
+                return None;
+            }
+
+            let int_slice = BitSlice::with_offset_length(&*buf, bit_offset, entry_len);
+            let int = carrier_to_integer(int_slice, signed, to_endian(endianness));
+            (Term::Integer(int).into(), entry_len)
+        }
+        BinaryEntrySpecifier::Float { unit, endianness } => {
+            let entry_len = unit as usize * size?;
+            if bit_length < entry_len {
+                return None;
+            }
+
+            let float_slice = BitSlice::with_offset_length(&*buf, bit_offset, entry_len);
+            let bits = carrier_to_integer(float_slice, false, to_endian(endianness));
+            let float = match entry_len {
+                32 => f32::from_bits(bits.to_u32().unwrap()) as f64,
+                64 => f64::from_bits(bits.to_u64().unwrap()),
+                _ => return None,
+            };
+            if !float.is_finite() {
+                return None;
+            }
+            (Term::Float(float.into()).into(), entry_len)
+        }
+        BinaryEntrySpecifier::Bytes { unit } | BinaryEntrySpecifier::Bits { unit } => {
+            let unit = unit as usize;
+            let entry_len = match size {
+                Some(size) => unit * size,
+                None => bit_length,
+            };
+            if bit_length < entry_len || entry_len % unit != 0 {
+                return None;
+            }
+
+            let value = Term::BinarySlice {
+                buf: buf.clone(),
+                bit_offset,
+                bit_length: entry_len,
+            };
+            (value.into(), entry_len)
+        }
+        _ => unimplemented!("{:?}", specifier),
+    };
+
+    let rest = Term::BinarySlice {
+        buf,
+        bit_offset: bit_offset + entry_len,
+        bit_length: bit_length - entry_len,
+    };
+    Some(vec![value, rest.into()])
+}
@@ -1,27 +1,36 @@
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
 use std::rc::Rc;
 
 use num_traits::cast::ToPrimitive;
 
-use libeir_intern::Ident;
+use libeir_intern::{Ident, Symbol};
 use libeir_ir::constant::{AtomicTerm, Const, ConstKind};
 use libeir_ir::operation::binary_construct::{
     BinaryConstructFinish, BinaryConstructPush, BinaryConstructStart,
 };
+use libeir_ir::operation::receive::{ReceiveDone, ReceiveStart, ReceiveWait};
 use libeir_ir::MapPutUpdate;
-use libeir_ir::{BinOp, Block, FunctionIdent, LogicOp, OpKind, PrimOpKind, Value, ValueKind};
+use libeir_ir::{BinOp, Block, CallKind, FunctionIdent, LogicOp, OpKind, PrimOpKind};
+use libeir_ir::{Value, ValueKind};
 use libeir_ir::{BinaryEntrySpecifier, Endianness};
 
-use libeir_util_binary::{integer_to_carrier, BitSlice, BitVec, Endian};
+use libeir_util_binary::{integer_to_carrier, BitCarrier, BitSlice, BitVec, Endian};
 
-use crate::module::{ErlangFunction, ErlangModule, ModuleType, NativeModule, NativeReturn};
+use crate::mailbox::Mailbox;
+use crate::module::{
+    ErlangFunction, ErlangModule, FunctionError, ModuleType, NativeModule, NativeReturn,
+};
 use crate::term::{ErlEq, MapTerm, Pid, Term};
-use crate::vm::VMState;
+use crate::vm::{Exception, VMState};
 
 mod r#match;
+mod stack;
+
+use self::stack::CallStack;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TermCall {
     pub fun: Rc<Term>,
     pub args: Vec<Rc<Term>>,
@@ -29,10 +38,145 @@ pub struct TermCall {
 
 pub enum Continuation {
     Term(TermCall),
+    /// The process ran out of messages in a receive. The call should be
+    /// retried once a message arrives.
+    Wait(TermCall),
     ReturnOk(Rc<Term>),
     ReturnThrow(Rc<Term>, Rc<Term>, Rc<Term>),
 }
 
+fn is_apply(ident: &FunctionIdent) -> bool {
+    ident.module.name == Symbol::intern("erlang")
+        && ident.name.name == Symbol::intern("apply")
+        && ident.arity == 3
+}
+
+fn is_apply_fun(ident: &FunctionIdent) -> bool {
+    ident.module.name == Symbol::intern("erlang")
+        && ident.name.name == Symbol::intern("apply")
+        && ident.arity == 2
+}
+
+/// The arity of a function term, `None` if the term is not a function.
+fn fun_arity(vm: &VMState, fun: &Term) -> Option<usize> {
+    match fun {
+        Term::CapturedFunction { ident } => Some(ident.arity),
+        Term::BoundLambda { ident, block, .. } => match vm.modules.get(&ident.module.name) {
+            Some(ModuleType::Erlang(erl, _)) => {
+                let fun = erl.function(ident).ok()?;
+                // Minus the return and throw continuations
+                Some(fun.fun.block_args(*block).len() - 2)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The reason of the `error` raised when `ident` can't be run, `undef`
+/// or `{lower_failed, {Module, Name, Arity}}`.
+fn function_error_reason(ident: &FunctionIdent, err: FunctionError) -> Rc<Term> {
+    match err {
+        FunctionError::Undefined => Term::new_atom("undef").into(),
+        FunctionError::LowerFailed => {
+            let mfa = Term::Tuple(vec![
+                Term::Atom(ident.module.name).into(),
+                Term::Atom(ident.name.name).into(),
+                Term::new_i64(ident.arity as i64).into(),
+            ]);
+            Term::Tuple(vec![Term::new_atom("lower_failed").into(), mfa.into()]).into()
+        }
+    }
+}
+
+/// Raises an `error` class exception with `reason` through the throw
+/// continuation of `call`.
+fn throw_error(proc: &ProcessContext, call: &TermCall, reason: Rc<Term>) -> TermCall {
+    TermCall {
+        fun: call.args[1].clone(),
+        args: vec![Term::new_atom("error").into(), reason, proc.stack.trace(0)],
+    }
+}
+
+/// The trace of an exception a native function raised because of
+/// argument `arg` of the call made at `call_site`. Points at the source
+/// span of the argument as `[{argument, Arg, {Start, End}}]` when the
+/// call recorded one, otherwise empty.
+fn argument_trace(vm: &VMState, call_site: Option<(FunctionIdent, Block)>, arg: usize) -> Rc<Term> {
+    let span = call_site.and_then(|(caller, block)| match vm.modules.get(&caller.module.name) {
+        Some(ModuleType::Erlang(erl, _)) => {
+            let fun = erl.function(&caller).ok()?;
+            // The first three reads are the callee and continuations
+            fun.fun.block_read_span(block, arg + 3)
+        }
+        _ => None,
+    });
+
+    match span {
+        Some(span) => {
+            let entry = Term::Tuple(vec![
+                Term::new_atom("argument").into(),
+                Term::new_i64(arg as i64).into(),
+                Term::Tuple(vec![
+                    Term::new_i64(span.start().to_usize() as i64).into(),
+                    Term::new_i64(span.end().to_usize() as i64).into(),
+                ])
+                .into(),
+            ]);
+            Term::slice_to_list(&[entry.into()], Term::Nil.into())
+        }
+        None => Term::Nil.into(),
+    }
+}
+
+/// A trace whose top frame is `fun` called with the argument list
+/// `args`, `[{Module, Function, Args, []} | Rest]`.
+fn args_trace(fun: &FunctionIdent, args: Rc<Term>, rest: Rc<Term>) -> Rc<Term> {
+    let frame = Term::Tuple(vec![
+        Term::Atom(fun.module.name).into(),
+        Term::Atom(fun.name.name).into(),
+        args,
+        Term::Nil.into(),
+    ]);
+    Term::slice_to_list(&[frame.into()], rest)
+}
+
+fn is_empty_binary(term: &Term) -> bool {
+    match term {
+        Term::Binary(bin) => bin.bit_len() == 0,
+        _ => false,
+    }
+}
+
+/// Gets the buffer of an appendable binary if nothing was appended
+/// after it yet, it can then be extended in place.
+fn append_buffer(term: &Term) -> Option<Rc<RefCell<BitVec>>> {
+    match term {
+        Term::AppendBinary { buf, bit_length } if buf.borrow().bit_len() == *bit_length => {
+            Some(buf.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Copies a binary into a new buffer that can be appended to.
+fn copy_to_append_buffer(term: &Term) -> Rc<RefCell<BitVec>> {
+    let mut new = BitVec::new();
+    match term {
+        Term::Binary(bin) => new.push(&**bin),
+        Term::BinarySlice {
+            buf,
+            bit_offset,
+            bit_length,
+        } => new.push(BitSlice::with_offset_length(&**buf, *bit_offset, *bit_length)),
+        Term::AppendBinary { buf, bit_length } => {
+            new.push(BitSlice::with_offset_length(&*buf.borrow(), 0, *bit_length))
+        }
+        _ => panic!(),
+    }
+    Rc::new(RefCell::new(new))
+}
+
 pub struct CallExecutor {
     binds: HashMap<Value, Rc<Term>>,
 }
@@ -46,6 +190,8 @@ impl CallExecutor {
 
     pub fn run(&mut self, vm: &VMState, proc: &mut ProcessContext, call: TermCall) -> Continuation {
         self.binds.clear();
+        let tail = std::mem::replace(&mut proc.tail_call, false);
+        proc.stack.enter(&call.fun);
         match &*call.fun {
             Term::BoundLambda {
                 ident,
@@ -54,13 +200,34 @@ impl CallExecutor {
             } => {
                 let module = &vm.modules[&ident.module.name];
                 match module {
-                    ModuleType::Erlang(erl, _overlay) => Continuation::Term(
-                        self.run_erlang(vm, erl, ident, Some((*block, &*environment)), &call.args)
-                            .unwrap(),
-                    ),
+                    ModuleType::Erlang(erl, _overlay) => {
+                        let next = self.run_erlang(
+                            vm,
+                            proc,
+                            erl,
+                            ident,
+                            Some((*block, &*environment)),
+                            &call.args,
+                        );
+                        match next {
+                            Ok(next) => self.continue_or_wait(proc, next, &call),
+                            Err(err) => Continuation::Term(throw_error(
+                                proc,
+                                &call,
+                                function_error_reason(ident, err),
+                            )),
+                        }
+                    }
                     ModuleType::Native(_native) => unreachable!(),
                 }
             }
+            Term::CapturedFunction { ident } if is_apply(ident) => self.run_apply(vm, proc, &call),
+            Term::CapturedFunction { ident } if is_apply_fun(ident) => {
+                self.run_apply_fun(vm, proc, &call)
+            }
+            Term::CapturedFunction { ident } if !vm.function_exists(ident) => {
+                Continuation::Term(throw_error(proc, &call, Term::new_atom("undef").into()))
+            }
             Term::CapturedFunction { ident } => {
                 let module = &vm.modules[&ident.module.name];
                 match module {
@@ -72,9 +239,15 @@ impl CallExecutor {
                             }
                         }
                         println!("{}", ident);
-                        Continuation::Term(
-                            self.run_erlang(vm, erl, ident, None, &call.args).unwrap(),
-                        )
+                        proc.stack.push(ident, &call.args[0], &call.args[1], tail);
+                        match self.run_erlang(vm, proc, erl, ident, None, &call.args) {
+                            Ok(next) => self.continue_or_wait(proc, next, &call),
+                            Err(err) => Continuation::Term(throw_error(
+                                proc,
+                                &call,
+                                function_error_reason(ident, err),
+                            )),
+                        }
                     }
                     ModuleType::Native(native) => Continuation::Term(
                         if let Some(res) = self.run_native(vm, proc, native, ident, &call.args) {
@@ -102,6 +275,91 @@ impl CallExecutor {
         }
     }
 
+    /// `erlang:apply/3`, resolving the function from runtime values and
+    /// dispatching to it with the same continuations.
+    fn run_apply(&mut self, vm: &VMState, proc: &ProcessContext, call: &TermCall) -> Continuation {
+        assert!(call.args.len() == 5);
+
+        let module = call.args[2].as_atom();
+        let name = call.args[3].as_atom();
+        let args = Term::as_list(&call.args[4]);
+
+        let (module, name, args) = match (module, name, args) {
+            (Some(m), Some(n), Some(a)) => (m, n, a),
+            _ => {
+                return Continuation::Term(throw_error(proc, call, Term::new_atom("badarg").into()))
+            }
+        };
+
+        let ident = FunctionIdent {
+            module: Ident::with_empty_span(module),
+            name: Ident::with_empty_span(name),
+            arity: args.len(),
+        };
+        if !vm.function_exists(&ident) {
+            return Continuation::Term(throw_error(proc, call, Term::new_atom("undef").into()));
+        }
+
+        let mut call_args = Vec::with_capacity(args.len() + 2);
+        call_args.push(call.args[0].clone());
+        call_args.push(call.args[1].clone());
+        call_args.extend(args);
+
+        Continuation::Term(TermCall {
+            fun: Term::CapturedFunction { ident }.into(),
+            args: call_args,
+        })
+    }
+
+    /// `erlang:apply/2`, calling a function term with the elements of a
+    /// runtime list as arguments.
+    fn run_apply_fun(
+        &mut self,
+        vm: &VMState,
+        proc: &ProcessContext,
+        call: &TermCall,
+    ) -> Continuation {
+        assert!(call.args.len() == 4);
+
+        let fun = &call.args[2];
+        let args = match (fun_arity(vm, fun), Term::as_list(&call.args[3])) {
+            (Some(arity), Some(args)) if arity == args.len() => args,
+            (Some(_), Some(_)) => {
+                let reason = Term::Tuple(vec![
+                    Term::new_atom("badarity").into(),
+                    Term::Tuple(vec![fun.clone(), call.args[3].clone()]).into(),
+                ]);
+                return Continuation::Term(throw_error(proc, call, reason.into()));
+            }
+            _ => {
+                return Continuation::Term(throw_error(proc, call, Term::new_atom("badarg").into()))
+            }
+        };
+
+        let mut call_args = Vec::with_capacity(args.len() + 2);
+        call_args.push(call.args[0].clone());
+        call_args.push(call.args[1].clone());
+        call_args.extend(args);
+
+        Continuation::Term(TermCall {
+            fun: fun.clone(),
+            args: call_args,
+        })
+    }
+
+    fn continue_or_wait(
+        &self,
+        proc: &mut ProcessContext,
+        next: TermCall,
+        call: &TermCall,
+    ) -> Continuation {
+        if proc.mailbox.take_waiting() {
+            Continuation::Wait(call.clone())
+        } else {
+            Continuation::Term(next)
+        }
+    }
+
     pub fn run_native(
         &mut self,
         vm: &VMState,
@@ -111,6 +369,7 @@ impl CallExecutor {
         args: &[Rc<Term>],
     ) -> Option<TermCall> {
         if let Some(n_fun) = native.functions.get(&(ident.name.name, ident.arity)) {
+            let call_site = proc.call_site.take();
             match n_fun(vm, proc, &args[2..]) {
                 NativeReturn::Return { term } => Some(TermCall {
                     fun: args[0].clone(),
@@ -118,7 +377,35 @@ impl CallExecutor {
                 }),
                 NativeReturn::Throw { typ, reason } => Some(TermCall {
                     fun: args[1].clone(),
-                    args: vec![typ, reason, Term::Nil.into()],
+                    args: vec![typ, reason, proc.stack.trace(0)],
+                }),
+                NativeReturn::ThrowArg { typ, reason, arg } => Some(TermCall {
+                    fun: args[1].clone(),
+                    args: vec![typ, reason, argument_trace(vm, call_site, arg)],
+                }),
+                NativeReturn::ThrowWithArgs {
+                    typ,
+                    reason,
+                    args: frame_args,
+                } => {
+                    let caller = call_site.map(|(caller, _)| caller);
+                    Some(TermCall {
+                        fun: args[1].clone(),
+                        args: vec![
+                            typ,
+                            reason,
+                            // The top frame is the caller, it is replaced
+                            args_trace(
+                                caller.as_ref().unwrap_or(ident),
+                                frame_args,
+                                proc.stack.trace(1),
+                            ),
+                        ],
+                    })
+                }
+                NativeReturn::Rethrow { typ, reason, trace } => Some(TermCall {
+                    fun: args[1].clone(),
+                    args: vec![typ, reason, trace],
                 }),
             }
         } else {
@@ -129,38 +416,37 @@ impl CallExecutor {
     pub fn run_erlang(
         &mut self,
         vm: &VMState,
+        proc: &mut ProcessContext,
         module: &ErlangModule,
         ident: &FunctionIdent,
         state: Option<(Block, &[Rc<Term>])>,
         args: &[Rc<Term>],
-    ) -> Option<TermCall> {
-        if let Some(fun) = module.functions.get(&ident) {
-            // Environment
-            let block = if let Some((block, env)) = state {
-                let live = &fun.live.live_at(block);
+    ) -> Result<TermCall, FunctionError> {
+        let fun = module.function(ident)?;
 
-                for (v, t) in live.iter().zip(env.iter()) {
-                    self.binds.insert(v, t.clone());
-                }
-                assert!(live.iter().count() == env.len());
-
-                block
-            } else {
-                fun.fun.block_entry()
-            };
-
-            // Insert arguments
-            let block_arg_vals = fun.fun.block_args(block);
-            assert!(block_arg_vals.len() == args.len());
-            for (v, t) in block_arg_vals.iter().zip(args.iter()) {
-                self.binds.insert(*v, t.clone());
+        // Environment
+        let block = if let Some((block, env)) = state {
+            let live = &fun.live.live_at(block);
+
+            for (v, t) in live.iter().zip(env.iter()) {
+                self.binds.insert(v, t.clone());
             }
+            assert!(live.iter().count() == env.len());
 
-            // Execute operation
-            Some(self.run_erlang_op(vm, fun, block))
+            block
         } else {
-            None
+            fun.fun.block_entry()
+        };
+
+        // Insert arguments
+        let block_arg_vals = fun.fun.block_args(block);
+        assert!(block_arg_vals.len() == args.len());
+        for (v, t) in block_arg_vals.iter().zip(args.iter()) {
+            self.binds.insert(*v, t.clone());
         }
+
+        // Execute operation
+        Ok(self.run_erlang_op(vm, proc, &fun, block))
     }
 
     fn make_const_term(&self, fun: &ErlangFunction, const_val: Const) -> Rc<Term> {
@@ -285,18 +571,30 @@ impl CallExecutor {
         }
     }
 
-    pub fn run_erlang_op(&mut self, _vm: &VMState, fun: &ErlangFunction, block: Block) -> TermCall {
+    pub fn run_erlang_op(
+        &mut self,
+        vm: &VMState,
+        proc: &mut ProcessContext,
+        fun: &ErlangFunction,
+        block: Block,
+    ) -> TermCall {
         let reads = fun.fun.block_reads(block);
         println!("OP: {:?}", fun.fun.block_kind(block).unwrap());
         match fun.fun.block_kind(block).unwrap() {
-            OpKind::Call(_) => TermCall {
-                fun: self.make_term(fun, reads[0]),
-                args: reads
-                    .iter()
-                    .skip(1)
-                    .map(|r| self.make_term(fun, *r))
-                    .collect(),
-            },
+            OpKind::Call(kind) => {
+                if let CallKind::Function = kind {
+                    proc.call_site = Some((fun.fun.ident().clone(), block));
+                    proc.tail_call = fun.fun.is_tail_call(block);
+                }
+                TermCall {
+                    fun: self.make_term(fun, reads[0]),
+                    args: reads
+                        .iter()
+                        .skip(1)
+                        .map(|r| self.make_term(fun, *r))
+                        .collect(),
+                }
+            }
             OpKind::UnpackValueList(num) => {
                 assert!(reads.len() == 2);
                 let term = self.make_term(fun, reads[1]);
@@ -340,7 +638,7 @@ impl CallExecutor {
             }
             OpKind::TraceCaptureRaw => TermCall {
                 fun: self.make_term(fun, reads[0]),
-                args: vec![Term::Nil.into()],
+                args: vec![proc.stack.trace(0)],
             },
             OpKind::Match { branches } => self::r#match::match_op(self, fun, branches, block),
             OpKind::Dyn(dyn_op) => {
@@ -361,27 +659,35 @@ impl CallExecutor {
                         let specifier = bin_push.specifier;
 
                         let bin_term = self.make_term(fun, bin_ref);
-                        let mut bin = match &*bin_term {
-                            Term::Binary(bin) => (**bin).clone(),
-                            Term::BinarySlice {
-                                buf,
-                                bit_offset,
-                                bit_length,
-                            } => {
-                                let slice =
-                                    BitSlice::with_offset_length(&**buf, *bit_offset, *bit_length);
-                                let mut new = BitVec::new();
-                                new.push(slice);
-                                new
-                            }
-                            _ => panic!(),
-                        };
-
                         let val_term = self.make_term(fun, reads[3]);
 
                         assert!(reads.len() == 4 || reads.len() == 5);
                         let size_term = reads.get(4).map(|r| self.make_term(fun, *r));
 
+                        // `<<Acc/binary, ...>>` starts out by pushing `Acc`
+                        // to an empty binary. Continuing on from the buffer
+                        // of `Acc` keeps a binary built in a loop from being
+                        // copied on every iteration.
+                        if let BinaryEntrySpecifier::Bytes { unit: 1 } = specifier {
+                            if size_term.is_none() && is_empty_binary(&bin_term) {
+                                if let Some(buf) = append_buffer(&val_term) {
+                                    let bit_length = buf.borrow().bit_len();
+                                    return TermCall {
+                                        fun: self.make_term(fun, ok_cont),
+                                        args: vec![Term::AppendBinary { buf, bit_length }.into()],
+                                    };
+                                }
+                            }
+                        }
+
+                        // The value may share its buffer with the binary,
+                        // copy it out before the buffer is written to.
+                        let val_term = Term::snapshot_binary(&val_term);
+
+                        let buf = append_buffer(&bin_term)
+                            .unwrap_or_else(|| copy_to_append_buffer(&bin_term));
+                        let mut bin = buf.borrow_mut();
+
                         match specifier {
                             BinaryEntrySpecifier::Integer {
                                 signed: _,
@@ -439,15 +745,65 @@ impl CallExecutor {
                             k => unimplemented!("{:?}", k),
                         }
 
+                        let bit_length = bin.bit_len();
+                        drop(bin);
+
                         return TermCall {
                             fun: self.make_term(fun, ok_cont),
-                            args: vec![Term::Binary(bin.into()).into()],
+                            args: vec![Term::AppendBinary { buf, bit_length }.into()],
                         };
                     }
                     _ if tid == TypeId::of::<BinaryConstructFinish>() => TermCall {
                         fun: self.make_term(fun, reads[0]),
                         args: vec![self.make_term(fun, reads[1])],
                     },
+                    _ if tid == TypeId::of::<ReceiveStart>() => {
+                        // The timeout is scheduled on the virtual clock.
+                        // Anything but a non-negative integer is taken as
+                        // `infinity`.
+                        let timeout = self.make_term(fun, reads[1]);
+                        let deadline = timeout
+                            .as_i64()
+                            .filter(|t| *t >= 0)
+                            .map(|t| vm.now() + t as u64);
+                        proc.mailbox.receive_start(deadline);
+
+                        // The receive state lives in the mailbox, the
+                        // receive ref carries nothing.
+                        TermCall {
+                            fun: self.make_term(fun, reads[0]),
+                            args: vec![Term::Nil.into()],
+                        }
+                    }
+                    _ if tid == TypeId::of::<ReceiveWait>() => {
+                        if let Some(message) = proc.mailbox.receive_next() {
+                            TermCall {
+                                fun: self.make_term(fun, reads[1]),
+                                args: vec![message],
+                            }
+                        } else if proc.mailbox.receive_should_timeout(vm.now()) {
+                            proc.mailbox.receive_timeout();
+                            TermCall {
+                                fun: self.make_term(fun, reads[0]),
+                                args: vec![],
+                            }
+                        } else {
+                            // Yield, this call is discarded and the
+                            // operation retried when a message arrives.
+                            proc.mailbox.set_waiting();
+                            TermCall {
+                                fun: Term::Nil.into(),
+                                args: vec![],
+                            }
+                        }
+                    }
+                    _ if tid == TypeId::of::<ReceiveDone>() => {
+                        proc.mailbox.receive_done();
+                        TermCall {
+                            fun: self.make_term(fun, reads[0]),
+                            args: reads[2..].iter().map(|r| self.make_term(fun, *r)).collect(),
+                        }
+                    }
                     _ => unimplemented!(),
                 }
             }
@@ -561,16 +917,65 @@ impl CallExecutor {
     }
 }
 
+pub(crate) enum ProcessStatus {
+    Runnable(TermCall),
+    /// Blocked in a receive, the call is retried when a message
+    /// arrives.
+    Waiting(TermCall),
+    Running,
+    Exited(Result<Rc<Term>, Exception>),
+}
+
 pub struct ProcessContext {
     pub pid: Pid,
     pub dict: Vec<(Rc<Term>, Rc<Term>)>,
+    pub mailbox: Mailbox,
+    pub links: BTreeSet<Pid>,
+    pub(crate) status: ProcessStatus,
+    /// The function and block of the last function call, used to find
+    /// the source of an argument a native function raised on.
+    pub(crate) call_site: Option<(FunctionIdent, Block)>,
+    /// Whether the next call is a tail call, which reuses the top frame of
+    /// the stack.
+    pub(crate) tail_call: bool,
+    pub(crate) stack: CallStack,
 }
 
 impl ProcessContext {
-    pub fn new(pid: Pid) -> Self {
+    pub fn new(pid: Pid, call: TermCall) -> Self {
         ProcessContext {
             pid,
             dict: Vec::new(),
+            mailbox: Mailbox::new(),
+            links: BTreeSet::new(),
+            status: ProcessStatus::Runnable(call),
+            call_site: None,
+            tail_call: false,
+            stack: CallStack::default(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        match self.status {
+            ProcessStatus::Exited(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Puts a message in the mailbox, waking the process if it is
+    /// blocked in a receive.
+    pub fn deliver(&mut self, message: Rc<Term>) {
+        self.mailbox.push(message);
+        self.wake();
+    }
+
+    /// Makes the process runnable again if it is blocked in a receive.
+    pub(crate) fn wake(&mut self) {
+        if let ProcessStatus::Waiting(_) = self.status {
+            match std::mem::replace(&mut self.status, ProcessStatus::Running) {
+                ProcessStatus::Waiting(call) => self.status = ProcessStatus::Runnable(call),
+                _ => unreachable!(),
+            }
         }
     }
 }
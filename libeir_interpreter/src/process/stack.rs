@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use libeir_ir::FunctionIdent;
+
+use crate::term::Term;
+
+struct Frame {
+    ident: FunctionIdent,
+    ok_cont: Rc<Term>,
+    throw_cont: Rc<Term>,
+}
+
+/// The Erlang functions a process is currently executing, used to build
+/// stacktraces.
+///
+/// Calls are continuation passing, so there is no native stack to look
+/// at. A frame is pushed when an Erlang function is entered, and popped
+/// once the return or throw continuation it was called with is invoked.
+/// A tail call reuses the top frame, like it would on the BEAM.
+///
+/// Native functions and funs don't get frames.
+#[derive(Default)]
+pub(crate) struct CallStack {
+    frames: Vec<Frame>,
+    /// Lowest frame each continuation was passed to. The frames hold a
+    /// reference to their continuations, so the addresses stay valid.
+    conts: HashMap<*const Term, usize>,
+}
+
+impl CallStack {
+    /// Pushes a frame for a call to `ident`. A tail call reuses the top
+    /// frame instead of growing the stack. Calls not flagged as tail calls
+    /// are still treated as one if they return to the top frame's
+    /// continuation.
+    pub fn push(
+        &mut self,
+        ident: &FunctionIdent,
+        ok_cont: &Rc<Term>,
+        throw_cont: &Rc<Term>,
+        tail: bool,
+    ) {
+        let is_tail = match self.frames.last() {
+            Some(top) => tail || Rc::ptr_eq(&top.ok_cont, ok_cont),
+            None => false,
+        };
+
+        let idx = if is_tail {
+            let idx = self.frames.len() - 1;
+            self.forget_conts(idx);
+            let top = &mut self.frames[idx];
+            top.ident = ident.clone();
+            top.ok_cont = ok_cont.clone();
+            top.throw_cont = throw_cont.clone();
+            idx
+        } else {
+            self.frames.push(Frame {
+                ident: ident.clone(),
+                ok_cont: ok_cont.clone(),
+                throw_cont: throw_cont.clone(),
+            });
+            self.frames.len() - 1
+        };
+
+        self.conts.entry(&**ok_cont as *const Term).or_insert(idx);
+        self.conts
+            .entry(&**throw_cont as *const Term)
+            .or_insert(idx);
+    }
+
+    /// Called with every function term that is called. If it is the
+    /// continuation of a frame, that frame and everything above it has
+    /// returned.
+    pub fn enter(&mut self, fun: &Rc<Term>) {
+        if let Some(idx) = self.conts.get(&(&**fun as *const Term)).cloned() {
+            self.truncate(idx);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Pops every frame above the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        while self.frames.len() > len {
+            let idx = self.frames.len() - 1;
+            self.forget_conts(idx);
+            self.frames.pop();
+        }
+    }
+
+    /// Removes the continuations of the frame at `idx` from the lookup,
+    /// unless a lower frame was passed them too.
+    fn forget_conts(&mut self, idx: usize) {
+        let frame = &self.frames[idx];
+        for cont in [&frame.ok_cont, &frame.throw_cont].iter() {
+            let key = &***cont as *const Term;
+            if self.conts.get(&key) == Some(&idx) {
+                self.conts.remove(&key);
+            }
+        }
+    }
+
+    /// The stacktrace, innermost frame first, as a list of
+    /// `{Module, Function, Arity, Location}`. `skip` frames are left out
+    /// from the top.
+    pub fn trace(&self, skip: usize) -> Rc<Term> {
+        let frames: Vec<Rc<Term>> = self
+            .frames
+            .iter()
+            .rev()
+            .skip(skip)
+            .map(|frame| {
+                Term::Tuple(vec![
+                    Term::Atom(frame.ident.module.name).into(),
+                    Term::Atom(frame.ident.name.name).into(),
+                    Term::new_i64(frame.ident.arity as i64).into(),
+                    Term::Nil.into(),
+                ])
+                .into()
+            })
+            .collect();
+        Term::slice_to_list(&frames, Term::Nil.into())
+    }
+}
@@ -1,6 +1,8 @@
 use ::std::rc::Rc;
+use std::cell::RefCell;
 use std::cmp::{Ord, Ordering};
 use std::collections::HashMap;
+use std::fmt;
 use std::hash::{Hash, Hasher};
 
 use libeir_intern::{LocalInternedString, Symbol};
@@ -12,9 +14,17 @@ use libeir_util_binary::{BitSlice, BitVec};
 use ::num_bigint::BigInt;
 use ::num_traits::cast::ToPrimitive;
 
+/// Pids are allocated from a counter local to each `VMState`, the
+/// first process spawned is `<0.0.0>`, the next `<0.1.0>` and so on.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
 pub struct Pid(pub usize);
 
+impl fmt::Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<0.{}.0>", self.0)
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
 pub struct Reference(pub usize);
 
@@ -99,6 +109,11 @@ impl MapTerm {
     pub fn len(&self) -> usize {
         self.map.len()
     }
+
+    /// Iterates over the entries in term order of the keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&Rc<Term>, &Rc<Term>)> {
+        self.sorted.iter().map(|(k, v)| (k, v))
+    }
 }
 impl PartialEq for MapTerm {
     fn eq(&self, other: &MapTerm) -> bool {
@@ -139,6 +154,14 @@ pub enum Term {
         bit_offset: usize,
         bit_length: usize,
     },
+    /// A binary built by `BinaryConstructPush`, the first `bit_length`
+    /// bits of `buf`. Pushing to the binary that ends where the buffer
+    /// ends extends the buffer in place, which makes building a binary
+    /// by repeated appends amortized linear.
+    AppendBinary {
+        buf: Rc<RefCell<BitVec>>,
+        bit_length: usize,
+    },
     BoundLambda {
         ident: FunctionIdent,
         block: Block,
@@ -173,6 +196,7 @@ impl Term {
             Term::ListCell(_, _) => 10,
             Term::Binary(_) => 11,
             Term::BinarySlice { .. } => 11,
+            Term::AppendBinary { .. } => 11,
             _ => panic!(),
         }
     }
@@ -182,6 +206,12 @@ impl Eq for Term {}
 impl PartialEq for Term {
     fn eq(&self, other: &Self) -> bool {
         use Term::*;
+        if let Some(l) = self.append_binary_snapshot() {
+            return l == *other;
+        }
+        if let Some(r) = other.append_binary_snapshot() {
+            return *self == r;
+        }
         match (self, other) {
             (Nil, Nil) => true,
             (Integer(l), Integer(r)) => l == r,
@@ -260,6 +290,12 @@ impl Ord for Term {
 impl PartialOrd for Term {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         use Term::*;
+        if let Some(l) = self.append_binary_snapshot() {
+            return l.partial_cmp(other);
+        }
+        if let Some(r) = other.append_binary_snapshot() {
+            return self.partial_cmp(&r);
+        }
         match (self, other) {
             (Nil, Nil) => Some(Ordering::Equal),
             (Integer(l), Integer(r)) => l.partial_cmp(r),
@@ -340,6 +376,14 @@ impl PartialOrd for Term {
     }
 }
 
+/// Copies the first `bit_length` bits of an append buffer.
+fn append_binary_bits(buf: &RefCell<BitVec>, bit_length: usize) -> BitVec {
+    let buf = buf.borrow();
+    let mut bin = BitVec::new();
+    bin.push(BitSlice::with_offset_length(&*buf, 0, bit_length));
+    bin
+}
+
 impl Hash for Term {
     fn hash<H: Hasher>(&self, state: &mut H) {
         use Term::*;
@@ -367,6 +411,8 @@ impl Hash for Term {
                 let ls = BitSlice::with_offset_length(&**buf, *bit_offset, *bit_length);
                 ls.hash(state);
             }
+            // Hashes like the `Binary` it snapshots to, as the two compare equal.
+            AppendBinary { buf, bit_length } => append_binary_bits(buf, *bit_length).hash(state),
             BoundLambda {
                 ident,
                 block,
@@ -487,6 +533,7 @@ impl Term {
         match self {
             Term::Binary(_) => TermType::Binary,
             Term::BinarySlice { .. } => TermType::Binary,
+            Term::AppendBinary { .. } => TermType::Binary,
             Term::Nil => TermType::Nil,
             Term::Pid(_) => TermType::Pid,
             Term::Reference(_) => TermType::Reference,
@@ -558,6 +605,23 @@ impl Term {
         }
     }
 
+    /// Copies an `AppendBinary` into a regular binary, other terms are
+    /// returned as is.
+    pub fn snapshot_binary(term: &Rc<Term>) -> Rc<Term> {
+        match term.append_binary_snapshot() {
+            Some(snapshot) => snapshot.into(),
+            None => term.clone(),
+        }
+    }
+
+    fn append_binary_snapshot(&self) -> Option<Term> {
+        if let Term::AppendBinary { buf, bit_length } = self {
+            Some(Term::Binary(append_binary_bits(buf, *bit_length).into()))
+        } else {
+            None
+        }
+    }
+
     pub fn as_binary(&self) -> Option<&BitVec> {
         if let Term::Binary(bin) = self {
             Some(bin)
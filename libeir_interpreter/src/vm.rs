@@ -1,9 +1,9 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 
 use crate::module::{ErlangModule, ModuleType, NativeModule};
-use crate::process::{CallExecutor, Continuation, ProcessContext, TermCall};
+use crate::process::{CallExecutor, Continuation, ProcessContext, ProcessStatus, TermCall};
 use crate::term::{Pid, Reference, Term};
 
 use libeir_intern::Symbol;
@@ -15,6 +15,19 @@ pub enum WatchType {
     Monitor(Reference),
 }
 
+/// An exception that escaped a call made through `VMState::call`.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    /// One of `error`, `exit` or `throw`.
+    pub class: Rc<Term>,
+    pub reason: Rc<Term>,
+    pub stacktrace: Rc<Term>,
+}
+
+/// Number of calls a process gets to run before the scheduler moves on
+/// to the next one.
+const REDUCTIONS: usize = 1000;
+
 #[derive(Debug)]
 pub struct ReferenceGenerator(Reference);
 impl ReferenceGenerator {
@@ -29,11 +42,37 @@ impl ReferenceGenerator {
     }
 }
 
+/// A message scheduled by `erlang:send_after/3`.
+#[derive(Debug)]
+struct Timer {
+    reference: Reference,
+    deadline: u64,
+    to: Pid,
+    message: Rc<Term>,
+}
+
 pub struct VMState {
     pub modules: HashMap<Symbol, ModuleType>,
-    pub processes: RefCell<Vec<Rc<RefCell<ProcessContext>>>>,
+    /// Exited processes stay in the table until `run_until_exit` reaps
+    /// them.
+    pub processes: RefCell<BTreeMap<Pid, Rc<RefCell<ProcessContext>>>>,
+    next_pid: Cell<usize>,
 
     pub ref_gen: RefCell<ReferenceGenerator>,
+
+    /// Virtual time in milliseconds. It only moves through
+    /// `advance_clock`, or when every process is blocked and a timer or a
+    /// receive timeout is pending, in which case it jumps to the earliest
+    /// deadline.
+    clock: Cell<u64>,
+    timers: RefCell<Vec<Timer>>,
+
+    /// Names registered with `erlang:register/2`. Entries are removed when
+    /// the process exits.
+    registry: RefCell<HashMap<Symbol, Pid>>,
+
+    /// Everything written by `io:format`, until taken by `take_output`.
+    output: RefCell<String>,
     // Hashmap of all watches a process has placed on it.
     //pub watches: RefCell<HashMap<Pid, Vec<(Pid, WatchType)>>>,
 
@@ -44,8 +83,13 @@ impl VMState {
     pub fn new() -> Self {
         VMState {
             modules: HashMap::new(),
-            processes: RefCell::new(Vec::new()),
+            processes: RefCell::new(BTreeMap::new()),
+            next_pid: Cell::new(0),
             ref_gen: RefCell::new(ReferenceGenerator::new()),
+            clock: Cell::new(0),
+            timers: RefCell::new(Vec::new()),
+            registry: RefCell::new(HashMap::new()),
+            output: RefCell::new(String::new()),
             //watches: RefCell::new(HashMap::new()),
             //mailboxes: RefCell::new(HashMap::new()),
         }
@@ -66,6 +110,28 @@ impl VMState {
         }
     }
 
+    /// Replaces an already registered Erlang module with a new version.
+    /// Any NIF overlay registered for the module is kept.
+    ///
+    /// Calls made after the replacement resolve to the new version.
+    pub fn replace_module(&mut self, module: Module) {
+        let erl_mod = ErlangModule::from_eir(module);
+        match self.modules.remove(&erl_mod.name) {
+            None => {
+                self.modules
+                    .insert(erl_mod.name, ModuleType::Erlang(erl_mod, None));
+            }
+            Some(ModuleType::Erlang(_old, overlay)) => {
+                self.modules
+                    .insert(erl_mod.name, ModuleType::Erlang(erl_mod, overlay));
+            }
+            Some(ModuleType::Native(native)) => {
+                self.modules
+                    .insert(erl_mod.name, ModuleType::Erlang(erl_mod, Some(native)));
+            }
+        }
+    }
+
     pub fn add_native_module(&mut self, module: NativeModule) {
         match self.modules.remove(&module.name) {
             None => {
@@ -94,42 +160,445 @@ impl VMState {
         self.add_native_module(crate::erl_lib::make_lists());
         self.add_native_module(crate::erl_lib::make_math());
         self.add_native_module(crate::erl_lib::make_maps());
+        self.add_native_module(crate::erl_lib::make_io());
+    }
+
+    pub fn write_output(&self, string: &str) {
+        self.output.borrow_mut().push_str(string);
+    }
+
+    /// Takes everything written to standard output so far.
+    pub fn take_output(&self) -> String {
+        std::mem::replace(&mut *self.output.borrow_mut(), String::new())
+    }
+
+    /// Checks whether `ident` resolves to a function, either in an
+    /// Erlang module, its NIF overlay or a native module.
+    pub fn function_exists(&self, ident: &FunctionIdent) -> bool {
+        match self.modules.get(&ident.module.name) {
+            Some(ModuleType::Erlang(erl, overlay)) => {
+                erl.has_function(ident)
+                    || overlay.as_ref().map(|n| n.has_fun(ident)).unwrap_or(false)
+            }
+            Some(ModuleType::Native(native)) => native.has_fun(ident),
+            None => false,
+        }
     }
 
     pub fn call(
         &mut self,
         fun: &FunctionIdent,
         args: &[Term],
-    ) -> Result<Rc<Term>, (Rc<Term>, Rc<Term>, Rc<Term>)> {
-        let self_pid = {
-            let processes = self.processes.borrow();
-            Pid(processes.len())
-        };
-
-        let mut process = ProcessContext::new(self_pid);
-
+    ) -> Result<Rc<Term>, Exception> {
         let fun_term = Term::CapturedFunction { ident: fun.clone() };
+        let args: Vec<Rc<Term>> = args.iter().cloned().map(|v| v.into()).collect();
 
-        let mut n_args = Vec::new();
-        n_args.push(Term::ReturnOk.into());
-        n_args.push(Term::ReturnThrow.into());
-        n_args.extend(args.iter().cloned().map(|v| v.into()));
+        let pid = self.spawn(fun_term.into(), &args);
+        self.run_until_exit(pid)
+    }
 
+    /// Calls a function term (a captured function or a lambda) with the
+    /// given arguments within an existing process, running it to
+    /// completion. This is what native functions use to call back into
+    /// Erlang code.
+    pub fn call_term(
+        &self,
+        process: &mut ProcessContext,
+        fun: Rc<Term>,
+        args: &[Rc<Term>],
+    ) -> Result<Rc<Term>, (Rc<Term>, Rc<Term>, Rc<Term>)> {
         let mut continuation = TermCall {
-            fun: fun_term.into(),
-            args: n_args,
+            fun,
+            args: with_return_conts(args),
         };
 
+        // Every call is a step of this loop, reusing the same executor.
+        // Erlang calls never recurse on the native stack.
+        let depth = process.stack.len();
         let mut executor = CallExecutor::new();
         loop {
-            match executor.run(self, &mut process, continuation) {
+            match executor.run(self, process, continuation) {
                 Continuation::Term(call) => continuation = call,
+                Continuation::Wait(_) => {
+                    // No other process can run until the native returns,
+                    // nothing could ever deliver the message. The receive
+                    // is abandoned and raised as an error.
+                    let trace = process.stack.trace(0);
+                    process.stack.truncate(depth);
+                    process.mailbox.receive_timeout();
+                    return Err((
+                        Term::new_atom("error").into(),
+                        Term::new_atom("receive_in_native_callback").into(),
+                        trace,
+                    ));
+                }
                 Continuation::ReturnOk(ret) => return Ok(ret),
                 Continuation::ReturnThrow(r1, r2, r3) => return Err((r1, r2, r3)),
             }
         }
     }
 
+    /// Creates a new process calling `fun` with `args`. It is not run
+    /// until the scheduler gets to it.
+    pub fn spawn(&self, fun: Rc<Term>, args: &[Rc<Term>]) -> Pid {
+        // Pids are not reused once a process is reaped.
+        let pid = Pid(self.next_pid.get());
+        self.next_pid.set(pid.0 + 1);
+
+        let call = TermCall {
+            fun,
+            args: with_return_conts(args),
+        };
+        let process = ProcessContext::new(pid, call);
+        self.processes
+            .borrow_mut()
+            .insert(pid, Rc::new(RefCell::new(process)));
+
+        pid
+    }
+
+    /// Runs `f` on the process with the given pid. `current` is the
+    /// process that is executing, which is borrowed by the scheduler
+    /// and therefore not reachable through the process table.
+    pub fn with_process<F, R>(&self, current: &mut ProcessContext, pid: Pid, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut ProcessContext) -> R,
+    {
+        if pid == current.pid {
+            return Some(f(current));
+        }
+        let process = self.processes.borrow().get(&pid).cloned()?;
+        let mut process = process.borrow_mut();
+        Some(f(&mut *process))
+    }
+
+    /// Sends a message from `current` to `to`. Messages to dead processes
+    /// are dropped.
+    pub fn send(&self, current: &mut ProcessContext, to: Pid, message: Rc<Term>) {
+        self.with_process(current, to, |process| {
+            if process.is_alive() {
+                process.deliver(message);
+            }
+        });
+    }
+
+    /// Links `current` with `other`. Returns false if `other` is not
+    /// alive.
+    pub fn link(&self, current: &mut ProcessContext, other: Pid) -> bool {
+        if other == current.pid {
+            return true;
+        }
+        let self_pid = current.pid;
+        let alive = self
+            .with_process(current, other, |process| {
+                if process.is_alive() {
+                    process.links.insert(self_pid);
+                }
+                process.is_alive()
+            })
+            .unwrap_or(false);
+        if alive {
+            current.links.insert(other);
+        }
+        alive
+    }
+
+    pub fn unlink(&self, current: &mut ProcessContext, other: Pid) {
+        let self_pid = current.pid;
+        current.links.remove(&other);
+        self.with_process(current, other, |process| {
+            process.links.remove(&self_pid);
+        });
+    }
+
+    /// Registers `pid` under `name`. Fails if the name is taken, the
+    /// process already has a name or is not alive.
+    pub fn register(&self, current: &mut ProcessContext, name: Symbol, pid: Pid) -> bool {
+        {
+            let registry = self.registry.borrow();
+            if registry.contains_key(&name) || registry.values().any(|p| *p == pid) {
+                return false;
+            }
+        }
+        let alive = self
+            .with_process(current, pid, |process| process.is_alive())
+            .unwrap_or(false);
+        if alive {
+            self.registry.borrow_mut().insert(name, pid);
+        }
+        alive
+    }
+
+    /// Removes the registration of `name`. Returns false if it was not
+    /// registered.
+    pub fn unregister(&self, name: Symbol) -> bool {
+        self.registry.borrow_mut().remove(&name).is_some()
+    }
+
+    pub fn whereis(&self, name: Symbol) -> Option<Pid> {
+        self.registry.borrow().get(&name).cloned()
+    }
+
+    /// The current virtual time, in milliseconds.
+    pub fn now(&self) -> u64 {
+        self.clock.get()
+    }
+
+    /// Schedules `message` to be sent to `to` once the clock reaches
+    /// `time` milliseconds from now.
+    pub fn start_timer(&self, time: u64, to: Pid, message: Rc<Term>) -> Reference {
+        let reference = self.ref_gen.borrow_mut().next();
+        self.timers.borrow_mut().push(Timer {
+            reference,
+            deadline: self.clock.get() + time,
+            to,
+            message,
+        });
+        reference
+    }
+
+    /// Removes a pending timer, returning the milliseconds it had left.
+    /// Returns `None` if the timer already fired or doesn't exist.
+    pub fn cancel_timer(&self, reference: Reference) -> Option<u64> {
+        let mut timers = self.timers.borrow_mut();
+        let idx = timers.iter().position(|t| t.reference == reference)?;
+        let timer = timers.remove(idx);
+        Some(timer.deadline - self.clock.get())
+    }
+
+    /// Moves the clock forward, delivering the messages of every timer
+    /// that is due and timing out receives whose deadline passed. Must
+    /// not be called while a process is running.
+    pub fn advance_clock(&self, time: u64) {
+        self.clock.set(self.clock.get() + time);
+        self.fire_deadlines();
+    }
+
+    /// Delivers the messages of all due timers, earliest deadline first,
+    /// then wakes the processes whose receive timed out. Returns false
+    /// if nothing was due.
+    fn fire_deadlines(&self) -> bool {
+        let now = self.clock.get();
+        let (mut due, pending): (Vec<Timer>, Vec<Timer>) = self
+            .timers
+            .borrow_mut()
+            .drain(..)
+            .partition(|t| t.deadline <= now);
+        *self.timers.borrow_mut() = pending;
+
+        // Stable, timers with the same deadline fire in creation order
+        due.sort_by_key(|t| t.deadline);
+        for timer in due.iter() {
+            let process = match self.processes.borrow().get(&timer.to) {
+                Some(process) => process.clone(),
+                None => continue,
+            };
+            let mut process = process.borrow_mut();
+            if process.is_alive() {
+                process.deliver(timer.message.clone());
+            }
+        }
+
+        let mut timed_out = false;
+        for process in self.processes.borrow().values() {
+            let mut process = process.borrow_mut();
+            if let ProcessStatus::Waiting(_) = process.status {
+                if process.mailbox.receive_should_timeout(now) {
+                    process.wake();
+                    timed_out = true;
+                }
+            }
+        }
+
+        !due.is_empty() || timed_out
+    }
+
+    /// The earliest pending timer or receive timeout.
+    fn next_deadline(&self) -> Option<u64> {
+        let timers = self.timers.borrow().iter().map(|t| t.deadline).min();
+        let receives = self
+            .processes
+            .borrow()
+            .values()
+            .filter_map(|process| {
+                let process = process.borrow();
+                match process.status {
+                    ProcessStatus::Waiting(_) => process.mailbox.receive_deadline(),
+                    _ => None,
+                }
+            })
+            .min();
+        timers.into_iter().chain(receives).min()
+    }
+
+    /// When every process is blocked, jumps the clock to the earliest
+    /// deadline and fires it. Returns false if there was none.
+    fn fire_next_deadline(&self) -> bool {
+        match self.next_deadline() {
+            Some(deadline) => {
+                self.clock.set(self.clock.get().max(deadline));
+                self.fire_deadlines()
+            }
+            None => false,
+        }
+    }
+
+    fn run_until_exit(&self, pid: Pid) -> Result<Rc<Term>, Exception> {
+        loop {
+            self.reap(pid);
+
+            let process = self.processes.borrow()[&pid].clone();
+            let exited = match &process.borrow().status {
+                ProcessStatus::Exited(result) => Some(result.clone()),
+                _ => None,
+            };
+            if let Some(result) = exited {
+                self.processes.borrow_mut().remove(&pid);
+                return result;
+            }
+
+            if !self.run_round() && !self.fire_next_deadline() {
+                self.deadlock(pid);
+            }
+        }
+    }
+
+    /// Removes every exited process from the table, except `keep`.
+    fn reap(&self, keep: Pid) {
+        let mut processes = self.processes.borrow_mut();
+        let exited: Vec<Pid> = processes
+            .iter()
+            .filter(|(pid, process)| **pid != keep && !process.borrow().is_alive())
+            .map(|(pid, _)| *pid)
+            .collect();
+        for pid in exited {
+            processes.remove(&pid);
+        }
+    }
+
+    /// Every process is blocked and nothing is pending that could wake
+    /// one. The process that is waited on exits with `deadlock`.
+    fn deadlock(&self, pid: Pid) {
+        let reason: Rc<Term> = Term::new_atom("deadlock").into();
+        {
+            let process = self.processes.borrow()[&pid].clone();
+            let mut process = process.borrow_mut();
+            let stacktrace = process.stack.trace(0);
+            process.status = ProcessStatus::Exited(Err(Exception {
+                class: Term::new_atom("exit").into(),
+                reason: reason.clone(),
+                stacktrace,
+            }));
+        }
+        self.propagate_exit(pid, reason);
+    }
+
+    /// Runs every runnable process for a slice of reductions.
+    /// Returns false if no process was runnable.
+    fn run_round(&self) -> bool {
+        let mut ran = false;
+
+        // Processes spawned during the round wait for the next one
+        let pids: Vec<Pid> = self.processes.borrow().keys().cloned().collect();
+        for pid in pids {
+            let process_rc = self.processes.borrow()[&pid].clone();
+            let mut process = process_rc.borrow_mut();
+
+            let call = match std::mem::replace(&mut process.status, ProcessStatus::Running) {
+                ProcessStatus::Runnable(call) => call,
+                status => {
+                    process.status = status;
+                    continue;
+                }
+            };
+            ran = true;
+
+            let exit = self.run_reductions(&mut process, call);
+            drop(process);
+
+            if let Some(reason) = exit {
+                self.propagate_exit(pid, reason);
+            }
+        }
+
+        ran
+    }
+
+    /// Returns the exit reason if the process exited.
+    fn run_reductions(&self, process: &mut ProcessContext, mut call: TermCall) -> Option<Rc<Term>> {
+        let mut executor = CallExecutor::new();
+        for _ in 0..REDUCTIONS {
+            match executor.run(self, process, call) {
+                Continuation::Term(next) => call = next,
+                Continuation::Wait(retry) => {
+                    process.status = ProcessStatus::Waiting(retry);
+                    return None;
+                }
+                Continuation::ReturnOk(ret) => {
+                    process.status = ProcessStatus::Exited(Ok(ret));
+                    return Some(Term::new_atom("normal").into());
+                }
+                Continuation::ReturnThrow(typ, reason, trace) => {
+                    let exit_reason = exit_reason(&typ, &reason, &trace);
+                    process.status = ProcessStatus::Exited(Err(Exception {
+                        class: typ,
+                        reason,
+                        stacktrace: trace,
+                    }));
+                    return Some(exit_reason);
+                }
+            }
+        }
+        process.status = ProcessStatus::Runnable(call);
+        None
+    }
+
+    /// Sends exit signals to every process linked to `pid`.
+    ///
+    /// Processes trapping exits receive a `{'EXIT', Pid, Reason}`
+    /// message, others exit with the same reason unless it is `normal`.
+    fn propagate_exit(&self, pid: Pid, reason: Rc<Term>) {
+        self.registry.borrow_mut().retain(|_, p| *p != pid);
+
+        let links = {
+            let process = self.processes.borrow()[&pid].clone();
+            let mut process = process.borrow_mut();
+            std::mem::replace(&mut process.links, Default::default())
+        };
+
+        let normal = reason.as_atom() == Some(Symbol::intern("normal"));
+
+        for linked in links {
+            let process = match self.processes.borrow().get(&linked) {
+                Some(process) => process.clone(),
+                None => continue,
+            };
+            let mut process = process.borrow_mut();
+            process.links.remove(&pid);
+
+            if !process.is_alive() {
+                continue;
+            }
+
+            if process.mailbox.get_trap_exits() {
+                let message = Term::Tuple(vec![
+                    Term::new_atom("EXIT").into(),
+                    Term::Pid(pid).into(),
+                    reason.clone(),
+                ]);
+                process.deliver(message.into());
+            } else if !normal {
+                process.status = ProcessStatus::Exited(Err(Exception {
+                    class: Term::new_atom("exit").into(),
+                    reason: reason.clone(),
+                    stacktrace: Term::Nil.into(),
+                }));
+                drop(process);
+                self.propagate_exit(linked, reason.clone());
+            }
+        }
+    }
+
     //pub fn call(&mut self, module_name: &str, fun_name: &str, args: Vec<Term>)
     //            -> CallReturn {
     //    let fun_ident = FunctionIdent {
@@ -192,3 +661,25 @@ impl VMState {
     //    process.return_val.take().unwrap()
     //}
 }
+
+fn with_return_conts(args: &[Rc<Term>]) -> Vec<Rc<Term>> {
+    let mut n_args = Vec::new();
+    n_args.push(Term::ReturnOk.into());
+    n_args.push(Term::ReturnThrow.into());
+    n_args.extend(args.iter().cloned());
+    n_args
+}
+
+/// The reason a process exits with when it terminates with an
+/// uncaught exception.
+fn exit_reason(typ: &Rc<Term>, reason: &Rc<Term>, stack: &Rc<Term>) -> Rc<Term> {
+    let stack = stack.clone();
+    match typ.as_atom() {
+        Some(typ) if typ == Symbol::intern("exit") => reason.clone(),
+        Some(typ) if typ == Symbol::intern("throw") => {
+            let nocatch = Term::Tuple(vec![Term::new_atom("nocatch").into(), reason.clone()]);
+            Term::Tuple(vec![nocatch.into(), stack]).into()
+        }
+        _ => Term::Tuple(vec![reason.clone(), stack]).into(),
+    }
+}
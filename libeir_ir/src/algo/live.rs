@@ -5,11 +5,36 @@ use crate::{Block, Value};
 
 use cranelift_bforest::{BoundSet, Set, SetForest};
 use libeir_util_datastructures::aux_traits::{AuxDebug, AuxImpl, HasAux};
+use libeir_util_datastructures::pooled_entity_set::BoundEntitySet;
 
 impl Function {
     pub fn live_values(&self) -> LiveValues {
         calculate_live_values(self)
     }
+
+    /// Values read within the given region of blocks, but defined by
+    /// blocks outside of it. These are the values that would need to be
+    /// passed in if the region were extracted into its own function.
+    ///
+    /// Like liveness, only block arguments are considered values here,
+    /// constants and references to blocks are not.
+    pub fn region_free_values(&self, blocks: &BoundEntitySet<Block>) -> Vec<Value> {
+        let mut free = Vec::new();
+        for block in blocks.iter() {
+            for read in self.block_reads(block) {
+                self.value_walk_nested_values::<_, ()>(*read, &mut |v| {
+                    if let Some((def, _)) = self.value_argument(v) {
+                        if !blocks.contains(def) && !free.contains(&v) {
+                            free.push(v);
+                        }
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            }
+        }
+        free
+    }
 }
 
 /// # Value liveness calculation
@@ -31,6 +56,9 @@ pub struct LiveValues {
     /// Values that need to exist within every block.
     /// After block arguments, before operation.
     live_in: HashMap<Block, Set<Value>>,
+    /// Values that need to exist after every block.
+    /// After operation, the union of `live_at` of the successors.
+    live_out: HashMap<Block, Set<Value>>,
     /// The pool where `ebb_live` and `flow_live` is allocated.
     forest: SetForest<Value>,
 }
@@ -46,6 +74,7 @@ impl<C: HasAux<SetForest<Value>>> AuxDebug<C> for LiveValues {
         let mut b = f.debug_struct("LiveValues");
         b.field("live_at", &AuxImpl(&self.live_at, self));
         b.field("live_in", &AuxImpl(&self.live_in, self));
+        b.field("live_out", &AuxImpl(&self.live_out, self));
         b.finish()
     }
 }
@@ -63,6 +92,9 @@ impl LiveValues {
     pub fn live_in<'a>(&'a self, block: Block) -> BoundSet<'a, Value, ()> {
         self.live_in[&block].bind(&self.forest, &())
     }
+    pub fn live_out<'a>(&'a self, block: Block) -> BoundSet<'a, Value, ()> {
+        self.live_out[&block].bind(&self.forest, &())
+    }
 
     pub fn is_live_at(&self, block: Block, value: Value) -> bool {
         self.live_at[&block].contains(value, &self.forest, &())
@@ -70,6 +102,9 @@ impl LiveValues {
     pub fn is_live_in(&self, block: Block, value: Value) -> bool {
         self.live_in[&block].contains(value, &self.forest, &())
     }
+    pub fn is_live_out(&self, block: Block, value: Value) -> bool {
+        self.live_out[&block].contains(value, &self.forest, &())
+    }
 }
 
 fn dataflow_pass(
@@ -77,6 +112,7 @@ fn dataflow_pass(
     pool: &mut SetForest<Value>,
     live: &mut HashMap<Block, Set<Value>>,
     live_in: &mut HashMap<Block, Set<Value>>,
+    live_out: &mut HashMap<Block, Set<Value>>,
 ) -> bool {
     let graph = fun.block_graph();
     let mut visitor = graph.dfs_post_order();
@@ -94,6 +130,15 @@ fn dataflow_pass(
             }
         }
 
+        // Update the live_out values
+        if !live_out.contains_key(&block) {
+            live_out.insert(block, Set::new());
+        }
+        live_out
+            .get_mut(&block)
+            .unwrap()
+            .union_from(&set, pool, &());
+
         // Add the reads for the block OP to the current set
         for read in fun.block_reads(block) {
             // Only insert if it actually is a variable, not a block or constant
@@ -140,10 +185,11 @@ pub fn calculate_live_values(fun: &Function) -> LiveValues {
 
     let mut live_at: HashMap<Block, Set<Value>> = HashMap::new();
     let mut live_in: HashMap<Block, Set<Value>> = HashMap::new();
+    let mut live_out: HashMap<Block, Set<Value>> = HashMap::new();
 
     // Iterate dataflow until all dependencies have been resolved
     loop {
-        let res = dataflow_pass(fun, &mut forest, &mut live_at, &mut live_in);
+        let res = dataflow_pass(fun, &mut forest, &mut live_at, &mut live_in, &mut live_out);
         if res {
             break;
         }
@@ -163,6 +209,7 @@ pub fn calculate_live_values(fun: &Function) -> LiveValues {
         forest,
         live_at,
         live_in,
+        live_out,
     }
 }
 
@@ -251,4 +298,71 @@ a'foo':a'bar'/1 {
         assert!(b6_live.iter().count() == 1);
         assert!(b6_live.contains(b1_ret));
     }
+
+    #[test]
+    fn test_live_in_out() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'fun_shadowing'/1 {
+    entry(%ret, %thr, %A):
+        b1();
+    b1():
+        inner(ret, thr, %A);
+    inner(%iret, %ithr, %B):
+        %iret(%B);
+
+    ret(%rv):
+        %ret(%rv);
+    thr(%rt1, %rt2, %rt3):
+        %thr(%rt1, %rt2, %rt3);
+}
+",
+        );
+
+        let b1 = map.get_block("b1");
+        let inner = map.get_block("inner");
+
+        let fun_ret = map.get_value("ret");
+        let fun_a = map.get_value("A");
+        let inner_b = map.get_value("B");
+
+        let live = ir.live_values();
+
+        // `B` is bound by `inner`, it is only live within it
+        assert!(live.is_live_in(inner, inner_b));
+        assert!(!live.is_live_at(inner, inner_b));
+        assert!(!live.is_live_out(inner, inner_b));
+
+        // `A` is passed as `B`, so it is not needed after `b1`
+        assert!(live.is_live_in(b1, fun_a));
+        assert!(!live.is_live_out(b1, fun_a));
+        assert!(live.is_live_out(b1, fun_ret));
+        assert!(live.live_out(b1).iter().count() == 2);
+    }
+
+    #[test]
+    fn test_region_free_values() {
+        use libeir_util_datastructures::pooled_entity_set::{EntitySet, EntitySetPool};
+
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        b2(%a);
+    b2(%b):
+        b3();
+    b3():
+        %ret(%a, %b);
+}
+",
+        );
+
+        let mut pool = EntitySetPool::new();
+        let mut region = EntitySet::new();
+        region.insert(map.get_block("b2"), &mut pool);
+        region.insert(map.get_block("b3"), &mut pool);
+
+        let free = ir.region_free_values(&region.bind(&pool));
+        assert!(free == vec![map.get_value("ret"), map.get_value("a")]);
+    }
 }
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+
+use crate::{Block, Function};
+
+impl Function {
+    /// Finds the headers of all loops in the function.
+    ///
+    /// A loop header is the target of a back edge, an edge from a block
+    /// to one of its own dominators. Blocks unreachable from the entry
+    /// are never considered.
+    pub fn loop_headers(&self) -> HashSet<Block> {
+        let block_graph = self.block_graph();
+        let doms = block_graph.dominators();
+
+        let mut headers = HashSet::new();
+        for block in block_graph.dfs_iter() {
+            let dominators: HashSet<Block> = match doms.dominators(block) {
+                Some(iter) => iter.collect(),
+                None => continue,
+            };
+            for target in block_graph.outgoing(block) {
+                if dominators.contains(&target) {
+                    headers.insert(target);
+                }
+            }
+        }
+
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_loop_header() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        b2(%a, []);
+    b2(%b, %c):
+        b3();
+    b3():
+        b4();
+    b4():
+        b5(b6, %c);
+    b5(%e, %f):
+        b2(%e, %f);
+    b6():
+        %ret();
+}
+",
+        );
+
+        let b2 = map.get_block("b2");
+
+        let headers = ir.loop_headers();
+        assert!(headers.len() == 1);
+        assert!(headers.contains(&b2));
+    }
+
+    #[test]
+    fn test_no_loop() {
+        let ir = crate::parse_function_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        b2();
+    b2():
+        %ret(%a);
+}
+",
+        );
+
+        assert!(ir.loop_headers().is_empty());
+    }
+}
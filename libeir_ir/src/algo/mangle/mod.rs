@@ -4,9 +4,9 @@ use bumpalo::{collections::Vec as BVec, Bump};
 
 use libeir_diagnostics::SourceSpan;
 
-use crate::Block;
 use crate::ValueKind;
-use crate::{Function, FunctionBuilder};
+use crate::{Block, PrimOpKind, Value};
+use crate::{Function, FunctionBuilder, FunctionIdent};
 
 mod receiver;
 use receiver::MangleReceiver;
@@ -293,6 +293,9 @@ impl Mangler {
             |mang: &mut Mangler, recv: &mut R, from_block: MangleBlock, to_block: ToBlock| {
                 let to_op = recv.map_block_op(from_block);
                 let loc = from_block.map_fun(recv, |f, b| f.block_location(b)).inner();
+                let read_spans = from_block
+                    .map_fun(recv, |f, b| f.block_read_spans(b).to_vec())
+                    .inner();
 
                 // Get and map reads to new values
                 mang.value_buf.clear();
@@ -316,6 +319,7 @@ impl Mangler {
 
                 data.op = Some(to_op);
                 data.location = loc;
+                data.read_spans = read_spans;
 
                 for read in mang.value_buf.iter() {
                     data.reads
@@ -415,3 +419,73 @@ impl Mangler {
         dest
     }
 }
+
+/// Copies the whole body of `from` into `to` under the name `new_ident`,
+/// returns the copied entry block.
+///
+/// Captures of `from` itself are rewritten to capture `new_ident`.
+/// Constants and captures are deduplicated in `to`, so the ones that
+/// already exist there are reused. If `to` has no entry block yet, the
+/// copy becomes its entry and `to` is renamed to `new_ident`.
+///
+/// The clauses of `Case` operations are copied into the pattern
+/// container of `to`.
+pub fn copy_function(from: &Function, to: &mut Function, new_ident: FunctionIdent) -> Block {
+    let ident = from.ident();
+    assert!(ident.arity == new_ident.arity);
+
+    let self_mfa = [
+        from.cons().get(ident.module),
+        from.cons().get(ident.name),
+        from.cons().get(ident.arity),
+    ];
+    let is_self_capture = |value: Value| match from.value_primop(value) {
+        Some(prim) if *from.primop_kind(prim) == PrimOpKind::CaptureFunction => from
+            .primop_reads(prim)
+            .iter()
+            .zip(self_mfa.iter())
+            .all(|(read, expected)| expected.is_some() && from.value_const(*read) == *expected),
+        _ => false,
+    };
+
+    let mut self_captures = BTreeSet::new();
+    for block in from.block_graph().dfs_iter() {
+        for read in from.block_reads(block) {
+            from.value_walk_nested_values::<_, ()>(*read, &mut |value| {
+                if is_self_capture(value) {
+                    self_captures.insert(value);
+                }
+                Ok(())
+            })
+            .unwrap();
+        }
+    }
+
+    let set_entry = !to.has_entry();
+    let new_entry = {
+        let mut b = to.builder();
+
+        let mut mangler = Mangler::new();
+        mangler.start(FromT(from.block_entry()));
+        for value in self_captures {
+            let span = from
+                .value_locations(value)
+                .and_then(|spans| spans.first().copied())
+                .unwrap_or(SourceSpan::UNKNOWN);
+            let capture =
+                b.prim_capture_function(span, new_ident.module, new_ident.name, new_ident.arity);
+            mangler.add_rename_nofollow(FromT(value), ToT(capture));
+        }
+
+        let new_entry = mangler.run_across(from, &mut b);
+        if set_entry {
+            b.block_set_entry(new_entry);
+        }
+        new_entry
+    };
+
+    if set_entry {
+        to.set_ident(new_ident);
+    }
+    new_entry
+}
@@ -1,7 +1,9 @@
+use cranelift_entity::EntityList;
+
 use crate::OpKind;
 use crate::{Function, FunctionBuilder};
 
-use super::{MangleBlock, MangleValue, ToValue};
+use super::{FromT, MangleBlock, MangleTarget, MangleValue, ToT, ToValue};
 
 /// Trait used to generalize a single mangling implementation over
 /// both mangling within a single function container, and across
@@ -78,13 +80,39 @@ impl<'b, 'c> MangleReceiver<'b> for CopyMangleReceiver<'c, 'b> {
     fn to_fun<'a>(&'a self) -> &'a Function {
         self.to.fun()
     }
-    fn map_const(&mut self, _val: MangleValue) -> ToValue {
-        unimplemented!()
+    fn map_const(&mut self, val: MangleValue) -> ToValue {
+        match val {
+            MangleTarget::From(FromT(val)) => {
+                let constant = self.from.value_const(val).unwrap();
+                let new = self.to.cons_mut().import(self.from.cons(), constant);
+                ToT(self.to.value(new))
+            }
+            MangleTarget::To(val) => val,
+        }
     }
-    fn map_free_value(&mut self, _val: MangleValue) -> ToValue {
-        panic!()
+    fn map_free_value(&mut self, val: MangleValue) -> ToValue {
+        // Values free in the source mean nothing in the destination, they
+        // have to be renamed
+        val.to()
+            .expect("free value of the source function was not renamed")
     }
-    fn map_block_op(&mut self, _block: MangleBlock) -> OpKind {
-        unimplemented!()
+    fn map_block_op(&mut self, block: MangleBlock) -> OpKind {
+        let op = block.map_fun(&*self, |f, b| f.block_kind(b).unwrap().clone());
+        match op {
+            // Clauses live in the pattern container of the source, they
+            // are copied in the same order so the reads stay valid.
+            MangleTarget::From(FromT(OpKind::Case { clauses })) => {
+                let from = self.from;
+                let mut new_clauses = EntityList::new();
+                for clause in clauses.as_slice(&from.pool.clause) {
+                    let new = self.to.pat_copy_from(from, *clause);
+                    new_clauses.push(new, &mut self.to.fun_mut().pool.clause);
+                }
+                OpKind::Case {
+                    clauses: new_clauses,
+                }
+            }
+            op => op.inner(),
+        }
     }
 }
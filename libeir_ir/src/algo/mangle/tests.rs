@@ -1,3 +1,5 @@
+use libeir_intern::Ident;
+
 use crate::{NilTerm, StandardFormatConfig};
 
 use super::Mangler;
@@ -228,3 +230,67 @@ a'foo':a'bar'/0 {
     //    ),
     //}
 }
+
+#[test]
+fn copy_function_into_caller() {
+    let callee = crate::parse_function_unwrap(
+        "
+a'foo':a'loop'/1 {
+    entry(%ret, %thr, %x):
+        b1(%x);
+    b1(%y):
+        %fun = a'foo':a'loop'/1;
+        %fun(%y) => %ret except %thr;
+}
+",
+    );
+    let (mut caller, map) = crate::parse_function_map_unwrap(
+        "
+a'bar':a'main'/1 {
+    entry(%ret, %thr, %x):
+        %ret(%x);
+}
+",
+    );
+
+    let new_ident = crate::FunctionIdent {
+        module: Ident::from_str("bar"),
+        name: Ident::from_str("loop_copy"),
+        arity: 1,
+    };
+    let copied = super::copy_function(&callee, &mut caller, new_ident);
+    assert!(caller.ident().name.as_str().get() == "main");
+
+    let entry = map.get_block("entry");
+    let mut b = caller.builder();
+    b.block_clear(entry);
+    let args = [
+        map.get_value("ret"),
+        map.get_value("thr"),
+        map.get_value("x"),
+    ];
+    b.op_call_flow(entry, copied, &args);
+
+    assert!(caller.block_graph().dfs_iter().any(|block| block == copied));
+
+    let mut errors = Vec::new();
+    caller.validate(&mut errors);
+    assert_eq!(errors.len(), 0, "{:#?}", errors);
+
+    let after = crate::parse_function_unwrap(
+        "
+a'bar':a'main'/1 {
+    entry(%ret, %thr, %x):
+        b1(%ret, %thr, %x);
+    b1(%r, %t, %a):
+        b2(%a);
+    b2(%y):
+        %fun = a'bar':a'loop_copy'/1;
+        %fun(%y) => %r except %t;
+}
+",
+    );
+    assert!(caller
+        .graph_eq(caller.block_entry(), &after, after.block_entry())
+        .is_ok());
+}
@@ -1,6 +1,9 @@
 pub mod equality;
 pub mod func_tree;
 pub mod live;
+pub mod loops;
 pub mod mangle;
+pub mod nonterminating;
 pub mod op_branches;
+pub mod outline;
 pub mod validate;
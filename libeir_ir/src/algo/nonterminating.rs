@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use crate::{Block, CallKind, Const, Function, OpKind, PrimOpKind, Value, ValueKind};
+
+impl Function {
+    /// Finds tail calls of the function to itself that forward the entry
+    /// arguments unchanged, in functions that have no other way to return.
+    /// Once called, such a function never terminates.
+    ///
+    /// This is a heuristic meant for warnings. It misses most loops, and
+    /// doesn't account for side effects between the entry and the call.
+    pub fn detect_trivial_nonterminating(&self) -> Vec<Block> {
+        let entry = self.block_entry();
+        let entry_args = self.block_args(entry);
+        let ret = entry_args[0];
+        let params = &entry_args[2..];
+
+        let calls: HashSet<Block> = self
+            .block_iter()
+            .filter(|block| self.is_forwarding_self_call(*block, ret, params))
+            .collect();
+        if calls.is_empty() {
+            return Vec::new();
+        }
+
+        // Walk everything reachable from the entry, except the return
+        // continuations of the self calls, which are never taken. If the
+        // return continuation of the function is reachable it may return.
+        let mut seen = HashSet::new();
+        let mut stack = vec![entry];
+        while let Some(block) = stack.pop() {
+            if !seen.insert(block) {
+                continue;
+            }
+            for (idx, read) in self.block_reads(block).iter().enumerate() {
+                if idx == 1 && calls.contains(&block) {
+                    continue;
+                }
+                if *read == ret {
+                    return Vec::new();
+                }
+                if let Some(target) = self.value_block(*read) {
+                    stack.push(target);
+                }
+            }
+        }
+
+        let mut found: Vec<Block> = calls.into_iter().filter(|b| seen.contains(b)).collect();
+        found.sort();
+        found
+    }
+
+    fn is_forwarding_self_call(&self, block: Block, ret: Value, params: &[Value]) -> bool {
+        match self.block_kind(block) {
+            Some(OpKind::Call(CallKind::Function)) => (),
+            _ => return false,
+        }
+        let reads = self.block_reads(block);
+        if reads[1] != ret && !self.block_is_tail_position(block) {
+            return false;
+        }
+
+        let callee = match self.value_primop(reads[0]) {
+            Some(prim) if *self.primop_kind(prim) == PrimOpKind::CaptureFunction => prim,
+            _ => return false,
+        };
+        let ident = self.ident();
+        let is_const = |value: Value, expected: Option<Const>| {
+            expected.is_some() && self.value_const(value) == expected
+        };
+        let mfa = self.primop_reads(callee);
+        let is_self = is_const(mfa[0], self.cons().get(ident.module))
+            && is_const(mfa[1], self.cons().get(ident.name))
+            && is_const(mfa[2], self.cons().get(ident.arity));
+
+        is_self
+            && reads.len() == params.len() + 3
+            && reads[3..]
+                .iter()
+                .zip(params)
+                .all(|(arg, param)| self.forwarded_value(*arg) == *param)
+    }
+
+    /// Follows a block argument back to the value passed for it, as long
+    /// as every caller jumps to the block directly with the same value.
+    fn forwarded_value(&self, mut value: Value) -> Value {
+        let mut seen = HashSet::new();
+        while seen.insert(value) {
+            let (block, idx) = match self.value_kind(value) {
+                ValueKind::Argument(block, idx) => (block, idx),
+                _ => break,
+            };
+            let callers = self.value_usages(self.block_value(block));
+            let mut sources = callers
+                .iter()
+                .map(|caller| self.passed_argument(caller, block, idx));
+            match sources.next() {
+                Some(Some(source)) if sources.all(|s| s == Some(source)) => value = source,
+                _ => break,
+            }
+        }
+        value
+    }
+
+    /// The value `caller` passes for argument `idx` when jumping to
+    /// `target`.
+    fn passed_argument(&self, caller: Block, target: Block, idx: usize) -> Option<Value> {
+        let reads = self.block_reads(caller);
+        if reads.first() != Some(&self.block_value(target)) {
+            return None;
+        }
+        match self.block_kind(caller)? {
+            OpKind::Call(CallKind::ControlFlow) => reads.get(idx + 1).copied(),
+            OpKind::UnpackValueList(_) => {
+                let list = self.value_primop(reads[1])?;
+                match self.primop_kind(list) {
+                    PrimOpKind::ValueList => self.primop_reads(list).get(idx).copied(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn forwarding_self_call() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'loop'/1 {
+    entry(%ret, %thr, %x):
+        b1(%x);
+    b1(%y):
+        %fun = a'foo':a'loop'/1;
+        %fun(%y) => %ret except %thr;
+}
+",
+        );
+        assert!(ir.detect_trivial_nonterminating() == vec![map.get_block("b1")]);
+    }
+
+    #[test]
+    fn changed_arguments() {
+        let ir = crate::parse_function_unwrap(
+            "
+a'foo':a'loop'/1 {
+    entry(%ret, %thr, %x):
+        %fun = a'foo':a'loop'/1;
+        %fun(a'other') => %ret except %thr;
+}
+",
+        );
+        assert!(ir.detect_trivial_nonterminating().is_empty());
+    }
+
+    #[test]
+    fn other_return_path() {
+        let ir = crate::parse_function_unwrap(
+            "
+a'foo':a'loop'/1 {
+    entry(%ret, %thr, %x):
+        if_bool %x b1 b2;
+    b1():
+        %fun = a'foo':a'loop'/1;
+        %fun(%x) => %ret except %thr;
+    b2():
+        %ret(%x);
+}
+",
+        );
+        assert!(ir.detect_trivial_nonterminating().is_empty());
+    }
+}
@@ -1,6 +1,17 @@
 use crate::Function;
 use crate::{Block, CallKind, OpKind, Value};
 
+/// The semantic role of a control flow edge between two blocks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// Regular control flow within the function.
+    ControlFlow,
+    /// The return continuation of a function call.
+    CallReturn,
+    /// The escape continuation of a function call.
+    Exception,
+}
+
 pub struct BranchIter<'a> {
     fun: &'a Function,
     block: Block,
@@ -111,7 +122,10 @@ impl Function {
 
             (OpKind::Match { .. }, _, n) => self.value_list_get_n(reads[0], n).unwrap(),
 
-            (OpKind::Dyn(_dyn), _, _) => unimplemented!(),
+            (OpKind::Dyn(dyn_op), _, n) => {
+                let op_branches = self.dialect().get_op_branches(&**dyn_op).unwrap();
+                op_branches.branch_num(self, block, n)
+            }
             //(OpKind::Intrinsic(name), _, n) => {
             //    match name.as_str().get() {
             //        // receive_start only has a single branch target
@@ -127,3 +141,171 @@ impl Function {
         }
     }
 }
+
+impl Function {
+    /// Classifies the edge from `from` to `to` based on the operation
+    /// in `from`.
+    ///
+    /// Only function calls distinguish between their return and escape
+    /// continuations, every other edge is regular control flow.
+    pub fn edge_kind(&self, from: Block, to: Block) -> EdgeKind {
+        if let Some(OpKind::Call(CallKind::Function)) = self.block_kind(from) {
+            let reads = self.block_reads(from);
+            if self.value_block(reads[1]) == Some(to) {
+                return EdgeKind::CallReturn;
+            }
+            if self.value_block(reads[2]) == Some(to) {
+                return EdgeKind::Exception;
+            }
+        }
+        EdgeKind::ControlFlow
+    }
+
+    /// Returns true if `block` is a function call in tail position.
+    ///
+    /// In CPS a tail call is a call that forwards the return and escape
+    /// continuations the function was entered with, instead of capturing
+    /// a new return or escape block. Executing it needs no new frame, the
+    /// callee returns directly to our caller.
+    ///
+    /// Calls made from inside a closure forward the continuations of the
+    /// closure, not of the function, and are not detected.
+    pub fn is_tail_call(&self, block: Block) -> bool {
+        if let Some(OpKind::Call(CallKind::Function)) = self.block_kind(block) {
+            let reads = self.block_reads(block);
+            let entry_args = self.block_args(self.block_entry());
+            entry_args.len() >= 2 && reads[1] == entry_args[0] && reads[2] == entry_args[1]
+        } else {
+            false
+        }
+    }
+
+    /// Returns true if `block` is a function call in tail position.
+    ///
+    /// Like `is_tail_call`, but the continuations may also be blocks that
+    /// only pass their arguments on to the continuations of the function,
+    /// as lowering emits them before `SimplifyCfg` collapses them.
+    pub fn block_is_tail_position(&self, block: Block) -> bool {
+        if let Some(OpKind::Call(CallKind::Function)) = self.block_kind(block) {
+            let reads = self.block_reads(block);
+            let entry_args = self.block_args(self.block_entry());
+            entry_args.len() >= 2
+                && self.continuation_forwards_to(reads[1], entry_args[0])
+                && self.continuation_forwards_to(reads[2], entry_args[1])
+        } else {
+            false
+        }
+    }
+
+    /// Whether calling `cont` amounts to calling `target`, following
+    /// blocks that jump to the next continuation with their arguments
+    /// unchanged.
+    fn continuation_forwards_to(&self, mut cont: Value, target: Value) -> bool {
+        let mut seen = Vec::new();
+        while cont != target {
+            let block = match self.value_block(cont) {
+                Some(block) if !seen.contains(&block) => block,
+                _ => return false,
+            };
+            seen.push(block);
+            match self.block_kind(block) {
+                Some(OpKind::Call(CallKind::ControlFlow)) => (),
+                _ => return false,
+            }
+            let reads = self.block_reads(block);
+            if reads[1..] != *self.block_args(block) {
+                return false;
+            }
+            cont = reads[0];
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EdgeKind;
+
+    #[test]
+    fn call_edge_kinds() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        %f = a'erlang':a'+'/2;
+        %f(%a, 1) => b2 except b3;
+    b2(%r):
+        b4(%r);
+    b3(%t, %e, %s):
+        %thr(%t, %e, %s);
+    b4(%v):
+        %ret(%v);
+}
+",
+        );
+
+        let b1 = map.get_block("b1");
+        let b2 = map.get_block("b2");
+        let b3 = map.get_block("b3");
+        let b4 = map.get_block("b4");
+
+        assert!(ir.edge_kind(b1, b2) == EdgeKind::CallReturn);
+        assert!(ir.edge_kind(b1, b3) == EdgeKind::Exception);
+        assert!(ir.edge_kind(b2, b4) == EdgeKind::ControlFlow);
+    }
+
+    #[test]
+    fn tail_calls() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        %f1 = a'foo':a'baz'/1;
+        %f1(%a) => b2 except %thr;
+    b2(%r):
+        %f2 = a'foo':a'baz'/1;
+        %f2(%r) => %ret except %thr;
+    b3(%k, %e, %x):
+        %f3 = a'foo':a'baz'/1;
+        %f3(%x) => %k except %e;
+}
+",
+        );
+
+        assert!(!ir.is_tail_call(map.get_block("b1")));
+        assert!(ir.is_tail_call(map.get_block("b2")));
+        // Continuations that are arguments, but not the ones of the
+        // function
+        assert!(!ir.is_tail_call(map.get_block("b3")));
+    }
+
+    #[test]
+    fn tail_position_through_forwarding_blocks() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        %f1 = a'foo':a'baz'/1;
+        %f1(%a) => b2 except b3;
+    b2(%r):
+        b4(%r);
+    b3(%t, %e, %s):
+        %thr(%t, %e, %s);
+    b4(%v):
+        %ret(%v);
+    b5(%x):
+        %f2 = a'foo':a'baz'/1;
+        %f2(%x) => b6 except b3;
+    b6(%y):
+        %ret(a'none');
+}
+",
+        );
+
+        let b1 = map.get_block("b1");
+        assert!(!ir.is_tail_call(b1));
+        assert!(ir.block_is_tail_position(b1));
+        // The result is not returned as is
+        assert!(!ir.block_is_tail_position(map.get_block("b5")));
+    }
+}
@@ -0,0 +1,305 @@
+use cranelift_entity::EntityRef;
+use snafu::Snafu;
+
+use libeir_intern::Ident;
+use libeir_util_datastructures::pooled_entity_set::BoundEntitySet;
+
+use crate::algo::mangle::{MangleFrom, MangleTo, Mangler};
+use crate::{Block, Function, FunctionIdent, Value};
+
+#[derive(Snafu, Debug, PartialEq, Eq)]
+pub enum OutlineError {
+    #[snafu(display("entry {} is not part of the region", entry))]
+    EntryOutsideRegion { entry: Block },
+
+    #[snafu(display("region contains the entry block of the function"))]
+    FunctionEntryInRegion,
+
+    #[snafu(display("{} in the region branches to {} outside of it", block, target))]
+    BranchOutsideRegion { block: Block, target: Block },
+
+    #[snafu(display("{} in the region is not reachable from its entry", block))]
+    UnreachableInRegion { block: Block },
+}
+
+impl Function {
+    /// Moves a region of blocks into a new function, entered through the
+    /// region block `entry`.
+    ///
+    /// The new function takes the arguments of `entry` followed by the
+    /// free values of the region, see `region_free_values`. The return
+    /// and throw continuations of this function are not passed, they map
+    /// to the ones of the new function. The returned values are the free
+    /// values passed, in order.
+    ///
+    /// `entry` is rewritten to tail call the new function, which has to
+    /// be added to the module by the caller. The other blocks of the
+    /// region are left in place, and are dead unless branched to from
+    /// outside of it.
+    ///
+    /// Every block of the region must be reachable from `entry`, and the
+    /// region may only leave through free values, a read of a block
+    /// outside of it is not supported.
+    pub fn outline_region(
+        &mut self,
+        blocks: &BoundEntitySet<Block>,
+        entry: Block,
+    ) -> Result<(Function, Vec<Value>), OutlineError> {
+        self.validate_region(blocks, entry)?;
+
+        let fun_entry_args = self.block_args(self.block_entry());
+        let (ret, thr) = (fun_entry_args[0], fun_entry_args[1]);
+
+        let free = self.region_free_values(blocks);
+        let passed: Vec<Value> = free
+            .iter()
+            .cloned()
+            .filter(|v| *v != ret && *v != thr)
+            .collect();
+
+        let ident = self.ident();
+        let new_ident = FunctionIdent {
+            module: ident.module,
+            name: Ident::from_str(&format!("{}-outlined-{}", ident.name, entry.index())),
+            arity: self.block_args(entry).len() + passed.len(),
+        };
+        let mut new_fun = Function::new(self.span(), new_ident);
+
+        {
+            let mut b = new_fun.builder();
+
+            let new_entry = b.block_insert();
+            b.block_set_entry(new_entry);
+            let new_ret = b.block_arg_insert(new_entry);
+            let new_thr = b.block_arg_insert(new_entry);
+            let entry_args: Vec<Value> = self
+                .block_args(entry)
+                .iter()
+                .map(|_| b.block_arg_insert(new_entry))
+                .collect();
+
+            let mut mangler = Mangler::new();
+            mangler.start(MangleFrom(entry));
+            mangler.add_rename_nofollow(MangleFrom(ret), MangleTo(new_ret));
+            mangler.add_rename_nofollow(MangleFrom(thr), MangleTo(new_thr));
+            for value in passed.iter() {
+                let arg = b.block_arg_insert(new_entry);
+                mangler.add_rename_nofollow(MangleFrom(*value), MangleTo(arg));
+            }
+
+            let copied = mangler.run_across(self, &mut b);
+            b.op_call_flow(new_entry, copied, &entry_args);
+        }
+
+        let span = self.span();
+        let mut args = self.block_args(entry).to_vec();
+        args.extend(passed.iter().cloned());
+        {
+            let mut b = self.builder();
+            b.block_clear(entry);
+            let callee =
+                b.prim_capture_function(span, new_ident.module, new_ident.name, new_ident.arity);
+            b.op_call_function_next(span, entry, callee, ret, thr, &args);
+        }
+
+        Ok((new_fun, passed))
+    }
+
+    fn validate_region(
+        &self,
+        blocks: &BoundEntitySet<Block>,
+        entry: Block,
+    ) -> Result<(), OutlineError> {
+        if !blocks.contains(entry) {
+            return Err(OutlineError::EntryOutsideRegion { entry });
+        }
+        if blocks.contains(self.block_entry()) {
+            return Err(OutlineError::FunctionEntryInRegion);
+        }
+
+        // Walk the region from its entry, every branch has to stay
+        // within it. Targets can be nested in value lists, like the
+        // branches of a match.
+        let mut reached = vec![entry];
+        let mut stack = vec![entry];
+        while let Some(block) = stack.pop() {
+            self.block_walk_nested_values(block, &mut |value| {
+                if let Some(target) = self.value_block(value) {
+                    if !blocks.contains(target) {
+                        return Err(OutlineError::BranchOutsideRegion { block, target });
+                    }
+                    if !reached.contains(&target) {
+                        reached.push(target);
+                        stack.push(target);
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        // Values defined by a block that is not copied would be left
+        // without a definition.
+        match blocks.iter().find(|block| !reached.contains(block)) {
+            Some(block) => Err(OutlineError::UnreachableInRegion { block }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libeir_util_datastructures::pooled_entity_set::{EntitySet, EntitySetPool};
+
+    use super::OutlineError;
+
+    const SOURCE: &str = "
+a'foo':a'bar'/2 {
+    entry(%ret, %thr, %a, %b):
+        b1(%a);
+    b1(%x):
+        if_bool %x b2 b3;
+    b2():
+        %ret(%b);
+    b3():
+        %ret(a'none');
+}
+";
+
+    #[test]
+    fn outline_region() {
+        let (mut ir, map) = crate::parse_function_map_unwrap(SOURCE);
+
+        let mut pool = EntitySetPool::new();
+        let mut region = EntitySet::new();
+        region.insert(map.get_block("b1"), &mut pool);
+        region.insert(map.get_block("b2"), &mut pool);
+        region.insert(map.get_block("b3"), &mut pool);
+
+        let (outlined, passed) = ir
+            .outline_region(&region.bind(&pool), map.get_block("b1"))
+            .unwrap();
+        assert!(passed == vec![map.get_value("b")]);
+        assert!(outlined.ident().arity == 2);
+
+        let mut errors = Vec::new();
+        outlined.validate(&mut errors);
+        assert!(errors.is_empty());
+
+        let expected = crate::parse_function_unwrap(
+            "
+a'foo':a'bar-outlined-1'/2 {
+    entry(%ret, %thr, %x, %b):
+        b1(%x);
+    b1(%y):
+        if_bool %y b2 b3;
+    b2():
+        %ret(%b);
+    b3():
+        %ret(a'none');
+}
+",
+        );
+        assert!(outlined
+            .graph_eq(outlined.block_entry(), &expected, expected.block_entry())
+            .is_ok());
+
+        // The region entry now calls the outlined function
+        let expected = crate::parse_function_unwrap(
+            "
+a'foo':a'bar'/2 {
+    entry(%ret, %thr, %a, %b):
+        b1(%a);
+    b1(%x):
+        %f = a'foo':a'bar-outlined-1'/2;
+        %f(%x, %b) => %ret except %thr;
+}
+",
+        );
+        assert!(ir
+            .graph_eq(ir.block_entry(), &expected, expected.block_entry())
+            .is_ok());
+    }
+
+    #[test]
+    fn invalid_regions() {
+        let (mut ir, map) = crate::parse_function_map_unwrap(SOURCE);
+        let (b1, b2, b3) = (
+            map.get_block("b1"),
+            map.get_block("b2"),
+            map.get_block("b3"),
+        );
+
+        let mut pool = EntitySetPool::new();
+        let mut region = EntitySet::new();
+        region.insert(b1, &mut pool);
+        region.insert(b2, &mut pool);
+
+        assert!(
+            ir.outline_region(&region.bind(&pool), b3).err()
+                == Some(OutlineError::EntryOutsideRegion { entry: b3 })
+        );
+        assert!(
+            ir.outline_region(&region.bind(&pool), b1).err()
+                == Some(OutlineError::BranchOutsideRegion {
+                    block: b1,
+                    target: b3
+                })
+        );
+        assert!(
+            ir.outline_region(&region.bind(&pool), b2).err()
+                == Some(OutlineError::UnreachableInRegion { block: b1 })
+        );
+
+        region.insert(ir.block_entry(), &mut pool);
+        assert!(
+            ir.outline_region(&region.bind(&pool), b1).err()
+                == Some(OutlineError::FunctionEntryInRegion)
+        );
+    }
+
+    #[test]
+    fn outline_region_with_match() {
+        let source = "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        b1(%a);
+    b1(%x):
+        match %x {
+            [] => b2;
+            _ => b3;
+        };
+    b2(%h, %t):
+        %ret(%h);
+    b3():
+        %ret(a'none');
+}
+";
+        let (mut ir, map) = crate::parse_function_map_unwrap(source);
+        let (b1, b2, b3) = (
+            map.get_block("b1"),
+            map.get_block("b2"),
+            map.get_block("b3"),
+        );
+
+        // The match branches are read through a value list
+        let mut pool = EntitySetPool::new();
+        let mut region = EntitySet::new();
+        region.insert(b1, &mut pool);
+        region.insert(b2, &mut pool);
+        assert!(
+            ir.outline_region(&region.bind(&pool), b1).err()
+                == Some(OutlineError::BranchOutsideRegion {
+                    block: b1,
+                    target: b3
+                })
+        );
+
+        region.insert(b3, &mut pool);
+        let (outlined, passed) = ir.outline_region(&region.bind(&pool), b1).unwrap();
+        assert!(passed.is_empty());
+
+        let mut errors = Vec::new();
+        outlined.validate(&mut errors);
+        assert!(errors.is_empty());
+    }
+}
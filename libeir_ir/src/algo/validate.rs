@@ -8,7 +8,7 @@ use petgraph::algo::dominators::Dominators;
 
 use cranelift_bforest::{Set, SetForest};
 
-use crate::{Block, Value};
+use crate::{Block, Const, Value};
 use crate::{CallKind, Function, MatchKind, OpKind};
 
 #[derive(Debug)]
@@ -45,6 +45,42 @@ pub enum ValidationError {
     UnfinishedBlock {
         block: Block,
     },
+
+    /// A constant in the function contains itself.
+    CyclicConstant {
+        constant: Const,
+    },
+
+    /// A block was captured as a closure in `block`, but `value`, which
+    /// is live in `captured`, is not visible at the capture site.
+    DanglingCapture {
+        value: Value,
+        block: Block,
+        captured: Block,
+    },
+
+    /// A constant was used as a control flow target. `read` is the index
+    /// of the offending read of `block`.
+    ConstantBranchTarget {
+        block: Block,
+        read: usize,
+    },
+
+    /// `value` is argument `index` of `block`, but is defined as an
+    /// argument somewhere else, or is not an argument at all.
+    ForeignArgument {
+        value: Value,
+        block: Block,
+        index: usize,
+    },
+
+    /// `value` is defined as argument `index` of `block`, but is not in
+    /// that position of its argument list.
+    MissingArgument {
+        value: Value,
+        block: Block,
+        index: usize,
+    },
 }
 
 fn get_value_list<'a>(fun: &'a Function, value: Value) -> Option<&'a [Value]> {
@@ -60,12 +96,17 @@ fn get_value_list<'a>(fun: &'a Function, value: Value) -> Option<&'a [Value]> {
 impl Function {
     pub fn validate(&self, errors: &mut Vec<ValidationError>) {
         let block_graph = self.block_graph();
-        let doms = petgraph::algo::dominators::simple_fast(&block_graph, self.block_entry());
+        let doms = block_graph.dominators();
 
         // Validate internal graph invariants
         self.graph_validate_global();
 
+        if let Err(constant) = self.cons().validate_acyclic() {
+            errors.push(ValidationError::CyclicConstant { constant });
+        }
+
         self.validate_entry_invariants(errors);
+        self.validate_block_arguments(errors);
         self.validate_blocks(errors);
         self.validate_ssa_visibility(&doms, errors);
     }
@@ -90,6 +131,18 @@ impl Function {
         }
     }
 
+    fn validate_branch_target(&self, errors: &mut Vec<ValidationError>, block: Block, read: usize) {
+        let val = self.block_reads(block)[read];
+        let single = [val];
+        let targets = get_value_list(self, val).unwrap_or(&single);
+        if targets
+            .iter()
+            .any(|target| self.value_const(*target).is_some())
+        {
+            errors.push(ValidationError::ConstantBranchTarget { block, read });
+        }
+    }
+
     fn validate_blocks(&self, errors: &mut Vec<ValidationError>) {
         let block_graph = self.block_graph();
 
@@ -104,9 +157,12 @@ impl Function {
 
                 match kind {
                     OpKind::Call(CallKind::ControlFlow) => {
+                        self.validate_branch_target(errors, block, 0);
                         self.validate_call_to(errors, block, reads[0], reads.len() - 1);
                     }
                     OpKind::Call(CallKind::Function) => {
+                        self.validate_branch_target(errors, block, 1);
+                        self.validate_branch_target(errors, block, 2);
                         self.validate_call_to(errors, block, reads[0], reads.len() - 1);
                         self.validate_call_to(errors, block, reads[1], 1);
                         self.validate_call_to(errors, block, reads[2], 3);
@@ -132,18 +188,23 @@ impl Function {
                         }
                     }
                     OpKind::IfBool => {
+                        self.validate_branch_target(errors, block, 0);
+                        self.validate_branch_target(errors, block, 1);
                         self.validate_call_to(errors, block, reads[0], 0);
                         self.validate_call_to(errors, block, reads[1], 0);
                         if reads.len() == 4 {
+                            self.validate_branch_target(errors, block, 2);
                             self.validate_call_to(errors, block, reads[2], 0);
                         } else {
                             assert!(reads.len() == 3);
                         }
                     }
                     OpKind::UnpackValueList(n) => {
+                        self.validate_branch_target(errors, block, 0);
                         self.validate_call_to(errors, block, reads[0], *n);
                     }
                     OpKind::Match { branches } => {
+                        self.validate_branch_target(errors, block, 0);
                         let targets_opt = get_value_list(self, reads[0]);
                         let other_targets = &[reads[0]];
                         let targets = targets_opt.unwrap_or(other_targets);
@@ -192,6 +253,115 @@ impl Function {
     }
 }
 
+impl Function {
+    /// Every argument value must be defined by exactly the block and
+    /// position it appears at in an argument list.
+    fn validate_block_arguments(&self, errors: &mut Vec<ValidationError>) {
+        for block in self.block_iter() {
+            for (index, value) in self.block_args(block).iter().enumerate() {
+                if self.value_argument(*value) != Some((block, index)) {
+                    errors.push(ValidationError::ForeignArgument {
+                        value: *value,
+                        block,
+                        index,
+                    });
+                }
+            }
+        }
+
+        for value in self.values.keys() {
+            if let Some((block, index)) = self.value_argument(value) {
+                if self.block_arg_n(block, index) != Some(value) {
+                    errors.push(ValidationError::MissingArgument {
+                        value,
+                        block,
+                        index,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Function {
+    /// Checks that the usage set of every value is exactly the set of
+    /// blocks reading it, directly or nested in a primop. Returns the
+    /// mismatching `(value, block)` pairs, both blocks reading a value
+    /// without being in its usage set and usage entries of blocks that
+    /// don't read the value.
+    pub fn validate_usages(&self) -> Result<(), Vec<(Value, Block)>> {
+        let mut reads: FnvHashMap<Block, HashSet<Value>> = FnvHashMap::default();
+        let mut mismatches = Vec::new();
+
+        for block in self.block_iter() {
+            let block_reads = reads.entry(block).or_insert_with(HashSet::new);
+            self.block_walk_nested_values::<_, ()>(block, &mut |val| {
+                block_reads.insert(val);
+                Ok(())
+            })
+            .unwrap();
+
+            let mut sorted: Vec<Value> = block_reads.iter().cloned().collect();
+            sorted.sort();
+            for value in sorted {
+                if !self.value_usages(value).contains(block) {
+                    mismatches.push((value, block));
+                }
+            }
+        }
+
+        for value in self.values.keys() {
+            for block in self.value_usages(value).iter() {
+                if !reads.get(&block).map_or(false, |r| r.contains(&value)) {
+                    mismatches.push((value, block));
+                }
+            }
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+}
+
+impl Function {
+    /// Lists block arguments that are never read anywhere in the live
+    /// function, as `(block, argument index)`. The entry block is skipped,
+    /// its arguments are fixed by the calling convention.
+    ///
+    /// This only detects dead arguments, removing them is left to a pass.
+    pub fn dead_arguments(&self) -> Vec<(Block, usize)> {
+        let block_graph = self.block_graph();
+        let entry = self.block_entry();
+
+        let mut read = HashSet::new();
+        for block in block_graph.dfs_iter() {
+            for value in self.block_reads(block) {
+                self.value_walk_nested_values::<_, ()>(*value, &mut |val| {
+                    read.insert(val);
+                    Ok(())
+                })
+                .unwrap();
+            }
+        }
+
+        let mut dead = Vec::new();
+        for block in block_graph.dfs_iter() {
+            if block == entry {
+                continue;
+            }
+            for (idx, arg) in self.block_args(block).iter().enumerate() {
+                if !read.contains(arg) {
+                    dead.push((block, idx));
+                }
+            }
+        }
+        dead
+    }
+}
+
 impl Function {
     /// Strictly validate SSA visibility
     ///
@@ -254,6 +424,8 @@ impl Function {
             processed.clear();
         }
 
+        let live = self.live_values();
+
         // Go through all blocks and validate visibility
         for block in self.block_graph().dfs_iter() {
             let visible = &live_variables[&block];
@@ -262,6 +434,21 @@ impl Function {
                     if self.value_argument(val).is_some() && !visible.contains(val, &pool, &()) {
                         errors.push(ValidationError::InvalidRead { value: val, block });
                     }
+
+                    // Everything a captured block closes over must be
+                    // visible where the closure is created.
+                    if let Some(captured) = self.value_block(val) {
+                        for value in live.live_at(captured).iter() {
+                            if !visible.contains(value, &pool, &()) {
+                                errors.push(ValidationError::DanglingCapture {
+                                    value,
+                                    block,
+                                    captured,
+                                });
+                            }
+                        }
+                    }
+
                     Ok(())
                 })
                 .unwrap();
@@ -282,3 +469,145 @@ impl Function {
         live.insert(block, base_set);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ValidationError;
+
+    #[test]
+    fn dead_arguments() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        b2(%a, %a);
+    b2(%x, %unused):
+        %ret(%x);
+}
+",
+        );
+
+        let b2 = map.get_block("b2");
+        assert!(ir.dead_arguments() == vec![(b2, 1)]);
+    }
+
+    #[test]
+    fn constant_branch_target() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        if_bool %a b2 a'false';
+    b2():
+        %ret(%a);
+}
+",
+        );
+
+        let mut errors = Vec::new();
+        ir.validate(&mut errors);
+
+        let b1 = map.get_block("b1");
+        assert!(errors.iter().any(|err| match err {
+            ValidationError::ConstantBranchTarget { block, read } => *block == b1 && *read == 1,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn dangling_capture() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        if_bool %a b2 b4;
+    b2():
+        %ret(b3);
+    b3(%r):
+        %ret(%x);
+    b4():
+        b5(%a);
+    b5(%x):
+        %ret(%x);
+}
+",
+        );
+
+        let mut errors = Vec::new();
+        ir.validate(&mut errors);
+
+        let b2 = map.get_block("b2");
+        let b3 = map.get_block("b3");
+        let x = map.get_value("x");
+        assert!(errors.iter().any(|err| match err {
+            ValidationError::DanglingCapture {
+                value,
+                block,
+                captured,
+            } => *value == x && *block == b2 && *captured == b3,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn foreign_argument() {
+        let (mut ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    b1(%ret, %thr, %a):
+        b2(%a);
+    b2(%x):
+        %ret(%x);
+}
+",
+        );
+
+        let mut errors = Vec::new();
+        ir.validate(&mut errors);
+        assert!(errors.is_empty());
+
+        // Corrupt the function by making `%a` an argument of `b2` as well
+        let b2 = map.get_block("b2");
+        let a = map.get_value("a");
+        ir.blocks[b2].arguments.push(a, &mut ir.pool.value);
+
+        let mut errors = Vec::new();
+        ir.validate(&mut errors);
+        assert!(errors.iter().any(|err| match err {
+            ValidationError::ForeignArgument {
+                value,
+                block,
+                index,
+            } => *value == a && *block == b2 && *index == 1,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn corrupted_usages() {
+        let (mut ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        b2(%a);
+    b2(%x):
+        %ret(%x);
+}
+",
+        );
+        assert!(ir.validate_usages().is_ok());
+
+        let entry = map.get_block("entry");
+        let b2 = map.get_block("b2");
+        let x = map.get_value("x");
+        ir.values[x].usages.remove(b2, &mut ir.pool.block_set, &());
+        ir.values[x]
+            .usages
+            .insert(entry, &mut ir.pool.block_set, &());
+
+        let mismatches = ir.validate_usages().unwrap_err();
+        assert!(mismatches.len() == 2);
+        assert!(mismatches.contains(&(x, b2)));
+        assert!(mismatches.contains(&(x, entry)));
+    }
+}
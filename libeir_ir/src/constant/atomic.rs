@@ -1,7 +1,7 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::hash::{Hash, Hasher};
 
-use libeir_util_number::{cast, BigInt, NumCast};
+use libeir_util_number::{cast, float_to_string, BigInt, NumCast};
 
 use libeir_intern::Symbol;
 
@@ -134,7 +134,7 @@ impl From<f64> for AtomicTerm {
 }
 impl Display for FloatTerm {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        write!(fmt, "f{}", self.0)
+        write!(fmt, "f{}", float_to_string(self.0))
     }
 }
 
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 
 use libeir_intern::Ident;
@@ -5,7 +6,7 @@ use libeir_intern::Ident;
 use libeir_util_datastructures::aux_hash_map::AuxHashMap;
 use libeir_util_datastructures::aux_traits::{AuxEq, AuxHash};
 
-use cranelift_entity::{entity_impl, EntityList, ListPool, PrimaryMap};
+use cranelift_entity::{entity_impl, EntityList, EntityRef, ListPool, PrimaryMap};
 
 mod atomic;
 pub use atomic::*;
@@ -18,6 +19,20 @@ pub use libeir_util_number::{FromPrimitive, Integer, ToPrimitive};
 pub struct Const(u32);
 entity_impl!(Const, "const_value");
 
+impl Const {
+    /// Compares `a` in `cons_a` with `b` in `cons_b` by structure. Unlike
+    /// `==`, which is only meaningful within a single container, this works
+    /// across containers.
+    pub fn structurally_eq(
+        a: Const,
+        cons_a: &ConstantContainer,
+        b: Const,
+        cons_b: &ConstantContainer,
+    ) -> bool {
+        cons_a.eq_other(a, cons_b, b)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ConstKind {
     Atomic(AtomicTerm),
@@ -97,6 +112,10 @@ pub struct ConstantContainer {
     const_values: PrimaryMap<Const, ConstKind>,
     value_map: AuxHashMap<ConstKind, Const, ListPool<Const>>,
     pub const_pool: ListPool<Const>,
+
+    /// Integer constants that originate from character literals.
+    /// Purely cosmetic, only used when printing.
+    char_hints: HashSet<Const>,
 }
 
 impl Default for ConstantContainer {
@@ -105,6 +124,7 @@ impl Default for ConstantContainer {
             const_values: PrimaryMap::new(),
             value_map: AuxHashMap::new(),
             const_pool: ListPool::new(),
+            char_hints: HashSet::new(),
         }
     }
 }
@@ -118,6 +138,30 @@ impl ConstantContainer {
         &self.const_values[value]
     }
 
+    /// Number of distinct constants in the container.
+    pub fn len(&self) -> usize {
+        self.const_values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.const_values.is_empty()
+    }
+
+    /// Returns the constant for `kind`, inserting it if it is not already
+    /// in the container. Every insertion goes through here, so equal
+    /// constants always share a single `Const`.
+    pub fn intern(&mut self, kind: ConstKind) -> Const {
+        if let Some(val) = self.value_map.get(&kind, &self.const_pool) {
+            *val
+        } else {
+            let val = self.const_values.push(kind.clone());
+            self.value_map
+                .try_insert(kind, val, &self.const_pool)
+                .unwrap();
+            val
+        }
+    }
+
     pub fn list_cell(&mut self, head: Const, tail: Const) -> Const {
         self.from(ConstKind::ListCell { head, tail })
     }
@@ -151,6 +195,17 @@ impl ConstantContainer {
     //    }
     //}
 
+    /// Hints that the given integer constant was written as a character
+    /// literal. Since constants are interned, this applies to every use of
+    /// the same integer.
+    pub fn set_char_hint(&mut self, val: Const) {
+        self.char_hints.insert(val);
+    }
+
+    pub fn has_char_hint(&self, val: Const) -> bool {
+        self.char_hints.contains(&val)
+    }
+
     pub fn as_bool(&self, val: Const) -> Option<bool> {
         let kind = &self.const_values[val];
         match kind {
@@ -206,25 +261,151 @@ impl ConstantContainer {
         TupleBuilder::new()
     }
 
+    /// Structural equality of `l` in this container and `r` in `r_cont`.
+    /// Within one container this is the same as comparing the `Const`s.
     pub fn eq_other(&self, l: Const, r_cont: &ConstantContainer, r: Const) -> bool {
         match (&self.const_values[l], &r_cont.const_values[r]) {
-            (ConstKind::Atomic(la), ConstKind::Atomic(ra)) if la == ra => true,
-            (ConstKind::Atomic(_), ConstKind::Atomic(_)) => false,
+            (ConstKind::Atomic(la), ConstKind::Atomic(ra)) => la == ra,
+            (
+                ConstKind::ListCell { head: lh, tail: lt },
+                ConstKind::ListCell { head: rh, tail: rt },
+            ) => self.eq_other(*lh, r_cont, *rh) && self.eq_other(*lt, r_cont, *rt),
             (ConstKind::Tuple { entries: t1 }, ConstKind::Tuple { entries: t2 }) => {
                 let s1 = t1.as_slice(&self.const_pool);
                 let s2 = t2.as_slice(&r_cont.const_pool);
-                if s1.len() != s2.len() {
-                    return false;
+                s1.len() == s2.len()
+                    && s1
+                        .iter()
+                        .zip(s2.iter())
+                        .all(|(e1, e2)| self.eq_other(*e1, r_cont, *e2))
+            }
+            (
+                ConstKind::Map {
+                    keys: lk,
+                    values: lv,
+                },
+                ConstKind::Map {
+                    keys: rk,
+                    values: rv,
+                },
+            ) => {
+                // Entries are ordered by key index, which is container
+                // specific. Look up each key on the other side instead.
+                let lk = lk.as_slice(&self.const_pool);
+                let lv = lv.as_slice(&self.const_pool);
+                let rk = rk.as_slice(&r_cont.const_pool);
+                let rv = rv.as_slice(&r_cont.const_pool);
+                lk.len() == rk.len()
+                    && lk.iter().zip(lv.iter()).all(|(lkey, lval)| {
+                        rk.iter()
+                            .position(|rkey| self.eq_other(*lkey, r_cont, *rkey))
+                            .map(|idx| self.eq_other(*lval, r_cont, rv[idx]))
+                            .unwrap_or(false)
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// Copies `value` from the container `from` into this one, returning
+    /// the equivalent constant here.
+    pub fn import(&mut self, from: &ConstantContainer, value: Const) -> Const {
+        match &from.const_values[value] {
+            ConstKind::Atomic(atomic) => self.from(ConstKind::Atomic(atomic.clone())),
+            ConstKind::ListCell { head, tail } => {
+                let head = self.import(from, *head);
+                let tail = self.import(from, *tail);
+                self.list_cell(head, tail)
+            }
+            ConstKind::Tuple { entries } => {
+                let mut builder = self.tuple_builder();
+                for entry in entries.as_slice(&from.const_pool) {
+                    let entry = self.import(from, *entry);
+                    builder.push(entry, self);
+                }
+                builder.finish(self)
+            }
+            ConstKind::Map { keys, values } => {
+                let mut entries: Vec<(Const, Const)> = keys
+                    .as_slice(&from.const_pool)
+                    .iter()
+                    .zip(values.as_slice(&from.const_pool))
+                    .map(|(k, v)| (self.import(from, *k), self.import(from, *v)))
+                    .collect();
+                // Keys are ordered by index, which differs between containers
+                entries.sort_by_key(|(k, _)| *k);
+
+                let mut keys = EntityList::new();
+                let mut values = EntityList::new();
+                for (k, v) in entries {
+                    keys.push(k, &mut self.const_pool);
+                    values.push(v, &mut self.const_pool);
                 }
-                for (e1, e2) in s1.iter().zip(s2.iter()) {
-                    if !self.eq_other(*e1, r_cont, *e2) {
-                        return false;
+                self.from(ConstKind::Map { keys, values })
+            }
+        }
+    }
+
+    /// Validates that no constant contains itself.
+    ///
+    /// Constants are normally built bottom up, which makes cycles
+    /// impossible, but a malformed `ConstKind` referencing a not yet
+    /// created constant can close a loop. That would hang anything
+    /// recursing through the constant, like printing.
+    ///
+    /// On failure, returns a constant that is part of the cycle.
+    pub fn validate_acyclic(&self) -> Result<(), Const> {
+        #[derive(Copy, Clone, PartialEq)]
+        enum Mark {
+            Unvisited,
+            InProgress,
+            Done,
+        }
+
+        let mut marks = vec![Mark::Unvisited; self.const_values.len()];
+        let mut stack: Vec<(Const, usize)> = Vec::new();
+
+        for root in self.const_values.keys() {
+            if marks[root.index()] != Mark::Unvisited {
+                continue;
+            }
+            marks[root.index()] = Mark::InProgress;
+            stack.push((root, 0));
+
+            while let Some((node, child_idx)) = stack.pop() {
+                let child = match &self.const_values[node] {
+                    ConstKind::Atomic(_) => None,
+                    ConstKind::ListCell { head, tail } => [*head, *tail].get(child_idx).cloned(),
+                    ConstKind::Tuple { entries } => {
+                        entries.as_slice(&self.const_pool).get(child_idx).cloned()
+                    }
+                    ConstKind::Map { keys, values } => {
+                        let keys = keys.as_slice(&self.const_pool);
+                        let values = values.as_slice(&self.const_pool);
+                        keys.iter().chain(values.iter()).nth(child_idx).cloned()
+                    }
+                };
+
+                match child {
+                    None => marks[node.index()] = Mark::Done,
+                    Some(child) => {
+                        stack.push((node, child_idx + 1));
+                        match marks.get(child.index()) {
+                            Some(Mark::Unvisited) => {
+                                marks[child.index()] = Mark::InProgress;
+                                stack.push((child, 0));
+                            }
+                            Some(Mark::InProgress) => return Err(child),
+                            Some(Mark::Done) => (),
+                            // Dangling reference, can't be part of a cycle
+                            None => (),
+                        }
                     }
                 }
-                true
             }
-            l => unimplemented!("{:?}", l),
         }
+
+        Ok(())
     }
 }
 
@@ -263,13 +444,7 @@ where
 
 impl IntoConst for ConstKind {
     fn into_const(self, c: &mut ConstantContainer) -> Const {
-        if let Some(val) = c.value_map.get(&self, &c.const_pool) {
-            *val
-        } else {
-            let val = c.const_values.push(self.clone());
-            c.value_map.try_insert(self, val, &c.const_pool).unwrap();
-            val
-        }
+        c.intern(self)
     }
     fn get_const(self, c: &ConstantContainer) -> Option<Const> {
         c.value_map.get(&self, &c.const_pool).cloned()
@@ -325,3 +500,119 @@ impl TupleBuilder {
 //        for typ
 //    }
 //}
+
+#[cfg(test)]
+mod tests {
+    use cranelift_entity::{EntityList, EntityRef};
+
+    use libeir_intern::{Ident, Symbol};
+
+    use super::{Const, ConstKind, ConstantContainer, NilTerm};
+
+    #[test]
+    fn structurally_eq_across_containers() {
+        let mut c1 = ConstantContainer::new();
+        let mut c2 = ConstantContainer::new();
+
+        // Pad the second container so equal constants get differing indices
+        c2.from(Ident::from_str("padding"));
+
+        let build = |c: &mut ConstantContainer| {
+            let mut tuple = c.tuple_builder();
+            let a = c.from(Ident::from_str("a"));
+            tuple.push(a, c);
+            let one = c.from(1);
+            let nil = c.from(NilTerm);
+            let list = c.list_cell(one, nil);
+            tuple.push(list, c);
+            tuple.finish(c)
+        };
+        let t1 = build(&mut c1);
+        let t2 = build(&mut c2);
+        assert!(t1 != t2);
+        assert!(Const::structurally_eq(t1, &c1, t2, &c2));
+
+        let mut tuple = c2.tuple_builder();
+        let b = c2.from(Ident::from_str("b"));
+        tuple.push(b, &mut c2);
+        let other = tuple.finish(&mut c2);
+        assert!(!Const::structurally_eq(t1, &c1, other, &c2));
+        assert!(!Const::structurally_eq(t1, &c1, b, &c2));
+    }
+
+    #[test]
+    fn import_across_containers() {
+        let mut c1 = ConstantContainer::new();
+        let a = c1.from(Ident::from_str("a"));
+        let one = c1.from(1);
+        let nil = c1.nil();
+        let list = c1.list_cell(one, nil);
+        let map = c1.from(ConstKind::Map {
+            keys: EntityList::from_slice(&[a, one], &mut c1.const_pool),
+            values: EntityList::from_slice(&[list, a], &mut c1.const_pool),
+        });
+        let mut tuple = c1.tuple_builder();
+        tuple.push(map, &mut c1);
+        tuple.push(list, &mut c1);
+        let value = tuple.finish(&mut c1);
+
+        // Pad the target so constants get different indices
+        let mut c2 = ConstantContainer::new();
+        c2.from(1);
+        c2.from(Ident::from_str("b"));
+
+        let imported = c2.import(&c1, value);
+        assert!(Const::structurally_eq(value, &c1, imported, &c2));
+    }
+
+    #[test]
+    fn intern_deduplicates() {
+        let mut c = ConstantContainer::new();
+        let bin: Vec<u8> = (0..10 * 1024).map(|n| n as u8).collect();
+
+        let first = c.from(bin.clone());
+        let len = c.len();
+        let second = c.from(bin);
+        assert!(first == second);
+        assert!(c.len() == len);
+
+        let atom = c.from(Ident::from_str("a"));
+        assert!(c.intern(ConstKind::Atomic(Symbol::intern("a").into())) == atom);
+        let int = c.from(1);
+        assert!(c.from(1) == int);
+        assert!(c.len() == len + 2);
+    }
+
+    #[test]
+    fn acyclic_constants() {
+        let mut c = ConstantContainer::new();
+        let nil = c.from(NilTerm);
+        let one = c.from(1);
+        let list = c.from(ConstKind::ListCell {
+            head: one,
+            tail: nil,
+        });
+        c.from(ConstKind::ListCell {
+            head: one,
+            tail: list,
+        });
+        assert!(c.validate_acyclic() == Ok(()));
+    }
+
+    #[test]
+    fn cyclic_constant() {
+        let mut c = ConstantContainer::new();
+        let one = c.from(1);
+
+        // Reference the constant that is about to be created as its own
+        // tail.
+        let next = Const::new(c.const_values.len());
+        let cell = c.from(ConstKind::ListCell {
+            head: one,
+            tail: next,
+        });
+        assert!(cell == next);
+
+        assert!(c.validate_acyclic() == Err(cell));
+    }
+}
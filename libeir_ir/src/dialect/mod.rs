@@ -73,4 +73,13 @@ impl Dialect {
     pub fn get_op_printer<'a>(&self, obj: &'a dyn Op) -> Option<&'a dyn OpPrinter> {
         self.op_printer.get(obj.meta_entry())
     }
+
+    /// Registers the parser used for `@name` operations in the textual IR.
+    pub fn register_op_parser_impl<T: OpParser + 'static>(&mut self, name: Symbol, parser: T) {
+        self.op_parser.insert(name, Box::new(parser));
+    }
+
+    pub fn get_op_parser(&self, name: Symbol) -> Option<&dyn OpParser> {
+        self.op_parser.get(&name).map(|p| &**p)
+    }
 }
@@ -0,0 +1,270 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use cranelift_entity::{EntityRef, SecondaryMap};
+
+use super::{AttributeKey, AttributeValue, Block, Function};
+
+trait MetaMap: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn clone_box(&self) -> Box<dyn MetaMap>;
+    fn renumber(&self, order: &[Block]) -> Box<dyn MetaMap>;
+}
+
+impl<T> MetaMap for SecondaryMap<Block, T>
+where
+    T: Clone + Default + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn MetaMap> {
+        Box::new(self.clone())
+    }
+    fn renumber(&self, order: &[Block]) -> Box<dyn MetaMap> {
+        let mut map = SecondaryMap::<Block, T>::new();
+        for (idx, old) in order.iter().enumerate() {
+            map[Block::new(idx)] = self[*old].clone();
+        }
+        Box::new(map)
+    }
+}
+
+/// Per block side tables of arbitrary metadata, at most one table for
+/// each metadata type. Lets passes stash analysis results on blocks
+/// without growing `BlockData`.
+#[derive(Default)]
+pub struct BlockMeta {
+    maps: HashMap<TypeId, Box<dyn MetaMap>>,
+}
+
+impl BlockMeta {
+    /// Moves the metadata of the block `order[n]` to block `n`, in every
+    /// table.
+    pub(super) fn renumber(&mut self, order: &[Block]) {
+        for map in self.maps.values_mut() {
+            *map = map.renumber(order);
+        }
+    }
+}
+
+impl Clone for BlockMeta {
+    fn clone(&self) -> Self {
+        BlockMeta {
+            maps: self
+                .maps
+                .iter()
+                .map(|(k, v)| (*k, v.clone_box()))
+                .collect(),
+        }
+    }
+}
+
+/// Block metadata holding profile frequencies for the outgoing edges of a
+/// block, as `(successor, weight)` pairs. Edges without an entry weigh
+/// `DEFAULT_EDGE_WEIGHT`. Used by `reorder_blocks_for_layout` to pick
+/// fallthrough successors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EdgeWeights(pub Vec<(Block, u64)>);
+
+pub const DEFAULT_EDGE_WEIGHT: u64 = 1;
+
+/// Block metadata holding the attributes set on a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct BlockAttributes(Vec<(AttributeKey, AttributeValue)>);
+
+/// Block metadata
+impl Function {
+    /// Gets the side table of `T` metadata for blocks, creating it if it
+    /// doesn't exist. Blocks without an entry read as `T::default()`,
+    /// this includes blocks inserted after the table was created.
+    pub fn block_meta<T>(&mut self) -> &mut SecondaryMap<Block, T>
+    where
+        T: Clone + Default + 'static,
+    {
+        self.block_meta
+            .maps
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SecondaryMap::<Block, T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Gets the side table of `T` metadata for blocks, if it exists.
+    pub fn block_meta_get<T>(&self) -> Option<&SecondaryMap<Block, T>>
+    where
+        T: Clone + Default + 'static,
+    {
+        self.block_meta
+            .maps
+            .get(&TypeId::of::<T>())
+            .map(|map| map.as_any().downcast_ref().unwrap())
+    }
+
+    /// The frequency weight of the edge from `block` to `successor`, see
+    /// `EdgeWeights`.
+    pub fn block_edge_weight(&self, block: Block, successor: Block) -> u64 {
+        self.block_meta_get::<EdgeWeights>()
+            .and_then(|meta| {
+                meta[block]
+                    .0
+                    .iter()
+                    .find(|(b, _)| *b == successor)
+                    .map(|(_, w)| *w)
+            })
+            .unwrap_or(DEFAULT_EDGE_WEIGHT)
+    }
+
+    /// Gets the value of the `key` attribute of `block`, if it is set.
+    pub fn block_attribute(&self, block: Block, key: AttributeKey) -> Option<&AttributeValue> {
+        self.block_meta_get::<BlockAttributes>()?[block]
+            .0
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Whether the `key` attribute is set on `block`. An attribute set to
+    /// `false` counts as not set.
+    pub fn block_has_attribute(&self, block: Block, key: AttributeKey) -> bool {
+        match self.block_attribute(block, key) {
+            None | Some(AttributeValue::Bool(false)) => false,
+            Some(_) => true,
+        }
+    }
+
+    /// Whether `block` is marked `Cold`, see `block_has_attribute`.
+    pub fn block_is_cold(&self, block: Block) -> bool {
+        self.block_has_attribute(block, AttributeKey::Cold)
+    }
+
+    /// Whether `block` is marked `Hot`, see `block_has_attribute`.
+    pub fn block_is_hot(&self, block: Block) -> bool {
+        self.block_has_attribute(block, AttributeKey::Hot)
+    }
+
+    /// Iterates over all attributes set on `block`, in the order they were
+    /// first set.
+    pub fn block_attributes(
+        &self,
+        block: Block,
+    ) -> impl Iterator<Item = (AttributeKey, &AttributeValue)> {
+        self.block_meta_get::<BlockAttributes>()
+            .into_iter()
+            .flat_map(move |meta| meta[block].0.iter())
+            .map(|(k, v)| (*k, v))
+    }
+
+    pub(crate) fn block_attribute_set(
+        &mut self,
+        block: Block,
+        key: AttributeKey,
+        value: AttributeValue,
+    ) {
+        let attrs = &mut self.block_meta::<BlockAttributes>()[block].0;
+        match attrs.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => attrs.push((key, value)),
+        }
+    }
+
+    pub(crate) fn block_attribute_remove(&mut self, block: Block, key: AttributeKey) {
+        if self.block_meta_get::<BlockAttributes>().is_some() {
+            self.block_meta::<BlockAttributes>()[block]
+                .0
+                .retain(|(k, _)| *k != key);
+        }
+    }
+
+    /// Removes the side table of `T` metadata for blocks.
+    pub fn block_meta_clear<T>(&mut self)
+    where
+        T: Clone + Default + 'static,
+    {
+        self.block_meta.maps.remove(&TypeId::of::<T>());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AttributeKey, AttributeValue, FunctionBuilder};
+
+    #[test]
+    fn block_meta_survives_insert() {
+        let mut ir = crate::parse_function_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        b1(%a);
+    b1(%b):
+        %ret(%b);
+}
+",
+        );
+
+        let blocks: Vec<_> = ir.block_iter().collect();
+        for (n, block) in blocks.iter().enumerate() {
+            ir.block_meta::<u32>()[*block] = n as u32 + 1;
+        }
+
+        let new_block = {
+            let mut b = FunctionBuilder::new(&mut ir);
+            b.block_insert()
+        };
+
+        let meta = ir.block_meta_get::<u32>().unwrap();
+        for (n, block) in blocks.iter().enumerate() {
+            assert_eq!(meta[*block], n as u32 + 1);
+        }
+        assert_eq!(meta[new_block], 0);
+
+        assert!(ir.block_meta_get::<u64>().is_none());
+    }
+
+    #[test]
+    fn block_attributes() {
+        let (mut ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        b1(%a);
+    b1(%b):
+        %ret(%b);
+}
+",
+        );
+        let entry = map.get_block("entry");
+        let b1 = map.get_block("b1");
+
+        assert!(ir.block_attribute(b1, AttributeKey::Cold).is_none());
+
+        {
+            let mut b = FunctionBuilder::new(&mut ir);
+            b.block_set_attribute(b1, AttributeKey::Cold, AttributeValue::None);
+            b.block_set_attribute(b1, AttributeKey::Hot, AttributeValue::Bool(false));
+            b.block_set_attribute(b1, AttributeKey::Hot, AttributeValue::Bool(true));
+        }
+
+        assert!(ir.block_has_attribute(b1, AttributeKey::Cold));
+        assert!(ir.block_attribute(b1, AttributeKey::Hot) == Some(&AttributeValue::Bool(true)));
+        assert!(!ir.block_has_attribute(b1, AttributeKey::Continuation));
+        assert!(ir.block_attributes(entry).next().is_none());
+        assert!(ir.block_attributes(b1).count() == 2);
+
+        ir.builder().block_remove_attribute(b1, AttributeKey::Cold);
+        assert!(!ir.block_has_attribute(b1, AttributeKey::Cold));
+        assert!(ir.block_has_attribute(b1, AttributeKey::Hot));
+
+        // Set to false, the attribute exists but is not set
+        ir.builder()
+            .block_set_attribute(b1, AttributeKey::Hot, AttributeValue::Bool(false));
+        assert!(ir.block_attribute(b1, AttributeKey::Hot).is_some());
+        assert!(!ir.block_has_attribute(b1, AttributeKey::Hot));
+        assert!(!ir.block_is_hot(b1));
+    }
+}
@@ -2,14 +2,16 @@ use libeir_diagnostics::SourceSpan;
 
 use super::Function;
 use super::ValueKind;
+use super::{AttributeKey, AttributeValue, EdgeWeights};
 use super::{Block, Const, Location, PrimOp, Value};
 use super::{PrimOpData, PrimOpKind};
 
 use crate::constant::{ConstantContainer, IntoConst};
-use crate::pattern::PatternContainer;
+use crate::pattern::{PatternClause, PatternContainer};
 use crate::BinOp;
 
-use cranelift_entity::EntityList;
+use cranelift_entity::{EntityList, EntityRef, EntitySet, PrimaryMap, SecondaryMap};
+use smallvec::SmallVec;
 
 mod op;
 pub use op::CaseBuilder;
@@ -36,7 +38,9 @@ impl IntoValue for Value {
 }
 impl IntoValue for Block {
     fn into_value<'a>(self, b: &mut FunctionBuilder<'a>) -> Value {
-        b.fun.values.push(ValueKind::Block(self))
+        let value = b.fun.values.push(ValueKind::Block(self));
+        b.fun.check_limits();
+        value
     }
     fn get_value(self, fun: &Function) -> Option<Value> {
         fun.values.get(ValueKind::Block(self))
@@ -44,7 +48,9 @@ impl IntoValue for Block {
 }
 impl IntoValue for PrimOp {
     fn into_value<'a>(self, b: &mut FunctionBuilder<'a>) -> Value {
-        b.fun.values.push(ValueKind::PrimOp(self))
+        let value = b.fun.values.push(ValueKind::PrimOp(self));
+        b.fun.check_limits();
+        value
     }
     fn get_value(self, fun: &Function) -> Option<Value> {
         fun.values.get(ValueKind::PrimOp(self))
@@ -58,6 +64,7 @@ where
         let constant = b.fun.constant_container.from(self);
         let value = b.fun.values.push(ValueKind::Const(constant));
         b.fun.constant_values.insert(value);
+        b.fun.check_limits();
         value
     }
     fn get_value(self, fun: &Function) -> Option<Value> {
@@ -148,6 +155,17 @@ impl<'a> FunctionBuilder<'a> {
     pub fn pat_mut(&mut self) -> &mut PatternContainer {
         &mut self.fun.pattern_container
     }
+    /// Copies a pattern clause of `from` into this function, importing
+    /// its constants.
+    pub fn pat_copy_from(&mut self, from: &Function, clause: PatternClause) -> PatternClause {
+        let fun = &mut *self.fun;
+        fun.pattern_container.copy_from(
+            clause,
+            from.pat(),
+            from.cons(),
+            &mut fun.constant_container,
+        )
+    }
 
     pub fn cons(&self) -> &ConstantContainer {
         &self.fun.constant_container
@@ -264,6 +282,147 @@ impl<'a> FunctionBuilder<'a> {
         self.block_buf = Some(block_buf);
         self.value_buf = Some(value_buf);
     }
+
+    /// Clears and rebuilds the predecessor and successor sets of every
+    /// block by scanning the reads. Restores the graph invariants after
+    /// raw edits to block reads that did not keep the edges up to date.
+    pub fn recompute_cfg_edges(&mut self) {
+        let mut block_buf = self.block_buf.take().unwrap();
+        let mut value_buf = self.value_buf.take().unwrap();
+        debug_assert!(block_buf.is_empty());
+        debug_assert!(value_buf.is_empty());
+
+        block_buf.extend(self.fun.block_iter());
+        for block in block_buf.iter() {
+            let block_data = &mut self.fun.blocks[*block];
+            block_data.predecessors.clear(&mut self.fun.pool.block_set);
+            block_data.successors.clear(&mut self.fun.pool.block_set);
+        }
+
+        for block in block_buf.iter() {
+            self.fun
+                .block_walk_nested_values::<_, ()>(*block, &mut |val| {
+                    value_buf.push(val);
+                    Ok(())
+                })
+                .unwrap();
+
+            for value in value_buf.iter() {
+                if let ValueKind::Block(dest_block) = self.fun.values[*value].kind {
+                    self.fun.blocks[*block].successors.insert(
+                        dest_block,
+                        &mut self.fun.pool.block_set,
+                        &(),
+                    );
+                    self.fun.blocks[dest_block].predecessors.insert(
+                        *block,
+                        &mut self.fun.pool.block_set,
+                        &(),
+                    );
+                }
+            }
+            value_buf.clear();
+        }
+
+        block_buf.clear();
+        self.block_buf = Some(block_buf);
+        self.value_buf = Some(value_buf);
+    }
+
+    /// Renumbers the blocks of the function into layout order. Reachable
+    /// blocks are laid out in reverse postorder, except that a block is
+    /// directly followed by its fallthrough successor whenever that one is
+    /// not placed yet. The fallthrough successor is the block read with
+    /// the heaviest edge weight, see `EdgeWeights`, the first one on ties.
+    /// `Hot` successors are preferred over any weight, `Cold` ones are
+    /// never picked.
+    /// The entry block stays first. Reachable `Cold` blocks are placed
+    /// after all other reachable blocks, unreachable blocks are placed last
+    /// in their original order.
+    ///
+    /// Every reference to a block is updated, including block metadata.
+    /// Returns the new block for each old block.
+    pub fn reorder_blocks_for_layout(&mut self) -> SecondaryMap<Block, Block> {
+        let order = self.fun.layout_order();
+
+        let mut renames = SecondaryMap::new();
+        for (idx, old) in order.iter().enumerate() {
+            renames[*old] = Block::new(idx);
+        }
+
+        let mut blocks = PrimaryMap::with_capacity(order.len());
+        for old in order.iter() {
+            blocks.push(self.fun.blocks[*old].clone());
+        }
+        self.fun.blocks = blocks;
+
+        self.fun.entry_block = self.fun.entry_block.map(|block| renames[block]);
+        self.fun
+            .values
+            .map_blocks(&mut self.fun.pool.block_set, |block| renames[block]);
+        self.fun.block_meta.renumber(&order);
+        if self.fun.block_meta_get::<EdgeWeights>().is_some() {
+            for weights in self.fun.block_meta::<EdgeWeights>().values_mut() {
+                for (succ, _) in weights.0.iter_mut() {
+                    *succ = renames[*succ];
+                }
+            }
+        }
+
+        self.recompute_cfg_edges();
+
+        renames
+    }
+}
+
+impl Function {
+    /// The order `reorder_blocks_for_layout` places blocks in.
+    fn layout_order(&self) -> Vec<Block> {
+        let mut rpo: Vec<Block> = self.block_graph().dfs_post_order_iter().collect();
+        rpo.reverse();
+
+        let entry = self.entry_block;
+        let deferred = |block: Block| Some(block) != entry && self.block_is_cold(block);
+
+        let mut placed = EntitySet::new();
+        let mut order = Vec::with_capacity(self.blocks.len());
+
+        let chain_starts = rpo
+            .iter()
+            .filter(|b| !deferred(**b))
+            .chain(rpo.iter().filter(|b| deferred(**b)))
+            .cloned()
+            .chain(self.block_iter());
+        for start in chain_starts {
+            let mut next = Some(start);
+            while let Some(block) = next {
+                if !placed.insert(block) {
+                    break;
+                }
+                order.push(block);
+
+                next = self.layout_fallthrough(block);
+            }
+        }
+
+        order
+    }
+
+    fn layout_fallthrough(&self, block: Block) -> Option<Block> {
+        let mut best: Option<(Block, (bool, u64))> = None;
+        for read in self.block_reads(block) {
+            if let Some(succ) = self.value_block(*read) {
+                if self.block_is_cold(succ) {
+                    continue;
+                }
+                let rank = (self.block_is_hot(succ), self.block_edge_weight(block, succ));
+                if best.map(|(_, r)| rank > r).unwrap_or(true) {
+                    best = Some((succ, rank));
+                }
+            }
+        }
+        best.map(|(succ, _)| succ)
+    }
 }
 
 /// Block modifiers
@@ -287,6 +446,29 @@ impl<'a> FunctionBuilder<'a> {
         self.fun.block_arg_insert(block)
     }
 
+    /// Inserts a new block with `n` arguments, returns the block and its
+    /// argument values.
+    pub fn block_insert_with_args(&mut self, n: usize) -> (Block, SmallVec<[Value; 4]>) {
+        let block = self.block_insert();
+        let args = (0..n).map(|_| self.block_arg_insert(block)).collect();
+        (block, args)
+    }
+
+    /// Inserts the entry block of the function with the return and throw
+    /// continuations followed by `arity` arguments. Returns the block and
+    /// all of its arguments, continuations first.
+    ///
+    /// Panics if the function already has an entry block.
+    pub fn create_entry(&mut self, arity: usize) -> (Block, Vec<Value>) {
+        assert!(!self.fun.has_entry(), "function already has an entry block");
+        let block = self.block_insert();
+        self.block_set_entry(block);
+        let args = (0..arity + 2)
+            .map(|_| self.block_arg_insert(block))
+            .collect();
+        (block, args)
+    }
+
     pub fn block_args(&self, block: Block) -> &[Value] {
         self.fun.block_args(block)
     }
@@ -303,6 +485,37 @@ impl<'a> FunctionBuilder<'a> {
         self.fun.blocks[block].location = loc;
     }
 
+    /// Sets the `key` attribute of `block` to `value`, replacing any
+    /// previous value. Attributes are hints for passes and printers, they
+    /// don't change what the block does.
+    pub fn block_set_attribute(&mut self, block: Block, key: AttributeKey, value: AttributeValue) {
+        self.fun.block_attribute_set(block, key, value);
+    }
+
+    pub fn block_remove_attribute(&mut self, block: Block, key: AttributeKey) {
+        self.fun.block_attribute_remove(block, key);
+    }
+
+    /// Sets the profile frequency of the edge from `block` to `successor`.
+    pub fn block_set_edge_weight(&mut self, block: Block, successor: Block, weight: u64) {
+        let weights = &mut self.fun.block_meta::<EdgeWeights>()[block].0;
+        match weights.iter_mut().find(|(b, _)| *b == successor) {
+            Some((_, w)) => *w = weight,
+            None => weights.push((successor, weight)),
+        }
+    }
+
+    /// Associates a source span with each read of the operation in the
+    /// block, `SourceSpan::UNKNOWN` for reads without one. Used to point
+    /// at the offending operand of a call in diagnostics.
+    pub fn block_set_read_spans(&mut self, block: Block, spans: &[SourceSpan]) {
+        let data = &mut self.fun.blocks[block];
+        assert!(data.op.is_some());
+        assert!(data.reads.len(&self.fun.pool.value) == spans.len());
+        data.read_spans.clear();
+        data.read_spans.extend(spans.iter().cloned());
+    }
+
     /// This will explicitly clear the operation contained in the
     /// block. This will remove all successors, and will cause
     /// this block to be removed from their predecessors.
@@ -322,6 +535,7 @@ impl<'a> FunctionBuilder<'a> {
             }
             data.successors.clear(&mut self.fun.pool.block_set);
             data.reads.clear(&mut self.fun.pool.value);
+            data.read_spans.clear();
         }
 
         for value in value_buf.iter() {
@@ -339,6 +553,66 @@ impl<'a> FunctionBuilder<'a> {
         self.value_buf = Some(value_buf);
     }
 
+    /// Detaches `block` from the graph. Its operation is cleared, and it
+    /// is removed from the successors of its predecessors and the
+    /// predecessors of its successors.
+    ///
+    /// Blocks are stored in a `PrimaryMap`, so the block itself and its
+    /// argument values stay allocated, as an empty block nothing refers to.
+    /// Panics if `block` is the entry block, and in debug builds if any
+    /// other block still reads it.
+    pub fn block_remove(&mut self, block: Block) {
+        assert!(
+            self.fun.entry_block != Some(block),
+            "cannot remove the entry block"
+        );
+
+        #[cfg(debug_assertions)]
+        {
+            let block_val = self.fun.block_value(block);
+            for usage in self.fun.value_usages(block_val).iter() {
+                if usage == block {
+                    continue;
+                }
+                let reads_block = self
+                    .fun
+                    .block_walk_nested_values(usage, &mut |val| {
+                        if val == block_val {
+                            Err(())
+                        } else {
+                            Ok(())
+                        }
+                    })
+                    .is_err();
+                assert!(!reads_block, "{} is still read by {}", block, usage);
+            }
+        }
+
+        self.block_clear(block);
+
+        let mut block_buf = self.block_buf.take().unwrap();
+        debug_assert!(block_buf.is_empty());
+
+        block_buf.extend(
+            self.fun.blocks[block]
+                .predecessors
+                .iter(&self.fun.pool.block_set),
+        );
+        for predecessor in block_buf.iter() {
+            self.fun.blocks[*predecessor].successors.remove(
+                block,
+                &mut self.fun.pool.block_set,
+                &(),
+            );
+        }
+        self.fun.blocks[block]
+            .predecessors
+            .clear(&mut self.fun.pool.block_set);
+
+        block_buf.clear();
+        self.block_buf = Some(block_buf);
+    }
+
     pub fn block_value_map<F>(&mut self, block: Block, mut map: F)
     where
         F: FnMut(Value) -> Value,
@@ -361,10 +635,12 @@ impl<'a> FunctionBuilder<'a> {
     {
         let op;
         let loc;
+        let read_spans;
         {
             let from_data = &self.fun.blocks[from];
             op = from_data.op.clone();
             loc = from_data.location;
+            read_spans = from_data.read_spans.clone();
         }
 
         let mut reads = EntityList::new();
@@ -380,6 +656,7 @@ impl<'a> FunctionBuilder<'a> {
         let to_data = &mut self.fun.blocks[to];
         to_data.op = op;
         to_data.reads = reads;
+        to_data.read_spans = read_spans;
         to_data.location = loc;
 
         self.graph_update_block(to);
@@ -389,10 +666,11 @@ impl<'a> FunctionBuilder<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::FunctionIdent;
+    use crate::{FunctionIdent, OpKind, PatternClause};
 
     use libeir_diagnostics::SourceSpan;
-    use libeir_intern::Ident;
+    use libeir_intern::{Ident, Symbol};
+    use libeir_util_datastructures::pooled_entity_set::EntitySet as PooledEntitySet;
 
     #[test]
     fn graph_impl() {
@@ -418,4 +696,354 @@ mod tests {
             b.fun().graph_validate_global();
         }
     }
+
+    #[test]
+    fn block_insert_with_args() {
+        let ident = FunctionIdent {
+            module: Ident::from_str("test"),
+            name: Ident::from_str("test"),
+            arity: 1,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+        let mut b = fun.builder();
+
+        let (block, args) = b.block_insert_with_args(3);
+        assert!(b.block_args(block).len() == 3);
+        assert!(b.block_args(block) == &args[..]);
+    }
+
+    #[test]
+    fn create_entry() {
+        let ident = FunctionIdent {
+            module: Ident::from_str("test"),
+            name: Ident::from_str("test"),
+            arity: 2,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+        let mut b = fun.builder();
+
+        let (entry, args) = b.create_entry(2);
+        assert!(b.fun().block_entry() == entry);
+        assert!(b.block_args(entry) == &args[..]);
+        assert!(args.len() == 4);
+    }
+
+    #[test]
+    fn entity_set_ops() {
+        let ident = FunctionIdent {
+            module: Ident::from_str("test"),
+            name: Ident::from_str("test"),
+            arity: 0,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+        let mut b = fun.builder();
+        let blocks: Vec<Block> = (0..4).map(|_| b.block_insert()).collect();
+
+        let fun = b.fun_mut();
+        let mut make = |idxs: &[usize]| {
+            let mut set = PooledEntitySet::new();
+            for idx in idxs {
+                set.insert(blocks[*idx], fun.entity_set_pool_mut());
+            }
+            set
+        };
+        let mut union = make(&[0, 1]);
+        let mut intersection = make(&[0, 1, 2]);
+        let mut difference = make(&[0, 1, 2]);
+        let other = make(&[1, 2, 3]);
+
+        fun.entity_set_union_with(&mut union, &other);
+        fun.entity_set_intersect_with(&mut intersection, &other);
+        fun.entity_set_difference_with(&mut difference, &other);
+
+        let pool = fun.entity_set_pool();
+        assert!(union.iter(pool).collect::<Vec<_>>() == blocks);
+        assert!(intersection.iter(pool).collect::<Vec<_>>() == &blocks[1..3]);
+        assert!(difference.iter(pool).collect::<Vec<_>>() == &blocks[..1]);
+        assert!(other.iter(pool).collect::<Vec<_>>() == &blocks[1..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn create_entry_twice() {
+        let ident = FunctionIdent {
+            module: Ident::from_str("test"),
+            name: Ident::from_str("test"),
+            arity: 0,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+        let mut b = fun.builder();
+
+        b.create_entry(0);
+        b.create_entry(0);
+    }
+
+    fn tuple_clause(b: &mut FunctionBuilder, arity: usize) -> PatternClause {
+        let clause = b.pat_mut().clause_start(SourceSpan::UNKNOWN);
+        let tup = b.pat_mut().node_empty(None);
+        b.pat_mut().tuple(tup);
+        for _ in 0..arity {
+            let elem = b.pat_mut().node_empty(None);
+            b.pat_mut().wildcard(elem);
+            b.pat_mut().tuple_elem_push(tup, elem);
+            b.pat_mut().clause_bind_push(clause, elem);
+        }
+        b.pat_mut().node_finish(tup);
+        b.pat_mut().clause_node_push(clause, tup);
+        b.pat_mut().clause_finish(clause);
+        clause
+    }
+
+    #[test]
+    fn op_match() {
+        let ident = FunctionIdent {
+            module: Ident::from_str("test"),
+            name: Ident::from_str("test"),
+            arity: 1,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+        let mut b = fun.builder();
+
+        let (entry, args) = b.create_entry(1);
+
+        let pair = tuple_clause(&mut b, 2);
+        let (pair_block, pair_args) = b.block_insert_with_args(2);
+        b.op_call_flow(pair_block, args[0], &[pair_args[0]]);
+
+        let unit = tuple_clause(&mut b, 0);
+        let unit_block = b.block_insert();
+        let unit_val = b.value(Symbol::intern("unit"));
+        b.op_call_flow(unit_block, args[0], &[unit_val]);
+
+        let no_match = b.op_match(
+            SourceSpan::UNKNOWN,
+            entry,
+            args[2],
+            &[(pair, pair_block), (unit, unit_block)],
+        );
+        b.op_unreachable(SourceSpan::UNKNOWN, no_match);
+
+        match b.fun().block_kind(entry) {
+            Some(OpKind::Case { clauses }) => {
+                assert!(clauses.as_slice(&b.fun().pool.clause) == &[pair, unit]);
+            }
+            _ => panic!(),
+        }
+
+        let reads = b.fun().block_reads(entry);
+        assert!(reads.len() == 6);
+        assert!(b.fun().value_block(reads[0]) == Some(no_match));
+        assert!(b.fun().value_block(reads[2]) == Some(pair_block));
+        assert!(b.fun().value_block(reads[4]) == Some(unit_block));
+        assert!(reads[5] == args[2]);
+
+        // The guards take the binds of their clause
+        let pair_guard = b.fun().value_block(reads[1]).unwrap();
+        assert!(b.block_args(pair_guard).len() == 4);
+
+        b.fun().graph_validate_global();
+    }
+
+    #[test]
+    #[should_panic]
+    fn op_match_bind_mismatch() {
+        let ident = FunctionIdent {
+            module: Ident::from_str("test"),
+            name: Ident::from_str("test"),
+            arity: 1,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+        let mut b = fun.builder();
+
+        let (entry, args) = b.create_entry(1);
+
+        let pair = tuple_clause(&mut b, 2);
+        let (target, _) = b.block_insert_with_args(1);
+
+        b.op_case_clauses(SourceSpan::UNKNOWN, entry, args[2], &[(pair, target)]);
+    }
+
+    #[test]
+    fn block_remove() {
+        let (mut fun, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        b2(%a);
+    b2(%x):
+        %ret(%x);
+    b3():
+        b2(a'orphan');
+}
+",
+        );
+        let b2 = map.get_block("b2");
+        let b3 = map.get_block("b3");
+
+        let mut b = fun.builder();
+        b.block_remove(b3);
+        b.fun().graph_validate_global();
+
+        let pool = &b.fun().pool.block_set;
+        assert!(b.fun().block_kind(b3).is_none());
+        assert!(b.fun().blocks[b3].successors.is_empty());
+        assert!(!b.fun().blocks[b2].predecessors.contains(b3, pool, &()));
+        assert!(b.fun().blocks[b2]
+            .predecessors
+            .contains(map.get_block("entry"), pool, &()));
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn block_remove_still_read() {
+        let (mut fun, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        b2(%a);
+    b2(%x):
+        %ret(%x);
+}
+",
+        );
+        fun.builder().block_remove(map.get_block("b2"));
+    }
+
+    #[test]
+    fn recompute_cfg_edges() {
+        let ident = FunctionIdent {
+            module: Ident::from_str("test"),
+            name: Ident::from_str("test"),
+            arity: 1,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+        let mut b = fun.builder();
+
+        let ba = b.block_insert();
+        let bb = b.block_insert();
+        let bc = b.block_insert();
+        b.op_call_flow(ba, bb, &[]);
+        b.op_call_flow(bb, bc, &[]);
+
+        // Drop a real edge and add a bogus one
+        b.fun.blocks[ba].successors.clear(&mut b.fun.pool.block_set);
+        b.fun.blocks[bc]
+            .predecessors
+            .insert(ba, &mut b.fun.pool.block_set, &());
+
+        b.recompute_cfg_edges();
+        b.fun().graph_validate_global();
+
+        let pool = &b.fun().pool.block_set;
+        assert!(b.fun().blocks[ba].successors.contains(bb, pool, &()));
+        assert!(!b.fun().blocks[bc].predecessors.contains(ba, pool, &()));
+        assert!(b.fun().blocks[bc].predecessors.contains(bb, pool, &()));
+    }
+
+    #[test]
+    fn reorder_blocks_for_layout() {
+        let (mut fun, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        b3(%a);
+    b2(%x):
+        %ret(%x);
+    b3(%y):
+        b2(%y);
+}
+",
+        );
+        let original = fun.clone();
+
+        let renames = fun.builder().reorder_blocks_for_layout();
+        fun.graph_validate_global();
+
+        assert!(fun.block_entry() == Block::new(0));
+        assert!(renames[map.get_block("entry")] == Block::new(0));
+        assert!(renames[map.get_block("b3")] == Block::new(1));
+        assert!(renames[map.get_block("b2")] == Block::new(2));
+
+        assert!(original
+            .graph_eq(original.block_entry(), &fun, fun.block_entry())
+            .is_ok());
+    }
+
+    #[test]
+    fn reorder_blocks_for_layout_edge_weights() {
+        let text = "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a left right;
+    left():
+        join(a'left');
+    right():
+        join(a'right');
+    join(%x):
+        %ret(%x);
+}
+";
+
+        let (mut fun, map) = crate::parse_function_map_unwrap(text);
+        let renames = fun.builder().reorder_blocks_for_layout();
+        assert!(renames[map.get_block("left")] == Block::new(1));
+
+        let (mut fun, map) = crate::parse_function_map_unwrap(text);
+        let original = fun.clone();
+        let (entry, right) = (map.get_block("entry"), map.get_block("right"));
+        fun.builder().block_set_edge_weight(entry, right, 10);
+
+        let renames = fun.builder().reorder_blocks_for_layout();
+        fun.graph_validate_global();
+
+        assert!(renames[right] == Block::new(1));
+        assert!(renames[map.get_block("join")] == Block::new(2));
+        assert!(fun.block_edge_weight(Block::new(0), Block::new(1)) == 10);
+        assert!(original
+            .graph_eq(original.block_entry(), &fun, fun.block_entry())
+            .is_ok());
+    }
+
+    #[test]
+    fn reorder_blocks_for_layout_hot_cold() {
+        let text = "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a left right;
+    left():
+        join(a'left');
+    right():
+        join(a'right');
+    join(%x):
+        %ret(%x);
+}
+";
+
+        let (mut fun, map) = crate::parse_function_map_unwrap(text);
+        let (entry, left) = (map.get_block("entry"), map.get_block("left"));
+        let right = map.get_block("right");
+        let mut b = fun.builder();
+        b.block_set_edge_weight(entry, left, 10);
+        b.block_set_attribute(right, AttributeKey::Hot, AttributeValue::None);
+        let renames = b.reorder_blocks_for_layout();
+        assert!(renames[right] == Block::new(1));
+
+        let (mut fun, map) = crate::parse_function_map_unwrap(text);
+        let original = fun.clone();
+        let left = map.get_block("left");
+        fun.builder()
+            .block_set_attribute(left, AttributeKey::Cold, AttributeValue::None);
+
+        let renames = fun.builder().reorder_blocks_for_layout();
+        fun.graph_validate_global();
+
+        assert!(renames[map.get_block("right")] == Block::new(1));
+        assert!(renames[map.get_block("join")] == Block::new(2));
+        assert!(renames[left] == Block::new(3));
+        assert!(fun.block_is_cold(Block::new(3)));
+        assert!(original
+            .graph_eq(original.block_entry(), &fun, fun.block_entry())
+            .is_ok());
+    }
 }
@@ -2,10 +2,13 @@ use libeir_diagnostics::SourceSpan;
 
 use cranelift_entity::EntityList;
 
+use libeir_intern::Symbol;
+
 use crate::binary::BinaryEntrySpecifier;
+use crate::constant::NilTerm;
 use crate::operation::{DynOp, OpBuild};
 use crate::IntoValue;
-use crate::{BasicType, CallKind, MapPutUpdate, MatchKind, OpKind};
+use crate::{BasicType, BinOp, CallKind, MapPutUpdate, MatchKind, OpKind};
 use crate::{Block, PatternClause, Value};
 
 use super::FunctionBuilder;
@@ -30,6 +33,31 @@ impl<'a> FunctionBuilder<'a> {
         self.graph_update_block(block);
     }
 
+    /// Chains a sequence of clauses into a sequential fallthrough, starting
+    /// at `block`.
+    ///
+    /// Each clause is a pair of a pattern check block and a body block. The
+    /// check block is called with three arguments: the scrutinee, the body
+    /// block of the clause, and a continuation leading to the next clause.
+    /// It is expected to branch to one of the two continuations.
+    ///
+    /// Returns the block that is reached when no clause matches.
+    pub fn op_call_flow_chain(
+        &mut self,
+        block: Block,
+        scrutinee: Value,
+        clauses: &[(Block, Block)],
+    ) -> Block {
+        let mut current = block;
+        for (check, body) in clauses.iter() {
+            let (next, next_val) = self.block_insert_get_val();
+            let body_val = self.value(*body);
+            self.op_call_flow(current, *check, &[scrutinee, body_val, next_val]);
+            current = next;
+        }
+        current
+    }
+
     pub fn op_call_function_next<'b, V>(
         &'b mut self,
         span: SourceSpan,
@@ -79,6 +107,119 @@ impl<'a> FunctionBuilder<'a> {
         (ret, thr)
     }
 
+    /// Calls `guard_body` as a guard sub-expression.
+    ///
+    /// `guard_body` is called like a function, with a return and a throw
+    /// continuation. Its result is passed on to `success`. If it raises, the
+    /// exception is discarded and `fail` is called with no arguments.
+    pub fn op_guard_try(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        guard_body: Block,
+        success: Block,
+        fail: Block,
+    ) {
+        let handler = self.block_insert();
+        self.block_arg_insert(handler);
+        self.block_arg_insert(handler);
+        self.block_arg_insert(handler);
+        self.op_call_flow(handler, fail, &[]);
+
+        let success_val = self.value(success);
+        let handler_val = self.value(handler);
+        self.op_call_function_next(span, block, guard_body, success_val, handler_val, &[]);
+    }
+
+    /// Calls `module:function(args...)` where the module and function
+    /// are runtime values. This is a call to `erlang:apply/3` with the
+    /// arguments collected into a list, leaving resolution to the
+    /// runtime. Non-atom values raise `badarg`, a function that can't be
+    /// resolved raises `undef`.
+    pub fn op_apply_mfa_next(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        module: Value,
+        function: Value,
+        args: &[Value],
+        ret: Value,
+        thr: Value,
+    ) {
+        let (target, apply_args) = self.apply_mfa_args(span, module, function, args);
+        self.op_call_function_next(span, block, target, ret, thr, &apply_args);
+    }
+    pub fn op_apply_mfa(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        module: Value,
+        function: Value,
+        args: &[Value],
+    ) -> (Block, Block) {
+        let (target, apply_args) = self.apply_mfa_args(span, module, function, args);
+        self.op_call_function(span, block, target, &apply_args)
+    }
+
+    fn apply_mfa_args(
+        &mut self,
+        span: SourceSpan,
+        module: Value,
+        function: Value,
+        args: &[Value],
+    ) -> (Value, [Value; 3]) {
+        let target = self.prim_capture_function(
+            span,
+            Symbol::intern("erlang"),
+            Symbol::intern("apply"),
+            3,
+        );
+
+        let mut list = self.value(NilTerm);
+        for arg in args.iter().rev() {
+            list = self.prim_list_cell(span, *arg, list);
+        }
+
+        (target, [module, function, list])
+    }
+
+    /// Calls `fun` with the elements of the runtime list `args` as its
+    /// arguments, for when the number of arguments is not known until
+    /// runtime. This is a call to `erlang:apply/2`, the list is unpacked
+    /// against the arity of `fun` by the runtime. A mismatch raises
+    /// `{badarity, {Fun, Args}}`.
+    pub fn op_apply_fun_next(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        fun: Value,
+        args: Value,
+        ret: Value,
+        thr: Value,
+    ) {
+        let target = self.apply_fun_target(span);
+        self.op_call_function_next(span, block, target, ret, thr, &[fun, args]);
+    }
+    pub fn op_apply_fun(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        fun: Value,
+        args: Value,
+    ) -> (Block, Block) {
+        let target = self.apply_fun_target(span);
+        self.op_call_function(span, block, target, &[fun, args])
+    }
+
+    fn apply_fun_target(&mut self, span: SourceSpan) -> Value {
+        self.prim_capture_function(
+            span,
+            Symbol::intern("erlang"),
+            Symbol::intern("apply"),
+            2,
+        )
+    }
+
     pub fn op_trace_capture_raw_next(&mut self, span: SourceSpan, block: Block, next: Value) {
         let data = self.fun.blocks.get_mut(block).unwrap();
         assert!(data.op.is_none());
@@ -124,11 +265,66 @@ impl<'a> FunctionBuilder<'a> {
     pub fn op_map_put_build(&mut self, span: SourceSpan, value: Value) -> MapPutBuilder {
         MapPutBuilder::new(span, value, self)
     }
+    pub fn op_map_put_build_next(
+        &mut self,
+        span: SourceSpan,
+        value: Value,
+        ok: Value,
+        fail: Value,
+    ) -> MapPutBuilder {
+        MapPutBuilder::new_next(span, value, ok, fail, self)
+    }
 
     pub fn op_case_build(&mut self, span: SourceSpan) -> CaseBuilder {
         CaseBuilder::new(span)
     }
 
+    /// Builds a `Case` matching `scrutinee` against each clause in order.
+    /// The block paired with the first matching clause is called with the
+    /// values bound by the clause, so it must take one argument per bind.
+    ///
+    /// The clauses get guards that always succeed, and may not read any
+    /// outside values.
+    ///
+    /// Returns the block that is reached when no clause matches.
+    pub fn op_match(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        scrutinee: Value,
+        clauses: &[(PatternClause, Block)],
+    ) -> Block {
+        let no_match = self.block_insert();
+
+        let mut case_b = self.op_case_build(span);
+        case_b.match_on = Some(scrutinee);
+        case_b.no_match = Some(self.value(no_match));
+
+        for (clause, target) in clauses.iter() {
+            let num_binds = self.pat().clause_binds(*clause).len();
+            debug_assert!(self.pat().clause_values(*clause).is_empty());
+            debug_assert!(self.block_args(*target).len() == num_binds);
+
+            // Guard lambda returning true
+            let guard = self.block_insert();
+            let guard_cont = self.block_arg_insert(guard);
+            let _guard_throw_cont = self.block_arg_insert(guard);
+            for _ in 0..num_binds {
+                self.block_arg_insert(guard);
+            }
+            let true_val = self.value(true);
+            self.op_call_flow(guard, guard_cont, &[true_val]);
+
+            let guard_val = self.value(guard);
+            let target_val = self.value(*target);
+            case_b.push_clause(*clause, guard_val, target_val, self);
+        }
+
+        case_b.finish(block, self);
+
+        no_match
+    }
+
     pub fn op_unpack_value_list_next(
         &mut self,
         block: Block,
@@ -239,6 +435,88 @@ impl<'a> FunctionBuilder<'a> {
         (true_cont, false_cont)
     }
 
+    /// Tests `subject` against a conjunction of comparisons, each one
+    /// `subject op operand`. The comparisons are calls to the `erlang`
+    /// comparison functions, chained so that a comparison only runs when
+    /// all before it held. Comparisons never raise, the throw continuations
+    /// are unreachable.
+    ///
+    /// Returns the blocks continued to when all comparisons hold, and when
+    /// one of them does not.
+    pub fn op_compare_chain(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        subject: Value,
+        comparisons: &[(BinOp, Value)],
+    ) -> (Block, Block) {
+        let true_cont = self.fun.block_insert();
+        let false_cont = self.fun.block_insert();
+
+        let mut block = block;
+        for (op, operand) in comparisons.iter() {
+            let fun_val = self.prim_capture_function(
+                span,
+                Symbol::intern("erlang"),
+                Symbol::intern(op.erlang_function()),
+                2,
+            );
+            let (ok_block, err_block) =
+                self.op_call_function(span, block, fun_val, &[subject, *operand]);
+            self.op_unreachable(span, err_block);
+
+            let res = self.block_args(ok_block)[0];
+            let (holds, fails) = self.op_if_bool_strict(span, ok_block, res);
+            self.op_call_flow(fails, false_cont, &[]);
+            block = holds;
+        }
+        self.op_call_flow(block, true_cont, &[]);
+
+        (true_cont, false_cont)
+    }
+
+    /// Branches to `match_block` with the tuple elements as arguments if
+    /// `value` is a tuple of exactly `arity` elements, otherwise to
+    /// `fail_block`.
+    pub fn op_is_tuple_of_arity_next(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        value: Value,
+        arity: usize,
+        match_block: Value,
+        fail_block: Value,
+    ) {
+        let mut match_builder = self.op_match_build(span);
+        match_builder.push_tuple_next(match_block, arity, self);
+        match_builder.push_wildcard_next(fail_block, self);
+        match_builder.finish(block, value, self);
+    }
+    pub fn op_is_tuple_of_arity(
+        &mut self,
+        span: SourceSpan,
+        block: Block,
+        value: Value,
+        arity: usize,
+    ) -> (Block, Block) {
+        let (match_block, match_block_val) = self.block_insert_get_val();
+        for _ in 0..arity {
+            self.block_arg_insert(match_block);
+        }
+        let (fail_block, fail_block_val) = self.block_insert_get_val();
+
+        self.op_is_tuple_of_arity_next(
+            span,
+            block,
+            value,
+            arity,
+            match_block_val,
+            fail_block_val,
+        );
+
+        (match_block, fail_block)
+    }
+
     pub fn op_unreachable(&mut self, span: SourceSpan, block: Block) {
         let data = self.fun.blocks.get_mut(block).unwrap();
         assert!(data.op.is_none());
@@ -430,19 +708,16 @@ impl MatchBuilder {
         block
     }
 
-    pub fn push_binary(
+    pub fn push_binary_next(
         &mut self,
+        next: Value,
         specifier: BinaryEntrySpecifier,
         size: Option<Value>,
         b: &mut FunctionBuilder,
-    ) -> Block {
-        let (block, block_val) = b.block_insert_get_val();
-        b.block_arg_insert(block);
-        b.block_arg_insert(block);
-
+    ) {
         self.kinds.push(MatchKind::Binary(specifier));
 
-        self.branches.push(block_val, &mut b.fun.pool.value);
+        self.branches.push(next, &mut b.fun.pool.value);
 
         let args = if let Some(size) = size {
             b.prim_value_list(&[size])
@@ -450,6 +725,18 @@ impl MatchBuilder {
             b.prim_value_list(&[])
         };
         self.branch_args.push(args, &mut b.fun.pool.value);
+    }
+    pub fn push_binary(
+        &mut self,
+        specifier: BinaryEntrySpecifier,
+        size: Option<Value>,
+        b: &mut FunctionBuilder,
+    ) -> Block {
+        let (block, block_val) = b.block_insert_get_val();
+        b.block_arg_insert(block);
+        b.block_arg_insert(block);
+
+        self.push_binary_next(block_val, specifier, size, b);
 
         block
     }
@@ -537,8 +824,8 @@ impl MatchBuilder {
 
 pub struct MapPutBuilder {
     span: SourceSpan,
-    ok: Block,
-    fail: Block,
+    /// The continuation blocks, if they were created by the builder.
+    targets: Option<(Block, Block)>,
     reads: EntityList<Value>,
     actions: Vec<MapPutUpdate>,
 }
@@ -550,16 +837,27 @@ impl MapPutBuilder {
         let (fail, fail_val) = b.block_insert_get_val();
         b.block_arg_insert(fail);
 
+        let mut this = Self::new_next(span, value, ok_val, fail_val, b);
+        this.targets = Some((ok, fail));
+        this
+    }
+
+    pub fn new_next(
+        span: SourceSpan,
+        value: Value,
+        ok: Value,
+        fail: Value,
+        b: &mut FunctionBuilder,
+    ) -> Self {
         let mut reads = EntityList::new();
-        reads.push(ok_val, &mut b.fun.pool.value);
-        reads.push(fail_val, &mut b.fun.pool.value);
+        reads.push(ok, &mut b.fun.pool.value);
+        reads.push(fail, &mut b.fun.pool.value);
 
         reads.push(value, &mut b.fun.pool.value);
 
         MapPutBuilder {
             span,
-            ok,
-            fail,
+            targets: None,
             reads,
             actions: Vec::new(),
         }
@@ -578,7 +876,15 @@ impl MapPutBuilder {
         self.reads.push(val, &mut b.fun.pool.value);
     }
 
+    /// Finishes a builder created with `new`, returning the ok and fail
+    /// blocks.
     pub fn finish(self, block: Block, b: &mut FunctionBuilder) -> (Block, Block) {
+        let targets = self.targets.unwrap();
+        self.finish_next(block, b);
+        targets
+    }
+
+    pub fn finish_next(self, block: Block, b: &mut FunctionBuilder) {
         let data = b.fun.blocks.get_mut(block).unwrap();
         assert!(data.op.is_none());
         assert!(data.reads.is_empty());
@@ -590,7 +896,5 @@ impl MapPutBuilder {
         data.location = b.fun.locations.location(None, None, None, self.span);
 
         b.graph_update_block(block);
-
-        (self.ok, self.fail)
     }
 }
@@ -3,7 +3,7 @@ use cranelift_entity::EntityList;
 use libeir_diagnostics::SourceSpan;
 
 use super::{BinOp, FunctionBuilder, PrimOpData, PrimOpKind};
-use crate::{ConstKind, IntoValue, LogicOp, Value, ValueKind};
+use crate::{BasicType, ConstKind, IntoValue, LogicOp, Value, ValueKind};
 
 /// PrimOp constructors
 impl<'a> FunctionBuilder<'a> {
@@ -182,7 +182,9 @@ impl<'a> FunctionBuilder<'a> {
             },
             &self.fun.pool,
         );
-        self.fun.values.push(ValueKind::PrimOp(primop))
+        let value = self.fun.values.push(ValueKind::PrimOp(primop));
+        self.fun.check_limits();
+        value
     }
 
     pub(crate) fn prim_value_list_from_entity_list(&mut self, values: EntityList<Value>) -> Value {
@@ -206,7 +208,9 @@ impl<'a> FunctionBuilder<'a> {
             },
             &self.fun.pool,
         );
-        self.fun.values.push(ValueKind::PrimOp(primop))
+        let value = self.fun.values.push(ValueKind::PrimOp(primop));
+        self.fun.check_limits();
+        value
     }
 
     pub fn prim_logic_op(&mut self, span: SourceSpan, op: LogicOp, values: &[Value]) -> Value {
@@ -294,6 +298,40 @@ impl<'a> FunctionBuilder<'a> {
             .push_with_location(ValueKind::PrimOp(primop), Some(loc))
     }
 
+    pub fn prim_is_type(&mut self, span: SourceSpan, typ: BasicType, value: Value) -> Value {
+        let loc = self.fun.locations.location(None, None, None, span);
+        let mut reads = EntityList::new();
+        reads.push(value, &mut self.fun.pool.value);
+
+        let primop = self.fun.primops.push(
+            PrimOpData {
+                op: PrimOpKind::IsType(typ),
+                reads,
+            },
+            &self.fun.pool,
+        );
+        self.fun
+            .values
+            .push_with_location(ValueKind::PrimOp(primop), Some(loc))
+    }
+
+    pub fn prim_type_tag(&mut self, span: SourceSpan, value: Value) -> Value {
+        let loc = self.fun.locations.location(None, None, None, span);
+        let mut reads = EntityList::new();
+        reads.push(value, &mut self.fun.pool.value);
+
+        let primop = self.fun.primops.push(
+            PrimOpData {
+                op: PrimOpKind::TypeTag,
+                reads,
+            },
+            &self.fun.pool,
+        );
+        self.fun
+            .values
+            .push_with_location(ValueKind::PrimOp(primop), Some(loc))
+    }
+
     pub fn prim_from_kind(&mut self, span: SourceSpan, op: PrimOpKind, vals: &[Value]) -> Value {
         match op {
             PrimOpKind::ValueList => self.prim_value_list(vals),
@@ -311,7 +349,20 @@ impl<'a> FunctionBuilder<'a> {
                 assert!(vals.len() == 2);
                 self.prim_list_cell(span, vals[0], vals[1])
             }
-            p => unimplemented!("{:?}", p),
+            PrimOpKind::Map => {
+                assert!(vals.len() % 2 == 0);
+                let keys: Vec<_> = vals.iter().step_by(2).cloned().collect();
+                let values: Vec<_> = vals.iter().skip(1).step_by(2).cloned().collect();
+                self.prim_map(span, &keys, &values)
+            }
+            PrimOpKind::IsType(typ) => {
+                assert!(vals.len() == 1);
+                self.prim_is_type(span, typ, vals[0])
+            }
+            PrimOpKind::TypeTag => {
+                assert!(vals.len() == 1);
+                self.prim_type_tag(span, vals[0])
+            }
         }
     }
 }
@@ -0,0 +1,176 @@
+use snafu::Snafu;
+
+use super::builder::FunctionBuilder;
+use super::{Block, Function, Value};
+
+/// Upper bounds on the number of blocks and values in a function, `None`
+/// is unbounded. The `try_` insertion methods of `FunctionBuilder` fail
+/// instead of going over them. Every other insertion records the first
+/// limit it exceeded in the function, see `Function::allocation_error`,
+/// which lets lowering of untrusted code bail out instead of running
+/// away on pathological input.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct AllocationLimits {
+    pub max_blocks: Option<usize>,
+    pub max_values: Option<usize>,
+}
+
+impl AllocationLimits {
+    fn check(&self, blocks: usize, values: usize) -> Result<(), AllocationError> {
+        if let Some(limit) = self.max_blocks {
+            if blocks > limit {
+                return Err(AllocationError::BlockLimit { limit });
+            }
+        }
+        if let Some(limit) = self.max_values {
+            if values > limit {
+                return Err(AllocationError::ValueLimit { limit });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Snafu, Debug, Clone, PartialEq, Eq)]
+pub enum AllocationError {
+    #[snafu(display("function exceeds the limit of {} blocks", limit))]
+    BlockLimit { limit: usize },
+
+    #[snafu(display("function exceeds the limit of {} values", limit))]
+    ValueLimit { limit: usize },
+}
+
+impl Function {
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn value_count(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn allocation_limits(&self) -> AllocationLimits {
+        self.limits
+    }
+
+    /// The first limit that was exceeded by an insertion that can't fail,
+    /// like `FunctionBuilder::block_insert`.
+    pub fn allocation_error(&self) -> Option<&AllocationError> {
+        self.limit_exceeded.as_ref()
+    }
+
+    /// Called after every insertion of a block or value.
+    pub(crate) fn check_limits(&mut self) {
+        if self.limit_exceeded.is_none() {
+            self.limit_exceeded = self
+                .limits
+                .check(self.block_count(), self.value_count())
+                .err();
+        }
+    }
+}
+
+impl<'a> FunctionBuilder<'a> {
+    /// Sets the limits the function is checked against. They are kept
+    /// with the function, and apply to later builders as well.
+    pub fn set_allocation_limits(&mut self, limits: AllocationLimits) {
+        let fun = self.fun_mut();
+        fun.limits = limits;
+        fun.limit_exceeded = None;
+        fun.check_limits();
+    }
+
+    /// Like `block_insert`, but fails instead of going over the block or
+    /// value limit. The block itself takes a value.
+    pub fn try_block_insert(&mut self) -> Result<Block, AllocationError> {
+        let fun = self.fun();
+        fun.limits
+            .check(fun.block_count() + 1, fun.value_count() + 1)?;
+        Ok(self.block_insert())
+    }
+
+    /// Like `block_arg_insert`, but fails instead of going over the value
+    /// limit.
+    pub fn try_block_arg_insert(&mut self, block: Block) -> Result<Value, AllocationError> {
+        let fun = self.fun();
+        fun.limits.check(fun.block_count(), fun.value_count() + 1)?;
+        Ok(self.block_arg_insert(block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libeir_diagnostics::SourceSpan;
+    use libeir_intern::Ident;
+
+    use super::{AllocationError, AllocationLimits};
+    use crate::{Function, FunctionIdent};
+
+    fn new_function() -> Function {
+        let ident = FunctionIdent {
+            module: Ident::from_str("foo"),
+            name: Ident::from_str("bar"),
+            arity: 0,
+        };
+        Function::new(SourceSpan::UNKNOWN, ident)
+    }
+
+    #[test]
+    fn block_limit() {
+        let mut fun = new_function();
+
+        let mut b = fun.builder();
+        b.set_allocation_limits(AllocationLimits {
+            max_blocks: Some(3),
+            max_values: Some(5),
+        });
+
+        // Every block takes a value
+        let entry = b.try_block_insert().unwrap();
+        b.try_block_insert().unwrap();
+        b.try_block_insert().unwrap();
+        assert!(b.try_block_insert() == Err(AllocationError::BlockLimit { limit: 3 }));
+        assert!(b.fun().block_count() == 3);
+        assert!(b.fun().value_count() == 3);
+
+        b.try_block_arg_insert(entry).unwrap();
+        b.try_block_arg_insert(entry).unwrap();
+        assert!(b.try_block_arg_insert(entry) == Err(AllocationError::ValueLimit { limit: 5 }));
+        assert!(b.fun().value_count() == 5);
+        assert!(b.fun().allocation_error().is_none());
+
+        // Limits stay with the function
+        assert!(fun.builder().try_block_insert().is_err());
+    }
+
+    #[test]
+    fn block_insert_value_limit() {
+        let mut fun = new_function();
+
+        let mut b = fun.builder();
+        b.set_allocation_limits(AllocationLimits {
+            max_blocks: None,
+            max_values: Some(1),
+        });
+
+        b.try_block_insert().unwrap();
+        assert!(b.try_block_insert() == Err(AllocationError::ValueLimit { limit: 1 }));
+    }
+
+    #[test]
+    fn infallible_insert_records_error() {
+        let mut fun = new_function();
+
+        let mut b = fun.builder();
+        b.set_allocation_limits(AllocationLimits {
+            max_blocks: Some(1),
+            max_values: None,
+        });
+
+        b.block_insert();
+        assert!(b.fun().allocation_error().is_none());
+        b.block_insert();
+        b.block_insert();
+        assert!(b.fun().allocation_error() == Some(&AllocationError::BlockLimit { limit: 1 }));
+    }
+}
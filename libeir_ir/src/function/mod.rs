@@ -23,7 +23,7 @@ mod pool_container;
 use pool_container::PoolContainer;
 
 mod op;
-pub use op::{BasicType, CallKind, MapPutUpdate, MatchKind, OpKind};
+pub use op::{BasicType, CallKind, Effects, MapPutUpdate, MatchKind, OpKind};
 
 mod primop;
 pub use primop::{BinOp, LogicOp, PrimOpKind};
@@ -38,6 +38,13 @@ pub use location::{Location, LocationContainer};
 mod format;
 pub use format::{ContainerDebug, ContainerDebugAdapter};
 
+mod block_meta;
+use block_meta::BlockMeta;
+pub use block_meta::{EdgeWeights, DEFAULT_EDGE_WEIGHT};
+
+mod limits;
+pub use limits::{AllocationError, AllocationLimits};
+
 //mod serialize;
 
 /// Block/continuation
@@ -74,6 +81,9 @@ pub struct BlockData {
 
     pub(crate) op: Option<OpKind>,
     pub(crate) reads: EntityList<Value>,
+    /// Source spans of the individual reads, for pointing at a specific
+    /// operand in diagnostics. Either empty or as long as `reads`.
+    pub(crate) read_spans: Vec<SourceSpan>,
 
     pub(crate) location: Location,
 
@@ -110,13 +120,21 @@ impl AuxEq<PoolContainer> for PrimOpData {
     }
 }
 
+/// Keys of the attributes that can be set on a block, see
+/// `FunctionBuilder::block_set_attribute`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum AttributeKey {
     Continuation,
+    /// The block is unlikely to be executed, like an error path.
+    Cold,
+    /// The block is likely to be executed often.
+    Hot,
 }
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AttributeValue {
     None,
+    Bool(bool),
+    Int(i64),
 }
 
 #[derive(Clone)]
@@ -140,6 +158,9 @@ pub struct Function {
     // Auxiliary information
     pub constant_values: HashSet<Value>,
     pub locations: LocationContainer,
+    block_meta: BlockMeta,
+    limits: AllocationLimits,
+    limit_exceeded: Option<AllocationError>,
 }
 
 impl Function {
@@ -287,10 +308,16 @@ impl Function {
         }
     }
 
+    /// The blocks that read `value`, directly or nested in a primop.
     pub fn value_usages(&self, value: Value) -> BoundSet<Block, ()> {
         self.values[value].usages.bind(&self.pool.block_set, &())
     }
 
+    /// The number of blocks that read `value`, see `value_usages`.
+    pub fn value_usage_count(&self, value: Value) -> usize {
+        self.value_usages(value).iter().count()
+    }
+
     /// Walks all nested values contained within
     /// the tree of potential PrimOps.
     pub fn value_walk_nested_values<F, R>(&self, value: Value, visit: &mut F) -> Result<(), R>
@@ -372,6 +399,7 @@ impl Function {
 
             op: None,
             reads: EntityList::new(),
+            read_spans: Vec::new(),
 
             predecessors: Set::new(),
             successors: Set::new(),
@@ -379,6 +407,7 @@ impl Function {
             location,
         });
         self.values.push(ValueKind::Block(block));
+        self.check_limits();
         block
     }
 
@@ -386,6 +415,7 @@ impl Function {
         let arg_num = self.blocks[block].arguments.len(&self.pool.value);
         let val = self.values.push(ValueKind::Argument(block, arg_num));
         self.blocks[block].arguments.push(val, &mut self.pool.value);
+        self.check_limits();
         val
     }
 
@@ -421,6 +451,21 @@ impl Function {
         self.blocks[block].reads.as_slice(&self.pool.value)
     }
 
+    /// Source spans of the reads of the block, empty if none were
+    /// recorded. See `FunctionBuilder::block_set_read_spans`.
+    pub fn block_read_spans(&self, block: Block) -> &[SourceSpan] {
+        &self.blocks[block].read_spans
+    }
+
+    /// Source span of the `n`th read of the block, if one was recorded.
+    pub fn block_read_span(&self, block: Block, n: usize) -> Option<SourceSpan> {
+        self.blocks[block]
+            .read_spans
+            .get(n)
+            .cloned()
+            .filter(|span| *span != SourceSpan::UNKNOWN)
+    }
+
     pub fn block_value(&self, block: Block) -> Value {
         self.values.get(ValueKind::Block(block)).unwrap()
     }
@@ -454,8 +499,15 @@ impl Function {
 
     pub fn block_op_eq(&self, lb: Block, r_fun: &Function, rb: Block) -> bool {
         match (self.block_kind(lb).unwrap(), r_fun.block_kind(rb).unwrap()) {
-            (OpKind::Case { .. }, _) => unimplemented!(),
-            (_, OpKind::Case { .. }) => unimplemented!(),
+            (OpKind::Case { clauses: c1 }, OpKind::Case { clauses: c2 }) => {
+                let c1 = c1.as_slice(&self.pool.clause);
+                let c2 = c2.as_slice(&r_fun.pool.clause);
+                c1.len() == c2.len()
+                    && c1.iter().zip(c2).all(|(l, r)| {
+                        self.pat()
+                            .clause_eq(self.cons(), *l, r_fun.pat(), r_fun.cons(), *r)
+                    })
+            }
             (OpKind::Call(l), OpKind::Call(r)) => l == r,
             (OpKind::IfBool, OpKind::IfBool) => true,
             (OpKind::Dyn(l), OpKind::Dyn(r)) => l.op_eq(&**r),
@@ -563,6 +615,63 @@ impl SetPoolProvider for Block {
     }
 }
 
+pub trait EntitySetPoolProvider: EntityRef {
+    fn entity_set_pool(fun: &Function) -> &EntitySetPool<Self>;
+    fn entity_set_pool_mut(fun: &mut Function) -> &mut EntitySetPool<Self>;
+}
+impl EntitySetPoolProvider for Block {
+    fn entity_set_pool(fun: &Function) -> &EntitySetPool<Block> {
+        &fun.pool.block_entity_set
+    }
+    fn entity_set_pool_mut(fun: &mut Function) -> &mut EntitySetPool<Block> {
+        &mut fun.pool.block_entity_set
+    }
+}
+impl EntitySetPoolProvider for Value {
+    fn entity_set_pool(fun: &Function) -> &EntitySetPool<Value> {
+        &fun.pool.value_entity_set
+    }
+    fn entity_set_pool_mut(fun: &mut Function) -> &mut EntitySetPool<Value> {
+        &mut fun.pool.value_entity_set
+    }
+}
+
+/// Set algebra on `EntitySet`s of blocks and values, using the pools of
+/// the function.
+impl Function {
+    pub fn entity_set_pool<E: EntitySetPoolProvider>(&self) -> &EntitySetPool<E> {
+        E::entity_set_pool(self)
+    }
+
+    pub fn entity_set_pool_mut<E: EntitySetPoolProvider>(&mut self) -> &mut EntitySetPool<E> {
+        E::entity_set_pool_mut(self)
+    }
+
+    pub fn entity_set_union_with<E: EntitySetPoolProvider>(
+        &mut self,
+        set: &mut EntitySet<E>,
+        other: &EntitySet<E>,
+    ) {
+        set.union_with(other, E::entity_set_pool_mut(self))
+    }
+
+    pub fn entity_set_intersect_with<E: EntitySetPoolProvider>(
+        &mut self,
+        set: &mut EntitySet<E>,
+        other: &EntitySet<E>,
+    ) {
+        set.intersect_with(other, E::entity_set_pool_mut(self))
+    }
+
+    pub fn entity_set_difference_with<E: EntitySetPoolProvider>(
+        &mut self,
+        set: &mut EntitySet<E>,
+        other: &EntitySet<E>,
+    ) {
+        set.difference_with(other, E::entity_set_pool_mut(self))
+    }
+}
+
 impl Function {
     pub fn new(span: SourceSpan, ident: FunctionIdent) -> Self {
         Function {
@@ -581,6 +690,8 @@ impl Function {
                 value: ListPool::new(),
                 clause: ListPool::new(),
                 block_set: SetForest::new(),
+                block_entity_set: EntitySetPool::new(),
+                value_entity_set: EntitySetPool::new(),
             },
 
             pattern_container: PatternContainer::new(),
@@ -589,6 +700,9 @@ impl Function {
             constant_values: HashSet::new(),
 
             locations: LocationContainer::new(),
+            block_meta: BlockMeta::default(),
+            limits: AllocationLimits::default(),
+            limit_exceeded: None,
         }
     }
 
@@ -596,6 +710,14 @@ impl Function {
         &self.ident
     }
 
+    pub(crate) fn set_ident(&mut self, ident: FunctionIdent) {
+        self.ident = ident;
+    }
+
+    pub(crate) fn has_entry(&self) -> bool {
+        self.entry_block.is_some()
+    }
+
     pub fn entry_arg_num(&self) -> usize {
         self.block_args(self.block_entry()).len()
     }
@@ -177,4 +177,100 @@ impl OpKind {
             _ => false,
         }
     }
+
+    /// The side effects the operation may have, besides branching to one
+    /// of its successors. Failures reported through a failure branch are
+    /// not counted as raising.
+    pub fn effects(&self) -> Effects {
+        match self {
+            OpKind::Call(CallKind::ControlFlow) => Effects::PURE,
+            // The callee is unknown
+            OpKind::Call(CallKind::Function) => Effects::ALL,
+            OpKind::IfBool => Effects::PURE,
+            OpKind::TraceCaptureRaw => Effects {
+                reads_process: true,
+                ..Effects::PURE
+            },
+            OpKind::TraceConstruct => Effects::PURE,
+            OpKind::MapPut { .. } => Effects::PURE,
+            OpKind::UnpackValueList(_) => Effects::PURE,
+            OpKind::Case { .. } => Effects::PURE,
+            OpKind::Match { .. } => Effects::PURE,
+            OpKind::Unreachable => Effects {
+                may_raise: true,
+                ..Effects::PURE
+            },
+            OpKind::Dyn(op) => op.effects(),
+        }
+    }
+}
+
+/// Side effects of an operation, see `OpKind::effects`. Passes that
+/// reorder, hoist or remove operations must leave effectful ones alone.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Effects {
+    /// Reads process state, like the mailbox or the process dictionary.
+    pub reads_process: bool,
+    /// Writes process state.
+    pub writes_process: bool,
+    /// May raise an exception.
+    pub may_raise: bool,
+}
+
+impl Effects {
+    pub const PURE: Effects = Effects {
+        reads_process: false,
+        writes_process: false,
+        may_raise: false,
+    };
+    pub const ALL: Effects = Effects {
+        reads_process: true,
+        writes_process: true,
+        may_raise: true,
+    };
+
+    pub fn is_pure(&self) -> bool {
+        *self == Effects::PURE
+    }
+
+    pub fn union(self, other: Effects) -> Effects {
+        Effects {
+            reads_process: self.reads_process || other.reads_process,
+            writes_process: self.writes_process || other.writes_process,
+            may_raise: self.may_raise || other.may_raise,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Effects;
+    use crate::PrimOpKind;
+
+    #[test]
+    fn op_effects() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/2 {
+    entry(%ret, %thr, %pid, %msg):
+        %tup = {%pid, %msg};
+        b1(%tup);
+    b1(%tup_arg):
+        %send = a'erlang':a'!'/2;
+        %send(%pid, %tup_arg) => %ret except %thr;
+}
+",
+        );
+
+        // The tuple is the only argument of the call to b1
+        let entry_reads = ir.block_reads(map.get_block("entry"));
+        let construct = ir.value_primop(entry_reads[1]).unwrap();
+        assert!(*ir.primop_kind(construct) == PrimOpKind::Tuple);
+        assert!(ir.primop_kind(construct).effects().is_pure());
+
+        let send = ir.block_kind(map.get_block("b1")).unwrap().effects();
+        assert!(!send.is_pure());
+        assert!(send.writes_process);
+        assert!(send == Effects::ALL);
+    }
 }
@@ -9,4 +9,6 @@ pub struct PoolContainer {
     pub value: ListPool<Value>,
     pub clause: ListPool<PatternClause>,
     pub block_set: SetForest<Block>,
+    pub block_entity_set: EntitySetPool<Block>,
+    pub value_entity_set: EntitySetPool<Value>,
 }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::Effects;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BinOp {
     /// ==
@@ -29,6 +31,32 @@ impl BinOp {
             _ => false,
         }
     }
+
+    /// The same comparison with the operands swapped, `a < b` is `b > a`.
+    pub fn swapped(self) -> BinOp {
+        match self {
+            BinOp::LessEqual => BinOp::GreaterEqual,
+            BinOp::Less => BinOp::Greater,
+            BinOp::GreaterEqual => BinOp::LessEqual,
+            BinOp::Greater => BinOp::Less,
+            op => op,
+        }
+    }
+
+    /// Name of the function in the `erlang` module that implements the
+    /// comparison.
+    pub fn erlang_function(self) -> &'static str {
+        match self {
+            BinOp::Equal => "==",
+            BinOp::NotEqual => "/=",
+            BinOp::LessEqual => "=<",
+            BinOp::Less => "<",
+            BinOp::GreaterEqual => ">=",
+            BinOp::Greater => ">",
+            BinOp::ExactEqual => "=:=",
+            BinOp::ExactNotEqual => "=/=",
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -76,3 +104,11 @@ pub enum PrimOpKind {
     /// `(m, f, a)`
     CaptureFunction,
 }
+
+impl PrimOpKind {
+    /// The side effects of evaluating the primop, see `OpKind::effects`.
+    /// Primops only compute a value from their reads, they have none.
+    pub fn effects(&self) -> Effects {
+        Effects::PURE
+    }
+}
@@ -2,9 +2,9 @@ use std::collections::HashMap;
 use std::ops::{Index, IndexMut};
 
 use super::{Block, Const, Location, PrimOp};
-use cranelift_bforest::Set;
+use cranelift_bforest::{Set, SetForest};
 use cranelift_entity::packed_option::ReservedValue;
-use cranelift_entity::{entity_impl, PrimaryMap};
+use cranelift_entity::{entity_impl, Keys, PrimaryMap};
 use libeir_util_datastructures::aux_traits::AuxDebug;
 use libeir_util_datastructures::pooled_entity_set::EntitySet;
 
@@ -89,6 +89,40 @@ impl ValueMap {
     pub fn get(&self, kind: ValueKind) -> Option<Value> {
         self.back.get(&kind).cloned()
     }
+
+    pub(crate) fn keys(&self) -> Keys<Value> {
+        self.primary.keys()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.primary.len()
+    }
+
+    /// Renames every block referenced by values, in their kinds as well
+    /// as their usage sets.
+    pub(crate) fn map_blocks<F>(&mut self, pool: &mut SetForest<Block>, map: F)
+    where
+        F: Fn(Block) -> Block,
+    {
+        let mut usages = Vec::new();
+
+        self.back.clear();
+        for (value, data) in self.primary.iter_mut() {
+            data.kind = match data.kind {
+                ValueKind::Argument(block, num) => ValueKind::Argument(map(block), num),
+                ValueKind::Block(block) => ValueKind::Block(map(block)),
+                kind => kind,
+            };
+            self.back.insert(data.kind, value);
+
+            // The sets are ordered by block, they have to be rebuilt
+            usages.extend(data.usages.iter(pool));
+            data.usages.clear(pool);
+            for block in usages.drain(..) {
+                data.usages.insert(map(block), pool, &());
+            }
+        }
+    }
 }
 
 impl Index<Value> for ValueMap {
@@ -0,0 +1,211 @@
+//! Text form of `FunctionIdent`, `module:name/arity`. Atoms are quoted
+//! when needed, like Erlang would print them.
+
+use std::fmt::{Display, Formatter, Write};
+use std::str::FromStr;
+
+use snafu::Snafu;
+
+use libeir_intern::Ident;
+
+use crate::FunctionIdent;
+
+const RESERVED_WORDS: &[&str] = &[
+    "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor", "case",
+    "catch", "cond", "div", "end", "fun", "if", "let", "not", "of", "or", "orelse", "receive",
+    "rem", "try", "when", "xor",
+];
+
+#[derive(Snafu, Debug, Clone, PartialEq, Eq)]
+pub enum ParseFunctionIdentError {
+    #[snafu(display("expected an atom at offset {}", offset))]
+    ExpectedAtom { offset: usize },
+
+    #[snafu(display("unclosed quoted atom starting at offset {}", offset))]
+    UnclosedAtom { offset: usize },
+
+    #[snafu(display("expected '{}' at offset {}", expected, offset))]
+    ExpectedChar { expected: char, offset: usize },
+
+    #[snafu(display("invalid arity at offset {}", offset))]
+    InvalidArity { offset: usize },
+}
+
+fn is_bare_atom(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+        && !RESERVED_WORDS.contains(&name)
+}
+
+/// Writes the atom `name` like Erlang prints it, quoted unless it is a
+/// plain lowercase word that isn't a reserved word.
+pub fn write_atom<W: Write>(out: &mut W, name: &str) -> std::fmt::Result {
+    if is_bare_atom(name) {
+        return out.write_str(name);
+    }
+    out.write_char('\'')?;
+    for c in name.chars() {
+        if c == '\'' || c == '\\' {
+            out.write_char('\\')?;
+        }
+        out.write_char(c)?;
+    }
+    out.write_char('\'')
+}
+
+impl Display for FunctionIdent {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write_atom(f, &self.module.as_str())?;
+        f.write_char(':')?;
+        write_atom(f, &self.name.as_str())?;
+        write!(f, "/{}", self.arity)
+    }
+}
+
+/// Parses an atom at the start of `input`, returns it and the rest of
+/// the input. `offset` is the position of `input` in the full string.
+fn parse_atom(input: &str, offset: usize) -> Result<(Ident, &str), ParseFunctionIdentError> {
+    if input.starts_with('\'') {
+        let mut name = String::new();
+        let mut chars = input.char_indices().skip(1);
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\'' => return Ok((Ident::from_str(&name), &input[idx + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => name.push(escaped),
+                    None => break,
+                },
+                c => name.push(c),
+            }
+        }
+        UnclosedAtom { offset }.fail()
+    } else {
+        let end = input
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '@'))
+            .unwrap_or(input.len());
+        let name = &input[..end];
+        if name.starts_with(|c: char| c.is_ascii_lowercase()) {
+            Ok((Ident::from_str(name), &input[end..]))
+        } else {
+            ExpectedAtom { offset }.fail()
+        }
+    }
+}
+
+fn expect_char(
+    input: &str,
+    expected: char,
+    offset: usize,
+) -> Result<&str, ParseFunctionIdentError> {
+    if input.starts_with(expected) {
+        Ok(&input[expected.len_utf8()..])
+    } else {
+        ExpectedChar { expected, offset }.fail()
+    }
+}
+
+impl FromStr for FunctionIdent {
+    type Err = ParseFunctionIdentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let offset = |rest: &str| s.len() - rest.len();
+
+        let (module, rest) = parse_atom(s, 0)?;
+        let rest = expect_char(rest, ':', offset(rest))?;
+        let (name, rest) = parse_atom(rest, offset(rest))?;
+        let rest = expect_char(rest, '/', offset(rest))?;
+
+        // `usize::from_str` accepts a leading `+`, the printed form never
+        // has one.
+        let arity_offset = offset(rest);
+        let arity_valid = !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit());
+        let arity = match rest.parse() {
+            Ok(arity) if arity_valid => arity,
+            _ => {
+                return InvalidArity {
+                    offset: arity_offset,
+                }
+                .fail()
+            }
+        };
+
+        Ok(FunctionIdent {
+            module,
+            name,
+            arity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseFunctionIdentError;
+    use crate::FunctionIdent;
+    use libeir_intern::Ident;
+
+    fn ident(module: &str, name: &str, arity: usize) -> FunctionIdent {
+        FunctionIdent {
+            module: Ident::from_str(module),
+            name: Ident::from_str(name),
+            arity,
+        }
+    }
+
+    #[test]
+    fn parse_bare() {
+        let parsed: FunctionIdent = "lists:reverse/1".parse().unwrap();
+        assert_eq!(parsed, ident("lists", "reverse", 1));
+        assert_eq!(parsed.to_string(), "lists:reverse/1");
+    }
+
+    #[test]
+    fn round_trip_quoted() {
+        let idents = [
+            ident("weird name", "f", 0),
+            ident("woo", "-run/0-fun-0-", 2),
+            ident("Upper", "it's", 1),
+            ident("back\\slash", "case", 3),
+            ident("a@b", "x_1", 10),
+        ];
+        for expected in idents.iter() {
+            let text = expected.to_string();
+            let parsed: FunctionIdent = text.parse().unwrap();
+            assert_eq!(parsed, *expected, "{}", text);
+        }
+
+        assert_eq!(ident("weird name", "f", 0).to_string(), "'weird name':f/0");
+        assert_eq!(ident("Upper", "it's", 1).to_string(), "'Upper':'it\\'s'/1");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "Lists:reverse/1".parse::<FunctionIdent>(),
+            Err(ParseFunctionIdentError::ExpectedAtom { offset: 0 })
+        );
+        assert_eq!(
+            "'lists:reverse/1".parse::<FunctionIdent>(),
+            Err(ParseFunctionIdentError::UnclosedAtom { offset: 0 })
+        );
+        assert_eq!(
+            "lists.reverse/1".parse::<FunctionIdent>(),
+            Err(ParseFunctionIdentError::ExpectedChar {
+                expected: ':',
+                offset: 5
+            })
+        );
+        assert_eq!(
+            "lists:reverse".parse::<FunctionIdent>(),
+            Err(ParseFunctionIdentError::ExpectedChar {
+                expected: '/',
+                offset: 13
+            })
+        );
+        assert_eq!(
+            "lists:reverse/+1".parse::<FunctionIdent>(),
+            Err(ParseFunctionIdentError::InvalidArity { offset: 14 })
+        );
+    }
+}
@@ -1,10 +1,12 @@
+use petgraph::algo::dominators::{self, Dominators};
 use petgraph::visit::{Dfs, DfsPostOrder};
 use petgraph::visit::{
-    GraphBase, IntoNeighbors, IntoNeighborsDirected, VisitMap, Visitable, Walker,
+    GraphBase, IntoNeighbors, IntoNeighborsDirected, IntoNodeIdentifiers, NodeIndexable, VisitMap,
+    Visitable, Walker,
 };
 use petgraph::Direction;
 
-use cranelift_entity::{EntityRef, EntitySet};
+use cranelift_entity::{EntityRef, EntitySet, Keys};
 
 use itertools::Either;
 
@@ -12,11 +14,36 @@ use cranelift_bforest::SetIter;
 
 use crate::Block;
 use crate::Function;
+use crate::OpKind;
 
 impl Function {
     pub fn block_graph(&self) -> BlockGraph<'_> {
         BlockGraph::new(self)
     }
+
+    /// Calls `f` once for each block reachable from the entry, in reverse
+    /// post order. The entry block is always visited first.
+    pub fn walk_blocks<F>(&self, mut f: F)
+    where
+        F: FnMut(Block),
+    {
+        let graph = self.block_graph();
+        let post_order: Vec<Block> = graph.dfs_post_order_iter().collect();
+        for block in post_order.into_iter().rev() {
+            f(block);
+        }
+    }
+
+    /// The operations of the blocks reachable from the entry, in reverse
+    /// post order. Blocks without an operation are skipped.
+    pub fn ops_rpo(&self) -> impl Iterator<Item = (Block, &OpKind)> + '_ {
+        let graph = self.block_graph();
+        let post_order: Vec<Block> = graph.dfs_post_order_iter().collect();
+        post_order
+            .into_iter()
+            .rev()
+            .filter_map(move |block| self.block_kind(block).map(|op| (block, op)))
+    }
 }
 
 /// This is a newtype that contains implementations of petgraphs graph traits.
@@ -54,6 +81,32 @@ impl<'a> BlockGraph<'a> {
         self.dfs_post_order().iter(self)
     }
 
+    /// Computes the dominator tree of the blocks reachable from the entry
+    /// block. Unreachable blocks have no dominators.
+    pub fn dominators(&self) -> Dominators<Block> {
+        dominators::simple_fast(self, self.fun.block_entry())
+    }
+
+    /// The strongly connected components of the graph, in reverse
+    /// topological order.
+    ///
+    /// Every block of the function is a node, blocks that are not
+    /// reachable from the entry form components of their own. Edges only
+    /// ever go from a block to the blocks it reads, so a dead block never
+    /// ends up in a component with a live one. Use
+    /// `LiveBlockGraph::sccs` to only get the live components.
+    pub fn sccs(&self) -> Vec<Vec<Block>> {
+        petgraph::algo::tarjan_scc(self)
+    }
+
+    /// The components of `sccs` that are loops: those with more than one
+    /// block, or a single block that branches to itself.
+    pub fn loops(&self) -> Vec<Vec<Block>> {
+        let mut sccs = self.sccs();
+        sccs.retain(|scc| is_loop(self.fun, scc));
+        sccs
+    }
+
     pub fn outgoing(&'a self, block: Block) -> impl Iterator<Item = Block> + 'a {
         self.fun.blocks[block]
             .successors
@@ -61,6 +114,15 @@ impl<'a> BlockGraph<'a> {
     }
 }
 
+pub(super) fn is_loop(fun: &Function, scc: &[Block]) -> bool {
+    match scc {
+        [block] => fun.blocks[*block]
+            .successors
+            .contains(*block, &fun.pool.block_set, &()),
+        _ => true,
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct BlockEdge(Block, usize);
 
@@ -122,6 +184,29 @@ impl<'a> IntoNeighborsDirected for &'a BlockGraph<'a> {
     }
 }
 
+impl<'a> IntoNodeIdentifiers for &'a BlockGraph<'a> {
+    type NodeIdentifiers = Keys<Block>;
+    #[inline]
+    fn node_identifiers(self) -> Self::NodeIdentifiers {
+        self.fun.blocks.keys()
+    }
+}
+
+impl<'a> NodeIndexable for BlockGraph<'a> {
+    #[inline]
+    fn node_bound(&self) -> usize {
+        self.fun.blocks.len()
+    }
+    #[inline]
+    fn to_index(&self, block: Block) -> usize {
+        block.index()
+    }
+    #[inline]
+    fn from_index(&self, index: usize) -> Block {
+        Block::new(index)
+    }
+}
+
 pub struct EntityVisitMap<E>
 where
     E: EntityRef,
@@ -171,7 +256,7 @@ impl<'a> Visitable for BlockGraph<'a> {
 #[cfg(test)]
 mod tests {
 
-    use crate::{Function, FunctionBuilder, FunctionIdent};
+    use crate::{Function, FunctionBuilder, FunctionIdent, OpKind};
     use libeir_diagnostics::SourceSpan;
     use libeir_intern::Ident;
 
@@ -188,9 +273,8 @@ mod tests {
         let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
         let mut b = FunctionBuilder::new(&mut fun);
 
-        let b1 = b.block_insert();
-        b.block_set_entry(b1);
-        let b1_ret = b.block_arg_insert(b1);
+        let (b1, b1_args) = b.create_entry(1);
+        let b1_ret = b1_args[0];
 
         let b2 = b.block_insert();
 
@@ -239,4 +323,162 @@ mod tests {
                 == &[]
         );
     }
+
+    #[test]
+    fn walk_blocks() {
+        let (fun, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a yes no;
+    yes():
+        join(a'true');
+    no():
+        join(a'false');
+    join(%r):
+        %ret(%r);
+    dead():
+        join(a'dead');
+}
+",
+        );
+
+        let mut visited = Vec::new();
+        fun.walk_blocks(|block| visited.push(block));
+
+        assert!(visited[0] == map.get_block("entry"));
+        assert!(visited.len() == 4);
+        for name in &["entry", "yes", "no", "join"] {
+            let block = map.get_block(name);
+            assert!(visited.iter().filter(|b| **b == block).count() == 1);
+        }
+        assert!(!visited.contains(&map.get_block("dead")));
+
+        let join = visited.iter().position(|b| *b == map.get_block("join"));
+        assert!(join == Some(3));
+    }
+
+    #[test]
+    fn ops_rpo() {
+        let (fun, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a yes no;
+    yes():
+        join(a'true');
+    no():
+        join(a'false');
+    join(%r):
+        %ret(%r);
+    dead():
+        join(a'dead');
+}
+",
+        );
+
+        let ops: Vec<_> = fun.ops_rpo().collect();
+        assert!(ops.len() == 4);
+        assert!(ops[0].0 == map.get_block("entry"));
+        match ops[0].1 {
+            OpKind::IfBool => (),
+            other => panic!("expected if_bool, got {:?}", other),
+        }
+        assert!(ops[3].0 == map.get_block("join"));
+        assert!(!ops.iter().any(|(b, _)| *b == map.get_block("dead")));
+
+        // Blocks still being built have no operation
+        let ident = FunctionIdent {
+            module: Ident::from_str("woo"),
+            name: Ident::from_str("woo"),
+            arity: 0,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+        let mut b = FunctionBuilder::new(&mut fun);
+        let (entry, _) = b.create_entry(0);
+        let next = b.block_insert();
+        b.op_call_flow(entry, next, &[]);
+
+        let blocks: Vec<_> = fun.ops_rpo().map(|(block, _)| block).collect();
+        assert!(blocks == vec![entry]);
+    }
+
+    #[test]
+    fn dominators() {
+        let (fun, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a yes no;
+    yes():
+        join(a'true');
+    no():
+        join(a'false');
+    join(%r):
+        tail(%r);
+    tail(%t):
+        %ret(%t);
+    dead():
+        join(a'dead');
+}
+",
+        );
+        let entry = map.get_block("entry");
+        let join = map.get_block("join");
+        let tail = map.get_block("tail");
+
+        let doms = fun.block_graph().dominators();
+        assert!(doms.root() == entry);
+        assert!(doms.immediate_dominator(entry).is_none());
+        assert!(doms.immediate_dominator(map.get_block("yes")) == Some(entry));
+        assert!(doms.immediate_dominator(join) == Some(entry));
+        assert!(doms.dominators(tail).unwrap().collect::<Vec<_>>() == vec![tail, join, entry]);
+        assert!(doms.immediate_dominator(map.get_block("dead")).is_none());
+    }
+
+    #[test]
+    fn sccs() {
+        let (fun, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        head(%a);
+    head(%x):
+        if_bool %x body exit;
+    body():
+        head(a'false');
+    exit():
+        spin();
+    spin():
+        spin();
+    dead():
+        dead();
+}
+",
+        );
+        let graph = fun.block_graph();
+
+        let sccs = graph.sccs();
+        assert!(sccs.len() == 5);
+
+        let mut loops = graph.loops();
+        for scc in loops.iter_mut() {
+            scc.sort();
+        }
+        let mut head_loop = vec![map.get_block("head"), map.get_block("body")];
+        head_loop.sort();
+        assert!(loops.len() == 3);
+        assert!(loops.contains(&head_loop));
+        assert!(loops.contains(&vec![map.get_block("spin")]));
+        assert!(loops.contains(&vec![map.get_block("dead")]));
+        assert!(!loops.contains(&vec![map.get_block("exit")]));
+
+        let live_graph = fun.live_block_graph();
+        let live_loops = live_graph.loops();
+        assert!(live_loops.len() == 2);
+        let live_sccs = live_graph.sccs();
+        assert!(live_sccs.len() == 4);
+        assert!(live_sccs.last().unwrap() == &vec![map.get_block("entry")]);
+        assert!(!live_loops.contains(&vec![map.get_block("dead")]));
+    }
 }
@@ -1,17 +1,18 @@
-use std::collections::HashSet;
-
 use petgraph::visit::{Dfs, DfsPostOrder};
-use petgraph::visit::{GraphBase, IntoNeighbors, IntoNeighborsDirected, Visitable};
+use petgraph::visit::{
+    GraphBase, IntoNeighbors, IntoNeighborsDirected, IntoNodeIdentifiers, NodeIndexable, Visitable,
+};
 use petgraph::Direction;
 
 use itertools::Either;
 
 use cranelift_bforest::SetIter;
+use cranelift_entity::EntitySet;
 
 use crate::Block;
 use crate::Function;
 
-use super::block_graph::EntityVisitMap;
+use super::block_graph::{is_loop, EntityVisitMap};
 use super::block_graph::{BlockEdge, BlockSuccessors};
 use super::BlockGraph;
 
@@ -26,18 +27,20 @@ impl Function {
 /// This has identical semantics to `BlockGraph`, with the following difference:
 /// - Back edges do not exist to non-live blocks
 ///
-/// If back edges to non-live blocks are acceptable, it is recommended to use
-/// `BlockGraph` instead.
+/// Construction does a single DFS from the entry block to find the live
+/// blocks. If back edges to non-live blocks are acceptable, it is
+/// recommended to use `BlockGraph` instead.
 pub struct LiveBlockGraph<'a> {
     pub graph: BlockGraph<'a>,
-    pub live: HashSet<Block>,
+    pub live: EntitySet<Block>,
 }
 
 impl<'a> LiveBlockGraph<'a> {
     pub fn new(fun: &'a Function) -> Self {
         let graph = fun.block_graph();
 
-        let mut live = HashSet::new();
+        let mut live = EntitySet::new();
+        live.resize(fun.blocks.len());
         for block in graph.dfs_iter() {
             live.insert(block);
         }
@@ -45,6 +48,11 @@ impl<'a> LiveBlockGraph<'a> {
         LiveBlockGraph { graph, live }
     }
 
+    /// Whether `block` is reachable from the entry block.
+    pub fn is_live(&self, block: Block) -> bool {
+        self.live.contains(block)
+    }
+
     pub fn dfs(&self) -> Dfs<Block, EntityVisitMap<Block>> {
         self.graph.dfs()
     }
@@ -59,6 +67,19 @@ impl<'a> LiveBlockGraph<'a> {
         self.graph.dfs_post_order_iter()
     }
 
+    /// The strongly connected components of the live blocks, in reverse
+    /// topological order. See `BlockGraph::sccs`.
+    pub fn sccs(&self) -> Vec<Vec<Block>> {
+        petgraph::algo::tarjan_scc(self)
+    }
+
+    /// The components of `sccs` that are loops. See `BlockGraph::loops`.
+    pub fn loops(&self) -> Vec<Vec<Block>> {
+        let mut sccs = self.sccs();
+        sccs.retain(|scc| is_loop(self.graph.fun, scc));
+        sccs
+    }
+
     pub fn outgoing(&'a self, block: Block) -> impl Iterator<Item = Block> + 'a {
         self.graph.outgoing(block)
     }
@@ -67,7 +88,7 @@ impl<'a> LiveBlockGraph<'a> {
         self.graph.fun.blocks[block]
             .predecessors
             .iter(&self.graph.fun.pool.block_set)
-            .filter(move |b| self.live.contains(b))
+            .filter(move |b| self.is_live(*b))
     }
 }
 
@@ -90,7 +111,7 @@ impl<'a> Iterator for LiveBlockPredecessors<'a> {
     #[inline]
     fn next(&mut self) -> Option<Block> {
         while let Some(block) = self.iter.next() {
-            if self.graph.live.contains(&block) {
+            if self.graph.is_live(block) {
                 return Some(block);
             }
         }
@@ -122,6 +143,30 @@ impl<'a> IntoNeighborsDirected for &'a LiveBlockGraph<'a> {
     }
 }
 
+impl<'a> IntoNodeIdentifiers for &'a LiveBlockGraph<'a> {
+    type NodeIdentifiers = std::vec::IntoIter<Block>;
+    #[inline]
+    fn node_identifiers(self) -> Self::NodeIdentifiers {
+        let live: Vec<Block> = self.live.keys().filter(|b| self.is_live(*b)).collect();
+        live.into_iter()
+    }
+}
+
+impl<'a> NodeIndexable for LiveBlockGraph<'a> {
+    #[inline]
+    fn node_bound(&self) -> usize {
+        self.graph.node_bound()
+    }
+    #[inline]
+    fn to_index(&self, block: Block) -> usize {
+        self.graph.to_index(block)
+    }
+    #[inline]
+    fn from_index(&self, index: usize) -> Block {
+        self.graph.from_index(index)
+    }
+}
+
 impl<'a> Visitable for &'a LiveBlockGraph<'a> {
     type Map = EntityVisitMap<Block>;
     #[inline]
@@ -154,9 +199,8 @@ mod tests {
         let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
         let mut b = FunctionBuilder::new(&mut fun);
 
-        let b1 = b.block_insert();
-        b.block_set_entry(b1);
-        let b1_ret = b.block_arg_insert(b1);
+        let (b1, b1_args) = b.create_entry(1);
+        let b1_ret = b1_args[0];
 
         let b2 = b.block_insert();
 
@@ -204,5 +248,9 @@ mod tests {
                 .collect::<Vec<_>>()
                 == &[]
         );
+
+        assert!(graph.is_live(b1));
+        assert!(graph.is_live(b2));
+        assert!(!graph.is_live(b3));
     }
 }
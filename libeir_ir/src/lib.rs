@@ -2,7 +2,6 @@
 //#![deny(warnings)]
 
 use std::cmp::Ordering;
-use std::fmt::{Display, Formatter};
 
 use libeir_intern::Ident;
 
@@ -19,7 +18,9 @@ pub mod traits;
 mod algo;
 pub use algo::func_tree::{FunctionEntry, FunctionTree};
 pub use algo::live::LiveValues;
-pub use algo::mangle::{MangleFrom, MangleTarget, MangleTo, Mangler};
+pub use algo::mangle::{copy_function, MangleFrom, MangleTarget, MangleTo, Mangler};
+pub use algo::op_branches::EdgeKind;
+pub use algo::outline::OutlineError;
 pub use algo::validate::ValidationError;
 
 pub mod text;
@@ -31,12 +32,14 @@ pub use graph::LiveBlockGraph;
 pub mod constant;
 pub mod pattern;
 
+pub use function::EntitySetPoolProvider;
 pub use function::ValueKind;
+pub use function::{AllocationError, AllocationLimits};
 pub use function::{AttributeKey, AttributeValue};
 pub use function::{
-    BasicType, BinOp, CallKind, LogicOp, MapPutUpdate, MatchKind, OpKind, PrimOpKind,
+    BasicType, BinOp, CallKind, Effects, LogicOp, MapPutUpdate, MatchKind, OpKind, PrimOpKind,
 };
-pub use function::{Block, Function, Location, PrimOp, Value};
+pub use function::{Block, EdgeWeights, Function, Location, PrimOp, Value, DEFAULT_EDGE_WEIGHT};
 pub use function::{ContainerDebug, ContainerDebugAdapter};
 
 pub use function::builder::{CaseBuilder, DynValue, FunctionBuilder, IntoValue};
@@ -48,7 +51,7 @@ pub use constant::{FromPrimitive, Integer, ToPrimitive};
 
 pub use pattern::{PatternClause, PatternContainer, PatternNode, PatternValue};
 
-pub use text::printer::{FormatConfig, StandardFormatConfig};
+pub use text::printer::{DeterministicFormatConfig, FormatConfig, StandardFormatConfig};
 pub use text::{
     parse_function, parse_function_map, parse_function_map_unwrap, parse_function_unwrap,
     parse_module, parse_module_unwrap,
@@ -58,7 +61,10 @@ pub mod binary;
 pub use binary::{BinaryEntrySpecifier, Endianness};
 
 mod module;
-pub use module::{FunctionDefinition, FunctionIndex, Module};
+pub use module::{FunctionDefinition, FunctionIndex, FunctionLowerer, Module};
+
+mod function_ident;
+pub use function_ident::{write_atom, ParseFunctionIdentError};
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, PartialOrd)]
 pub struct FunctionIdent {
@@ -71,8 +77,3 @@ impl Ord for FunctionIdent {
         self.partial_cmp(other).unwrap()
     }
 }
-impl Display for FunctionIdent {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        write!(f, "{}:{}/{}", self.module, self.name, self.arity)
-    }
-}
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use std::ops::{Index, IndexMut};
+use std::rc::Rc;
 
 use cranelift_entity::{entity_impl, PrimaryMap};
 
@@ -7,15 +8,35 @@ use crate::{Function, FunctionIdent};
 use libeir_diagnostics::SourceSpan;
 use libeir_intern::{Ident, Symbol};
 
+/// Lowers the bodies of functions that were declared without one, see
+/// `Module::add_lazy_function`.
+pub trait FunctionLowerer {
+    /// Builds the body of `fun`, which has no blocks yet.
+    fn lower_function(&self, fun: &mut Function) -> Result<(), ()>;
+}
+
 pub struct FunctionDefinition {
     index: FunctionIndex,
     fun: Function,
+    lowered: bool,
+    failed: bool,
 }
 impl FunctionDefinition {
     pub fn index(&self) -> FunctionIndex {
         self.index
     }
 
+    /// False for a lazily declared function until `Module::lower_function`
+    /// has been called for it.
+    pub fn is_lowered(&self) -> bool {
+        self.lowered
+    }
+
+    /// True for a lazily declared function whose lowering failed.
+    pub fn lower_failed(&self) -> bool {
+        self.failed
+    }
+
     pub fn function(&self) -> &Function {
         &self.fun
     }
@@ -34,6 +55,7 @@ pub struct Module {
     span: SourceSpan,
     functions: PrimaryMap<FunctionIndex, FunctionDefinition>,
     name_map: BTreeMap<(Symbol, usize), FunctionIndex>,
+    lowerer: Option<Rc<dyn FunctionLowerer>>,
 }
 impl Module {
     pub fn new(name: Ident) -> Self {
@@ -42,6 +64,7 @@ impl Module {
             span: SourceSpan::UNKNOWN,
             functions: PrimaryMap::new(),
             name_map: BTreeMap::new(),
+            lowerer: None,
         }
     }
 
@@ -51,6 +74,7 @@ impl Module {
             span,
             functions: PrimaryMap::new(),
             name_map: BTreeMap::new(),
+            lowerer: None,
         }
     }
 
@@ -67,6 +91,28 @@ impl Module {
         span: SourceSpan,
         name: Ident,
         arity: usize,
+    ) -> &mut FunctionDefinition {
+        self.insert_function(span, name, arity, true)
+    }
+
+    /// Declares a function whose body is built by the module's lowerer
+    /// the first time `lower_function` is called for it.
+    pub fn add_lazy_function(
+        &mut self,
+        span: SourceSpan,
+        name: Ident,
+        arity: usize,
+    ) -> &mut FunctionDefinition {
+        assert!(self.lowerer.is_some());
+        self.insert_function(span, name, arity, false)
+    }
+
+    fn insert_function(
+        &mut self,
+        span: SourceSpan,
+        name: Ident,
+        arity: usize,
+        lowered: bool,
     ) -> &mut FunctionDefinition {
         let ident = FunctionIdent {
             module: self.name,
@@ -79,6 +125,8 @@ impl Module {
         let def = FunctionDefinition {
             index: FunctionIndex(0),
             fun,
+            lowered,
+            failed: false,
         };
 
         let index = self.functions.push(def);
@@ -89,6 +137,34 @@ impl Module {
         def_mut
     }
 
+    pub fn set_lowerer(&mut self, lowerer: Rc<dyn FunctionLowerer>) {
+        self.lowerer = Some(lowerer);
+    }
+
+    /// Lowers the body of a lazily declared function. The result is kept,
+    /// later calls return the already lowered function.
+    ///
+    /// Returns `Err` if `ident` is not in the module or lowering failed.
+    /// A failure is kept too, the partly built body is discarded and the
+    /// function is not lowered again.
+    pub fn lower_function(&mut self, ident: &FunctionIdent) -> Result<&mut FunctionDefinition, ()> {
+        let idx = self.ident_index(ident).ok_or(())?;
+        let def = &mut self.functions[idx];
+        if def.failed {
+            return Err(());
+        }
+        if !def.lowered {
+            let lowerer = self.lowerer.as_ref().unwrap();
+            if lowerer.lower_function(&mut def.fun).is_err() {
+                def.fun = Function::new(def.fun.span(), *def.fun.ident());
+                def.failed = true;
+                return Err(());
+            }
+            def.lowered = true;
+        }
+        Ok(def)
+    }
+
     pub fn ident_index(&self, ident: &FunctionIdent) -> Option<FunctionIndex> {
         self.name_map.get(&(ident.name.name, ident.arity)).cloned()
     }
@@ -117,6 +193,8 @@ impl Clone for Module {
             let def = FunctionDefinition {
                 index: FunctionIndex(0),
                 fun: fun.clone(),
+                lowered: def.lowered,
+                failed: def.failed,
             };
             let index = functions.push(def);
             name_map.insert((ident.name.name, ident.arity), index);
@@ -126,6 +204,7 @@ impl Clone for Module {
             span: self.span,
             functions,
             name_map,
+            lowerer: self.lowerer.clone(),
         }
     }
 }
@@ -151,3 +230,52 @@ impl Index<&FunctionIdent> for Module {
         &self.functions[idx]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::{FunctionLowerer, Module};
+    use crate::{Function, FunctionBuilder, FunctionIdent};
+    use libeir_diagnostics::SourceSpan;
+    use libeir_intern::Ident;
+
+    /// Builds part of a body and then fails.
+    struct FailingLowerer {
+        calls: Rc<Cell<usize>>,
+    }
+    impl FunctionLowerer for FailingLowerer {
+        fn lower_function(&self, fun: &mut Function) -> Result<(), ()> {
+            self.calls.set(self.calls.get() + 1);
+            let mut b = FunctionBuilder::new(fun);
+            let entry = b.block_insert();
+            b.block_set_entry(entry);
+            Err(())
+        }
+    }
+
+    #[test]
+    fn lower_function_failure_is_kept() {
+        let calls = Rc::new(Cell::new(0));
+        let mut module = Module::new(Ident::from_str("woo"));
+        module.set_lowerer(Rc::new(FailingLowerer {
+            calls: calls.clone(),
+        }));
+        module.add_lazy_function(SourceSpan::UNKNOWN, Ident::from_str("bad"), 0);
+
+        let ident = FunctionIdent {
+            module: Ident::from_str("woo"),
+            name: Ident::from_str("bad"),
+            arity: 0,
+        };
+        assert!(module.lower_function(&ident).is_err());
+        assert!(module.lower_function(&ident).is_err());
+        assert_eq!(calls.get(), 1);
+
+        let def = &module[&ident];
+        assert!(!def.is_lowered());
+        assert!(def.lower_failed());
+        assert_eq!(def.function().block_iter().count(), 0);
+    }
+}
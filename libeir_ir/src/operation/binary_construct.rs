@@ -42,12 +42,15 @@
 use std::any::TypeId;
 use std::default::Default;
 
+use libeir_intern::Symbol;
 use meta_table::{impl_meta_entry, MetaEntry};
+use pretty::{DocAllocator, RefDoc};
 
 use super::{DynOp, Op, OpBuild};
 use crate::dialect::Dialect;
-use crate::traits::OpBranches;
-use crate::{BinaryEntrySpecifier, Block, Function, FunctionBuilder, Value};
+use crate::text::printer::binary_specifier_to_doc;
+use crate::traits::{parens_values, DynArg, FormatOpCtx, OpBranches, OpParser, OpPrinter};
+use crate::{BinaryEntrySpecifier, Block, DynValue, Effects, Function, FunctionBuilder, Value};
 
 pub struct BinaryConstructToken(());
 
@@ -70,6 +73,9 @@ impl Op for BinaryConstructStart {
     fn meta_entry(&self) -> &dyn MetaEntry {
         self
     }
+    fn effects(&self) -> Effects {
+        Effects::PURE
+    }
 }
 
 impl OpBranches for BinaryConstructStart {
@@ -106,6 +112,23 @@ impl OpBuild for BinaryConstructStart {
     type Token = BinaryConstructToken;
 }
 
+impl OpParser for BinaryConstructStart {
+    fn parse(&self, b: &mut FunctionBuilder, block: Block, args: &[DynArg]) -> Result<(), String> {
+        match parens_values(args).as_deref() {
+            Some(&[cont]) => {
+                b.op_intrinsic(
+                    block,
+                    BinaryConstructStart,
+                    &[cont],
+                    BinaryConstructToken(()),
+                );
+                Ok(())
+            }
+            _ => Err("expected (cont)".to_owned()),
+        }
+    }
+}
+
 /// ## `binary_construct_push`
 /// (ok: fn(bin_ref), fail: fn(), bin_ref, value)
 /// (ok: fn(bin_ref), fail: fn(), bin_ref, value, size)
@@ -128,6 +151,9 @@ impl Op for BinaryConstructPush {
     fn meta_entry(&self) -> &dyn MetaEntry {
         self
     }
+    fn effects(&self) -> Effects {
+        Effects::PURE
+    }
     fn op_eq(&self, other: &dyn Op) -> bool {
         if let Some(other_i) = other.downcast_ref::<Self>() {
             self == other_i
@@ -200,6 +226,55 @@ impl OpBuild for BinaryConstructPush {
     type Token = BinaryConstructToken;
 }
 
+impl OpParser for BinaryConstructPush {
+    fn parse(&self, b: &mut FunctionBuilder, block: Block, args: &[DynArg]) -> Result<(), String> {
+        let reads = match args {
+            [DynArg::BinarySpecifier(spec), reads] => reads.values().map(|r| (*spec, r)),
+            _ => None,
+        };
+        match reads {
+            Some((specifier, ref reads)) if reads.len() == 4 || reads.len() == 5 => {
+                b.op_intrinsic(
+                    block,
+                    BinaryConstructPush { specifier },
+                    reads,
+                    BinaryConstructToken(()),
+                );
+                Ok(())
+            }
+            _ => Err("expected binary <specifier> (ok, fail, bin_ref, value[, size])".to_owned()),
+        }
+    }
+}
+
+impl OpPrinter for BinaryConstructPush {
+    fn to_doc<'doc>(&self, ctx: &mut dyn FormatOpCtx<'doc>, block: Block) -> RefDoc<'doc, ()> {
+        let arena = ctx.arena();
+        let reads = ctx.function().block_reads(block).to_vec();
+
+        let args = arena
+            .intersperse(
+                reads
+                    .iter()
+                    .map(|v| ctx.value_use_to_doc(DynValue::Value(*v))),
+                arena.text(",").append(arena.softline()),
+            )
+            .nest(1)
+            .parens();
+
+        arena
+            .nil()
+            .append(arena.text("@binary_construct_push"))
+            .append(arena.space())
+            .append(arena.text("binary"))
+            .append(arena.space())
+            .append(binary_specifier_to_doc(arena, &self.specifier))
+            .append(arena.space())
+            .append(args)
+            .into_doc()
+    }
+}
+
 /// ## `binary_construct_finish`
 /// (cont: fn(result), ref)
 #[derive(Debug, Clone)]
@@ -219,6 +294,9 @@ impl Op for BinaryConstructFinish {
     fn meta_entry(&self) -> &dyn MetaEntry {
         self
     }
+    fn effects(&self) -> Effects {
+        Effects::PURE
+    }
 }
 
 impl OpBranches for BinaryConstructFinish {
@@ -260,13 +338,43 @@ impl OpBuild for BinaryConstructFinish {
     type Token = BinaryConstructToken;
 }
 
+impl OpParser for BinaryConstructFinish {
+    fn parse(&self, b: &mut FunctionBuilder, block: Block, args: &[DynArg]) -> Result<(), String> {
+        match parens_values(args).as_deref() {
+            Some(&[cont, bin_ref]) => {
+                b.op_intrinsic(
+                    block,
+                    BinaryConstructFinish,
+                    &[cont, bin_ref],
+                    BinaryConstructToken(()),
+                );
+                Ok(())
+            }
+            _ => Err("expected (cont, bin_ref)".to_owned()),
+        }
+    }
+}
+
 pub fn register(dialect: &mut Dialect) {
     dialect.register_op::<BinaryConstructStart>();
     dialect.register_op_branches_impl(&BinaryConstructStart);
+    dialect.register_op_parser_impl(
+        Symbol::intern("binary_construct_start"),
+        BinaryConstructStart,
+    );
 
     dialect.register_op::<BinaryConstructPush>();
     dialect.register_op_branches_impl(&BinaryConstructPush::default());
+    dialect.register_op_printer_impl(&BinaryConstructPush::default());
+    dialect.register_op_parser_impl(
+        Symbol::intern("binary_construct_push"),
+        BinaryConstructPush::default(),
+    );
 
     dialect.register_op::<BinaryConstructFinish>();
     dialect.register_op_branches_impl(&BinaryConstructFinish);
+    dialect.register_op_parser_impl(
+        Symbol::intern("binary_construct_finish"),
+        BinaryConstructFinish,
+    );
 }
@@ -7,6 +7,8 @@ use std::raw::TraitObject;
 use meta_table::MetaEntry;
 use stack_dst::Value;
 
+use crate::Effects;
+
 pub mod binary_construct;
 pub mod receive;
 
@@ -29,6 +31,11 @@ pub trait Op: MetaEntry + Send {
     fn debug_fmt(&self, formatter: &mut Formatter) -> FmtResult {
         write!(formatter, "Op[{}]", self.name())
     }
+
+    /// See `OpKind::effects`. Assumes the worst unless overridden.
+    fn effects(&self) -> Effects {
+        Effects::ALL
+    }
 }
 
 impl dyn Op {
@@ -27,12 +27,13 @@
 
 use std::any::TypeId;
 
+use libeir_intern::Symbol;
 use meta_table::{impl_meta_entry, MetaEntry};
 
 use super::{DynOp, Op, OpBuild};
 use crate::dialect::Dialect;
-use crate::traits::OpBranches;
-use crate::{Block, Function, FunctionBuilder, Value};
+use crate::traits::{parens_values, DynArg, OpBranches, OpParser};
+use crate::{Block, Effects, Function, FunctionBuilder, Value};
 
 pub struct ReceiveToken(());
 
@@ -65,6 +66,10 @@ impl Op for ReceiveStart {
     fn meta_entry(&self) -> &dyn MetaEntry {
         self
     }
+    fn effects(&self) -> Effects {
+        // Raises on an invalid timeout
+        Effects::ALL
+    }
     fn op_eq(&self, other: &dyn Op) -> bool {
         self.type_id() == other.type_id()
     }
@@ -109,6 +114,18 @@ impl OpBuild for ReceiveStart {
     type Token = ReceiveToken;
 }
 
+impl OpParser for ReceiveStart {
+    fn parse(&self, b: &mut FunctionBuilder, block: Block, args: &[DynArg]) -> Result<(), String> {
+        match parens_values(args).as_deref() {
+            Some(&[cont, timeout]) => {
+                b.op_intrinsic(block, ReceiveStart, &[cont, timeout], ReceiveToken(()));
+                Ok(())
+            }
+            _ => Err("expected (cont, timeout)".to_owned()),
+        }
+    }
+}
+
 /// ## `receive_wait`
 /// (timeout: fn(), check_message: fn(msg), recv_ref)
 ///
@@ -136,11 +153,18 @@ impl Op for ReceiveWait {
         DynOp::new(self.clone())
     }
     fn type_id(&self) -> TypeId {
-        TypeId::of::<ReceiveStart>()
+        TypeId::of::<ReceiveWait>()
     }
     fn meta_entry(&self) -> &dyn MetaEntry {
         self
     }
+    fn effects(&self) -> Effects {
+        Effects {
+            reads_process: true,
+            writes_process: true,
+            may_raise: false,
+        }
+    }
     fn op_eq(&self, other: &dyn Op) -> bool {
         self.type_id() == other.type_id()
     }
@@ -192,6 +216,23 @@ impl OpBuild for ReceiveWait {
     type Token = ReceiveToken;
 }
 
+impl OpParser for ReceiveWait {
+    fn parse(&self, b: &mut FunctionBuilder, block: Block, args: &[DynArg]) -> Result<(), String> {
+        match parens_values(args).as_deref() {
+            Some(&[timeout, check_message, recv_ref]) => {
+                b.op_intrinsic(
+                    block,
+                    ReceiveWait,
+                    &[timeout, check_message, recv_ref],
+                    ReceiveToken(()),
+                );
+                Ok(())
+            }
+            _ => Err("expected (timeout, check_message, recv_ref)".to_owned()),
+        }
+    }
+}
+
 /// ## `receive_done`
 /// (next: fn(...), recv_ref, ...)
 ///
@@ -220,11 +261,17 @@ impl Op for ReceiveDone {
         DynOp::new(self.clone())
     }
     fn type_id(&self) -> TypeId {
-        TypeId::of::<ReceiveStart>()
+        TypeId::of::<ReceiveDone>()
     }
     fn meta_entry(&self) -> &dyn MetaEntry {
         self
     }
+    fn effects(&self) -> Effects {
+        Effects {
+            writes_process: true,
+            ..Effects::PURE
+        }
+    }
     fn op_eq(&self, other: &dyn Op) -> bool {
         self.type_id() == other.type_id()
     }
@@ -280,13 +327,28 @@ impl OpBuild for ReceiveDone {
     type Token = ReceiveToken;
 }
 
+impl OpParser for ReceiveDone {
+    fn parse(&self, b: &mut FunctionBuilder, block: Block, args: &[DynArg]) -> Result<(), String> {
+        match parens_values(args) {
+            Some(ref reads) if reads.len() >= 2 => {
+                b.op_intrinsic(block, ReceiveDone, reads, ReceiveToken(()));
+                Ok(())
+            }
+            _ => Err("expected (next, recv_ref, ...)".to_owned()),
+        }
+    }
+}
+
 pub fn register(dialect: &mut Dialect) {
     dialect.register_op::<ReceiveStart>();
     dialect.register_op_branches_impl(&ReceiveStart);
+    dialect.register_op_parser_impl(Symbol::intern("receive_start"), ReceiveStart);
 
     dialect.register_op::<ReceiveWait>();
     dialect.register_op_branches_impl(&ReceiveWait);
+    dialect.register_op_parser_impl(Symbol::intern("receive_wait"), ReceiveWait);
 
     dialect.register_op::<ReceiveDone>();
     dialect.register_op_branches_impl(&ReceiveDone);
+    dialect.register_op_parser_impl(Symbol::intern("receive_done"), ReceiveDone);
 }
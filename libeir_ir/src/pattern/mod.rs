@@ -5,6 +5,7 @@ use cranelift_entity::{entity_impl, EntityList, ListPool, PrimaryMap};
 use libeir_diagnostics::SourceSpan;
 
 use crate::binary::BinaryEntrySpecifier;
+use crate::constant::ConstantContainer;
 use crate::Const;
 
 #[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -291,47 +292,70 @@ impl PatternContainer {
         data.finished = true;
     }
 
-    pub fn copy_from(&mut self, clause: PatternClause, from: &PatternContainer) -> PatternClause {
+    /// Copies `clause` from the container `from` into this one, returning
+    /// the new clause. Constants in the patterns are imported from
+    /// `from_cons` into `to_cons`, the constant containers of the two
+    /// functions.
+    ///
+    /// The values and binds of the copy are in the same order as the
+    /// original, so the reads of a `Case` carry over unchanged.
+    pub fn copy_from(
+        &mut self,
+        clause: PatternClause,
+        from: &PatternContainer,
+        from_cons: &ConstantContainer,
+        to_cons: &mut ConstantContainer,
+    ) -> PatternClause {
         let from_clause = &from.clauses[clause];
+        assert!(from_clause.finished);
 
         let mut value_map = self.tmp_val_map.take().unwrap();
         let mut node_map = self.tmp_node_map.take().unwrap();
 
-        let mut new_values = EntityList::new();
+        let new_clause = self.clause_start(from_clause.span);
+
         for from_val in from_clause.values.as_slice(&from.value_pool) {
-            let new_val = self.values.push(());
-            new_values.push(new_val, &mut self.value_pool);
+            let new_val = self.clause_value(new_clause);
             value_map.insert(*from_val, new_val);
         }
+        // Values bound to nodes may be read by other nodes of the clause,
+        // they have to be mapped before any node is copied.
+        let node_binds: Vec<(PatternValue, PatternNode)> =
+            from.clause_node_binds_iter(clause).collect();
+        for (from_val, _) in node_binds.iter() {
+            value_map.insert(*from_val, self.values.push(()));
+        }
 
-        let mut new_roots = EntityList::new();
+        let mut copy = PatternCopy {
+            value_map: &value_map,
+            node_map: &mut node_map,
+            from,
+            from_cons,
+            to_cons,
+        };
         for node in from_clause.root_nodes.as_slice(&from.node_pool) {
-            let new = copy_pattern_node(&value_map, &mut node_map, *node, from, self);
-            new_roots.push(new, &mut self.node_pool);
+            let new = copy.node(*node, self);
+            self.clause_node_push(new_clause, new);
         }
 
-        let mut new_binds = EntityList::new();
+        for (from_val, from_node) in node_binds.iter() {
+            let data = &mut self.clauses[new_clause];
+            data.node_binds_keys
+                .push(node_map[from_node], &mut self.node_pool);
+            data.node_binds_vals
+                .push(value_map[from_val], &mut self.value_pool);
+        }
         for bind in from_clause.binds.as_slice(&from.node_pool) {
-            new_binds.push(node_map[bind], &mut self.node_pool);
+            self.clause_bind_push(new_clause, node_map[bind]);
         }
+        self.clause_finish(new_clause);
 
         value_map.clear();
         self.tmp_val_map = Some(value_map);
         node_map.clear();
         self.tmp_node_map = Some(node_map);
 
-        unimplemented!()
-        //self.clauses.push(PatternClauseData {
-        //    root_nodes: new_roots,
-
-        //    node_binds_keys: unimplemented!(),
-        //    node_binds_vals: unimplemented!(),
-
-        //    binds: new_binds,
-        //    values: new_values,
-
-        //    finished: true,
-        //})
+        new_clause
     }
 
     /// Given a HashMap containing the mapping, this will go through all the
@@ -390,70 +414,259 @@ impl PatternContainer {
     pub fn node_kind(&self, node: PatternNode) -> &PatternNodeKind {
         self.nodes[node].kind.as_ref().unwrap()
     }
+
+    /// Structural equality of two clauses, possibly from different
+    /// containers. The patterns must have the same shape, equal
+    /// constants, and bind and read values at the same positions.
+    pub fn clause_eq(
+        &self,
+        cons: &ConstantContainer,
+        clause: PatternClause,
+        r_pat: &PatternContainer,
+        r_cons: &ConstantContainer,
+        r_clause: PatternClause,
+    ) -> bool {
+        let ctx = ClauseEqCtx {
+            l_pat: self,
+            l_cons: cons,
+            l_clause: clause,
+            r_pat,
+            r_cons,
+            r_clause,
+        };
+
+        let l_roots = self.clause_root_nodes(clause);
+        let r_roots = r_pat.clause_root_nodes(r_clause);
+        let l_binds = self.clause_binds(clause);
+        let r_binds = r_pat.clause_binds(r_clause);
+
+        let mut node_map = HashMap::new();
+        l_roots.len() == r_roots.len()
+            && l_binds.len() == r_binds.len()
+            && self.clause_values(clause).len() == r_pat.clause_values(r_clause).len()
+            && l_roots
+                .iter()
+                .zip(r_roots)
+                .all(|(l, r)| ctx.node_eq(&mut node_map, *l, *r))
+            && l_binds
+                .iter()
+                .zip(r_binds)
+                .all(|(l, r)| node_map.get(l) == Some(r))
+    }
 }
 
-fn copy_pattern_node(
-    value_map: &HashMap<PatternValue, PatternValue>,
-    node_map: &mut HashMap<PatternNode, PatternNode>,
-    node: PatternNode,
-    from: &PatternContainer,
-    to: &mut PatternContainer,
-) -> PatternNode {
-    let data = &from.nodes[node];
-    match data.kind.as_ref().unwrap() {
-        PatternNodeKind::Wildcard => {
-            let new = to.node_empty(Some(data.span));
-            to.wildcard(new);
-            node_map.insert(node, new);
-            new
+struct ClauseEqCtx<'a> {
+    l_pat: &'a PatternContainer,
+    l_cons: &'a ConstantContainer,
+    l_clause: PatternClause,
+    r_pat: &'a PatternContainer,
+    r_cons: &'a ConstantContainer,
+    r_clause: PatternClause,
+}
+
+impl<'a> ClauseEqCtx<'a> {
+    fn value_eq(
+        &self,
+        node_map: &HashMap<PatternNode, PatternNode>,
+        l: PatternValue,
+        r: PatternValue,
+    ) -> bool {
+        let l_idx = self
+            .l_pat
+            .clause_values(self.l_clause)
+            .iter()
+            .position(|v| *v == l);
+        let r_idx = self
+            .r_pat
+            .clause_values(self.r_clause)
+            .iter()
+            .position(|v| *v == r);
+        if l_idx.is_some() || r_idx.is_some() {
+            return l_idx == r_idx;
         }
-        PatternNodeKind::Value(val) => {
-            let new = to.node_empty(Some(data.span));
-            to.value(new, *val);
-            node_map.insert(node, new);
-            new
+
+        // Values bound to a node of the clause
+        let l_node = self
+            .l_pat
+            .clause_node_binds_iter(self.l_clause)
+            .find(|(v, _)| *v == l)
+            .map(|(_, node)| node);
+        let r_node = self
+            .r_pat
+            .clause_node_binds_iter(self.r_clause)
+            .find(|(v, _)| *v == r)
+            .map(|(_, node)| node);
+        match (l_node, r_node) {
+            (Some(l_node), Some(r_node)) => node_map.get(&l_node) == Some(&r_node),
+            _ => false,
         }
-        PatternNodeKind::Tuple(elems) => {
-            let new = to.node_empty(Some(data.span));
-            to.tuple(new);
+    }
 
-            for elem in elems.as_slice(&from.node_pool) {
-                let copied = copy_pattern_node(value_map, node_map, *elem, from, to);
-                to.tuple_elem_push(new, copied);
-            }
+    fn node_eq(
+        &self,
+        node_map: &mut HashMap<PatternNode, PatternNode>,
+        l: PatternNode,
+        r: PatternNode,
+    ) -> bool {
+        node_map.insert(l, r);
 
-            to.node_finish(new);
-            node_map.insert(node, new);
-            new
+        let (l_pat, r_pat) = (self.l_pat, self.r_pat);
+        match (l_pat.node_kind(l), r_pat.node_kind(r)) {
+            (PatternNodeKind::Wildcard, PatternNodeKind::Wildcard) => true,
+            (PatternNodeKind::Const(lc), PatternNodeKind::Const(rc)) => {
+                self.l_cons.eq_other(*lc, self.r_cons, *rc)
+            }
+            (PatternNodeKind::Value(lv), PatternNodeKind::Value(rv)) => {
+                self.value_eq(node_map, *lv, *rv)
+            }
+            (
+                PatternNodeKind::Binary {
+                    specifier: ls,
+                    value: lv,
+                    size: lsize,
+                    remaining: lr,
+                },
+                PatternNodeKind::Binary {
+                    specifier: rs,
+                    value: rv,
+                    size: rsize,
+                    remaining: rr,
+                },
+            ) => {
+                let size_eq = match (lsize, rsize) {
+                    (None, None) => true,
+                    (Some(l), Some(r)) => self.value_eq(node_map, *l, *r),
+                    _ => false,
+                };
+                ls == rs
+                    && size_eq
+                    && self.node_eq(node_map, *lv, *rv)
+                    && self.node_eq(node_map, *lr, *rr)
+            }
+            (PatternNodeKind::Tuple(le), PatternNodeKind::Tuple(re)) => {
+                let le = le.as_slice(&l_pat.node_pool);
+                let re = re.as_slice(&r_pat.node_pool);
+                le.len() == re.len()
+                    && le
+                        .iter()
+                        .zip(re)
+                        .all(|(l, r)| self.node_eq(node_map, *l, *r))
+            }
+            (
+                PatternNodeKind::List { head: lh, tail: lt },
+                PatternNodeKind::List { head: rh, tail: rt },
+            ) => self.node_eq(node_map, *lh, *rh) && self.node_eq(node_map, *lt, *rt),
+            (
+                PatternNodeKind::Map {
+                    keys: lk,
+                    values: lv,
+                },
+                PatternNodeKind::Map {
+                    keys: rk,
+                    values: rv,
+                },
+            ) => {
+                let lk = lk.as_slice(&l_pat.value_pool);
+                let rk = rk.as_slice(&r_pat.value_pool);
+                let lv = lv.as_slice(&l_pat.node_pool);
+                let rv = rv.as_slice(&r_pat.node_pool);
+                lk.len() == rk.len()
+                    && lk
+                        .iter()
+                        .zip(rk)
+                        .all(|(l, r)| self.value_eq(node_map, *l, *r))
+                    && lv
+                        .iter()
+                        .zip(rv)
+                        .all(|(l, r)| self.node_eq(node_map, *l, *r))
+            }
+            _ => false,
         }
-        PatternNodeKind::List { head, tail } => {
-            let head_copied = copy_pattern_node(value_map, node_map, *head, from, to);
-            let tail_copied = copy_pattern_node(value_map, node_map, *tail, from, to);
+    }
+}
 
-            let new = to.node_empty(Some(data.span));
-            to.list(new, head_copied, tail_copied);
+struct PatternCopy<'a> {
+    value_map: &'a HashMap<PatternValue, PatternValue>,
+    node_map: &'a mut HashMap<PatternNode, PatternNode>,
+    from: &'a PatternContainer,
+    from_cons: &'a ConstantContainer,
+    to_cons: &'a mut ConstantContainer,
+}
 
-            node_map.insert(node, new);
-            new
-        }
-        PatternNodeKind::Map { keys, values } => {
-            let new = to.node_empty(Some(data.span));
-            to.map(new);
+impl<'a> PatternCopy<'a> {
+    fn node(&mut self, node: PatternNode, to: &mut PatternContainer) -> PatternNode {
+        let from = self.from;
+        let data = &from.nodes[node];
+        let new = match data.kind.as_ref().unwrap() {
+            PatternNodeKind::Wildcard => {
+                let new = to.node_empty(Some(data.span));
+                to.wildcard(new);
+                new
+            }
+            PatternNodeKind::Const(constant) => {
+                let new = to.node_empty(Some(data.span));
+                let constant = self.to_cons.import(self.from_cons, *constant);
+                to.constant(new, constant);
+                new
+            }
+            PatternNodeKind::Value(val) => {
+                let new = to.node_empty(Some(data.span));
+                to.value(new, self.value_map[val]);
+                new
+            }
+            PatternNodeKind::Binary {
+                specifier,
+                value,
+                size,
+                remaining,
+            } => {
+                let value = self.node(*value, to);
+                let remaining = self.node(*remaining, to);
+                let size = size.map(|size| self.value_map[&size]);
+
+                let new = to.node_empty(Some(data.span));
+                to.binary(new, *specifier, value, size, remaining);
+                new
+            }
+            PatternNodeKind::Tuple(elems) => {
+                let new = to.node_empty(Some(data.span));
+                to.tuple(new);
 
-            for (key, val) in keys
-                .as_slice(&from.value_pool)
-                .iter()
-                .zip(values.as_slice(&from.node_pool))
-            {
-                let copied = copy_pattern_node(value_map, node_map, *val, from, to);
-                to.map_push(new, value_map[key], copied);
+                for elem in elems.as_slice(&from.node_pool) {
+                    let copied = self.node(*elem, to);
+                    to.tuple_elem_push(new, copied);
+                }
+
+                to.node_finish(new);
+                new
             }
+            PatternNodeKind::List { head, tail } => {
+                let head_copied = self.node(*head, to);
+                let tail_copied = self.node(*tail, to);
 
-            to.node_finish(new);
-            node_map.insert(node, new);
-            new
-        }
-        _ => unimplemented!("{:?}", data.kind),
+                let new = to.node_empty(Some(data.span));
+                to.list(new, head_copied, tail_copied);
+                new
+            }
+            PatternNodeKind::Map { keys, values } => {
+                let new = to.node_empty(Some(data.span));
+                to.map(new);
+
+                for (key, val) in keys
+                    .as_slice(&from.value_pool)
+                    .iter()
+                    .zip(values.as_slice(&from.node_pool))
+                {
+                    let copied = self.node(*val, to);
+                    to.map_push(new, self.value_map[key], copied);
+                }
+
+                to.node_finish(new);
+                new
+            }
+        };
+        self.node_map.insert(node, new);
+        new
     }
 }
 
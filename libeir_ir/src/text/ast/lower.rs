@@ -9,8 +9,9 @@ use libeir_util_parse::ErrorReceiver;
 use snafu::Snafu;
 
 use crate::text::ast;
-use crate::PatternNode;
-use crate::{Block, Value};
+use crate::traits::DynArg;
+use crate::{BasicType, Block, LogicOp, PrimOpKind, Value};
+use crate::{CaseBuilder, PatternClause, PatternNode};
 use crate::{Function, FunctionBuilder, FunctionIdent, Module};
 
 type ErrCollector<'a> = &'a mut dyn ErrorReceiver<E = LowerError, W = LowerError>;
@@ -39,6 +40,15 @@ pub enum LowerError {
     UndefinedBind {
         span: SourceSpan,
     },
+
+    UnknownOp {
+        span: SourceSpan,
+    },
+
+    InvalidOp {
+        span: SourceSpan,
+        reason: String,
+    },
 }
 
 impl ToDiagnostic for LowerError {
@@ -65,6 +75,15 @@ impl ToDiagnostic for LowerError {
                 .with_message("undefined block name")
                 .with_labels(vec![Label::primary(span.source_id(), *span)
                     .with_message("block name was not defined in the IR")]),
+            LowerError::UnknownOp { span } => Diagnostic::error()
+                .with_message("unknown operation")
+                .with_labels(vec![Label::primary(span.source_id(), *span)
+                    .with_message("operation is not registered in the dialect")]),
+            LowerError::InvalidOp { span, reason } => Diagnostic::error()
+                .with_message("invalid operation")
+                .with_labels(vec![
+                    Label::primary(span.source_id(), *span).with_message(reason.clone())
+                ]),
             _ => Diagnostic::error().with_message(msg),
         }
     }
@@ -253,7 +272,27 @@ fn lower_operation(
     op: &ast::Op,
 ) -> Result<(), ()> {
     match op {
-        ast::Op::Dyn(ident, opts) => unimplemented!(),
+        ast::Op::Dyn(ident, opts) => {
+            let dialect = b.fun().dialect().clone();
+            let parser = if let Some(parser) = dialect.get_op_parser(ident.name) {
+                parser
+            } else {
+                errors.error(LowerError::UnknownOp { span: ident.span });
+                return Err(());
+            };
+
+            let args: Result<Vec<_>, _> = opts
+                .iter()
+                .map(|opt| lower_dyn_opt(errors, b, scope, opt))
+                .collect();
+            if let Err(reason) = parser.parse(b, block, &args?) {
+                errors.error(LowerError::InvalidOp {
+                    span: ident.span,
+                    reason,
+                });
+                return Err(());
+            }
+        }
         ast::Op::CallControlFlow(call) => {
             let target = lower_value(errors, b, scope, &call.target)?;
             let args: Result<Vec<_>, _> = call
@@ -321,13 +360,32 @@ fn lower_operation(
                     ast::MatchKind::Tuple(n) => {
                         builder.push_tuple_next(next, *n, b);
                     }
-                    s => unimplemented!("{:?}", s),
+                    ast::MatchKind::Binary(spec, size) => {
+                        let size_v = size
+                            .as_ref()
+                            .map(|s| lower_value(errors, b, scope, s))
+                            .transpose()?;
+                        builder.push_binary_next(next, *spec, size_v, b);
+                    }
                 }
             }
 
             let match_val = lower_value(errors, b, scope, &match_op.value)?;
             builder.finish(block, match_val, b);
         }
+        ast::Op::MapPut(map_put) => {
+            let map = lower_value(errors, b, scope, &map_put.map)?;
+            let ok = lower_value(errors, b, scope, &map_put.ok)?;
+            let fail = lower_value(errors, b, scope, &map_put.fail)?;
+
+            let mut builder = b.op_map_put_build_next(SourceSpan::UNKNOWN, map, ok, fail);
+            for entry in map_put.entries.iter() {
+                let key = lower_value(errors, b, scope, &entry.key)?;
+                let value = lower_value(errors, b, scope, &entry.value)?;
+                builder.push_kv(key, value, entry.action, b);
+            }
+            builder.finish_next(block, b);
+        }
         ast::Op::Unreachable => {
             b.op_unreachable(SourceSpan::UNKNOWN, block);
         }
@@ -342,7 +400,15 @@ fn lower_operation(
                 let clause = b.pat_mut().clause_start(SourceSpan::UNKNOWN);
 
                 for pattern in entry.patterns.iter() {
-                    let pat = lower_case_pattern(errors, b, scope, &mut binds, pattern)?;
+                    let pat = lower_case_pattern(
+                        errors,
+                        b,
+                        scope,
+                        &mut binds,
+                        clause,
+                        &mut case_b,
+                        pattern,
+                    )?;
                     b.pat_mut().clause_node_push(clause, pat);
                 }
 
@@ -376,16 +442,37 @@ fn lower_operation(
     Ok(())
 }
 
+fn lower_dyn_opt(
+    errors: ErrCollector,
+    b: &mut FunctionBuilder,
+    scope: &mut HashMapStack<Name, (SourceSpan, Value)>,
+    opt: &ast::DynOpt,
+) -> Result<DynArg, ()> {
+    match opt {
+        ast::DynOpt::Parens(opts) => {
+            let args: Result<Vec<_>, _> = opts
+                .iter()
+                .map(|opt| lower_dyn_opt(errors, b, scope, opt))
+                .collect();
+            Ok(DynArg::Parens(args?))
+        }
+        ast::DynOpt::Value(value) => Ok(DynArg::Value(lower_value(errors, b, scope, value)?)),
+        ast::DynOpt::BinarySpecifier(spec) => Ok(DynArg::BinarySpecifier(*spec)),
+    }
+}
+
 fn lower_case_pattern(
     errors: ErrCollector,
     b: &mut FunctionBuilder,
     scope: &mut HashMapStack<Name, (SourceSpan, Value)>,
     binds: &mut HashMap<Ident, (SourceSpan, PatternNode)>,
+    clause: PatternClause,
+    case_b: &mut CaseBuilder,
     pattern: &ast::CasePattern,
 ) -> Result<PatternNode, ()> {
     match pattern {
         ast::CasePattern::Binding { name, pattern } => {
-            let child = lower_case_pattern(errors, b, scope, binds, pattern)?;
+            let child = lower_case_pattern(errors, b, scope, binds, clause, case_b, pattern)?;
             if binds.contains_key(name) {
                 errors.error(LowerError::DuplicateDefinititon {
                     current: name.span,
@@ -401,7 +488,99 @@ fn lower_case_pattern(
             b.pat_mut().wildcard(node);
             Ok(node)
         }
-        _ => unimplemented!(),
+        ast::CasePattern::Value(value) => {
+            let value = lower_value(errors, b, scope, value)?;
+            if let Some(cons) = b.fun().value_const(value) {
+                let node = b.pat_mut().node_empty(Some(SourceSpan::UNKNOWN));
+                b.pat_mut().constant(node, cons);
+                Ok(node)
+            } else {
+                errors.error(LowerError::InvalidOp {
+                    span: SourceSpan::UNKNOWN,
+                    reason: "case pattern value must be a constant".to_string(),
+                });
+                Err(())
+            }
+        }
+        ast::CasePattern::ValueRead(value) => {
+            let value = lower_value(errors, b, scope, value)?;
+            let pat_val = b.pat_mut().clause_value(clause);
+            case_b.push_value(value, b);
+
+            let node = b.pat_mut().node_empty(Some(SourceSpan::UNKNOWN));
+            b.pat_mut().value(node, pat_val);
+            Ok(node)
+        }
+        ast::CasePattern::Tuple { elements } => {
+            let elems = elements
+                .iter()
+                .map(|elem| lower_case_pattern(errors, b, scope, binds, clause, case_b, elem))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let node = b.pat_mut().node_empty(Some(SourceSpan::UNKNOWN));
+            b.pat_mut().tuple(node);
+            for elem in elems {
+                b.pat_mut().tuple_elem_push(node, elem);
+            }
+            b.pat_mut().node_finish(node);
+            Ok(node)
+        }
+        ast::CasePattern::ListCell { head, tail } => {
+            let head = lower_case_pattern(errors, b, scope, binds, clause, case_b, head)?;
+            let tail = lower_case_pattern(errors, b, scope, binds, clause, case_b, tail)?;
+
+            let node = b.pat_mut().node_empty(Some(SourceSpan::UNKNOWN));
+            b.pat_mut().list(node, head, tail);
+            Ok(node)
+        }
+        ast::CasePattern::Binary {
+            specifier,
+            size,
+            value,
+            remaining,
+        } => {
+            let size = match size {
+                None => None,
+                Some(ast::CaseBinarySize::ValueRead(size)) => {
+                    let size = lower_value(errors, b, scope, size)?;
+                    let pat_val = b.pat_mut().clause_value(clause);
+                    case_b.push_value(size, b);
+                    Some(pat_val)
+                }
+                Some(ast::CaseBinarySize::Bind(name)) => {
+                    if let Some((_, node)) = binds.get(name) {
+                        Some(b.pat_mut().clause_node_value(clause, *node))
+                    } else {
+                        errors.error(LowerError::UndefinedBind { span: name.span });
+                        return Err(());
+                    }
+                }
+            };
+            let value = lower_case_pattern(errors, b, scope, binds, clause, case_b, value)?;
+            let remaining = lower_case_pattern(errors, b, scope, binds, clause, case_b, remaining)?;
+
+            let node = b.pat_mut().node_empty(Some(SourceSpan::UNKNOWN));
+            b.pat_mut().binary(node, *specifier, value, size, remaining);
+            Ok(node)
+        }
+        ast::CasePattern::Map { entries } => {
+            let mut lowered = Vec::with_capacity(entries.len());
+            for (key, value) in entries.iter() {
+                let key = lower_value(errors, b, scope, key)?;
+                let pat_val = b.pat_mut().clause_value(clause);
+                case_b.push_value(key, b);
+                let value = lower_case_pattern(errors, b, scope, binds, clause, case_b, value)?;
+                lowered.push((pat_val, value));
+            }
+
+            let node = b.pat_mut().node_empty(Some(SourceSpan::UNKNOWN));
+            b.pat_mut().map(node);
+            for (key, value) in lowered {
+                b.pat_mut().map_push(node, key, value);
+            }
+            b.pat_mut().node_finish(node);
+            Ok(node)
+        }
     }
 }
 
@@ -437,6 +616,7 @@ fn lower_value(
             crate::constant::Integer::Big(int) => Ok(b.value(int.clone())),
         },
         ast::Value::Nil => Ok(b.value(crate::constant::NilTerm)),
+        ast::Value::Binary(bytes) => Ok(b.value(crate::constant::BinaryTerm(bytes.clone()))),
         ast::Value::ValueList(list) => {
             let v_buf: Result<Vec<Value>, _> = list
                 .iter()
@@ -451,6 +631,15 @@ fn lower_value(
                 .collect();
             Ok(b.prim_tuple(SourceSpan::UNKNOWN, &v_buf?))
         }
+        ast::Value::Map(entries) => {
+            let mut keys = Vec::with_capacity(entries.len());
+            let mut values = Vec::with_capacity(entries.len());
+            for (k, v) in entries.iter() {
+                keys.push(lower_value(errors, b, scope, k)?);
+                values.push(lower_value(errors, b, scope, v)?);
+            }
+            Ok(b.prim_map(SourceSpan::UNKNOWN, &keys, &values))
+        }
         ast::Value::CaptureFunction(m, f, a) => {
             let m_v = lower_value(errors, b, scope, &*m)?;
             let f_v = lower_value(errors, b, scope, &*f)?;
@@ -464,6 +653,44 @@ fn lower_value(
 
             Ok(b.prim_binop(SourceSpan::UNKNOWN, *op, lhs_v, rhs_v))
         }
+        ast::Value::PrimOp(name, args) => {
+            let is_type = |typ| Some((PrimOpKind::IsType(typ), &args[..]));
+            let kind = match (name.name.as_str().get(), args.as_slice()) {
+                ("and", _) => Some((PrimOpKind::LogicOp(LogicOp::And), &args[..])),
+                ("or", _) => Some((PrimOpKind::LogicOp(LogicOp::Or), &args[..])),
+                ("eq", _) => Some((PrimOpKind::LogicOp(LogicOp::Eq), &args[..])),
+                ("type_tag", [_]) => Some((PrimOpKind::TypeTag, &args[..])),
+                ("is_list", [_]) => is_type(BasicType::List),
+                ("is_cons", [_]) => is_type(BasicType::ListCell),
+                ("is_nil", [_]) => is_type(BasicType::Nil),
+                ("is_map", [_]) => is_type(BasicType::Map),
+                ("is_number", [_]) => is_type(BasicType::Number),
+                ("is_float", [_]) => is_type(BasicType::Float),
+                ("is_integer", [_]) => is_type(BasicType::Integer),
+                ("is_smallint", [_]) => is_type(BasicType::SmallInteger),
+                ("is_bigint", [_]) => is_type(BasicType::BigInteger),
+                ("is_tuple", [_, ast::Value::Integer(arity)]) => arity
+                    .to_usize()
+                    .map(|arity| (PrimOpKind::IsType(BasicType::Tuple(arity)), &args[..1])),
+                _ => None,
+            };
+            let (kind, args) = match kind {
+                Some(kind) => kind,
+                None => {
+                    errors.error(LowerError::InvalidOp {
+                        span: name.span,
+                        reason: format!("unknown primop `{}` with {} arguments", name, args.len()),
+                    });
+                    return Err(());
+                }
+            };
+
+            let v_buf: Result<Vec<Value>, _> = args
+                .iter()
+                .map(|v| lower_value(errors, b, scope, v))
+                .collect();
+            Ok(b.prim_from_kind(SourceSpan::UNKNOWN, kind, &v_buf?))
+        }
         ast::Value::List(head, tail) => {
             let mut acc = tail
                 .as_ref()
@@ -1,7 +1,7 @@
 use libeir_intern::Ident;
 
 use crate::constant::Integer;
-use crate::{BasicType, BinOp, BinaryEntrySpecifier};
+use crate::{BasicType, BinOp, BinaryEntrySpecifier, MapPutUpdate};
 
 mod lower;
 pub use lower::{LowerError, LowerMap};
@@ -50,6 +50,7 @@ pub struct Assignment {
 pub enum DynOpt {
     Parens(Vec<DynOpt>),
     Value(Value),
+    BinarySpecifier(BinaryEntrySpecifier),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -62,9 +63,25 @@ pub enum Op {
     TraceCaptureRaw(TraceCaptureRawOp),
     Match(MatchOp),
     Case(CaseOp),
+    MapPut(MapPutOp),
     Unreachable,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct MapPutOp {
+    pub map: Value,
+    pub entries: Vec<MapPutEntry>,
+    pub ok: Value,
+    pub fail: Value,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MapPutEntry {
+    pub key: Value,
+    pub value: Value,
+    pub action: MapPutUpdate,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct CaseOp {
     pub value: Value,
@@ -82,7 +99,10 @@ pub struct CaseEntry {
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum CasePattern {
+    /// A constant the matched value must be equal to.
     Value(Value),
+    /// Matches on a value read by the case operation.
+    ValueRead(Value),
     Binding {
         name: Ident,
         pattern: Box<CasePattern>,
@@ -94,9 +114,27 @@ pub enum CasePattern {
     Tuple {
         elements: Vec<CasePattern>,
     },
+    Binary {
+        specifier: BinaryEntrySpecifier,
+        size: Option<CaseBinarySize>,
+        value: Box<CasePattern>,
+        remaining: Box<CasePattern>,
+    },
+    /// Keys are values read by the case operation.
+    Map {
+        entries: Vec<(Value, CasePattern)>,
+    },
     Wildcard,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum CaseBinarySize {
+    /// A value read by the case operation.
+    ValueRead(Value),
+    /// A node bound earlier in the same clause.
+    Bind(Ident),
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct MatchOp {
     pub value: Value,
@@ -159,13 +197,16 @@ pub enum Value {
     Atom(Ident),
     Integer(Integer),
     Nil,
+    Binary(Vec<u8>),
 
     // Composites
     ValueList(Vec<Value>),
     Tuple(Vec<Value>),
     List(Vec<Value>, Option<Box<Value>>),
+    Map(Vec<(Value, Value)>),
     CaptureFunction(Box<Value>, Box<Value>, Box<Value>),
     BinOp(Box<Value>, BinOp, Box<Value>),
+    PrimOp(Ident, Vec<Value>),
 }
 impl Value {
     pub fn value(&self) -> Option<Ident> {
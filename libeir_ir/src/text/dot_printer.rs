@@ -2,16 +2,45 @@ use std::marker::PhantomData;
 
 use super::printer as pr;
 use super::printer::{FormatState, FunctionFormatData};
-use crate::Function;
+use crate::{EdgeKind, Function};
 use pretty::Arena;
 
 use libeir_util_dot_graph::GraphPrinter;
 
-use petgraph::visit::{Dfs, IntoNeighbors};
+use petgraph::visit::{Dfs, IntoNeighbors, VisitMap};
+
+/// Optional annotations of the dot output, all off by default.
+#[derive(Debug, Clone, Default)]
+pub struct DotConfig {
+    /// Fills the entry block with a distinct color.
+    pub mark_entry: bool,
+    /// Also prints the blocks that are not reachable from the entry,
+    /// filled gray. These are left out otherwise.
+    pub show_unreachable: bool,
+    /// Labels every edge with the number of arguments its target takes.
+    pub edge_arg_counts: bool,
+    /// Fills blocks marked with the `Hot` or `Cold` attribute.
+    pub mark_hot_cold: bool,
+}
+
+const ENTRY_FILL_COLOR: &str = "lightblue";
+const UNREACHABLE_FILL_COLOR: &str = "gray80";
+const HOT_FILL_COLOR: &str = "salmon";
+const COLD_FILL_COLOR: &str = "lightcyan";
 
 pub fn function_into_graph_printer<O>(fun: &Function, g: &mut GraphPrinter<O>)
 where
     O: std::fmt::Write,
+{
+    function_into_graph_printer_with_config(fun, &DotConfig::default(), g)
+}
+
+pub fn function_into_graph_printer_with_config<O>(
+    fun: &Function,
+    dot_config: &DotConfig,
+    g: &mut GraphPrinter<O>,
+) where
+    O: std::fmt::Write,
 {
     let mut buf = String::new();
 
@@ -20,6 +49,8 @@ where
         block_iterator_config: pr::DfsBlockIteratorConfig,
         value_formatter: pr::StandardValueFormatter,
         block_value_layout: pr::ReferencePrimopBlockValueLayout::default(),
+        char_literals: false,
+        string_literals: false,
     };
     let mut state = FormatState {
         function: fun,
@@ -34,26 +65,192 @@ where
         config: PhantomData,
     };
 
+    let entry = fun.block_entry();
     let block_graph = fun.block_graph();
-    let mut block_dfs = Dfs::new(&block_graph, fun.block_entry());
+    let mut block_dfs = Dfs::new(&block_graph, entry);
 
+    let mut blocks = Vec::new();
     while let Some(block) = block_dfs.next(&block_graph) {
+        blocks.push(block);
+    }
+    let num_reachable = blocks.len();
+    if dot_config.show_unreachable {
+        // Cleared blocks have no body and are not part of the graph
+        blocks.extend(fun.block_iter().filter(|block| {
+            fun.block_kind(*block).is_some() && !block_dfs.discovered.is_visited(block)
+        }));
+    }
+
+    for (idx, block) in blocks.iter().cloned().enumerate() {
         let block_val = fun.block_value(block);
 
         let doc = ctx.block_to_doc(&mut config, &mut state, block);
         buf.clear();
         doc.render_fmt(80, &mut buf).unwrap();
-        g.node(block_val, &buf);
+        if dot_config.mark_entry && block == entry {
+            g.node_filled(block_val, &buf, ENTRY_FILL_COLOR);
+        } else if idx >= num_reachable {
+            g.node_filled(block_val, &buf, UNREACHABLE_FILL_COLOR);
+        } else if dot_config.mark_hot_cold && fun.block_is_hot(block) {
+            g.node_filled(block_val, &buf, HOT_FILL_COLOR);
+        } else if dot_config.mark_hot_cold && fun.block_is_cold(block) {
+            g.node_filled(block_val, &buf, COLD_FILL_COLOR);
+        } else {
+            g.node(block_val, &buf);
+        }
 
-        for out in block_graph.neighbors(block) {
+        // Successors are emitted sorted by block index so the output is
+        // stable across runs.
+        let mut successors: Vec<_> = block_graph.neighbors(block).collect();
+        successors.sort();
+
+        for out in successors {
             let out_val = fun.block_value(out);
-            g.edge(block_val, out_val, "");
+            let label = if dot_config.edge_arg_counts {
+                fun.block_args(out).len().to_string()
+            } else {
+                String::new()
+            };
+            match fun.edge_kind(block, out) {
+                EdgeKind::Exception => g.edge_colored(block_val, out_val, &label, "red"),
+                _ => g.edge(block_val, out_val, &label),
+            }
         }
     }
 }
 
 pub fn function_to_dot(fun: &Function) -> String {
+    function_to_dot_with_config(fun, &DotConfig::default())
+}
+
+pub fn function_to_dot_with_config(fun: &Function, config: &DotConfig) -> String {
     let mut g = GraphPrinter::new();
-    function_into_graph_printer(fun, &mut g);
+    function_into_graph_printer_with_config(fun, config, &mut g);
     g.finish().unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use libeir_diagnostics::SourceSpan;
+    use libeir_intern::Ident;
+
+    use super::{function_to_dot, function_to_dot_with_config, DotConfig};
+    use crate::{AttributeKey, AttributeValue, Function, FunctionIdent};
+
+    #[test]
+    fn stable_edge_order() {
+        let ident = FunctionIdent {
+            module: Ident::from_str("woo"),
+            name: Ident::from_str("hoo"),
+            arity: 1,
+        };
+        let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+
+        let (entry, blocks) = {
+            let mut b = fun.builder();
+            let (entry, args) = b.create_entry(1);
+            let blocks = [b.block_insert(), b.block_insert(), b.block_insert()];
+            for block in blocks.iter() {
+                b.op_call_flow(*block, args[0], &[]);
+            }
+
+            // The successors are read in the opposite order of creation
+            let vals: Vec<_> = blocks.iter().map(|block| b.value(*block)).collect();
+            b.op_if_bool_next(
+                SourceSpan::UNKNOWN,
+                entry,
+                vals[2],
+                vals[1],
+                vals[0],
+                args[2],
+            );
+
+            (entry, blocks)
+        };
+
+        let dot = function_to_dot(&fun);
+        let edges: Vec<&str> = dot
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| line.contains(" -> "))
+            .collect();
+        let expected: Vec<String> = blocks
+            .iter()
+            .map(|block| {
+                format!(
+                    "{} -> {} [ label=<> ];",
+                    fun.block_value(entry),
+                    fun.block_value(*block)
+                )
+            })
+            .collect();
+        assert_eq!(edges, expected);
+    }
+
+    #[test]
+    fn annotations() {
+        let ir = crate::parse_function_unwrap(
+            "
+a'woo':a'hoo'/1 {
+    entry(%ret, %thr, %a):
+        b1(%a);
+    b1(%x):
+        %ret(%x);
+    b2():
+        b1(a'dead');
+}
+",
+        );
+
+        let plain = function_to_dot(&ir);
+        assert!(!plain.contains("fillcolor"));
+        assert!(!plain.contains("a'dead'"));
+
+        let config = DotConfig {
+            mark_entry: true,
+            show_unreachable: true,
+            edge_arg_counts: true,
+            mark_hot_cold: false,
+        };
+        let annotated = function_to_dot_with_config(&ir, &config);
+        assert!(annotated.contains("fillcolor=\"lightblue\""));
+        assert!(annotated.contains("fillcolor=\"gray80\""));
+        assert!(annotated.contains("a'dead'"));
+        assert!(annotated.contains("[ label=<1>"));
+    }
+
+    #[test]
+    fn hot_cold() {
+        let (mut ir, map) = crate::parse_function_map_unwrap(
+            "
+a'woo':a'hoo'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a b1 b2;
+    b1():
+        %ret(a'true');
+    b2():
+        %thr(a'error', a'badarg', a'nil');
+}
+",
+        );
+        {
+            let mut b = ir.builder();
+            b.block_set_attribute(map.get_block("b1"), AttributeKey::Hot, AttributeValue::None);
+            b.block_set_attribute(
+                map.get_block("b2"),
+                AttributeKey::Cold,
+                AttributeValue::None,
+            );
+        }
+
+        assert!(!function_to_dot(&ir).contains("fillcolor"));
+
+        let config = DotConfig {
+            mark_hot_cold: true,
+            ..DotConfig::default()
+        };
+        let marked = function_to_dot_with_config(&ir, &config);
+        assert!(marked.contains("fillcolor=\"salmon\""));
+        assert!(marked.contains("fillcolor=\"lightcyan\""));
+    }
+}
@@ -0,0 +1,126 @@
+//! Structured JSON form of a function, for external tooling such as
+//! visualizers and diff tools. Unlike the text format this is not meant
+//! to be parsed back.
+
+use serde_json::{json, Value as Json};
+
+use crate::{CallKind, Function, OpKind, Value, ValueKind};
+
+impl Function {
+    /// Emits the function as JSON. Blocks are listed in index order, each
+    /// with its arguments, operation, reads and successors. Blocks and
+    /// values are referred to by their entity names, `block3`, `value7`.
+    pub fn to_json(&self) -> Json {
+        let blocks: Vec<Json> = self
+            .block_iter()
+            .map(|block| {
+                let args: Vec<String> = self
+                    .block_args(block)
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect();
+                let reads: Vec<Json> = self
+                    .block_reads(block)
+                    .iter()
+                    .map(|read| self.value_to_json(*read))
+                    .collect();
+                let successors: Vec<String> = self
+                    .block_graph()
+                    .outgoing(block)
+                    .map(|succ| succ.to_string())
+                    .collect();
+
+                json!({
+                    "id": block.to_string(),
+                    "args": args,
+                    "op": self.block_kind(block).map(|op| self.op_to_json(op)),
+                    "reads": reads,
+                    "successors": successors,
+                })
+            })
+            .collect();
+
+        json!({
+            "ident": self.ident().to_string(),
+            "entry": self.block_entry().to_string(),
+            "blocks": blocks,
+        })
+    }
+
+    fn op_to_json(&self, op: &OpKind) -> Json {
+        match op {
+            OpKind::Call(CallKind::ControlFlow) => json!({ "name": "call_flow" }),
+            OpKind::Call(CallKind::Function) => json!({ "name": "call_function" }),
+            OpKind::IfBool => json!({ "name": "if_bool" }),
+            OpKind::TraceCaptureRaw => json!({ "name": "trace_capture_raw" }),
+            OpKind::TraceConstruct => json!({ "name": "trace_construct" }),
+            OpKind::MapPut { action } => json!({ "name": "map_put", "actions": action }),
+            OpKind::UnpackValueList(arity) => {
+                json!({ "name": "unpack_value_list", "arity": arity })
+            }
+            OpKind::Case { clauses } => {
+                json!({ "name": "case", "clauses": clauses.len(&self.pool.clause) })
+            }
+            OpKind::Match { branches } => json!({ "name": "match", "branches": branches }),
+            OpKind::Unreachable => json!({ "name": "unreachable" }),
+            OpKind::Dyn(dyn_op) => json!({ "name": dyn_op.name() }),
+        }
+    }
+
+    fn value_to_json(&self, value: Value) -> Json {
+        match self.value_kind(value) {
+            ValueKind::Argument(_, _) => json!({ "arg": value.to_string() }),
+            ValueKind::Block(block) => json!({ "block": block.to_string() }),
+            ValueKind::Const(cons) => {
+                let mut buf = Vec::new();
+                self.cons().write(cons, &mut buf);
+                json!({ "const": String::from_utf8(buf).unwrap() })
+            }
+            ValueKind::PrimOp(prim) => {
+                let reads: Vec<Json> = self
+                    .primop_reads(prim)
+                    .iter()
+                    .map(|read| self.value_to_json(*read))
+                    .collect();
+                json!({ "primop": self.primop_kind(prim), "reads": reads })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn function_to_json() {
+        let (ir, map) = crate::parse_function_map_unwrap(
+            "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a yes no;
+    yes():
+        %ret(a'true');
+    no():
+        %ret(a'false');
+}
+",
+        );
+
+        let json = ir.to_json();
+        let blocks = json["blocks"].as_array().unwrap();
+        assert!(blocks.len() == 3);
+
+        let entry = map.get_block("entry").to_string();
+        assert!(json["entry"] == entry.as_str());
+
+        let entry_json = blocks.iter().find(|b| b["id"] == entry.as_str()).unwrap();
+        assert!(entry_json["op"]["name"] == "if_bool");
+        assert!(entry_json["args"].as_array().unwrap().len() == 3);
+        assert!(entry_json["successors"].as_array().unwrap().len() == 2);
+
+        let yes = map.get_block("yes").to_string();
+        let yes_json = blocks.iter().find(|b| b["id"] == yes.as_str()).unwrap();
+        assert!(yes_json["op"]["name"] == "call_flow");
+        assert!(yes_json["reads"][1]["const"] == "a'true'");
+    }
+}
@@ -4,7 +4,9 @@
 pub mod printer;
 
 pub mod dot_printer;
-pub use dot_printer::function_to_dot;
+pub use dot_printer::{function_to_dot, function_to_dot_with_config, DotConfig};
+
+mod json;
 
 //pub trait TextFormatter {
 //    // TODO add result
@@ -71,3 +73,6 @@ pub use parser::{
 };
 
 pub mod ast;
+
+#[cfg(test)]
+mod round_trip;
@@ -97,6 +97,7 @@ pub enum Token {
     Greater,
     MapOpen,
     Colon,
+    ColonEquals,
     Semicolon,
     Comma,
     Question,
@@ -104,6 +105,11 @@ pub enum Token {
     Percent,
     Equals,
     EqualsEquals,
+    SlashEquals,
+    EqualsLess,
+    GreaterEquals,
+    EqualsColonEquals,
+    EqualsSlashEquals,
     FatArrow,
     Underscore,
     Pipe,
@@ -123,6 +129,8 @@ pub enum Token {
     Case,
     Guard,
     Except,
+    Binary,
+    MapPut,
 }
 
 lazy_static! {
@@ -142,6 +150,8 @@ lazy_static! {
         map.insert(Symbol::intern("case"), Token::Case);
         map.insert(Symbol::intern("except"), Token::Except);
         map.insert(Symbol::intern("guard"), Token::Guard);
+        map.insert(Symbol::intern("binary"), Token::Binary);
+        map.insert(Symbol::intern("map_put"), Token::MapPut);
         map
     };
 }
@@ -334,20 +344,32 @@ where
             '[' => pop!(self, Token::SquareOpen),
             ']' => pop!(self, Token::SquareClose),
             '<' => pop!(self, Token::Less),
-            '>' => pop!(self, Token::Greater),
+            '>' => match self.peek() {
+                '=' => pop2!(self, Token::GreaterEquals),
+                _ => pop!(self, Token::Greater),
+            },
             '%' => match self.peek() {
                 '{' => pop2!(self, Token::MapOpen),
                 c if c.is_alphanumeric() => self.lex_variable(),
                 _ => unimplemented!(),
             },
             ',' => pop!(self, Token::Comma),
-            ':' => pop!(self, Token::Colon),
+            ':' => match self.peek() {
+                '=' => pop2!(self, Token::ColonEquals),
+                _ => pop!(self, Token::Colon),
+            },
             ';' => pop!(self, Token::Semicolon),
-            '/' => pop!(self, Token::ForwardSlash),
+            '/' => match self.peek() {
+                '=' => pop2!(self, Token::SlashEquals),
+                _ => pop!(self, Token::ForwardSlash),
+            },
             '|' => pop!(self, Token::Pipe),
-            '=' => match self.peek() {
-                '>' => pop2!(self, Token::FatArrow),
-                '=' => pop2!(self, Token::EqualsEquals),
+            '=' => match (self.peek(), self.peek_next()) {
+                ('>', _) => pop2!(self, Token::FatArrow),
+                ('=', _) => pop2!(self, Token::EqualsEquals),
+                ('<', _) => pop2!(self, Token::EqualsLess),
+                (':', '=') => pop3!(self, Token::EqualsColonEquals),
+                ('/', '=') => pop3!(self, Token::EqualsSlashEquals),
                 _ => pop!(self, Token::Equals),
             },
             '_' => pop!(self, Token::Underscore),
@@ -1,12 +1,14 @@
 use std::sync::Arc;
 
 use libeir_diagnostics::{CodeMap, SourceIndex};
-use libeir_intern::Ident;
+use libeir_intern::{Ident, Symbol};
+use libeir_util_number::ToPrimitive;
 use libeir_util_parse::{
     self as parse, error_tee, ErrorReceiver, Parse, Scanner, Source, SourceError,
 };
 
-use crate::FunctionIdent;
+use crate::constant::Integer;
+use crate::{BinaryEntrySpecifier, Endianness, FunctionIdent};
 
 mod lexer;
 use lexer::{Lexer, Token};
@@ -38,6 +40,61 @@ pub(crate) mod grammar {
     include!(concat!(env!("OUT_DIR"), "/text/parser/grammar.rs"));
 }
 
+/// An option within the parentheses of a binary specifier,
+/// `integer(signed, big, 8)`.
+pub(crate) enum SpecifierOpt {
+    Word(Symbol),
+    Integer(Integer),
+}
+
+/// Resolves a binary specifier from its textual form, as printed by the
+/// IR printer. Returns `None` if the name or options are invalid.
+pub(crate) fn binary_specifier(
+    name: Symbol,
+    opts: &[SpecifierOpt],
+) -> Option<BinaryEntrySpecifier> {
+    let endianness = |opt: &SpecifierOpt| match opt {
+        SpecifierOpt::Word(word) => match word.as_str().get() {
+            "big" => Some(Endianness::Big),
+            "little" => Some(Endianness::Little),
+            "native" => Some(Endianness::Native),
+            _ => None,
+        },
+        _ => None,
+    };
+    let unit = |opt: &SpecifierOpt| match opt {
+        SpecifierOpt::Integer(int) => int.to_i64(),
+        _ => None,
+    };
+
+    let spec = match (name.as_str().get(), opts) {
+        ("integer", [signed, end, u]) => BinaryEntrySpecifier::Integer {
+            signed: match signed {
+                SpecifierOpt::Word(word) if *word == "signed" => true,
+                SpecifierOpt::Word(word) if *word == "unsigned" => false,
+                _ => return None,
+            },
+            endianness: endianness(end)?,
+            unit: unit(u)?,
+        },
+        ("float", [end, u]) => BinaryEntrySpecifier::Float {
+            endianness: endianness(end)?,
+            unit: unit(u)?,
+        },
+        ("bytes", [u]) => BinaryEntrySpecifier::Bytes { unit: unit(u)? },
+        ("bits", [u]) => BinaryEntrySpecifier::Bits { unit: unit(u)? },
+        ("utf8", []) => BinaryEntrySpecifier::Utf8,
+        ("utf16", [end]) => BinaryEntrySpecifier::Utf16 {
+            endianness: endianness(end)?,
+        },
+        ("utf32", [end]) => BinaryEntrySpecifier::Utf32 {
+            endianness: endianness(end)?,
+        },
+        _ => return None,
+    };
+    Some(spec)
+}
+
 pub struct NamedFunction {
     pub name: Ident,
     pub function: super::ast::Function,
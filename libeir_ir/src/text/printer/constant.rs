@@ -6,8 +6,17 @@ pub fn constant_to_doc<'a>(
     arena: &'a Arena<'a>,
     container: &ConstantContainer,
     constant: Const,
+    char_literals: bool,
+    string_literals: bool,
 ) -> RefDoc<'a, ()> {
-    constant_to_doc_state(arena, container, constant, ConstantState::Normal)
+    constant_to_doc_state(
+        arena,
+        container,
+        constant,
+        ConstantState::Normal,
+        char_literals,
+        string_literals,
+    )
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -33,8 +42,25 @@ fn constant_to_doc_state<'a>(
     container: &ConstantContainer,
     constant: Const,
     state: ConstantState,
+    char_literals: bool,
+    string_literals: bool,
 ) -> RefDoc<'a, ()> {
+    if let ConstantState::Normal = state {
+        if string_literals {
+            if let Some(string) = printable_string(container, constant) {
+                return arena.text(format!("\"{}\"", string)).into_doc();
+            }
+        }
+    }
+
     match container.const_kind(constant) {
+        ConstKind::Atomic(AtomicTerm::Int(int))
+            if char_literals && container.has_char_hint(constant) && is_printable(int.0) =>
+        {
+            norm_state!(arena, state)
+                .append(arena.text(format!("${}", int.0 as u8 as char)))
+                .into_doc()
+        }
         ConstKind::Atomic(atomic) => norm_state!(arena, state)
             .append(atomic_to_doc(arena, atomic))
             .into_doc(),
@@ -47,12 +73,16 @@ fn constant_to_doc_state<'a>(
                     container,
                     *head,
                     ConstantState::Normal,
+                    char_literals,
+                    string_literals,
                 ))
                 .append(constant_to_doc_state(
                     arena,
                     container,
                     *tail,
                     ConstantState::ListTail,
+                    char_literals,
+                    string_literals,
                 ))
                 .append(arena.text("]"))
                 .into_doc(),
@@ -65,12 +95,16 @@ fn constant_to_doc_state<'a>(
                     container,
                     *head,
                     ConstantState::Normal,
+                    char_literals,
+                    string_literals,
                 ))
                 .append(constant_to_doc_state(
                     arena,
                     container,
                     *tail,
                     ConstantState::ListTail,
+                    char_literals,
+                    string_literals,
                 ))
                 .into_doc(),
         },
@@ -79,7 +113,14 @@ fn constant_to_doc_state<'a>(
                 .append(arena.text("{"))
                 .append(arena.intersperse(
                     entries.as_slice(&container.const_pool).iter().map(|c| {
-                        constant_to_doc_state(arena, container, *c, ConstantState::Normal)
+                        constant_to_doc_state(
+                            arena,
+                            container,
+                            *c,
+                            ConstantState::Normal,
+                            char_literals,
+                            string_literals,
+                        )
                     }),
                     arena.text(",").append(arena.space()),
                 ))
@@ -101,6 +142,8 @@ fn constant_to_doc_state<'a>(
                                     container,
                                     *k,
                                     ConstantState::Normal,
+                                    char_literals,
+                                    string_literals,
                                 ))
                                 .append(arena.space())
                                 .append(arena.text("=>"))
@@ -110,6 +153,8 @@ fn constant_to_doc_state<'a>(
                                     container,
                                     *v,
                                     ConstantState::Normal,
+                                    char_literals,
+                                    string_literals,
                                 ))
                         }),
                     arena.text(",").append(arena.space()),
@@ -120,6 +165,123 @@ fn constant_to_doc_state<'a>(
     }
 }
 
+fn is_printable(num: i64) -> bool {
+    num >= 0x20 && num < 0x7f
+}
+
+/// Renders `constant` as the contents of a string literal if it is a
+/// proper, non-empty list of printable characters. Like Erlang, common
+/// whitespace and control escapes count as printable.
+fn printable_string(container: &ConstantContainer, mut constant: Const) -> Option<String> {
+    let mut string = String::new();
+    loop {
+        match container.const_kind(constant) {
+            ConstKind::ListCell { head, tail } => {
+                let num = match container.const_kind(*head) {
+                    ConstKind::Atomic(AtomicTerm::Int(int)) if (0..=0xff).contains(&int.0) => int.0,
+                    _ => return None,
+                };
+                match num as u8 {
+                    b'"' => string.push_str("\\\""),
+                    b'\\' => string.push_str("\\\\"),
+                    b'\n' => string.push_str("\\n"),
+                    b'\r' => string.push_str("\\r"),
+                    b'\t' => string.push_str("\\t"),
+                    0x0b => string.push_str("\\v"),
+                    0x08 => string.push_str("\\b"),
+                    0x0c => string.push_str("\\f"),
+                    0x1b => string.push_str("\\e"),
+                    _ if is_printable(num) => string.push(num as u8 as char),
+                    _ => return None,
+                }
+                constant = *tail;
+            }
+            ConstKind::Atomic(AtomicTerm::Nil) if !string.is_empty() => return Some(string),
+            _ => return None,
+        }
+    }
+}
+
 fn atomic_to_doc<'a>(arena: &'a Arena<'a>, atomic: &AtomicTerm) -> RefDoc<'a, ()> {
-    arena.text(format!("{}", atomic)).into_doc()
+    match atomic {
+        AtomicTerm::Binary(bin) => arena
+            .text("binary")
+            .append(
+                arena
+                    .intersperse(
+                        bin.value().iter().map(|byte| arena.as_string(byte)),
+                        arena.text(",").append(arena.space()),
+                    )
+                    .enclose("[", "]"),
+            )
+            .into_doc(),
+        _ => arena.text(format!("{}", atomic)).into_doc(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty::Arena;
+
+    use super::constant_to_doc;
+    use crate::{Const, ConstantContainer};
+
+    fn render(container: &ConstantContainer, constant: Const, char_literals: bool) -> String {
+        render_with(container, constant, char_literals, false)
+    }
+
+    fn render_with(
+        container: &ConstantContainer,
+        constant: Const,
+        char_literals: bool,
+        string_literals: bool,
+    ) -> String {
+        let arena = Arena::new();
+        let doc = constant_to_doc(&arena, container, constant, char_literals, string_literals);
+        let mut out = String::new();
+        doc.render_fmt(80, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn char_hinted_integer() {
+        let mut hinted_container = ConstantContainer::new();
+        let hinted = hinted_container.from('A');
+        hinted_container.set_char_hint(hinted);
+
+        let mut plain_container = ConstantContainer::new();
+        let plain = plain_container.from(65i64);
+
+        assert_eq!(render(&hinted_container, hinted, true), "$A");
+        assert_eq!(render(&plain_container, plain, true), "65");
+
+        // Off by default
+        assert_eq!(render(&hinted_container, hinted, false), "65");
+    }
+
+    #[test]
+    fn printable_list_as_string() {
+        let mut container = ConstantContainer::new();
+        let nil = container.nil();
+        let hello = "hello\n".chars().rev().fold(nil, |tail, c| {
+            let head = container.from(c as i64);
+            container.list_cell(head, tail)
+        });
+
+        assert_eq!(render_with(&container, hello, false, true), "\"hello\\n\"");
+        assert_eq!(
+            render_with(&container, hello, false, false),
+            "[104, 101, 108, 108, 111, 10 | []]"
+        );
+
+        // Lists with non printable elements stay lists
+        let zero = container.from(0i64);
+        let tail = container.list_cell(zero, nil);
+        let h = container.from(104i64);
+        let list = container.list_cell(h, tail);
+        assert_eq!(render_with(&container, list, false, true), "[104, 0 | []]");
+
+        // So does the empty list
+        assert_eq!(render_with(&container, nil, false, true), "[]");
+    }
 }
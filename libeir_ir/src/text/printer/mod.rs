@@ -1,6 +1,6 @@
 #![allow(unused)]
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fmt::Write;
 use std::marker::PhantomData;
@@ -11,11 +11,13 @@ use pretty::{Arena, DocAllocator, RefDoc};
 
 use crate::graph::EntityVisitMap;
 use crate::{
-    BinOp, Block, CallKind, Const, Function, LogicOp, Module, OpKind, PrimOpKind, Value, ValueKind,
+    BasicType, Block, CallKind, Const, Function, LogicOp, Module, OpKind, PrimOpKind, Value,
+    ValueKind,
 };
 
 mod constant;
 mod operation;
+pub(crate) use operation::binary_specifier_to_doc;
 
 type DynError = Box<dyn Error>;
 
@@ -63,6 +65,13 @@ where
 
     /// Layout for values within a function.
     pub block_value_layout: L,
+
+    /// Print integer constants hinted as characters as `$x` literals.
+    pub char_literals: bool,
+
+    /// Print constant lists of printable characters as `"..."` string
+    /// literals.
+    pub string_literals: bool,
 }
 
 pub type StandardFormatConfig =
@@ -74,6 +83,31 @@ impl Default for StandardFormatConfig {
             block_iterator_config: DfsBlockIteratorConfig,
             value_formatter: StandardValueFormatter,
             block_value_layout: ReferencePrimopBlockValueLayout::default(),
+            char_literals: false,
+            string_literals: false,
+        }
+    }
+}
+
+/// Prints blocks and values numbered in the order they are reached from
+/// the entry block, instead of by entity index. The output only depends
+/// on the structure of the function, not on the order its entities were
+/// allocated in, which makes it suitable for comparing against golden
+/// files.
+pub type DeterministicFormatConfig = FormatConfig<
+    StructuralBlockIteratorConfig,
+    DeterministicValueFormatter,
+    ReferencePrimopBlockValueLayout,
+>;
+impl Default for DeterministicFormatConfig {
+    fn default() -> Self {
+        FormatConfig {
+            width: 80,
+            block_iterator_config: StructuralBlockIteratorConfig,
+            value_formatter: DeterministicValueFormatter::default(),
+            block_value_layout: ReferencePrimopBlockValueLayout::default(),
+            char_literals: false,
+            string_literals: false,
         }
     }
 }
@@ -114,6 +148,51 @@ impl BlockIterator for DfsBlockIterator {
     }
 }
 
+/// Blocks reachable from the entry in depth first preorder, where the
+/// successors of a block are visited in the order they are read. Unlike
+/// `DfsBlockIteratorConfig`, which visits successors in entity order, this
+/// only depends on the structure of the function.
+fn structural_block_order(fun: &Function) -> Vec<Block> {
+    let mut order = Vec::new();
+    let mut seen = HashSet::new();
+    let mut stack = vec![fun.block_entry()];
+    let mut successors = Vec::new();
+    while let Some(block) = stack.pop() {
+        if !seen.insert(block) {
+            continue;
+        }
+        order.push(block);
+
+        fun.block_walk_nested_values::<_, ()>(block, &mut |value| {
+            if let Some(successor) = fun.value_block(value) {
+                successors.push(successor);
+            }
+            Ok(())
+        })
+        .unwrap();
+        stack.extend(successors.drain(..).rev());
+    }
+    order
+}
+
+pub struct StructuralBlockIteratorConfig;
+impl BlockIteratorConfig for StructuralBlockIteratorConfig {
+    type Iter = StructuralBlockIterator;
+    fn new(&self, fun: &Function) -> Self::Iter {
+        StructuralBlockIterator {
+            blocks: structural_block_order(fun).into_iter(),
+        }
+    }
+}
+pub struct StructuralBlockIterator {
+    blocks: std::vec::IntoIter<Block>,
+}
+impl BlockIterator for StructuralBlockIterator {
+    fn next(&mut self, _fun: &Function) -> Option<Block> {
+        self.blocks.next()
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[allow(dead_code)]
 pub enum ValueSite {
@@ -121,6 +200,13 @@ pub enum ValueSite {
     Use,
 }
 pub trait ValueFormatter {
+    /// Called once for each function before any of it is formatted.
+    fn layout_function(&mut self, _fun: &Function) {}
+
+    fn block(&self, out: &mut String, _fun: &Function, block: Block) {
+        write!(out, "{}", block).unwrap();
+    }
+
     fn value(&self, out: &mut String, fun: &Function, site: ValueSite, value: Value);
 }
 
@@ -136,6 +222,60 @@ impl ValueFormatter for StandardValueFormatter {
     }
 }
 
+/// Names blocks and values by the order they are first reached in
+/// `StructuralBlockIteratorConfig` order. Block arguments are numbered
+/// before the primops read in the block. Values defined outside of the
+/// blocks reachable from the entry keep their entity index.
+#[derive(Default)]
+pub struct DeterministicValueFormatter {
+    blocks: HashMap<Block, usize>,
+    values: HashMap<Value, usize>,
+}
+impl ValueFormatter for DeterministicValueFormatter {
+    fn layout_function(&mut self, fun: &Function) {
+        self.blocks.clear();
+        self.values.clear();
+
+        let order = structural_block_order(fun);
+        for (idx, block) in order.iter().enumerate() {
+            self.blocks.insert(*block, idx);
+        }
+
+        let values = &mut self.values;
+        for block in order {
+            for arg in fun.block_args(block) {
+                let next = values.len();
+                values.entry(*arg).or_insert(next);
+            }
+            fun.block_walk_nested_values::<_, ()>(block, &mut |value| {
+                if fun.value_primop(value).is_some() {
+                    let next = values.len();
+                    values.entry(value).or_insert(next);
+                }
+                Ok(())
+            })
+            .unwrap();
+        }
+    }
+
+    fn block(&self, out: &mut String, _fun: &Function, block: Block) {
+        match self.blocks.get(&block) {
+            Some(idx) => write!(out, "block{}", idx).unwrap(),
+            None => write!(out, "{}", block).unwrap(),
+        }
+    }
+
+    fn value(&self, out: &mut String, fun: &Function, _site: ValueSite, value: Value) {
+        match fun.value_kind(value) {
+            ValueKind::Block(block) => self.block(out, fun, block),
+            _ => match self.values.get(&value) {
+                Some(idx) => write!(out, "%{}", idx).unwrap(),
+                None => write!(out, "%{}", value.index()).unwrap(),
+            },
+        }
+    }
+}
+
 pub trait BlockValueLayout {
     /// Lays out the root scope for the module. This is called once
     /// at the beginning of processing a module.
@@ -268,7 +408,11 @@ where
     ) -> RefDoc<'a, ()> {
         let arena = self.arena;
 
-        let ident = arena.as_string(block);
+        self.buf.clear();
+        config
+            .value_formatter
+            .block(&mut self.buf, state.function, block);
+        let ident = arena.as_string(&self.buf);
         let args = arena
             .intersperse(
                 state.function.block_args(block).iter().map(|v| {
@@ -376,33 +520,78 @@ where
                             .append(self.value_use(config, state, reads[1], Some(value)))
                             .append(arena.text("]"))
                     }
-                    PrimOpKind::BinOp(BinOp::Equal) => {
+                    PrimOpKind::Map => {
+                        assert!(reads.len() % 2 == 0);
+                        arena
+                            .intersperse(
+                                reads.chunks(2).map(|kv| {
+                                    arena
+                                        .nil()
+                                        .append(self.value_use(config, state, kv[0], Some(value)))
+                                        .append(arena.space())
+                                        .append(arena.text("=>"))
+                                        .append(arena.space())
+                                        .append(self.value_use(config, state, kv[1], Some(value)))
+                                }),
+                                arena.text(",").append(arena.space()),
+                            )
+                            .enclose(arena.text("%{"), arena.text("}"))
+                    }
+                    PrimOpKind::BinOp(op) => {
                         assert!(reads.len() == 2);
                         arena
                             .nil()
                             .append(self.value_use(config, state, reads[0], Some(value)))
                             .append(arena.space())
-                            .append(arena.text("=="))
+                            .append(arena.text(op.erlang_function()))
                             .append(arena.space())
                             .append(self.value_use(config, state, reads[1], Some(value)))
                     }
-                    PrimOpKind::LogicOp(LogicOp::And) => arena
-                        .intersperse(
-                            reads
-                                .iter()
-                                .map(|r| self.value_use(config, state, *r, Some(value))),
-                            arena.text(",").append(arena.space()),
-                        )
-                        .enclose("and[", "]"),
-                    PrimOpKind::LogicOp(LogicOp::Or) => arena
-                        .intersperse(
-                            reads
-                                .iter()
-                                .map(|r| self.value_use(config, state, *r, Some(value))),
-                            arena.text(",").append(arena.space()),
-                        )
-                        .enclose("or[", "]"),
-                    _ => unimplemented!("{:?}", prim_kind),
+                    PrimOpKind::LogicOp(op) => {
+                        let name = match op {
+                            LogicOp::Eq => "eq[",
+                            LogicOp::And => "and[",
+                            LogicOp::Or => "or[",
+                        };
+                        arena
+                            .intersperse(
+                                reads
+                                    .iter()
+                                    .map(|r| self.value_use(config, state, *r, Some(value))),
+                                arena.text(",").append(arena.space()),
+                            )
+                            .enclose(name, "]")
+                    }
+                    PrimOpKind::IsType(typ) => {
+                        assert!(reads.len() == 1);
+                        let mut args = vec![self.value_use(config, state, reads[0], Some(value))];
+                        let name = match typ {
+                            BasicType::List => "is_list[",
+                            BasicType::ListCell => "is_cons[",
+                            BasicType::Nil => "is_nil[",
+                            BasicType::Tuple(arity) => {
+                                args.push(arena.as_string(arity).into_doc());
+                                "is_tuple["
+                            }
+                            BasicType::Map => "is_map[",
+                            BasicType::Number => "is_number[",
+                            BasicType::Float => "is_float[",
+                            BasicType::Integer => "is_integer[",
+                            BasicType::SmallInteger => "is_smallint[",
+                            BasicType::BigInteger => "is_bigint[",
+                        };
+                        arena
+                            .intersperse(args, arena.text(",").append(arena.space()))
+                            .enclose(name, "]")
+                    }
+                    PrimOpKind::TypeTag => {
+                        assert!(reads.len() == 1);
+                        arena
+                            .nil()
+                            .append(arena.text("type_tag["))
+                            .append(self.value_use(config, state, reads[0], Some(value)))
+                            .append(arena.text("]"))
+                    }
                 }
             }
             _ => unimplemented!("{:?}", value_kind),
@@ -427,11 +616,17 @@ where
 
     fn constant(
         &mut self,
-        _config: &FormatConfig<B, V, L>,
+        config: &FormatConfig<B, V, L>,
         state: &mut FormatState,
         constant: Const,
     ) -> RefDoc<'a, ()> {
-        self::constant::constant_to_doc(&self.arena, state.function.cons(), constant)
+        self::constant::constant_to_doc(
+            &self.arena,
+            state.function.cons(),
+            constant,
+            config.char_literals,
+            config.string_literals,
+        )
     }
 
     fn value_use_only(
@@ -526,6 +721,7 @@ where
     S: BlockFormatSink,
 {
     let function = state.function;
+    config.value_formatter.layout_function(function);
     let mut block_iter = config.block_iterator_config.new(function);
 
     let arena = Arena::new();
@@ -631,6 +827,11 @@ impl Function {
         self.to_text(&mut StandardFormatConfig::default())
     }
 
+    /// Prints the function with `DeterministicFormatConfig`.
+    pub fn to_text_deterministic(&self) -> String {
+        self.to_text(&mut DeterministicFormatConfig::default())
+    }
+
     pub fn block_to_text<B, V, L>(&self, block: Block, config: &mut FormatConfig<B, V, L>) -> String
     where
         B: BlockIteratorConfig,
@@ -638,6 +839,7 @@ impl Function {
         L: BlockValueLayout,
     {
         let mut sink = StringSink::new();
+        config.value_formatter.layout_function(self);
 
         let arena = Arena::new();
         let mut ctx = FunctionFormatData {
@@ -698,4 +900,45 @@ a'woo':a'hoo'/1 {
         let text = ir.to_text(&mut StandardFormatConfig::default());
         println!("{}", text);
     }
+
+    #[test]
+    fn deterministic_numbering() {
+        let a = crate::parse_function_unwrap(
+            "
+a'woo':a'hoo'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a b2 b3;
+    b2():
+        %t = {%a, a'yes'};
+        %ret(%t);
+    b3():
+        %ret(a'no');
+}
+",
+        );
+        let b = crate::parse_function_unwrap(
+            "
+a'woo':a'hoo'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a b2 b3;
+    b3():
+        %ret(a'no');
+    b2():
+        %t = {%a, a'yes'};
+        %ret(%t);
+}
+",
+        );
+
+        assert!(a.to_text_standard() != b.to_text_standard());
+
+        let text = a.to_text_deterministic();
+        assert!(text == b.to_text_deterministic());
+        assert!(text.starts_with("block0(%0, %1, %2):"));
+
+        let parsed = crate::parse_function_unwrap(&format!("a'woo':a'hoo'/1 {{\n{}}}\n", text));
+        assert!(parsed
+            .graph_eq(parsed.block_entry(), &a, a.block_entry())
+            .is_ok());
+    }
 }
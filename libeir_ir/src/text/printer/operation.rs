@@ -1,8 +1,12 @@
 use pretty::{DocAllocator, RefDoc};
 
 use crate::binary::{BinaryEntrySpecifier, Endianness};
+use crate::pattern::PatternNodeKind;
 use crate::traits::FormatOpCtx;
-use crate::{BasicType, Block, CallKind, DynValue, MatchKind, OpKind, Value};
+use crate::{
+    BasicType, Block, CallKind, DynValue, Function, MapPutUpdate, MatchKind, OpKind, PatternClause,
+    PatternNode, PatternValue, Value,
+};
 
 use super::{
     get_value_list, BlockIteratorConfig, BlockValueLayout, FormatConfig, FormatState,
@@ -30,6 +34,10 @@ where
         self.format_data.arena
     }
 
+    fn function(&self) -> &Function {
+        self.state.function
+    }
+
     fn value_use_to_doc(&mut self, value: DynValue) -> RefDoc<'doc, ()> {
         let val = self.state.function.value_get(value).unwrap();
         self.format_data
@@ -37,7 +45,7 @@ where
     }
 }
 
-fn binary_specifier_to_doc<'a>(
+pub(crate) fn binary_specifier_to_doc<'a>(
     arena: &'a pretty::Arena<'a>,
     spec: &BinaryEntrySpecifier,
 ) -> RefDoc<'a, ()> {
@@ -93,11 +101,11 @@ fn binary_specifier_to_doc<'a>(
         BinaryEntrySpecifier::Utf8 => arena.text("utf8").append(arena.nil().parens()).into_doc(),
         BinaryEntrySpecifier::Utf16 { endianness } => arena
             .text("utf16")
-            .append(f_endianness(endianness))
+            .append(f_endianness(endianness).parens())
             .into_doc(),
         BinaryEntrySpecifier::Utf32 { endianness } => arena
             .text("utf32")
-            .append(f_endianness(endianness))
+            .append(f_endianness(endianness).parens())
             .into_doc(),
     }
 }
@@ -125,18 +133,86 @@ where
         let reads = state.function.block_reads(block);
 
         let op_doc = match op {
-            OpKind::Case { clauses, .. } => {
-                let block = arena.nil();
+            OpKind::Case { clauses } => {
+                let fun = state.function;
+                let clauses = clauses.as_slice(&fun.pool.clause);
+                let pat = fun.pat();
+
+                // Reads are laid out as
+                // (no_match, (guard, body)..., match_on, values...)
+                let values_base = 2 + clauses.len() * 2;
+                let mut num_values = 0;
+
+                let mut entries = Vec::with_capacity(clauses.len() + 1);
+                for (idx, clause) in clauses.iter().enumerate() {
+                    let binds = pat.clause_binds(*clause);
+                    let values = &reads[values_base + num_values..];
+                    num_values += pat.clause_values(*clause).len();
+
+                    let patterns: Vec<_> = pat
+                        .clause_root_nodes(*clause)
+                        .iter()
+                        .map(|node| self.pattern_to_doc(config, state, *clause, *node, values))
+                        .collect();
+                    let patterns = if patterns.len() == 1 {
+                        patterns.into_iter().next().unwrap()
+                    } else {
+                        arena
+                            .intersperse(patterns, arena.text(",").append(arena.space()))
+                            .enclose("<", ">")
+                            .into_doc()
+                    };
+
+                    let bind_names = arena
+                        .intersperse(
+                            (0..binds.len()).map(|n| arena.as_string(format!("bind{}", n))),
+                            arena.text(",").append(arena.space()),
+                        )
+                        .parens();
+
+                    let entry = arena
+                        .nil()
+                        .append(patterns)
+                        .append(arena.space())
+                        .append(arena.text("guard"))
+                        .append(arena.space())
+                        .append(self.value_use(config, state, reads[1 + idx * 2], None))
+                        .append(arena.space())
+                        .append(arena.text("=>"))
+                        .append(arena.space())
+                        .append(self.value_use(config, state, reads[2 + idx * 2], None))
+                        .append(bind_names)
+                        .append(arena.text(";"));
+                    entries.push(entry.indent(2));
+                }
+
+                let no_match = arena
+                    .text("_")
+                    .append(arena.space())
+                    .append(arena.text("=>"))
+                    .append(arena.space())
+                    .append(self.value_use(config, state, reads[0], None))
+                    .append(arena.text(";"));
+                entries.push(no_match.indent(2));
+
+                let match_on = self.value_use(config, state, reads[values_base - 1], None);
 
                 arena
                     .nil()
                     .append(arena.text("case"))
                     .append(arena.space())
-                    .append(block.nest(1).braces())
+                    .append(match_on)
+                    .append(arena.space())
+                    .append(
+                        arena
+                            .hardline()
+                            .append(arena.intersperse(entries, arena.hardline()))
+                            .append(arena.hardline())
+                            .braces(),
+                    )
             }
             OpKind::Match { branches } => {
                 let dests = reads[0];
-                let num_dests = state.function.value_list_length(dests);
                 let num_branches = branches.len();
                 let mut branches_formatted = Vec::with_capacity(num_branches);
                 for (i, kind) in branches.iter().enumerate() {
@@ -148,126 +224,44 @@ where
                     for n in 0..num_args {
                         args.push(state.function.value_list_get_n(args_vl, n).unwrap());
                     }
-                    let formatted = match kind {
-                        MatchKind::Value => {
-                            let val = self.value_use(config, state, args[0], None);
-                            let block_args = arena
-                                .intersperse(
-                                    args.iter()
-                                        .skip(1)
-                                        .map(|v| self.value_use(config, state, *v, None)),
-                                    arena.text(",").append(arena.softline()),
-                                )
-                                .nest(1)
-                                .parens();
-                            let body = arena.nil().append(block_val).append(block_args);
-                            arena
-                                .nil()
-                                .append(arena.text("value"))
-                                .append(arena.space())
-                                .append(val)
-                                .append(arena.space())
-                                .append(arena.text("=>"))
-                                .append(arena.space())
-                                .append(arena.nil().append(body))
-                        }
-                        MatchKind::Type(ty) => {
-                            let block_args = arena
-                                .intersperse(
-                                    args.iter().map(|v| self.value_use(config, state, *v, None)),
-                                    arena.text(",").append(arena.softline()),
-                                )
-                                .nest(1)
-                                .parens();
-                            let body = arena.nil().append(block_val).append(block_args);
-                            arena
-                                .nil()
-                                .append(arena.text("is_type"))
-                                .append(arena.space())
-                                .append(arena.text(type_to_text(ty)))
-                                .append(arena.space())
-                                .append(arena.text("=>"))
-                                .append(arena.space())
-                                .append(arena.nil().append(body))
-                        }
+                    let kind_doc = match kind {
+                        MatchKind::Value => arena
+                            .text("value")
+                            .append(arena.space())
+                            .append(self.value_use(config, state, args[0], None)),
+                        MatchKind::Type(ty) => arena
+                            .text("type")
+                            .append(arena.space())
+                            .append(arena.text(type_to_text(ty))),
                         MatchKind::Binary(ref spec) => {
-                            let doc = binary_specifier_to_doc(arena, spec);
-                            arena.nil().append(doc)
-                        }
-                        MatchKind::Tuple(arity) => {
-                            let block_args = arena
-                                .intersperse(
-                                    args.iter().map(|v| self.value_use(config, state, *v, None)),
-                                    arena.text(",").append(arena.softline()),
-                                )
-                                .nest(1)
-                                .parens();
-                            let body = arena.nil().append(block_val).append(block_args);
-                            arena
-                                .nil()
-                                .append(arena.text("{}"))
-                                .append(arena.space())
-                                .append(arena.text(format!("arity {}", arity)))
+                            let doc = arena
+                                .text("binary")
                                 .append(arena.space())
-                                .append(arena.text("=>"))
-                                .append(arena.space())
-                                .append(arena.nil().append(body))
-                        }
-                        MatchKind::ListCell => {
-                            let block_args = arena
-                                .intersperse(
-                                    args.iter().map(|v| self.value_use(config, state, *v, None)),
-                                    arena.text(",").append(arena.softline()),
-                                )
-                                .nest(1)
-                                .parens();
-                            let body = arena.nil().append(block_val).append(block_args);
-                            arena
-                                .nil()
-                                .append(arena.text("[]"))
-                                .append(arena.space())
-                                .append(arena.text("=>"))
-                                .append(arena.space())
-                                .append(arena.nil().append(body))
+                                .append(binary_specifier_to_doc(arena, spec));
+                            if let Some(size) = args.get(0) {
+                                doc.append(arena.space())
+                                    .append(self.value_use(config, state, *size, None))
+                            } else {
+                                doc
+                            }
                         }
+                        MatchKind::Tuple(arity) => arena
+                            .text("{}")
+                            .append(arena.space())
+                            .append(arena.text(format!("arity {}", arity))),
+                        MatchKind::ListCell => arena.text("[]"),
                         MatchKind::MapItem => {
-                            let val = self.value_use(config, state, args[0], None);
-                            let block_args = arena
-                                .intersperse(
-                                    args.iter()
-                                        .skip(1)
-                                        .map(|v| self.value_use(config, state, *v, None)),
-                                    arena.text(",").append(arena.softline()),
-                                )
-                                .nest(1)
-                                .parens();
-                            let body = arena.nil().append(block_val).append(block_args);
-                            arena
-                                .nil()
-                                .append(val)
-                                .append(arena.space())
-                                .append(arena.text("=>"))
-                                .append(arena.space())
-                                .append(arena.nil().append(body))
-                        }
-                        MatchKind::Wildcard => {
-                            let block_args = arena
-                                .intersperse(
-                                    args.iter().map(|v| self.value_use(config, state, *v, None)),
-                                    arena.text(",").append(arena.softline()),
-                                )
-                                .nest(1)
-                                .parens();
-                            let body = arena.nil().append(block_val).append(block_args);
-                            arena
-                                .nil()
-                                .append(arena.text("_"))
-                                .append(arena.space())
-                                .append(arena.text("=>"))
-                                .append(arena.space())
-                                .append(arena.nil().append(body))
+                            let key = self.value_use(config, state, args[0], None);
+                            arena.text("%{").append(key).append(arena.text("}"))
                         }
+                        MatchKind::Wildcard => arena.text("_"),
                     };
+                    let formatted = kind_doc
+                        .append(arena.space())
+                        .append(arena.text("=>"))
+                        .append(arena.space())
+                        .append(block_val)
+                        .append(arena.text(";"));
                     branches_formatted.push(formatted.indent(2));
                 }
 
@@ -287,6 +281,48 @@ where
                             .braces(),
                     )
             }
+            OpKind::MapPut { action } => {
+                let mut entries = Vec::with_capacity(action.len());
+                for (i, action) in action.iter().enumerate() {
+                    let key = self.value_use(config, state, reads[3 + i * 2], None);
+                    let val = self.value_use(config, state, reads[4 + i * 2], None);
+                    let op = match action {
+                        MapPutUpdate::Put => "=>",
+                        MapPutUpdate::Update => ":=",
+                    };
+                    let entry = arena
+                        .nil()
+                        .append(key)
+                        .append(arena.space())
+                        .append(arena.text(op))
+                        .append(arena.space())
+                        .append(val)
+                        .append(arena.text(";"));
+                    entries.push(entry.indent(2));
+                }
+
+                arena
+                    .nil()
+                    .append(arena.text("map_put"))
+                    .append(arena.space())
+                    .append(self.value_use(config, state, reads[2], None))
+                    .append(arena.space())
+                    .append(
+                        arena
+                            .hardline()
+                            .append(arena.intersperse(entries, arena.hardline()))
+                            .append(arena.hardline())
+                            .braces(),
+                    )
+                    .append(arena.space())
+                    .append(arena.text("=>"))
+                    .append(arena.space())
+                    .append(self.value_use(config, state, reads[0], None))
+                    .append(arena.space())
+                    .append(arena.text("except"))
+                    .append(arena.space())
+                    .append(self.value_use(config, state, reads[1], None))
+            }
             OpKind::Call(CallKind::Function) => {
                 let callee_val = self.value_use(config, state, reads[0], None);
                 let call_args = arena
@@ -399,7 +435,10 @@ where
                         )
                         .nest(1)
                         .parens();
-                    arena.as_string(op.name()).append(call_args)
+                    arena
+                        .text("@")
+                        .append(arena.as_string(op.name()))
+                        .append(call_args)
                 }
             }
             _ => {
@@ -412,13 +451,160 @@ where
     }
 }
 
+impl<'a, B, V, L> FunctionFormatData<'a, B, V, L>
+where
+    B: BlockIteratorConfig,
+    V: ValueFormatter,
+    L: BlockValueLayout,
+{
+    /// Formats a case pattern node. Bound nodes are prefixed with
+    /// `bindN@`, `N` being the position in the binds of the clause.
+    /// `values` are the case reads for the values of the clause.
+    fn pattern_to_doc(
+        &mut self,
+        config: &FormatConfig<B, V, L>,
+        state: &mut FormatState,
+        clause: PatternClause,
+        node: PatternNode,
+        values: &[Value],
+    ) -> RefDoc<'a, ()> {
+        let arena = self.arena;
+        let fun = state.function;
+        let pat = fun.pat();
+
+        let doc = match pat.node_kind(node) {
+            PatternNodeKind::Wildcard => arena.text("_"),
+            PatternNodeKind::Const(cons) => arena.nil().append(self.constant(config, state, *cons)),
+            PatternNodeKind::Value(val) => arena
+                .nil()
+                .append(self.pattern_value_to_doc(config, state, clause, *val, values)),
+            PatternNodeKind::Tuple(elems) => {
+                let elems: Vec<_> = elems
+                    .as_slice(&pat.node_pool)
+                    .iter()
+                    .map(|elem| self.pattern_to_doc(config, state, clause, *elem, values))
+                    .collect();
+                arena
+                    .intersperse(elems, arena.text(",").append(arena.space()))
+                    .enclose("{", "}")
+            }
+            PatternNodeKind::List { head, tail } => {
+                let (head, tail) = (*head, *tail);
+                arena
+                    .text("[")
+                    .append(self.pattern_to_doc(config, state, clause, head, values))
+                    .append(arena.space())
+                    .append(arena.text("|"))
+                    .append(arena.space())
+                    .append(self.pattern_to_doc(config, state, clause, tail, values))
+                    .append(arena.text("]"))
+            }
+            PatternNodeKind::Binary {
+                specifier,
+                value,
+                size,
+                remaining,
+            } => {
+                let (value, size, remaining) = (*value, *size, *remaining);
+                let mut doc = arena
+                    .text("binary")
+                    .append(arena.space())
+                    .append(binary_specifier_to_doc(arena, specifier));
+                if let Some(size) = size {
+                    let size = if pat.clause_values(clause).contains(&size) {
+                        self.pattern_value_to_doc(config, state, clause, size, values)
+                    } else {
+                        // The size is a node bound earlier in the clause
+                        let (_, size_node) = pat
+                            .clause_node_binds_iter(clause)
+                            .find(|(val, _)| *val == size)
+                            .unwrap();
+                        let idx = pat
+                            .clause_binds(clause)
+                            .iter()
+                            .position(|b| *b == size_node)
+                            .expect("binary size node is not bound by the clause");
+                        arena.as_string(format!("bind{}", idx)).into_doc()
+                    };
+                    doc = doc.append(arena.space()).append(size);
+                }
+                doc.append(arena.space()).append(
+                    arena
+                        .nil()
+                        .append(self.pattern_to_doc(config, state, clause, value, values))
+                        .append(arena.space())
+                        .append(arena.text("|"))
+                        .append(arena.space())
+                        .append(self.pattern_to_doc(config, state, clause, remaining, values))
+                        .parens(),
+                )
+            }
+            PatternNodeKind::Map {
+                keys,
+                values: nodes,
+            } => {
+                let entries: Vec<_> = keys
+                    .as_slice(&pat.value_pool)
+                    .iter()
+                    .zip(nodes.as_slice(&pat.node_pool))
+                    .map(|(key, node)| {
+                        arena
+                            .nil()
+                            .append(self.pattern_value_to_doc(config, state, clause, *key, values))
+                            .append(arena.space())
+                            .append(arena.text("=>"))
+                            .append(arena.space())
+                            .append(self.pattern_to_doc(config, state, clause, *node, values))
+                    })
+                    .collect();
+                arena
+                    .intersperse(entries, arena.text(",").append(arena.space()))
+                    .enclose("%{", "}")
+            }
+        };
+
+        match pat.clause_binds(clause).iter().position(|b| *b == node) {
+            Some(idx) => arena
+                .as_string(format!("bind{}", idx))
+                .append(arena.text("@"))
+                .append(doc)
+                .into_doc(),
+            None => doc.into_doc(),
+        }
+    }
+
+    /// Formats a value read by a pattern as `value <read>`.
+    fn pattern_value_to_doc(
+        &mut self,
+        config: &FormatConfig<B, V, L>,
+        state: &mut FormatState,
+        clause: PatternClause,
+        val: PatternValue,
+        values: &[Value],
+    ) -> RefDoc<'a, ()> {
+        let arena = self.arena;
+        let idx = state
+            .function
+            .pat()
+            .clause_values(clause)
+            .iter()
+            .position(|v| *v == val)
+            .unwrap();
+        arena
+            .text("value")
+            .append(arena.space())
+            .append(self.value_use(config, state, values[idx], None))
+            .into_doc()
+    }
+}
+
 fn type_to_text(ty: &BasicType) -> String {
     match ty {
         BasicType::List => "list".to_owned(),
         BasicType::ListCell => "cons".to_owned(),
         BasicType::Nil => "nil".to_owned(),
-        BasicType::Tuple(arity) => format!("tuple({})", arity),
-        BasicType::Map => "map".to_owned(),
+        BasicType::Tuple(arity) => format!("{{}} arity {}", arity),
+        BasicType::Map => "%{}".to_owned(),
         BasicType::Number => "number".to_owned(),
         BasicType::Float => "float".to_owned(),
         BasicType::Integer => "integer".to_owned(),
@@ -0,0 +1,497 @@
+//! Printer/parser conformance tests.
+//!
+//! For each op-emitting builder helper, a tiny function is built, printed
+//! with the standard printer, parsed back and compared structurally with
+//! the original. `Case` patterns are covered for constants, tuples, lists,
+//! read values, maps and binaries. The same is done for every primop kind.
+
+use std::sync::Arc;
+
+use libeir_diagnostics::{CodeMap, SourceSpan};
+use libeir_intern::{Ident, Symbol};
+
+use crate::binary::{BinaryEntrySpecifier, Endianness};
+use crate::operation::binary_construct::{
+    BinaryConstructFinish, BinaryConstructPush, BinaryConstructStart,
+};
+use crate::operation::receive::{ReceiveDone, ReceiveStart, ReceiveWait};
+use crate::{
+    BasicType, BinOp, Block, Function, FunctionBuilder, FunctionIdent, LogicOp, MapPutUpdate, Value,
+};
+
+/// Builds a function of the given arity. The closure receives the entry
+/// block along with its arguments, `[ret, thr, arg1, ...]`.
+fn build<F>(arity: usize, body: F) -> Function
+where
+    F: FnOnce(&mut FunctionBuilder, Block, &[Value]),
+{
+    let ident = FunctionIdent {
+        module: Ident::from_str("round_trip"),
+        name: Ident::from_str("test"),
+        arity,
+    };
+    let mut fun = Function::new(SourceSpan::UNKNOWN, ident);
+
+    {
+        let mut b = fun.builder();
+        let (entry, args) = b.create_entry(arity);
+        body(&mut b, entry, &args);
+    }
+
+    fun
+}
+
+/// Terminates `block` by returning all of its arguments to `ret`.
+fn ret(b: &mut FunctionBuilder, block: Block, ret: Value) {
+    let args = b.block_args(block).to_vec();
+    b.op_call_flow(block, ret, &args);
+}
+
+fn assert_round_trip(fun: &Function) {
+    let ident = fun.ident();
+    let text = format!(
+        "a'{}':a'{}'/{} {{\n{}}}\n",
+        ident.module,
+        ident.name,
+        ident.arity,
+        fun.to_text_standard()
+    );
+
+    let codemap = Arc::new(CodeMap::new());
+    let parsed = match crate::text::parser::function_codemap(&text, codemap.clone()) {
+        (Ok(parsed), _errors) => parsed,
+        (Err(()), errors) => {
+            errors.print(&codemap);
+            panic!("failed to parse printed function:\n{}", text);
+        }
+    };
+
+    if let Err(err) = fun.graph_eq(fun.block_entry(), &parsed, parsed.block_entry()) {
+        panic!("round trip mismatch ({:?}):\n{}", err, text);
+    }
+}
+
+#[test]
+fn call_flow() {
+    let fun = build(1, |b, entry, args| {
+        b.op_call_flow(entry, args[0], &[args[2]]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn call_function() {
+    let fun = build(1, |b, entry, args| {
+        let callee = b.prim_capture_function(
+            SourceSpan::UNKNOWN,
+            Symbol::intern("m"),
+            Symbol::intern("f"),
+            1,
+        );
+        let (ok, fail) = b.op_call_function(SourceSpan::UNKNOWN, entry, callee, &[args[2]]);
+        ret(b, ok, args[0]);
+        ret(b, fail, args[1]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn apply_mfa() {
+    let fun = build(3, |b, entry, args| {
+        let (ok, fail) = b.op_apply_mfa(SourceSpan::UNKNOWN, entry, args[2], args[3], &[args[4]]);
+        ret(b, ok, args[0]);
+        ret(b, fail, args[1]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn apply_fun() {
+    let fun = build(2, |b, entry, args| {
+        let (ok, fail) = b.op_apply_fun(SourceSpan::UNKNOWN, entry, args[2], args[3]);
+        ret(b, ok, args[0]);
+        ret(b, fail, args[1]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn trace_capture_raw() {
+    let fun = build(0, |b, entry, args| {
+        let cont = b.op_trace_capture_raw(SourceSpan::UNKNOWN, entry);
+        ret(b, cont, args[0]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn unpack_value_list() {
+    let fun = build(2, |b, entry, args| {
+        let list = b.prim_value_list(&[args[2], args[3]]);
+        let cont = b.op_unpack_value_list(entry, list, 2);
+        ret(b, cont, args[0]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn if_bool() {
+    let fun = build(1, |b, entry, args| {
+        let (tru, fal, or) = b.op_if_bool(SourceSpan::UNKNOWN, entry, args[2]);
+        ret(b, tru, args[0]);
+        ret(b, fal, args[0]);
+        ret(b, or, args[1]);
+    });
+    assert_round_trip(&fun);
+
+    let fun = build(1, |b, entry, args| {
+        let (tru, fal) = b.op_if_bool_strict(SourceSpan::UNKNOWN, entry, args[2]);
+        ret(b, tru, args[0]);
+        ret(b, fal, args[1]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn unreachable() {
+    let fun = build(0, |b, entry, _args| {
+        b.op_unreachable(SourceSpan::UNKNOWN, entry);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn match_kinds() {
+    let fun = build(2, |b, entry, args| {
+        let mut m = b.op_match_build(SourceSpan::UNKNOWN);
+        let atom = b.value(Symbol::intern("foo"));
+
+        let mut targets = vec![
+            m.push_value(atom, b),
+            m.push_type(BasicType::Map, b),
+            m.push_type(BasicType::Tuple(3), b),
+            m.push_type(BasicType::List, b),
+            m.push_type(BasicType::SmallInteger, b),
+            m.push_binary(BinaryEntrySpecifier::default(), Some(args[3]), b),
+            m.push_binary(
+                BinaryEntrySpecifier::Utf16 {
+                    endianness: Endianness::Little,
+                },
+                None,
+                b,
+            ),
+            m.push_tuple(2, b),
+            m.push_list_cell(b),
+            m.push_map_item(args[3], b),
+        ];
+        targets.push(m.push_wildcard(SourceSpan::UNKNOWN, b));
+        m.finish(entry, args[2], b);
+
+        for target in targets {
+            ret(b, target, args[0]);
+        }
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn is_tuple_of_arity() {
+    let fun = build(1, |b, entry, args| {
+        let (matched, fail) = b.op_is_tuple_of_arity(SourceSpan::UNKNOWN, entry, args[2], 2);
+        ret(b, matched, args[0]);
+        ret(b, fail, args[1]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn map_put() {
+    let fun = build(3, |b, entry, args| {
+        let key = b.value(Symbol::intern("key"));
+        let mut map_b = b.op_map_put_build(SourceSpan::UNKNOWN, args[2]);
+        map_b.push_kv(args[3], args[4], MapPutUpdate::Put, b);
+        map_b.push_kv(key, args[4], MapPutUpdate::Update, b);
+        let (ok, fail) = map_b.finish(entry, b);
+        ret(b, ok, args[0]);
+        ret(b, fail, args[1]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn receive() {
+    let fun = build(0, |b, entry, args| {
+        let timeout = b.value(Symbol::intern("infinity"));
+        let start = ReceiveStart::build(b, entry, timeout);
+        let recv_ref = b.block_args(start)[0];
+
+        let (timed_out, check) = ReceiveWait::build(b, start, recv_ref);
+        ret(b, timed_out, args[0]);
+
+        let message = b.block_args(check)[0];
+        let done = ReceiveDone::build(b, check, recv_ref, &[message]);
+        ret(b, done, args[0]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn binary_construct() {
+    let fun = build(2, |b, entry, args| {
+        let start = BinaryConstructStart::build(b, entry);
+        let bin_ref = b.block_args(start)[0];
+
+        let (ok, fail) = BinaryConstructPush::build(
+            b,
+            start,
+            bin_ref,
+            args[2],
+            BinaryEntrySpecifier::default(),
+            Some(args[3]),
+        );
+        ret(b, fail, args[1]);
+        let bin_ref = b.block_args(ok)[0];
+
+        let (ok, fail) =
+            BinaryConstructPush::build(b, ok, bin_ref, args[3], BinaryEntrySpecifier::Utf8, None);
+        ret(b, fail, args[1]);
+        let bin_ref = b.block_args(ok)[0];
+
+        let done = BinaryConstructFinish::build(b, ok, bin_ref);
+        ret(b, done, args[0]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn case() {
+    let fun = build(2, |b, entry, args| {
+        let atom = b.cons_mut().from(Symbol::intern("ok"));
+        let truth = b.value(true);
+
+        let mut case_b = b.op_case_build(SourceSpan::UNKNOWN);
+
+        // {bind0@a'ok', bind1@_}
+        let clause = b.pat_mut().clause_start(SourceSpan::UNKNOWN);
+        let ok_node = b.pat_mut().node_empty(None);
+        b.pat_mut().constant(ok_node, atom);
+        let wildcard = b.pat_mut().node_empty(None);
+        b.pat_mut().wildcard(wildcard);
+        let tuple = b.pat_mut().node_empty(None);
+        b.pat_mut().tuple(tuple);
+        b.pat_mut().tuple_elem_push(tuple, ok_node);
+        b.pat_mut().tuple_elem_push(tuple, wildcard);
+        b.pat_mut().node_finish(tuple);
+        b.pat_mut().clause_node_push(clause, tuple);
+        b.pat_mut().clause_bind_push(clause, ok_node);
+        b.pat_mut().clause_bind_push(clause, wildcard);
+        b.pat_mut().clause_finish(clause);
+
+        let guard = b.block_insert();
+        let guard_cont = b.block_arg_insert(guard);
+        b.block_arg_insert(guard);
+        b.block_arg_insert(guard);
+        b.op_call_flow(guard, guard_cont, &[truth]);
+        let body = b.block_insert();
+        b.block_arg_insert(body);
+        b.block_arg_insert(body);
+        ret(b, body, args[0]);
+        let guard_val = b.value(guard);
+        let body_val = b.value(body);
+        case_b.push_clause(clause, guard_val, body_val, b);
+
+        // [value %arg | bind0@_]
+        let clause = b.pat_mut().clause_start(SourceSpan::UNKNOWN);
+        let pat_val = b.pat_mut().clause_value(clause);
+        case_b.push_value(args[3], b);
+        let value_node = b.pat_mut().node_empty(None);
+        b.pat_mut().value(value_node, pat_val);
+        let tail = b.pat_mut().node_empty(None);
+        b.pat_mut().wildcard(tail);
+        let list = b.pat_mut().node_empty(None);
+        b.pat_mut().list(list, value_node, tail);
+        b.pat_mut().clause_node_push(clause, list);
+        b.pat_mut().clause_bind_push(clause, tail);
+        b.pat_mut().clause_finish(clause);
+
+        let guard = b.block_insert();
+        let guard_cont = b.block_arg_insert(guard);
+        b.block_arg_insert(guard);
+        b.op_call_flow(guard, guard_cont, &[truth]);
+        let body = b.block_insert();
+        b.block_arg_insert(body);
+        ret(b, body, args[0]);
+        let guard_val = b.value(guard);
+        let body_val = b.value(body);
+        case_b.push_clause(clause, guard_val, body_val, b);
+
+        let no_match = b.block_insert();
+        b.op_call_flow(no_match, args[1], &[args[2]]);
+        case_b.no_match = Some(b.value(no_match));
+        case_b.match_on = Some(args[2]);
+        case_b.finish(entry, b);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn case_binary_map() {
+    let fun = build(2, |b, entry, args| {
+        let truth = b.value(true);
+        let empty = b.cons_mut().from(Vec::<u8>::new());
+        let eight = b.value(8);
+
+        let mut case_b = b.op_case_build(SourceSpan::UNKNOWN);
+
+        // binary integer(unsigned,big,8) value 8
+        //     (bind0@_ | binary bytes(8) bind0 (bind1@_ | binary[]))
+        let clause = b.pat_mut().clause_start(SourceSpan::UNKNOWN);
+        let size_val = b.pat_mut().clause_value(clause);
+        case_b.push_value(eight, b);
+        let size_node = b.pat_mut().node_empty(None);
+        b.pat_mut().wildcard(size_node);
+        let data_size = b.pat_mut().clause_node_value(clause, size_node);
+        let data = b.pat_mut().node_empty(None);
+        b.pat_mut().wildcard(data);
+        let tail = b.pat_mut().node_empty(None);
+        b.pat_mut().constant(tail, empty);
+        let inner = b.pat_mut().node_empty(None);
+        b.pat_mut().binary(
+            inner,
+            BinaryEntrySpecifier::Bytes { unit: 8 },
+            data,
+            Some(data_size),
+            tail,
+        );
+        let outer = b.pat_mut().node_empty(None);
+        b.pat_mut().binary(
+            outer,
+            BinaryEntrySpecifier::default(),
+            size_node,
+            Some(size_val),
+            inner,
+        );
+        b.pat_mut().clause_node_push(clause, outer);
+        b.pat_mut().clause_bind_push(clause, size_node);
+        b.pat_mut().clause_bind_push(clause, data);
+        b.pat_mut().clause_finish(clause);
+
+        let guard = b.block_insert();
+        let guard_cont = b.block_arg_insert(guard);
+        b.block_arg_insert(guard);
+        b.block_arg_insert(guard);
+        b.op_call_flow(guard, guard_cont, &[truth]);
+        let body = b.block_insert();
+        b.block_arg_insert(body);
+        b.block_arg_insert(body);
+        ret(b, body, args[0]);
+        let guard_val = b.value(guard);
+        let body_val = b.value(body);
+        case_b.push_clause(clause, guard_val, body_val, b);
+
+        // %{value %arg => bind0@_}
+        let clause = b.pat_mut().clause_start(SourceSpan::UNKNOWN);
+        let key = b.pat_mut().clause_value(clause);
+        case_b.push_value(args[3], b);
+        let value = b.pat_mut().node_empty(None);
+        b.pat_mut().wildcard(value);
+        let map = b.pat_mut().node_empty(None);
+        b.pat_mut().map(map);
+        b.pat_mut().map_push(map, key, value);
+        b.pat_mut().node_finish(map);
+        b.pat_mut().clause_node_push(clause, map);
+        b.pat_mut().clause_bind_push(clause, value);
+        b.pat_mut().clause_finish(clause);
+
+        let guard = b.block_insert();
+        let guard_cont = b.block_arg_insert(guard);
+        b.block_arg_insert(guard);
+        b.op_call_flow(guard, guard_cont, &[truth]);
+        let body = b.block_insert();
+        b.block_arg_insert(body);
+        ret(b, body, args[0]);
+        let guard_val = b.value(guard);
+        let body_val = b.value(body);
+        case_b.push_clause(clause, guard_val, body_val, b);
+
+        let no_match = b.block_insert();
+        b.op_call_flow(no_match, args[1], &[args[2]]);
+        case_b.no_match = Some(b.value(no_match));
+        case_b.match_on = Some(args[2]);
+        case_b.finish(entry, b);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn binops() {
+    let fun = build(2, |b, entry, args| {
+        let ops = [
+            BinOp::Equal,
+            BinOp::NotEqual,
+            BinOp::LessEqual,
+            BinOp::Less,
+            BinOp::GreaterEqual,
+            BinOp::Greater,
+            BinOp::ExactEqual,
+            BinOp::ExactNotEqual,
+        ];
+        let results: Vec<_> = ops
+            .iter()
+            .map(|op| b.prim_binop(SourceSpan::UNKNOWN, *op, args[2], args[3]))
+            .collect();
+        let tuple = b.prim_tuple(SourceSpan::UNKNOWN, &results);
+        b.op_call_flow(entry, args[0], &[tuple]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn logic_ops() {
+    let fun = build(2, |b, entry, args| {
+        let lt = b.prim_binop(SourceSpan::UNKNOWN, BinOp::Less, args[2], args[3]);
+        let gt = b.prim_binop(SourceSpan::UNKNOWN, BinOp::Greater, args[2], args[3]);
+        let and = b.prim_logic_op(SourceSpan::UNKNOWN, LogicOp::And, &[lt, gt]);
+        let or = b.prim_logic_op(SourceSpan::UNKNOWN, LogicOp::Or, &[lt, gt]);
+        let eq = b.prim_logic_op(SourceSpan::UNKNOWN, LogicOp::Eq, &[and, or]);
+        b.op_call_flow(entry, args[0], &[eq]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn map() {
+    let fun = build(2, |b, entry, args| {
+        let key = b.value(Symbol::intern("key"));
+        let one = b.value(1);
+        let map = b.prim_map(SourceSpan::UNKNOWN, &[key, args[2]], &[args[3], one]);
+        b.op_call_flow(entry, args[0], &[map]);
+    });
+    assert_round_trip(&fun);
+}
+
+#[test]
+fn type_checks() {
+    let fun = build(1, |b, entry, args| {
+        let types = [
+            BasicType::List,
+            BasicType::ListCell,
+            BasicType::Nil,
+            BasicType::Tuple(3),
+            BasicType::Map,
+            BasicType::Number,
+            BasicType::Float,
+            BasicType::Integer,
+            BasicType::SmallInteger,
+            BasicType::BigInteger,
+        ];
+        let mut results: Vec<_> = types
+            .iter()
+            .map(|typ| b.prim_is_type(SourceSpan::UNKNOWN, *typ, args[2]))
+            .collect();
+        results.push(b.prim_type_tag(SourceSpan::UNKNOWN, args[2]));
+        let tuple = b.prim_tuple(SourceSpan::UNKNOWN, &results);
+        b.op_call_flow(entry, args[0], &[tuple]);
+    });
+    assert_round_trip(&fun);
+}
@@ -5,4 +5,4 @@ mod printer;
 pub use printer::{FormatOpCtx, OpPrinter};
 
 mod parser;
-pub use parser::OpParser;
+pub use parser::{parens_values, DynArg, OpParser};
@@ -1 +1,42 @@
-pub trait OpParser: Send + Sync {}
+use crate::{BinaryEntrySpecifier, Block, FunctionBuilder, Value};
+
+/// An argument to a dynamic operation, as written in the textual IR.
+#[derive(Debug, Clone)]
+pub enum DynArg {
+    Value(Value),
+    BinarySpecifier(BinaryEntrySpecifier),
+    Parens(Vec<DynArg>),
+}
+
+impl DynArg {
+    pub fn value(&self) -> Option<Value> {
+        match self {
+            DynArg::Value(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// If this is a parenthesized list of plain values, returns them.
+    pub fn values(&self) -> Option<Vec<Value>> {
+        match self {
+            DynArg::Parens(args) => args.iter().map(|arg| arg.value()).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the values of an argument list consisting of a single
+/// parenthesized list of values, `@op(a, b, c)`.
+pub fn parens_values(args: &[DynArg]) -> Option<Vec<Value>> {
+    match args {
+        [arg] => arg.values(),
+        _ => None,
+    }
+}
+
+pub trait OpParser: Send + Sync {
+    /// Builds the operation into `block` from the arguments given to it in
+    /// the textual IR. On failure, returns a description of what was
+    /// expected.
+    fn parse(&self, b: &mut FunctionBuilder, block: Block, args: &[DynArg]) -> Result<(), String>;
+}
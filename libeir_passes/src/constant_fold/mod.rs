@@ -0,0 +1,296 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use libeir_intern::Symbol;
+use libeir_ir::FunctionBuilder;
+use libeir_ir::{AtomTerm, AtomicTerm, BigIntTerm, CallKind, ConstKind, IntTerm, Integer};
+use libeir_ir::{Block, Const, Function, OpKind, PrimOpKind, Value};
+
+use super::FunctionPass;
+
+#[cfg(test)]
+mod tests;
+
+/// Folds calls to pure `erlang` builtins whose arguments are all
+/// constants.
+///
+/// Arithmetic, comparisons and boolean operators on numbers and atoms
+/// are folded. The call is replaced with a jump to the return
+/// continuation with the result. Calls that would raise, like a
+/// division by zero, are left untouched.
+///
+/// Results are followed into the return continuation when it is only
+/// reached from the folded call, so nested expressions fold completely.
+///
+/// The pass is not part of `PassManager::default`, it has to be pushed
+/// explicitly.
+pub struct ConstantFoldPass {
+    /// Block arguments that are only ever passed a single constant.
+    known: HashMap<Value, Const>,
+}
+
+impl ConstantFoldPass {
+    pub fn new() -> Self {
+        ConstantFoldPass {
+            known: HashMap::new(),
+        }
+    }
+}
+
+impl FunctionPass for ConstantFoldPass {
+    fn name(&self) -> &str {
+        "constant_fold"
+    }
+    fn run_function_pass(&mut self, b: &mut FunctionBuilder) {
+        self.known.clear();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            let blocks: Vec<Block> = b.fun().block_graph().dfs_iter().collect();
+            for block in blocks {
+                changed |= self.visit_block(b, block);
+            }
+        }
+    }
+}
+
+impl ConstantFoldPass {
+    /// Returns true if the block was folded, or a new argument became
+    /// known.
+    fn visit_block(&mut self, b: &mut FunctionBuilder, block: Block) -> bool {
+        match b.fun().block_kind(block) {
+            Some(OpKind::Call(CallKind::ControlFlow)) => {
+                let reads = b.fun().block_reads(block);
+                self.propagate(b.fun(), reads[0], &reads[1..])
+            }
+            Some(OpKind::Call(CallKind::Function)) => {
+                let result = match self.fold_call(b, block) {
+                    Some(result) => result,
+                    None => return false,
+                };
+                let ret = b.fun().block_reads(block)[1];
+
+                let result_val = b.value(result);
+                b.block_clear(block);
+                b.op_call_flow(block, ret, &[result_val]);
+
+                self.propagate(b.fun(), ret, &[result_val]);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records the arguments of `target` that are passed constants, when
+    /// `target` is a block only jumped to from one place.
+    fn propagate(&mut self, fun: &Function, target: Value, args: &[Value]) -> bool {
+        let target_block = match fun.value_block(target) {
+            Some(block) if block != fun.block_entry() && fun.value_usage_count(target) == 1 => {
+                block
+            }
+            _ => return false,
+        };
+
+        let mut changed = false;
+        for (arg, val) in fun.block_args(target_block).iter().zip(args.iter()) {
+            if self.known.contains_key(arg) {
+                continue;
+            }
+            if let Some(constant) = self.resolve(fun, *val) {
+                self.known.insert(*arg, constant);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn resolve(&self, fun: &Function, value: Value) -> Option<Const> {
+        fun.value_const(value)
+            .or_else(|| self.known.get(&value).cloned())
+    }
+
+    /// Computes the result of the call made by `block`, if it is a pure
+    /// builtin with constant arguments.
+    fn fold_call(&self, b: &mut FunctionBuilder, block: Block) -> Option<Const> {
+        let result = {
+            let fun = b.fun();
+            let reads = fun.block_reads(block);
+
+            let callee = fun.value_primop(reads[0])?;
+            if *fun.primop_kind(callee) != PrimOpKind::CaptureFunction {
+                return None;
+            }
+            let mfa = fun.primop_reads(callee);
+            let module = atom(fun, fun.value_const(mfa[0])?)?;
+            let name = atom(fun, fun.value_const(mfa[1])?)?;
+            if module != Symbol::intern("erlang") {
+                return None;
+            }
+
+            let mut args = Vec::with_capacity(reads.len() - 3);
+            for read in reads[3..].iter() {
+                match fun.cons().const_kind(self.resolve(fun, *read)?) {
+                    ConstKind::Atomic(term) => args.push(term),
+                    _ => return None,
+                }
+            }
+            match fun.cons().const_kind(fun.value_const(mfa[2])?) {
+                ConstKind::Atomic(AtomicTerm::Int(IntTerm(arity)))
+                    if *arity as usize == args.len() => {}
+                _ => return None,
+            }
+
+            fold_builtin(&name.as_str(), &args)?
+        };
+        Some(b.cons_mut().from(result))
+    }
+}
+
+fn atom(fun: &Function, constant: Const) -> Option<Symbol> {
+    match fun.cons().const_kind(constant) {
+        ConstKind::Atomic(AtomicTerm::Atom(AtomTerm(sym))) => Some(*sym),
+        _ => None,
+    }
+}
+
+enum Number {
+    Int(Integer),
+    Float(f64),
+}
+
+fn number(term: &AtomicTerm) -> Option<Number> {
+    match term {
+        AtomicTerm::Int(IntTerm(int)) => Some(Number::Int(Integer::Small(*int))),
+        AtomicTerm::BigInt(BigIntTerm(int)) => Some(Number::Int(Integer::Big(int.clone()))),
+        AtomicTerm::Float(float) => Some(Number::Float(float.value())),
+        _ => None,
+    }
+}
+
+fn boolean(term: &AtomicTerm) -> Option<bool> {
+    match term {
+        AtomicTerm::Atom(AtomTerm(sym)) if *sym == Symbol::intern("true") => Some(true),
+        AtomicTerm::Atom(AtomTerm(sym)) if *sym == Symbol::intern("false") => Some(false),
+        _ => None,
+    }
+}
+
+fn booleans(lhs: &AtomicTerm, rhs: &AtomicTerm) -> Option<(bool, bool)> {
+    Some((boolean(lhs)?, boolean(rhs)?))
+}
+
+fn float(float: f64) -> Option<AtomicTerm> {
+    // Overflowing float arithmetic raises badarith
+    if float.is_finite() {
+        Some(float.into())
+    } else {
+        None
+    }
+}
+
+fn to_float(num: &Number) -> f64 {
+    match num {
+        Number::Int(int) => int.to_float(),
+        Number::Float(float) => *float,
+    }
+}
+
+fn is_zero(num: &Number) -> bool {
+    match num {
+        Number::Int(int) => *int == 0,
+        Number::Float(float) => *float == 0.0,
+    }
+}
+
+fn arith(
+    lhs: Number,
+    rhs: Number,
+    int_op: fn(Integer, &Integer) -> Integer,
+    float_op: fn(f64, f64) -> f64,
+) -> Option<AtomicTerm> {
+    match (lhs, rhs) {
+        (Number::Int(l), Number::Int(r)) => Some(int_op(l, &r).into()),
+        (l, r) => float(float_op(to_float(&l), to_float(&r))),
+    }
+}
+
+/// Compares two terms in the standard term order. Only numbers and
+/// atoms are handled.
+fn compare(lhs: &AtomicTerm, rhs: &AtomicTerm) -> Option<Ordering> {
+    match (number(lhs), number(rhs)) {
+        (Some(Number::Int(l)), Some(Number::Int(r))) => Some(l.cmp(&r)),
+        (Some(Number::Int(l)), Some(Number::Float(r))) => l.partial_cmp(&r),
+        (Some(Number::Float(l)), Some(Number::Int(r))) => l.partial_cmp(&r),
+        (Some(Number::Float(l)), Some(Number::Float(r))) => l.partial_cmp(&r),
+        (Some(_), None) => match rhs {
+            AtomicTerm::Atom(_) => Some(Ordering::Less),
+            _ => None,
+        },
+        (None, Some(_)) => match lhs {
+            AtomicTerm::Atom(_) => Some(Ordering::Greater),
+            _ => None,
+        },
+        (None, None) => match (lhs, rhs) {
+            (AtomicTerm::Atom(AtomTerm(l)), AtomicTerm::Atom(AtomTerm(r))) => {
+                Some((*l.as_str()).cmp(&*r.as_str()))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Like `compare`, but integers and floats are never equal.
+fn exact_eq(lhs: &AtomicTerm, rhs: &AtomicTerm) -> Option<bool> {
+    match (number(lhs), number(rhs)) {
+        (Some(Number::Int(_)), Some(Number::Float(_)))
+        | (Some(Number::Float(_)), Some(Number::Int(_))) => Some(false),
+        _ => compare(lhs, rhs).map(|ord| ord == Ordering::Equal),
+    }
+}
+
+/// Evaluates `erlang:name/arity`. Returns `None` if the function is not
+/// a pure builtin, or if it would raise with these arguments.
+fn fold_builtin(name: &str, args: &[&AtomicTerm]) -> Option<AtomicTerm> {
+    match (name, args) {
+        ("+", [arg]) => number(arg).map(|_| (*arg).clone()),
+        ("-", [arg]) => match number(arg)? {
+            Number::Int(int) => Some((-int).into()),
+            Number::Float(float) => Some((-float).into()),
+        },
+        ("+", [lhs, rhs]) => arith(number(lhs)?, number(rhs)?, |l, r| l + r, |l, r| l + r),
+        ("-", [lhs, rhs]) => arith(number(lhs)?, number(rhs)?, |l, r| l - r, |l, r| l - r),
+        ("*", [lhs, rhs]) => arith(number(lhs)?, number(rhs)?, |l, r| l * r, |l, r| l * r),
+        ("/", [lhs, rhs]) => {
+            let (lhs, rhs) = (number(lhs)?, number(rhs)?);
+            if is_zero(&rhs) {
+                return None;
+            }
+            float(to_float(&lhs) / to_float(&rhs))
+        }
+        ("div", [lhs, rhs]) | ("rem", [lhs, rhs]) => match (number(lhs)?, number(rhs)?) {
+            (Number::Int(_), Number::Int(ref r)) if *r == 0 => None,
+            (Number::Int(l), Number::Int(r)) if name == "div" => Some((l / &r).into()),
+            (Number::Int(l), Number::Int(r)) => Some((l % &r).into()),
+            _ => None,
+        },
+
+        ("==", [lhs, rhs]) => compare(lhs, rhs).map(|ord| (ord == Ordering::Equal).into()),
+        ("/=", [lhs, rhs]) => compare(lhs, rhs).map(|ord| (ord != Ordering::Equal).into()),
+        ("=:=", [lhs, rhs]) => exact_eq(lhs, rhs).map(|eq| eq.into()),
+        ("=/=", [lhs, rhs]) => exact_eq(lhs, rhs).map(|eq| (!eq).into()),
+        ("<", [lhs, rhs]) => compare(lhs, rhs).map(|ord| (ord == Ordering::Less).into()),
+        ("=<", [lhs, rhs]) => compare(lhs, rhs).map(|ord| (ord != Ordering::Greater).into()),
+        (">", [lhs, rhs]) => compare(lhs, rhs).map(|ord| (ord == Ordering::Greater).into()),
+        (">=", [lhs, rhs]) => compare(lhs, rhs).map(|ord| (ord != Ordering::Less).into()),
+
+        ("not", [arg]) => boolean(arg).map(|b| (!b).into()),
+        // Unlike `andalso` and `orelse`, both operands must be booleans
+        ("and", [lhs, rhs]) => booleans(lhs, rhs).map(|(l, r)| (l && r).into()),
+        ("or", [lhs, rhs]) => booleans(lhs, rhs).map(|(l, r)| (l || r).into()),
+        ("xor", [lhs, rhs]) => booleans(lhs, rhs).map(|(l, r)| (l != r).into()),
+
+        _ => None,
+    }
+}
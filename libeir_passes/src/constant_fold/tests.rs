@@ -0,0 +1,111 @@
+use libeir_ir::parse_function_unwrap;
+
+use super::ConstantFoldPass;
+use crate::FunctionPass;
+
+#[test]
+fn fold_nested_arithmetic() {
+    let _ = env_logger::try_init();
+
+    // 1 + 2 * 3
+    let mut fun = parse_function_unwrap(
+        "
+a'foo':a'bar'/0 {
+    entry(%ret, %thr):
+        %mul = a'erlang':a'*'/2;
+        %mul(2, 3) => b1 except %thr;
+    b1(%m):
+        %add = a'erlang':a'+'/2;
+        %add(1, %m) => %ret except %thr;
+}
+",
+    );
+    let mut b = fun.builder();
+
+    let mut pass = ConstantFoldPass::new();
+    pass.run_function_pass(&mut b);
+
+    let after = parse_function_unwrap(
+        "
+a'foo':a'bar'/0 {
+    entry(%ret, %thr):
+        b1(6);
+    b1(%m):
+        %ret(7);
+}
+",
+    );
+
+    assert!(b
+        .fun()
+        .graph_eq(b.fun().block_entry(), &after, after.block_entry())
+        .is_ok());
+}
+
+#[test]
+fn leave_dynamic_and_raising() {
+    let _ = env_logger::try_init();
+
+    let source = "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %x):
+        %add = a'erlang':a'+'/2;
+        %add(%x, 0) => b1 except %thr;
+    b1(%r):
+        %div = a'erlang':a'div'/2;
+        %div(%r, 0) => b2 except %thr;
+    b2(%d):
+        %fdiv = a'erlang':a'/'/2;
+        %fdiv(1, 0) => %ret except %thr;
+}
+";
+    let mut fun = parse_function_unwrap(source);
+    let mut b = fun.builder();
+
+    let mut pass = ConstantFoldPass::new();
+    pass.run_function_pass(&mut b);
+
+    let after = parse_function_unwrap(source);
+    assert!(b
+        .fun()
+        .graph_eq(b.fun().block_entry(), &after, after.block_entry())
+        .is_ok());
+}
+
+#[test]
+fn fold_comparison_and_boolean() {
+    let _ = env_logger::try_init();
+
+    let mut fun = parse_function_unwrap(
+        "
+a'foo':a'bar'/0 {
+    entry(%ret, %thr):
+        %lt = a'erlang':a'<'/2;
+        %lt(1, a'atom') => b1 except %thr;
+    b1(%c):
+        %and = a'erlang':a'and'/2;
+        %and(%c, a'false') => %ret except %thr;
+}
+",
+    );
+    let mut b = fun.builder();
+
+    let mut pass = ConstantFoldPass::new();
+    pass.run_function_pass(&mut b);
+
+    let after = parse_function_unwrap(
+        "
+a'foo':a'bar'/0 {
+    entry(%ret, %thr):
+        b1(a'true');
+    b1(%c):
+        %ret(a'false');
+}
+",
+    );
+
+    assert!(b
+        .fun()
+        .graph_eq(b.fun().block_entry(), &after, after.block_entry())
+        .is_ok());
+}
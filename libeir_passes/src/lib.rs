@@ -2,13 +2,19 @@
 
 use log::{info, trace};
 
-use libeir_ir::{FunctionBuilder, Module};
+use libeir_ir::{Function, FunctionBuilder, Module};
 
 pub mod util;
 
 mod compile_pattern;
 pub use self::compile_pattern::CompilePatternPass;
 
+mod constant_fold;
+pub use self::constant_fold::ConstantFoldPass;
+
+mod merge_identical_blocks;
+pub use self::merge_identical_blocks::MergeIdenticalBlocksPass;
+
 mod naive_inline_closures;
 pub use self::naive_inline_closures::NaiveInlineClosuresPass;
 
@@ -45,22 +51,29 @@ impl PassManager {
 
     pub fn run(&mut self, module: &mut Module) {
         for fun_def in module.function_iter_mut() {
-            let fun = fun_def.function_mut();
-            let ident = *fun.ident();
+            if fun_def.is_lowered() {
+                self.run_function(fun_def.function_mut());
+            }
+        }
+    }
 
-            let mut b = FunctionBuilder::new(fun);
-            b.fun().graph_validate_global();
-            trace!("{}", b.fun().to_text_standard());
-            for pass in self.passes.iter_mut() {
-                match pass {
-                    PassType::Function(fun_pass) => {
-                        info!("======== {} FUNCTION_PASS: {}", ident, fun_pass.name());
-                        fun_pass.run_function_pass(&mut b);
-                        trace!("{}", b.fun().to_text_standard());
-                    }
+    /// Runs the passes over a single function. Used for functions that
+    /// are lowered lazily, after `run` has already been called.
+    pub fn run_function(&mut self, fun: &mut Function) {
+        let ident = *fun.ident();
+
+        let mut b = FunctionBuilder::new(fun);
+        b.fun().graph_validate_global();
+        trace!("{}", b.fun().to_text_standard());
+        for pass in self.passes.iter_mut() {
+            match pass {
+                PassType::Function(fun_pass) => {
+                    info!("======== {} FUNCTION_PASS: {}", ident, fun_pass.name());
+                    fun_pass.run_function_pass(&mut b);
+                    trace!("{}", b.fun().to_text_standard());
                 }
-                b.fun().graph_validate_global();
             }
+            b.fun().graph_validate_global();
         }
     }
 }
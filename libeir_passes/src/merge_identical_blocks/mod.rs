@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use libeir_ir::FunctionBuilder;
+use libeir_ir::{Block, Function, OpKind, Value};
+
+use super::FunctionPass;
+
+#[cfg(test)]
+mod tests;
+
+/// Merges blocks with identical bodies, tail merging.
+///
+/// Two blocks are identical when they have the same number of arguments,
+/// the same operation and the same reads, where a read of an argument of
+/// the block matches a read of the argument at the same position in the
+/// other. Every jump to a duplicate is redirected to the first such block
+/// in depth first order, the duplicate is left empty and unreachable.
+/// Redirecting can make predecessors identical, so this is repeated until
+/// nothing changes.
+///
+/// Blocks with a `Case` or dynamic operation are never merged.
+pub struct MergeIdenticalBlocksPass {
+    buckets: HashMap<BlockKey, Vec<Block>>,
+    merges: Vec<(Block, Block)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ReadKey {
+    Arg(usize),
+    Value(Value),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BlockKey {
+    num_args: usize,
+    reads: Vec<ReadKey>,
+}
+
+impl MergeIdenticalBlocksPass {
+    pub fn new() -> Self {
+        MergeIdenticalBlocksPass {
+            buckets: HashMap::new(),
+            merges: Vec::new(),
+        }
+    }
+}
+
+impl FunctionPass for MergeIdenticalBlocksPass {
+    fn name(&self) -> &str {
+        "merge_identical_blocks"
+    }
+    fn run_function_pass(&mut self, b: &mut FunctionBuilder) {
+        while self.find_merges(b.fun()) {
+            self.merge_blocks(b);
+        }
+    }
+}
+
+impl MergeIdenticalBlocksPass {
+    /// Fills `merges` with (duplicate, kept) pairs. Returns true if there
+    /// was anything to merge.
+    fn find_merges(&mut self, fun: &Function) -> bool {
+        self.buckets.clear();
+        self.merges.clear();
+
+        let entry = fun.block_entry();
+        let blocks: Vec<Block> = fun.block_graph().dfs_iter().collect();
+        for block in blocks {
+            if block == entry {
+                continue;
+            }
+            match fun.block_kind(block) {
+                None | Some(OpKind::Case { .. }) | Some(OpKind::Dyn(_)) => continue,
+                Some(_) => (),
+            }
+
+            let args = fun.block_args(block);
+            let reads = fun
+                .block_reads(block)
+                .iter()
+                .map(|read| match args.iter().position(|arg| arg == read) {
+                    Some(idx) => ReadKey::Arg(idx),
+                    None => ReadKey::Value(*read),
+                })
+                .collect();
+            let key = BlockKey {
+                num_args: args.len(),
+                reads,
+            };
+
+            let bucket = self.buckets.entry(key).or_insert_with(Vec::new);
+            let kind = fun.block_kind(block).unwrap();
+            match bucket
+                .iter()
+                .find(|other| ops_equal(kind, fun.block_kind(**other).unwrap()))
+            {
+                Some(keep) => self.merges.push((block, *keep)),
+                None => bucket.push(block),
+            }
+        }
+
+        !self.merges.is_empty()
+    }
+
+    fn merge_blocks(&mut self, b: &mut FunctionBuilder) {
+        for (dup, keep) in self.merges.iter().cloned() {
+            let dup_val = b.fun().block_value(dup);
+            let keep_val = b.fun().block_value(keep);
+
+            let usages: Vec<Block> = b.fun().value_usages(dup_val).iter().collect();
+            for usage in usages {
+                if usage == dup {
+                    continue;
+                }
+                b.block_copy_body_map(usage, usage, |val| {
+                    if val == dup_val {
+                        Some(keep_val)
+                    } else {
+                        None
+                    }
+                });
+            }
+
+            b.block_clear(dup);
+        }
+        b.recompute_cfg_edges();
+    }
+}
+
+fn ops_equal(a: &OpKind, b: &OpKind) -> bool {
+    match (a, b) {
+        (OpKind::Call(a), OpKind::Call(b)) => a == b,
+        (OpKind::IfBool, OpKind::IfBool) => true,
+        (OpKind::TraceCaptureRaw, OpKind::TraceCaptureRaw) => true,
+        (OpKind::TraceConstruct, OpKind::TraceConstruct) => true,
+        (OpKind::MapPut { action: a }, OpKind::MapPut { action: b }) => a == b,
+        (OpKind::UnpackValueList(a), OpKind::UnpackValueList(b)) => a == b,
+        (OpKind::Match { branches: a }, OpKind::Match { branches: b }) => a == b,
+        (OpKind::Unreachable, OpKind::Unreachable) => true,
+        _ => false,
+    }
+}
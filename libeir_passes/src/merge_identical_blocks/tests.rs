@@ -0,0 +1,105 @@
+use libeir_ir::parse_function_unwrap;
+
+use super::MergeIdenticalBlocksPass;
+use crate::FunctionPass;
+
+#[test]
+fn merge_identical_raises() {
+    let _ = env_logger::try_init();
+
+    let mut fun = parse_function_unwrap(
+        "
+a'foo':a'bar'/2 {
+    entry(%ret, %thr, %a, %b):
+        if_bool %a b1 b2;
+    b1():
+        if_bool %b b3 b4;
+    b2():
+        %thr(a'error', a'badarg', a'trace');
+    b3():
+        %ret(a'ok');
+    b4():
+        %thr(a'error', a'badarg', a'trace');
+}
+",
+    );
+    let mut b = fun.builder();
+
+    let mut pass = MergeIdenticalBlocksPass::new();
+    pass.run_function_pass(&mut b);
+
+    let after = parse_function_unwrap(
+        "
+a'foo':a'bar'/2 {
+    entry(%ret, %thr, %a, %b):
+        if_bool %a b1 b2;
+    b1():
+        if_bool %b b3 b2;
+    b2():
+        %thr(a'error', a'badarg', a'trace');
+    b3():
+        %ret(a'ok');
+}
+",
+    );
+
+    assert!(b.fun().block_graph().dfs_iter().count() == 4);
+    assert!(b
+        .fun()
+        .graph_eq(b.fun().block_entry(), &after, after.block_entry())
+        .is_ok());
+}
+
+#[test]
+fn merge_matching_arguments() {
+    let _ = env_logger::try_init();
+
+    let mut fun = parse_function_unwrap(
+        "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a b1 b2 b3;
+    b1():
+        b4(a'true');
+    b2():
+        b5(a'false');
+    b3():
+        b6(a'other');
+    b4(%x):
+        %ret(%x);
+    b5(%y):
+        %ret(%y);
+    b6(%z):
+        %ret(%a);
+}
+",
+    );
+    let mut b = fun.builder();
+
+    let mut pass = MergeIdenticalBlocksPass::new();
+    pass.run_function_pass(&mut b);
+
+    let after = parse_function_unwrap(
+        "
+a'foo':a'bar'/1 {
+    entry(%ret, %thr, %a):
+        if_bool %a b1 b2 b3;
+    b1():
+        b4(a'true');
+    b2():
+        b4(a'false');
+    b3():
+        b6(a'other');
+    b4(%x):
+        %ret(%x);
+    b6(%z):
+        %ret(%a);
+}
+",
+    );
+
+    assert!(b
+        .fun()
+        .graph_eq(b.fun().block_entry(), &after, after.block_entry())
+        .is_ok());
+}
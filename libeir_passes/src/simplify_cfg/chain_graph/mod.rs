@@ -800,7 +800,7 @@ impl ChainGraph {
                             let s_group = phi_groups[group].clone();
 
                             // Union into `other_group`
-                            phi_groups[other_group].union(&s_group, &mut node_pool);
+                            phi_groups[other_group].union_with(&s_group, &mut node_pool);
 
                             // Update back mappings for all entries in `group`
                             for s_node in s_group.iter(&node_pool) {
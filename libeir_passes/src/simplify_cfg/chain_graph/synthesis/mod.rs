@@ -168,7 +168,7 @@ impl Synthesis {
             }
 
             for pred in back[*segment_id].iter(&pool) {
-                chains.union(&self.segments[pred].chains, &mut self.chain_set_pool);
+                chains.union_with(&self.segments[pred].chains, &mut self.chain_set_pool);
             }
 
             self.segments[*segment_id].chains = chains;
@@ -258,7 +258,7 @@ where
                 if self.read() == '\\' {
                     return match self.lex_escape_sequence() {
                         Ok(Token::Char(c)) => Token::Char(c),
-                        Ok(Token::Integer(i)) => match std::char::from_u32(i.to_u32().unwrap()) {
+                        Ok(Token::Integer(i)) => match i.to_u32().and_then(std::char::from_u32) {
                             Some(c) => Token::Char(c),
                             None => Token::Error(LexicalError::InvalidEscape {
                                 span: self.span(),
@@ -454,7 +454,8 @@ where
             'r' => Ok(Token::Char('\r')),
             't' => Ok(Token::Char('\t')),
             'b' => Ok(Token::Char('\x08')),
-            // TODO: Figure out why Erlang lexes this as an escape: 'd' => Some('\d'),
+            // Delete
+            'd' => Ok(Token::Char('\x7F')),
             'e' => Ok(Token::Char('\x1B')),
             'f' => Ok(Token::Char('\x0C')),
             's' => Ok(Token::Char(' ')),
@@ -462,19 +463,34 @@ where
             '\'' => Ok(Token::Char('\'')),
             '"' => Ok(Token::Char('"')),
             '\\' => Ok(Token::Char('\\')),
-            // Possible octal escape
+            // Possible octal escape, up to three digits including the 0
             '0' => {
-                c = self.read();
-                if c.is_digit(8) {
-                    let mut num = String::new();
-                    while self.read().is_digit(8) {
-                        num.push(self.pop());
-                    }
-                    return Ok(to_integer_literal(&num, 8));
+                let mut num = "0".to_string();
+                while num.len() < 3 && self.read().is_digit(8) {
+                    num.push(self.pop());
+                }
+                if num.len() > 1 {
+                    Ok(to_integer_literal(&num, 8))
                 } else {
                     Ok(Token::Char('\0'))
                 }
             }
+            // Octal escape, up to three digits
+            first @ '1'..='7' => {
+                let mut num = first.to_string();
+                while num.len() < 3 && self.read().is_digit(8) {
+                    num.push(self.pop());
+                }
+                Ok(to_integer_literal(&num, 8))
+            }
+            // Control character, \^a is 1
+            '^' => match self.pop() {
+                '\0' => Err(LexicalError::InvalidEscape {
+                    span: self.span(),
+                    reason: "invalid control escape, expected a character".to_string(),
+                }),
+                ctrl => Ok(to_integer_literal(&(ctrl as u32 & 31).to_string(), 10)),
+            },
             // Hex escape
             'x' => {
                 c = self.read();
@@ -521,10 +537,12 @@ where
                     })
                 }
             }
-            _ => Err(LexicalError::InvalidEscape {
+            '\0' => Err(LexicalError::InvalidEscape {
                 span: self.span(),
-                reason: "invalid escape, unrecognized sequence".to_string(),
+                reason: "invalid escape, unexpected end of input".to_string(),
             }),
+            // Any other character escapes to itself
+            other => Ok(Token::Char(other)),
         }
     }
 
@@ -941,6 +959,59 @@ mod test {
         );
     }
 
+    #[test]
+    fn lex_char_literal() {
+        assert_lex!("$a", vec![Ok((1, Token::Char('a'), 3))]);
+
+        // Control escapes
+        assert_lex!(r#"$\n"#, vec![Ok((1, Token::Char('\n'), 4))]);
+        assert_lex!(r#"$\s"#, vec![Ok((1, Token::Char(' '), 4))]);
+        assert_lex!(r#"$\d"#, vec![Ok((1, Token::Char('\x7F'), 4))]);
+        assert_lex!(r#"$\e"#, vec![Ok((1, Token::Char('\x1B'), 4))]);
+
+        // Octal, at most three digits
+        assert_lex!(r#"$\123"#, vec![Ok((1, Token::Char('S'), 6))]);
+        assert_lex!(r#"$\7"#, vec![Ok((1, Token::Char('\x07'), 4))]);
+        assert_lex!(
+            r#"$\1234"#,
+            vec![
+                Ok((1, Token::Char('S'), 6)),
+                Ok((6, Token::Integer(4.into()), 7))
+            ]
+        );
+        assert_lex!(r#"$\0"#, vec![Ok((1, Token::Char('\0'), 4))]);
+        assert_lex!(r#"$\07"#, vec![Ok((1, Token::Char('\x07'), 5))]);
+        assert_lex!(
+            r#"$\0123"#,
+            vec![
+                Ok((1, Token::Char('\n'), 6)),
+                Ok((6, Token::Integer(3.into()), 7))
+            ]
+        );
+
+        // Hexadecimal
+        assert_lex!(r#"$\x41"#, vec![Ok((1, Token::Char('A'), 6))]);
+        assert_lex!(r#"$\x{1F600}"#, vec![Ok((1, Token::Char('\u{1F600}'), 11))]);
+
+        // Control characters
+        assert_lex!(r#"$\^a"#, vec![Ok((1, Token::Char('\x01'), 5))]);
+        assert_lex!(r#"$\^Z"#, vec![Ok((1, Token::Char('\x1A'), 5))]);
+
+        // Any other character escapes to itself
+        assert_lex!(r#"$\z"#, vec![Ok((1, Token::Char('z'), 4))]);
+
+        assert_lex!(
+            r#"$\x{}"#,
+            vec![Err(LexicalError::InvalidEscape {
+                span: SourceSpan::new(
+                    SourceIndex::new(SourceId::UNKNOWN, ByteIndex(1)),
+                    SourceIndex::new(SourceId::UNKNOWN, ByteIndex(6))
+                ),
+                reason: "invalid hex escape, must be at least one digit".to_string(),
+            })]
+        );
+    }
+
     #[test]
     fn lex_string() {
         assert_lex!(
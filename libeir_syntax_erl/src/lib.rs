@@ -9,7 +9,9 @@ mod preprocessor;
 
 pub use self::abstr::lower as lower_abstr;
 pub use self::lexer::*;
-pub use self::lower::{lower_module, LowerError};
+pub use self::lower::{
+    lower_module, lower_module_lazy, lower_module_with_limits, LazyLowerer, LowerError,
+};
 pub use self::parser::*;
 pub use self::preprocessor::*;
 
@@ -1,4 +1,5 @@
 use libeir_diagnostics::{Diagnostic, Label, SourceSpan, ToDiagnostic};
+use libeir_ir::AllocationError;
 
 use super::expr::BinaryTypeName;
 
@@ -71,6 +72,13 @@ pub enum LowerError {
     DuplicateRecordField { new: SourceSpan, old: SourceSpan },
     #[snafu(display("record is not defined"))]
     UndefinedRecord { span: SourceSpan },
+
+    /// The function went over the allocation limits set for lowering.
+    #[snafu(display("function is too large to lower: {}", source))]
+    AllocationLimit {
+        span: SourceSpan,
+        source: AllocationError,
+    },
 }
 
 impl ToDiagnostic for LowerError {
@@ -130,6 +138,11 @@ impl ToDiagnostic for LowerError {
                     Label::secondary(old.source_id(), *old).with_message("previously bound here"),
                 ])
             }
+            LowerError::AllocationLimit { span, .. } => Diagnostic::error()
+                .with_message(msg)
+                .with_labels(vec![
+                    Label::primary(span.source_id(), *span).with_message("in this function")
+                ]),
             _ => unimplemented!(),
         }
     }
@@ -32,7 +32,11 @@ pub(super) fn lower_literal(
                 b.value(NilTerm)
             }
         },
-        Literal::Char(_id, _span, c) => b.value(*c),
+        Literal::Char(_id, _span, c) => {
+            let cons = b.cons_mut().from(*c);
+            b.cons_mut().set_char_hint(cons);
+            b.value(cons)
+        }
     };
     (block, value)
 }
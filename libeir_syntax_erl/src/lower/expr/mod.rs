@@ -1,9 +1,10 @@
 use libeir_ir::constant::NilTerm;
 use libeir_ir::{Block as IrBlock, FunctionBuilder, Value as IrValue};
 
+use libeir_diagnostics::SourceSpan;
 use libeir_intern::{Ident, Symbol};
 
-use super::lower_function;
+use super::{limit_exceeded, lower_function};
 
 use super::pattern::lower_clause;
 use super::LowerCtx;
@@ -41,6 +42,9 @@ where
     let mut value = None;
 
     for expr in exprs {
+        if limit_exceeded(b) {
+            break;
+        }
         assert!(b.fun().block_kind(block).is_none());
         let (new_block, val) = lower_expr(ctx, b, block, expr);
         assert!(b.fun().block_kind(new_block).is_none());
@@ -52,7 +56,7 @@ where
     // the expressions in the block.
     ctx.scope.pop(scope_tok);
 
-    (block, value.unwrap())
+    (block, value.unwrap_or_else(|| ctx.sentinel()))
 }
 
 pub(super) fn lower_block_same_scope<'a, T>(
@@ -68,6 +72,9 @@ where
     let mut value = None;
 
     for expr in exprs {
+        if limit_exceeded(b) {
+            break;
+        }
         assert!(b.fun().block_kind(block).is_none());
         let (new_block, val) = lower_expr(ctx, b, block, expr);
         assert!(b.fun().block_kind(new_block).is_none());
@@ -75,7 +82,7 @@ where
         value = Some(val);
     }
 
-    (block, value.unwrap())
+    (block, value.unwrap_or_else(|| ctx.sentinel()))
 }
 
 pub(super) fn lower_single(
@@ -120,6 +127,9 @@ fn lower_expr(
 
             let arity_val = b.value(args.len());
 
+            // Remote calls where the module or function is only known at
+            // runtime go through `erlang:apply/3`.
+            let mut dynamic_remote = None;
             let callee_val = match &**callee {
                 Expr::Remote(Remote {
                     module, function, ..
@@ -127,7 +137,15 @@ fn lower_expr(
                     let mod_val = map_block!(block, lower_single(ctx, b, block, module));
                     let fun_val = map_block!(block, lower_single(ctx, b, block, function));
 
-                    b.prim_capture_function(span, mod_val, fun_val, arity_val)
+                    match (&**module, &**function) {
+                        (Expr::Literal(Literal::Atom(..)), Expr::Literal(Literal::Atom(..))) => {
+                            Some(b.prim_capture_function(span, mod_val, fun_val, arity_val))
+                        }
+                        _ => {
+                            dynamic_remote = Some((mod_val, fun_val));
+                            None
+                        }
+                    }
                 }
                 Expr::Literal(Literal::Atom(_id, name)) => {
                     let local = LocalFunctionName {
@@ -150,9 +168,12 @@ fn lower_expr(
                     let mod_val = b.value(module);
                     let fun_val = b.value(function);
 
-                    b.prim_capture_function(span, mod_val, fun_val, arity_val)
+                    Some(b.prim_capture_function(span, mod_val, fun_val, arity_val))
                 }
-                expr => map_block!(block, lower_single_same_scope(ctx, b, block, expr)),
+                expr => Some(map_block!(
+                    block,
+                    lower_single_same_scope(ctx, b, block, expr)
+                )),
             };
 
             for arg in args {
@@ -165,7 +186,22 @@ fn lower_expr(
             let loc = ctx.current_location(b, span);
             b.block_set_location(block, loc);
 
-            let (ok_block, fail_block) = b.op_call_function(span, block, callee_val, &arg_vals);
+            let (ok_block, fail_block) = match (callee_val, dynamic_remote) {
+                (Some(callee_val), None) => {
+                    let conts = b.op_call_function(span, block, callee_val, &arg_vals);
+
+                    let mut read_spans =
+                        vec![callee.span(), SourceSpan::UNKNOWN, SourceSpan::UNKNOWN];
+                    read_spans.extend(args.iter().map(|arg| arg.span()));
+                    b.block_set_read_spans(block, &read_spans);
+
+                    conts
+                }
+                (None, Some((mod_val, fun_val))) => {
+                    b.op_apply_mfa(span, block, mod_val, fun_val, &arg_vals)
+                }
+                _ => unreachable!(),
+            };
 
             let fail_type = b.block_args(fail_block)[0];
             let fail_error = b.block_args(fail_block)[1];
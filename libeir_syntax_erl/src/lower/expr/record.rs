@@ -44,10 +44,8 @@ pub(super) fn lower_record_access_expr(
 
     let record_val = map_block!(block, lower_single(ctx, b, block, &rec.record));
 
-    let mut match_builder = b.op_match_build(span);
-    let unpack_ok_block = match_builder.push_tuple(rec_def.record.fields.len() + 1, b);
-    let unpack_fail_block = match_builder.push_wildcard(span, b);
-    match_builder.finish(block, record_val, b);
+    let (unpack_ok_block, unpack_fail_block) =
+        b.op_is_tuple_of_arity(span, block, record_val, rec_def.record.fields.len() + 1);
     block = unpack_ok_block;
 
     b.op_call_flow(unpack_fail_block, fail_block, &[]);
@@ -80,10 +78,8 @@ pub(super) fn lower_record_update_expr(
     // Unpack tuple
     let record_val = map_block!(block, lower_single(ctx, b, block, &rec.record));
 
-    let mut match_builder = b.op_match_build(span);
-    let unpack_ok_block = match_builder.push_tuple(num_fields + 1, b);
-    let unpack_fail_block = match_builder.push_wildcard(span, b);
-    match_builder.finish(block, record_val, b);
+    let (unpack_ok_block, unpack_fail_block) =
+        b.op_is_tuple_of_arity(span, block, record_val, num_fields + 1);
     block = unpack_ok_block;
 
     b.op_call_flow(unpack_fail_block, fail_block, &[]);
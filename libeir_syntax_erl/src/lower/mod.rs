@@ -1,14 +1,17 @@
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
 use std::sync::Arc;
 
 use libeir_ir::{
-    Block as IrBlock, FunctionBuilder, IntoValue, Location, Module as IrModule, Value as IrValue,
+    AllocationLimits, Block as IrBlock, Function as IrFunction, FunctionBuilder, FunctionLowerer,
+    IntoValue, Location, Module as IrModule, Value as IrValue,
 };
 
 use libeir_diagnostics::{CodeMap, SourceSpan};
 use libeir_intern::{Ident, Symbol};
-use libeir_util_parse::ErrorReceiver;
+use libeir_util_parse::{ErrorReceiver, Errors};
 
-use crate::parser::ast::{Function, FunctionClause, Module, NamedFunction};
+use crate::parser::ast::{Function, FunctionClause, LocalFunctionName, Module, NamedFunction};
 
 macro_rules! map_block {
     ($block:ident, $call:expr) => {{
@@ -53,9 +56,36 @@ pub(crate) struct LowerCtx<'a> {
     /// Top is current function name.
     /// Used to generate debug info.
     functions: Vec<String>,
+
+    limits: AllocationLimits,
 }
 
 impl<'a> LowerCtx<'a> {
+    fn new(
+        codemap: Arc<CodeMap>,
+        module: &'a Module,
+        errors: &'a mut (dyn ErrorReceiver<E = LowerError, W = LowerError> + 'a),
+    ) -> Self {
+        LowerCtx {
+            codemap,
+            module,
+
+            scope: scope::ScopeTracker::new(),
+            exc_stack: ExceptionHandlerStack::new(),
+
+            sentinel_value: None,
+
+            errors,
+
+            val_buf: Vec::new(),
+
+            fun_num: 0,
+            functions: Vec::new(),
+
+            limits: AllocationLimits::default(),
+        }
+    }
+
     /// Since we want to catch as many errors as possible in a single
     /// compiler invocation, we frequently purposefully generate invalid
     /// IR so that the lowering process can continue.
@@ -160,47 +190,29 @@ pub fn lower_module<'a>(
     errors: &'a mut (dyn ErrorReceiver<E = LowerError, W = LowerError> + 'a),
     codemap: Arc<CodeMap>,
     module: &Module,
+) -> Result<IrModule, ()> {
+    lower_module_with_limits(errors, codemap, module, AllocationLimits::default())
+}
+
+/// Like `lower_module`, but a function going over `limits` fails to lower
+/// with `LowerError::AllocationLimit`. Lowering of that function stops at
+/// the next clause or expression.
+pub fn lower_module_with_limits<'a>(
+    errors: &'a mut (dyn ErrorReceiver<E = LowerError, W = LowerError> + 'a),
+    codemap: Arc<CodeMap>,
+    module: &Module,
+    limits: AllocationLimits,
 ) -> Result<IrModule, ()> {
     // TODO sort functions for more deterministic compilation
 
     let mut ir_module = IrModule::new_with_span(module.name, module.span);
 
-    let mut ctx = LowerCtx {
-        codemap,
-        module,
-
-        scope: scope::ScopeTracker::new(),
-        exc_stack: ExceptionHandlerStack::new(),
-
-        sentinel_value: None,
-
-        errors,
-
-        val_buf: Vec::new(),
-
-        fun_num: 0,
-        functions: Vec::new(),
-    };
+    let mut ctx = LowerCtx::new(codemap, module, errors);
+    ctx.limits = limits;
 
     for (ident, function) in module.functions.iter() {
-        assert!(ctx.scope.height() == 0);
-        ctx.fun_num = 0;
-
         let fun_def = ir_module.add_function(function.span, ident.function, function.arity);
-        let mut fun = fun_def.function_mut();
-        let mut builder = FunctionBuilder::new(&mut fun);
-
-        // We do not want the sentinel value to be a constant,
-        // since that would interfere with potential constant
-        // comparisons while lowering. Insert an orphaned block
-        // with an argument that we use.
-        // This has the added benefit of generating actually
-        // invalid IR when used.
-        let sentinel_block = builder.block_insert();
-        let sentinel_value = builder.block_arg_insert(sentinel_block);
-        ctx.sentinel_value = Some(sentinel_value);
-
-        lower_top_function(&mut ctx, &mut builder, function);
+        lower_named_function(&mut ctx, fun_def.function_mut(), function);
     }
 
     ctx.exc_stack.finish();
@@ -212,6 +224,130 @@ pub fn lower_module<'a>(
     }
 }
 
+/// Lowers the functions of a module one at a time, the first time each
+/// of them is needed. See `lower_module_lazy`.
+pub struct LazyLowerer {
+    codemap: Arc<CodeMap>,
+    module: Module,
+    errors: RefCell<Errors<LowerError, LowerError>>,
+    post_lower: Option<Box<dyn Fn(&mut IrFunction)>>,
+    limits: AllocationLimits,
+}
+
+impl LazyLowerer {
+    pub fn new(codemap: Arc<CodeMap>, module: Module) -> Self {
+        LazyLowerer {
+            codemap,
+            module,
+            errors: RefCell::new(Errors::new()),
+            post_lower: None,
+            limits: AllocationLimits::default(),
+        }
+    }
+
+    /// Sets a callback that is run on every function after it has been
+    /// lowered, usually to run passes over it.
+    pub fn set_post_lower<F>(&mut self, post_lower: F)
+    where
+        F: Fn(&mut IrFunction) + 'static,
+    {
+        self.post_lower = Some(Box::new(post_lower));
+    }
+
+    /// Sets the limits functions are lowered with, a function going over
+    /// them stops lowering early and fails.
+    pub fn set_allocation_limits(&mut self, limits: AllocationLimits) {
+        self.limits = limits;
+    }
+
+    /// The errors of all functions lowered so far.
+    pub fn errors(&self) -> Ref<Errors<LowerError, LowerError>> {
+        self.errors.borrow()
+    }
+}
+
+impl FunctionLowerer for LazyLowerer {
+    fn lower_function(&self, fun: &mut IrFunction) -> Result<(), ()> {
+        let ident = *fun.ident();
+        let name = LocalFunctionName {
+            span: SourceSpan::UNKNOWN,
+            function: ident.name,
+            arity: ident.arity,
+        };
+        let function = self.module.functions.get(&name).ok_or(())?;
+
+        let mut errors = Errors::new();
+        {
+            let mut ctx = LowerCtx::new(self.codemap.clone(), &self.module, &mut errors);
+            ctx.limits = self.limits;
+            lower_named_function(&mut ctx, fun, function);
+            ctx.exc_stack.finish();
+        }
+
+        let failed = errors.failed();
+        self.errors.borrow_mut().errors_from(errors);
+        if failed {
+            return Err(());
+        }
+
+        if let Some(post_lower) = &self.post_lower {
+            post_lower(fun);
+        }
+
+        Ok(())
+    }
+}
+
+/// Declares every function of the lowerer's module in a new IR module,
+/// without lowering any of them yet. A function is lowered the first time
+/// `Module::lower_function` is called for it.
+pub fn lower_module_lazy(lowerer: Rc<LazyLowerer>) -> IrModule {
+    let module = &lowerer.module;
+    let mut ir_module = IrModule::new_with_span(module.name, module.span);
+    ir_module.set_lowerer(lowerer.clone());
+
+    for (ident, function) in module.functions.iter() {
+        ir_module.add_lazy_function(function.span, ident.function, function.arity);
+    }
+
+    ir_module
+}
+
+fn lower_named_function(ctx: &mut LowerCtx, fun: &mut IrFunction, function: &NamedFunction) {
+    assert!(ctx.scope.height() == 0);
+    ctx.fun_num = 0;
+
+    let mut builder = FunctionBuilder::new(fun);
+    builder.set_allocation_limits(ctx.limits);
+
+    // We do not want the sentinel value to be a constant,
+    // since that would interfere with potential constant
+    // comparisons while lowering. Insert an orphaned block
+    // with an argument that we use.
+    // This has the added benefit of generating actually
+    // invalid IR when used.
+    let sentinel_block = builder.block_insert();
+    let sentinel_value = builder.block_arg_insert(sentinel_block);
+    ctx.sentinel_value = Some(sentinel_value);
+
+    lower_top_function(ctx, &mut builder, function);
+
+    // Lowering stops early once a limit is exceeded, the sentinel may be
+    // used in place of the remaining expressions.
+    if let Some(error) = builder.fun().allocation_error() {
+        ctx.error(LowerError::AllocationLimit {
+            span: function.span,
+            source: error.clone(),
+        });
+    }
+}
+
+/// Whether the function being built has gone over its allocation limits.
+/// No more clauses or expressions are lowered once it has.
+fn limit_exceeded(b: &FunctionBuilder) -> bool {
+    b.fun().allocation_error().is_some()
+}
+
 fn lower_function(ctx: &mut LowerCtx, b: &mut FunctionBuilder, fun: &Function) -> IrBlock {
     let entry = b.block_insert_with_span(Some(fun.span()));
 
@@ -282,6 +418,9 @@ fn lower_function_base(
         func_case.no_match = Some(b.value(match_fail_block));
 
         for clause in clauses.iter() {
+            if limit_exceeded(b) {
+                break;
+            }
             match lower_clause(
                 ctx,
                 b,
@@ -2,11 +2,11 @@ use std::collections::HashMap;
 
 use libeir_diagnostics::SourceSpan;
 use libeir_ir::pattern::{PatternClause, PatternValue};
-use libeir_ir::{Block as IrBlock, FunctionBuilder, LogicOp, Value as IrValue};
+use libeir_ir::{BinOp as IrBinOp, Block as IrBlock, FunctionBuilder, LogicOp, Value as IrValue};
 
-use crate::parser::ast::{Expr, Guard};
+use crate::parser::ast::{BinaryExpr, BinaryOp, Expr, Guard, Var};
 
-use super::{lower_block, LowerCtx, ScopeToken};
+use super::{lower_block, lower_single, LowerCtx, ScopeToken};
 
 use libeir_intern::Ident;
 
@@ -21,6 +21,31 @@ enum EqGuard {
     EqBind(usize, usize),
 }
 
+/// If `expr` is a comparison with a variable on one side, returns the
+/// variable, the comparison as seen from the variable, and the other side.
+fn var_comparison(expr: &Expr) -> Option<(Ident, IrBinOp, &Expr)> {
+    let (lhs, op, rhs) = match expr {
+        Expr::BinaryExpr(BinaryExpr { lhs, op, rhs, .. }) => (&**lhs, op, &**rhs),
+        _ => return None,
+    };
+    let op = match op {
+        BinaryOp::Equal => IrBinOp::Equal,
+        BinaryOp::NotEqual => IrBinOp::NotEqual,
+        BinaryOp::Lte => IrBinOp::LessEqual,
+        BinaryOp::Lt => IrBinOp::Less,
+        BinaryOp::Gte => IrBinOp::GreaterEqual,
+        BinaryOp::Gt => IrBinOp::Greater,
+        BinaryOp::StrictEqual => IrBinOp::ExactEqual,
+        BinaryOp::StrictNotEqual => IrBinOp::ExactNotEqual,
+        _ => return None,
+    };
+    match (lhs, rhs) {
+        (Expr::Var(Var(_, var)), other) => Some((*var, op, other)),
+        (other, Expr::Var(Var(_, var))) => Some((*var, op.swapped(), other)),
+        _ => None,
+    }
+}
+
 struct ClauseLowerCtx {
     span: SourceSpan,
 
@@ -258,18 +283,77 @@ impl ClauseLowerCtx {
         let mut and = Vec::new();
 
         // Clause guards
+        // Each guard in the sequence is evaluated separately. If one raises,
+        // only that guard fails, and the next one in the sequence is tried.
         if let Some(guard_seq) = guard {
             for guard in guard_seq {
-                for condition in guard.conditions.iter() {
-                    let (block_new, val) =
-                        lower_block(ctx, b, block, [condition].iter().map(|v| *v));
-                    and.push(val);
-                    block = block_new;
+                let body = b.block_insert();
+                let body_ret = b.block_arg_insert(body);
+                let body_thr = b.block_arg_insert(body);
+
+                ctx.exc_stack.push_handler(body_thr);
+                let mut body_block = body;
+                let mut conditions = guard.conditions.as_slice();
+                while let Some(condition) = conditions.first() {
+                    // A run of comparisons against the same variable, like
+                    // `0 < X, X < 10`, is lowered as a single chain.
+                    let run = var_comparison(condition)
+                        .map(|(var, _, _)| {
+                            conditions
+                                .iter()
+                                .take_while(|c| var_comparison(c).map(|(v, _, _)| v) == Some(var))
+                                .count()
+                        })
+                        .unwrap_or(0);
+
+                    if run >= 2 {
+                        let span = condition.span();
+                        let (var, _, _) = var_comparison(condition).unwrap();
+                        let subject = ctx.resolve(var);
+
+                        let mut comparisons = Vec::with_capacity(run);
+                        for condition in conditions[..run].iter() {
+                            let (_, op, operand) = var_comparison(condition).unwrap();
+                            let (block_new, val) = lower_single(ctx, b, body_block, operand);
+                            comparisons.push((op, val));
+                            body_block = block_new;
+                        }
+
+                        let (t, f) = b.op_compare_chain(span, body_block, subject, &comparisons);
+                        body_block = b.block_insert();
+                        let res = b.block_arg_insert(body_block);
+                        let true_val = b.value(true);
+                        b.op_call_flow(t, body_block, &[true_val]);
+                        let false_val = b.value(false);
+                        b.op_call_flow(f, body_block, &[false_val]);
+
+                        and.push(res);
+                        conditions = &conditions[run..];
+                    } else {
+                        let (block_new, val) =
+                            lower_block(ctx, b, body_block, [condition].iter().map(|v| *v));
+                        and.push(val);
+                        body_block = block_new;
+                        conditions = &conditions[1..];
+                    }
                 }
+                ctx.exc_stack.pop_handler();
 
                 let val = b.prim_logic_op(guard.span, LogicOp::And, &and);
                 and.clear();
-                or.push(val);
+                b.op_call_flow(body_block, body_ret, &[val]);
+
+                let join = b.block_insert();
+                let res = b.block_arg_insert(join);
+
+                let fail = b.block_insert();
+                let false_val = b.value(false);
+                b.op_call_flow(fail, join, &[false_val]);
+
+                b.op_guard_try(guard.span, block, body, join, fail);
+                block = join;
+
+                or.push(res);
             }
 
             let val = b.prim_logic_op(self.span, LogicOp::Or, &or);
@@ -142,7 +142,11 @@ fn pattern_to_tree_node(
         Expr::Literal(lit) => {
             let cons = match lit {
                 Literal::Atom(_id, ident) => b.cons_mut().from(*ident).into(),
-                Literal::Char(_span, _id, c) => b.cons_mut().from(*c).into(),
+                Literal::Char(_span, _id, c) => {
+                    let cons = b.cons_mut().from(*c);
+                    b.cons_mut().set_char_hint(cons);
+                    cons.into()
+                }
                 Literal::Integer(_span, _id, num) => b.cons_mut().from(num.clone()).into(),
                 Literal::Float(_span, _id, num) => b.cons_mut().from(*num).into(),
                 Literal::Binary(_id, ident) => {
@@ -336,7 +340,6 @@ fn pattern_to_tree_node(
                         });
                         None
                     } else {
-                        println!("SIZE EXPR {:?}", size_expr);
                         let ret = match size_expr {
                             Expr::Var(Var(_id, var)) => Either::Left(*var),
                             _ => {
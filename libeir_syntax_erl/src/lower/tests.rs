@@ -3,12 +3,17 @@ use std::sync::Arc;
 use crate::ast::*;
 use crate::*;
 
-use crate::lower::lower_module;
+use crate::lower::{
+    lower_module, lower_module_with_limits, lower_named_function, LowerCtx, LowerError,
+};
 use crate::parser::ParseConfig;
 
 use libeir_diagnostics::CodeMap;
-use libeir_ir::{Module as IrModule, StandardFormatConfig};
-use libeir_util_parse::Errors;
+use libeir_ir::{
+    AllocationLimits, Block as IrBlock, CallKind, Function as IrFunction, Module as IrModule,
+    OpKind, StandardFormatConfig,
+};
+use libeir_util_parse::{ErrorOrWarning, Errors};
 
 fn parse<T, S>(input: S, config: ParseConfig, codemap: Arc<CodeMap>) -> T
 where
@@ -62,6 +67,130 @@ pat(A, A) -> 1.
     println!("{}", fun.to_text(&mut StandardFormatConfig::default()));
 }
 
+/// Function call blocks in `fun` calling the local function `name`.
+fn calls_to(fun: &IrFunction, name: &str) -> Vec<IrBlock> {
+    let name = fun.cons().get(Symbol::intern(name));
+    fun.block_iter()
+        .filter(|block| match fun.block_kind(*block) {
+            Some(OpKind::Call(CallKind::Function)) => {
+                let callee = fun.block_reads(*block)[0];
+                fun.value_primop(callee)
+                    .map(|prim| fun.value_const(fun.primop_reads(prim)[1]) == name)
+                    .unwrap_or(false)
+            }
+            _ => false,
+        })
+        .collect()
+}
+
+#[test]
+fn tail_position_calls() {
+    let module = lower(
+        "-module(tail).
+
+g() -> 1.
+f() -> g().
+h() -> g() + 1.
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    let f = &module[module.name_arity_index(Symbol::intern("f"), 0).unwrap()];
+    let calls = calls_to(f.function(), "g");
+    assert!(calls.len() == 1);
+    assert!(f.function().block_is_tail_position(calls[0]));
+
+    let h = &module[module.name_arity_index(Symbol::intern("h"), 0).unwrap()];
+    let calls = calls_to(h.function(), "g");
+    assert!(calls.len() == 1);
+    assert!(!h.function().block_is_tail_position(calls[0]));
+}
+
+#[test]
+fn allocation_limit_lower() {
+    let codemap = Arc::new(CodeMap::new());
+    let parsed: Module = parse(
+        "-module(limit).
+
+large(X) ->
+    case X of
+        1 -> {a, X};
+        2 -> {b, X};
+        3 -> {c, X};
+        _ -> [X, X, X]
+    end.
+",
+        ParseConfig::default(),
+        codemap.clone(),
+    );
+
+    let limits = AllocationLimits {
+        max_blocks: Some(10),
+        max_values: None,
+    };
+
+    let mut errors = Errors::new();
+    assert!(lower_module_with_limits(&mut errors, codemap.clone(), &parsed, limits).is_err());
+    let reported = errors
+        .errors
+        .iter()
+        .filter(|e| match e {
+            ErrorOrWarning::Error(LowerError::AllocationLimit { .. }) => true,
+            _ => false,
+        })
+        .count();
+    assert!(reported == 1);
+
+    let mut errors = Errors::new();
+    assert!(lower_module(&mut errors, codemap.clone(), &parsed).is_ok());
+}
+
+#[test]
+fn allocation_limit_stops_lowering() {
+    let codemap = Arc::new(CodeMap::new());
+    let body = vec!["f(X)"; 50].join(", ");
+    let parsed: Module = parse(
+        format!("-module(limit).\n\nf(X) -> X.\nlong(X) -> {}.\n", body),
+        ParseConfig::default(),
+        codemap.clone(),
+    );
+    let function = parsed
+        .functions
+        .values()
+        .find(|f| f.name.name.as_str().get() == "long")
+        .unwrap();
+
+    let lower_long = |limits: AllocationLimits| {
+        let ident = libeir_ir::FunctionIdent {
+            module: parsed.name,
+            name: function.name,
+            arity: function.arity,
+        };
+        let mut fun = IrFunction::new(function.span, ident);
+        let mut errors = Errors::new();
+        {
+            let mut ctx = LowerCtx::new(codemap.clone(), &parsed, &mut errors);
+            ctx.limits = limits;
+            lower_named_function(&mut ctx, &mut fun, function);
+            ctx.exc_stack.finish();
+        }
+        (fun, errors.failed())
+    };
+
+    let (full, failed) = lower_long(AllocationLimits::default());
+    assert!(!failed);
+
+    let (limited, failed) = lower_long(AllocationLimits {
+        max_blocks: Some(10),
+        max_values: None,
+    });
+    assert!(failed);
+    // The remaining calls are never lowered
+    assert!(limited.block_count() < 20);
+    assert!(full.block_count() > 50);
+}
+
 //#[test]
 //fn compiler_lower() {
 //    let mut config = ParseConfig::default();
@@ -207,6 +207,7 @@ impl fmt::Display for DeprecatedFlag {
 /// Represents the set of allowed attributes in the body of a module
 #[derive(Debug, Clone)]
 pub enum Attribute {
+    Module(SourceSpan, Ident),
     Type(TypeDef),
     Spec(TypeSpec),
     Callback(Callback),
@@ -230,6 +231,7 @@ impl PartialEq for Attribute {
         }
 
         match (self, other) {
+            (&Attribute::Module(_, ref x), &Attribute::Module(_, ref y)) => x == y,
             (&Attribute::Type(ref x), &Attribute::Type(ref y)) => x == y,
             (&Attribute::Spec(ref x), &Attribute::Spec(ref y)) => x == y,
             (&Attribute::Callback(ref x), &Attribute::Callback(ref y)) => x == y,
@@ -122,6 +122,25 @@ impl Expr {
         }
     }
 
+    pub fn from_unary(unary: UnaryExpr) -> Self {
+        if unary.op != UnaryOp::Minus {
+            return Expr::UnaryExpr(unary);
+        }
+
+        // A minus directly adjacent to a number is a negative literal,
+        // `- 2` with whitespace in between remains a unary operation.
+        let operand_start = unary.span.start() + 1;
+        match *unary.operand {
+            Expr::Literal(Literal::Integer(span, _, ref int)) if span.start() == operand_start => {
+                Expr::Literal(Literal::Integer(unary.span, unary.id, -int.clone()))
+            }
+            Expr::Literal(Literal::Float(span, _, flt)) if span.start() == operand_start => {
+                Expr::Literal(Literal::Float(unary.span, unary.id, -flt))
+            }
+            _ => Expr::UnaryExpr(unary),
+        }
+    }
+
     pub fn from_binary(bin: Binary) -> Self {
         if bin.elements.len() != 1 {
             return Expr::Binary(bin);
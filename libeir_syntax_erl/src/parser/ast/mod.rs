@@ -4,6 +4,8 @@ mod functions;
 mod module;
 mod types;
 
+use std::fmt;
+
 use libeir_diagnostics::SourceIndex;
 
 pub use self::attributes::*;
@@ -103,6 +105,40 @@ pub enum BinaryOp {
     And,
 }
 
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryOp::Send => write!(f, "!"),
+            BinaryOp::OrElse => write!(f, "orelse"),
+            BinaryOp::AndAlso => write!(f, "andalso"),
+            BinaryOp::Equal => write!(f, "=="),
+            BinaryOp::NotEqual => write!(f, "/="),
+            BinaryOp::Lte => write!(f, "=<"),
+            BinaryOp::Lt => write!(f, "<"),
+            BinaryOp::Gte => write!(f, ">="),
+            BinaryOp::Gt => write!(f, ">"),
+            BinaryOp::StrictEqual => write!(f, "=:="),
+            BinaryOp::StrictNotEqual => write!(f, "=/="),
+            BinaryOp::Append => write!(f, "++"),
+            BinaryOp::Remove => write!(f, "--"),
+            BinaryOp::Add => write!(f, "+"),
+            BinaryOp::Sub => write!(f, "-"),
+            BinaryOp::Bor => write!(f, "bor"),
+            BinaryOp::Bxor => write!(f, "bxor"),
+            BinaryOp::Bsl => write!(f, "bsl"),
+            BinaryOp::Bsr => write!(f, "bsr"),
+            BinaryOp::Or => write!(f, "or"),
+            BinaryOp::Xor => write!(f, "xor"),
+            BinaryOp::Divide => write!(f, "/"),
+            BinaryOp::Multiply => write!(f, "*"),
+            BinaryOp::Div => write!(f, "div"),
+            BinaryOp::Rem => write!(f, "rem"),
+            BinaryOp::Band => write!(f, "band"),
+            BinaryOp::And => write!(f, "and"),
+        }
+    }
+}
+
 /// The set of all unary (prefix) operators which may be used in expressions
 #[derive(Debug, Clone, PartialEq)]
 pub enum UnaryOp {
@@ -70,6 +70,54 @@ pub struct Module {
     pub deprecations: HashSet<Deprecation>,
 }
 impl Module {
+    /// Called by the parser with all of the top-level expressions of a module.
+    ///
+    /// The `-module` declaration has to come before the first function. It is taken out of the
+    /// body, and the module is created from the rest by `new`.
+    pub fn from_body(
+        errs: &mut dyn ErrorReceiver<E = ParserError, W = ParserError>,
+        span: SourceSpan,
+        nid: &mut NodeIdGenerator,
+        mut body: Vec<TopLevel>,
+    ) -> Result<Self, ()> {
+        let first_function = body.iter().position(|item| match item {
+            TopLevel::Function(_) => true,
+            _ => false,
+        });
+        let declaration = body.iter().position(|item| match item {
+            TopLevel::Attribute(Attribute::Module(_, _)) => true,
+            _ => false,
+        });
+
+        let declared_first = match (declaration, first_function) {
+            (Some(decl), Some(fun)) => decl < fun,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if declared_first {
+            let name = match body.remove(declaration.unwrap()) {
+                TopLevel::Attribute(Attribute::Module(_, name)) => name,
+                _ => unreachable!(),
+            };
+            return Ok(Module::new(errs, span, nid, name, body));
+        }
+
+        let label = match first_function.map(|idx| &body[idx]) {
+            Some(TopLevel::Function(function)) => {
+                Label::primary(function.span.source_id(), function.span)
+                    .with_message("expected a -module declaration before this function")
+            }
+            _ => Label::primary(span.source_id(), span)
+                .with_message("expected a -module declaration"),
+        };
+        errs.error(ParserError::ShowDiagnostic {
+            diagnostic: Diagnostic::error()
+                .with_message("missing module declaration")
+                .with_labels(vec![label]),
+        });
+        Err(())
+    }
+
     /// Called by the parser to create the module once all of the top-level expressions have been
     /// parsed, in other words this is the last function called when parsing a module.
     ///
@@ -120,6 +168,18 @@ impl Module {
         // Walk every top-level expression and extend our initial module definition accordingly
         for item in body.drain(..) {
             match item {
+                TopLevel::Attribute(Attribute::Module(aspan, _)) => {
+                    errs.error(ParserError::ShowDiagnostic {
+                        diagnostic: Diagnostic::error()
+                            .with_message("multiple module declarations")
+                            .with_labels(vec![
+                                Label::primary(aspan.source_id(), aspan)
+                                    .with_message("invalid declaration occurs here"),
+                                Label::secondary(module.name.span.source_id(), module.name.span)
+                                    .with_message("module first declared here"),
+                            ]),
+                    });
+                }
                 TopLevel::Attribute(Attribute::Vsn(aspan, vsn)) => {
                     if module.vsn.is_none() {
                         module.vsn = Some(vsn);
@@ -41,13 +41,14 @@ mod errors;
 use std::collections::VecDeque;
 use std::path::PathBuf;
 
+use libeir_diagnostics::{Diagnostic, Label, SourceSpan};
 use libeir_util_parse::{error_tee, ErrorReceiver, Scanner, Source, SourceError};
 use libeir_util_parse::{Parse as GParse, Parser as GParser};
 
 pub type Parser = GParser<ParseConfig>;
 pub trait Parse<T> = GParse<T, Config = ParseConfig, Error = ParserError>;
 
-use crate::lexer::Lexer;
+use crate::lexer::{Lexer, Symbol, Token};
 use crate::preprocessor::{MacroContainer, Preprocessed, Preprocessor};
 
 pub use self::ast::{NodeId, NodeIdGenerator};
@@ -63,6 +64,14 @@ pub struct ParseConfig {
     pub include_paths: VecDeque<PathBuf>,
     pub code_paths: VecDeque<PathBuf>,
     pub macros: Option<MacroContainer>,
+    /// When set, attributes the parser doesn't specifically understand
+    /// are errors instead of being stored as generic attributes. Every
+    /// occurrence of such an attribute is reported.
+    pub strict_directives: bool,
+    /// How deeply macro expansions may nest before the preprocessor
+    /// gives up, this bounds self-referential macros like
+    /// `-define(X, ?X).`
+    pub macro_expansion_limit: usize,
 }
 impl ParseConfig {
     pub fn new() -> Self {
@@ -77,6 +86,8 @@ impl Default for ParseConfig {
             include_paths: VecDeque::new(),
             code_paths: VecDeque::new(),
             macros: None,
+            strict_directives: false,
+            macro_expansion_limit: 128,
         }
     }
 }
@@ -104,7 +115,21 @@ impl GParse for ast::Module {
             let lexer = Lexer::new(scanner);
             error_tee(&mut errors.clone().make_into_adapter(), |preproc_errors| {
                 let tokens = Preprocessor::new(parser, lexer, preproc_errors);
-                Self::parse_tokens(&mut errors, tokens)
+                if !parser.config.strict_directives {
+                    return Self::parse_tokens(&mut errors, tokens);
+                }
+
+                // The module is checked even when parsing reported errors,
+                // like a redefined attribute, so that every occurrence of
+                // an unknown attribute is reported.
+                let mut forms = Vec::new();
+                let tokens = record_attribute_forms(tokens, &mut forms);
+                let mut nid = NodeIdGenerator::new();
+                let result = Self::Parser::new().parse(&mut errors, &mut nid, tokens);
+                if let Ok(module) = &result {
+                    check_strict_directives(&mut errors, module, &forms);
+                }
+                to_parse_result(&mut errors, result)
             })
         })
     }
@@ -172,6 +197,55 @@ fn to_parse_result<T>(
     }
 }
 
+/// Records the name and span of every form starting with `-name`, as
+/// the tokens are passed on.
+fn record_attribute_forms<'f, I>(
+    tokens: I,
+    forms: &'f mut Vec<(Symbol, SourceSpan)>,
+) -> impl Iterator<Item = Preprocessed> + 'f
+where
+    I: IntoIterator<Item = Preprocessed>,
+    I::IntoIter: 'f,
+{
+    let mut form_start = true;
+    let mut minus = None;
+    tokens.into_iter().inspect(move |token| {
+        if let Ok((start, tok, end)) = token {
+            if let (Some(start), Token::Atom(name)) = (minus.take(), tok) {
+                forms.push((*name, SourceSpan::new(start, *end)));
+            }
+            if let (true, Token::Minus) = (form_start, tok) {
+                minus = Some(*start);
+            }
+            form_start = match tok {
+                Token::Dot => true,
+                _ => false,
+            };
+        }
+    })
+}
+
+/// Errors on every occurrence of an attribute that was stored as a
+/// generic attribute, used when `ParseConfig::strict_directives` is set.
+/// `forms` are the forms recorded by `record_attribute_forms`.
+fn check_strict_directives(
+    errs: &mut ParserErrorReceiver,
+    module: &ast::Module,
+    forms: &[(Symbol, SourceSpan)],
+) {
+    for (name, span) in forms.iter() {
+        if !module.attributes.keys().any(|attr| attr.name == *name) {
+            continue;
+        }
+        errs.error(ParserError::ShowDiagnostic {
+            diagnostic: Diagnostic::error()
+                .with_message("unknown directive")
+                .with_labels(vec![Label::primary(span.source_id(), *span)
+                    .with_message(format!("`-{}` is not a recognized directive", name))]),
+        });
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::sync::Arc;
@@ -182,6 +256,7 @@ mod test {
     use super::*;
 
     use libeir_diagnostics::*;
+    use libeir_util_number::Integer;
     use libeir_util_parse::{ErrorOrWarning, Errors};
 
     use crate::lexer::{Ident, Symbol};
@@ -822,6 +897,577 @@ bar() -> - 2.
         );
     }
 
+    #[test]
+    fn parse_negative_literal_span() {
+        let result: Expr = parse(ParseConfig::default(), Arc::new(CodeMap::new()), "-2");
+        match result {
+            Expr::Literal(Literal::Integer(span, _, int)) => {
+                assert_eq!(int, Integer::Small(-2));
+                assert_eq!(span.end().to_usize() - span.start().to_usize(), 2);
+            }
+            other => panic!("expected negative integer literal, got {:?}", other),
+        }
+
+        let result: Expr = parse(ParseConfig::default(), Arc::new(CodeMap::new()), "- 2");
+        match result {
+            Expr::UnaryExpr(UnaryExpr { op, operand, .. }) => {
+                assert_eq!(op, UnaryOp::Minus);
+                match *operand {
+                    Expr::Literal(Literal::Integer(_, _, int)) => {
+                        assert_eq!(int, Integer::Small(2))
+                    }
+                    other => panic!("expected integer literal, got {:?}", other),
+                }
+            }
+            other => panic!("expected unary expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_preprocessor_stringify() {
+        let result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+-define(str(X), ??X).
+
+bar() -> ?str(a + b).
+",
+        );
+
+        let fun = result.functions.values().next().unwrap();
+        match &fun.clauses[0].body[..] {
+            [Expr::Literal(Literal::String(_, ident))] => {
+                assert_eq!(ident.name.as_str().get(), "a + b")
+            }
+            other => panic!("expected string literal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_preprocessor_stringify_skips_comments() {
+        let result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+-define(str(X), ??X).
+
+bar() -> ?str(a % not part of the argument
+              + b).
+",
+        );
+
+        let fun = result.functions.values().next().unwrap();
+        match &fun.clauses[0].body[..] {
+            [Expr::Literal(Literal::String(_, ident))] => {
+                assert_eq!(ident.name.as_str().get(), "a + b")
+            }
+            other => panic!("expected string literal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_preprocessor_function_macros() {
+        let codemap = Arc::new(CodeMap::new());
+        let result: Module = parse(
+            ParseConfig::default(),
+            codemap.clone(),
+            "-module(foo).
+-define(square(X), ((X)*(X))).
+-define(show(X), {??X, X}).
+
+bar() -> ?square(1+1).
+baz(Y) -> ?show(?square(Y)).
+",
+        );
+        // Arguments are substituted as tokens, `??` uses the argument as
+        // written, before any expansion
+        let expected: Module = parse(
+            ParseConfig::default(),
+            codemap,
+            "-module(foo).
+
+bar() -> ((1+1)*(1+1)).
+baz(Y) -> {\"?square(Y)\", ((Y)*(Y))}.
+",
+        );
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn parse_preprocessor_multiline_macro_comments() {
+        let codemap = Arc::new(CodeMap::new());
+        let result: Module = parse(
+            ParseConfig::default(),
+            codemap.clone(),
+            "-module(foo).
+-define(PAIR(X), % the argument and its successor
+    {X, % first
+     X
+     + 1} % second
+).
+
+bar(Y) -> ?PAIR(Y).
+",
+        );
+        let expected: Module = parse(
+            ParseConfig::default(),
+            codemap,
+            "-module(foo).
+
+bar(Y) -> {Y, Y + 1}.
+",
+        );
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn parse_preprocessor_if_builtins() {
+        let result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+
+-if(length([a, b, c]) == 3).
+length() -> yes.
+-else.
+length() -> no.
+-endif.
+
+-if(is_list([]) andalso is_integer(hd([1])) andalso tl([1]) == []).
+list() -> yes.
+-else.
+list() -> no.
+-endif.
+
+-if(element(2, {a, b}) == b andalso tuple_size({a, b}) == 2).
+tuple() -> yes.
+-else.
+tuple() -> no.
+-endif.
+",
+        );
+
+        for fun in result.functions.values() {
+            match &fun.clauses[0].body[..] {
+                [Expr::Literal(Literal::Atom(_, ident))] => {
+                    assert_eq!(ident.name.as_str().get(), "yes")
+                }
+                other => panic!("expected atom, got {:?}", other),
+            }
+        }
+        assert_eq!(result.functions.len(), 3);
+    }
+
+    #[test]
+    fn parse_preprocessor_if_list_operators() {
+        let result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+
+-define(APPS, [kernel, stdlib]).
+
+-if([a, b] ++ [c] == [a, b, c] andalso length(?APPS ++ [sasl]) == 3).
+append() -> yes.
+-else.
+append() -> no.
+-endif.
+
+-if([1, 2, 3, 2] -- [2, 4] == [1, 3, 2]).
+remove() -> yes.
+-else.
+remove() -> no.
+-endif.
+
+-if(?APPS -- [kernel] == []).
+empty() -> no.
+-else.
+empty() -> yes.
+-endif.
+",
+        );
+
+        for fun in result.functions.values() {
+            match &fun.clauses[0].body[..] {
+                [Expr::Literal(Literal::Atom(_, ident))] => {
+                    assert_eq!(ident.name.as_str().get(), "yes")
+                }
+                other => panic!("expected atom, got {:?}", other),
+            }
+        }
+        assert_eq!(result.functions.len(), 3);
+    }
+
+    #[test]
+    fn parse_preprocessor_if_macro_operators() {
+        let result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+
+-define(MAX, 20).
+-define(MIN, 3).
+-define(IN_RANGE(X), X >= ?MIN andalso X =< ?MAX).
+
+-if(?MAX > 10 andalso ?MIN < 5).
+both() -> yes.
+-else.
+both() -> no.
+-endif.
+
+-if(?MAX > 10 andalso ?MIN > 5).
+one() -> no.
+-else.
+one() -> yes.
+-endif.
+
+-if(?MIN > 5 orelse ?IN_RANGE(?MAX - ?MIN)).
+range() -> yes.
+-else.
+range() -> no.
+-endif.
+",
+        );
+
+        for fun in result.functions.values() {
+            match &fun.clauses[0].body[..] {
+                [Expr::Literal(Literal::Atom(_, ident))] => {
+                    assert_eq!(ident.name.as_str().get(), "yes")
+                }
+                other => panic!("expected atom, got {:?}", other),
+            }
+        }
+        assert_eq!(result.functions.len(), 3);
+    }
+
+    #[test]
+    fn parse_function_name_arity_macros() {
+        let result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+
+-define(WHO, {?FUNCTION_NAME, ?FUNCTION_ARITY}).
+
+foo() -> ?FUNCTION_NAME.
+bar({A, B}, [C | _]) -> ?FUNCTION_ARITY;
+bar(_, _) -> ?WHO.
+",
+        );
+
+        let function = |name: &str, arity: usize| {
+            result
+                .functions
+                .values()
+                .find(|f| f.name.name.as_str().get() == name && f.arity == arity)
+                .unwrap()
+        };
+
+        match &function("foo", 0).clauses[0].body[..] {
+            [Expr::Literal(Literal::Atom(_, ident))] => {
+                assert_eq!(ident.name.as_str().get(), "foo")
+            }
+            other => panic!("expected atom, got {:?}", other),
+        }
+
+        let bar = function("bar", 2);
+        match &bar.clauses[0].body[..] {
+            [Expr::Literal(Literal::Integer(_, _, arity))] => assert_eq!(*arity, Integer::from(2)),
+            other => panic!("expected integer, got {:?}", other),
+        }
+        match &bar.clauses[1].body[..] {
+            [Expr::Tuple(Tuple { elements, .. })] => match &elements[..] {
+                [Expr::Literal(Literal::Atom(_, name)), Expr::Literal(Literal::Integer(_, _, arity))] =>
+                {
+                    assert_eq!(name.name.as_str().get(), "bar");
+                    assert_eq!(*arity, Integer::from(2));
+                }
+                other => panic!("expected name and arity, got {:?}", other),
+            },
+            other => panic!("expected tuple, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_function_name_arity_macros_after_record_access() {
+        let result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+
+-record(rec, {a, b}).
+
+field(R) -> {R#rec.a, ?FUNCTION_NAME, #rec.b, ?FUNCTION_ARITY}.
+",
+        );
+
+        let fun = result
+            .functions
+            .values()
+            .find(|f| f.name.name.as_str().get() == "field")
+            .unwrap();
+        match &fun.clauses[0].body[..] {
+            [Expr::Tuple(Tuple { elements, .. })] => match &elements[..] {
+                [_, Expr::Literal(Literal::Atom(_, name)), _, Expr::Literal(Literal::Integer(_, _, arity))] =>
+                {
+                    assert_eq!(name.name.as_str().get(), "field");
+                    assert_eq!(*arity, Integer::from(1));
+                }
+                other => panic!("expected name and arity, got {:?}", other),
+            },
+            other => panic!("expected tuple, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_binary_comprehensions() {
+        let result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+
+bytes(L) -> << <<X>> || X <- L >>.
+pairs(B) -> << <<X, Y>> || <<X:8>> <= B, Y <- [1, 2], X > Y >>.
+nested(L) -> << <<X, Y>> || <<X>> <= << <<Z>> || Z <- L >>, Y <- L >>.
+",
+        );
+
+        let body = |name: &str| {
+            let fun = result
+                .functions
+                .values()
+                .find(|f| f.name.name.as_str().get() == name)
+                .unwrap();
+            match &fun.clauses[0].body[..] {
+                [Expr::BinaryComprehension(compr)] => compr.clone(),
+                other => panic!("expected binary comprehension, got {:?}", other),
+            }
+        };
+
+        let bytes = body("bytes");
+        match &*bytes.body {
+            Expr::Binary(Binary { elements, .. }) => assert_eq!(elements.len(), 1),
+            other => panic!("expected binary, got {:?}", other),
+        }
+        match &bytes.qualifiers[..] {
+            [Expr::Generator(Generator { pattern, .. })] => match &**pattern {
+                Expr::Var(Var(_, ident)) => assert_eq!(ident.name.as_str().get(), "X"),
+                other => panic!("expected variable, got {:?}", other),
+            },
+            other => panic!("expected generator, got {:?}", other),
+        }
+
+        let pairs = body("pairs");
+        match &*pairs.body {
+            Expr::Binary(Binary { elements, .. }) => assert_eq!(elements.len(), 2),
+            other => panic!("expected binary, got {:?}", other),
+        }
+        match &pairs.qualifiers[..] {
+            [Expr::BinaryGenerator(_), Expr::Generator(_), Expr::BinaryExpr(filter)] => {
+                assert_eq!(filter.op, BinaryOp::Gt)
+            }
+            other => panic!("expected generators and a filter, got {:?}", other),
+        }
+
+        // The source of a binary generator can itself be a comprehension
+        let nested = body("nested");
+        match &nested.qualifiers[..] {
+            [Expr::BinaryGenerator(BinaryGenerator { expr, .. }), Expr::Generator(_)] => {
+                match &**expr {
+                    Expr::BinaryComprehension(inner) => assert_eq!(inner.qualifiers.len(), 1),
+                    other => panic!("expected binary comprehension, got {:?}", other),
+                }
+            }
+            other => panic!("expected generators, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_strict_directives() {
+        let source = "-module(foo).
+-made_up(bar).
+";
+
+        let result: Module = parse(ParseConfig::default(), Arc::new(CodeMap::new()), source);
+        let attr = &result.attributes[&ident!(made_up)];
+        assert_eq!(attr.value, atom!(&mut NodeIdGenerator::new(), bar));
+
+        let mut config = ParseConfig::default();
+        config.strict_directives = true;
+        let errs = parse_fail::<Module, _>(config, Arc::new(CodeMap::new()), source);
+        assert_eq!(errs.iter_diagnostics().count(), 1);
+    }
+
+    #[test]
+    fn parse_strict_directives_repeated() {
+        let source = "-module(foo).
+-made_up(bar).
+-made_up(baz).
+";
+
+        let mut config = ParseConfig::default();
+        config.strict_directives = true;
+        let errs = parse_fail::<Module, _>(config, Arc::new(CodeMap::new()), source);
+        let count = |message: &str| {
+            errs.iter_diagnostics()
+                .filter(|diagnostic| diagnostic.message == message)
+                .count()
+        };
+        assert_eq!(count("unknown directive"), 2);
+        assert_eq!(count("redefined attribute"), 1);
+    }
+
+    #[test]
+    fn parse_recursive_macro() {
+        let mut config = ParseConfig::default();
+        config.macro_expansion_limit = 16;
+        let mut errs = parse_fail::<Module, &str>(
+            config,
+            Arc::new(CodeMap::default()),
+            "-module(foo).
+-define(X, ?X).
+foo() -> ?X.
+",
+        );
+        match errs.errors.pop() {
+            Some(ErrorOrWarning::Error(ParserError::Preprocessor {
+                source: PreprocessorError::MacroRecursion { call, limit },
+            })) => {
+                assert_eq!(call.name(), Symbol::intern("X"));
+                assert_eq!(limit, 16);
+            }
+            Some(err) => panic!(
+                "expected macro recursion error, but got a different error instead: {:?}",
+                err
+            ),
+            None => panic!("expected macro recursion error, but didn't get any errors!"),
+        }
+    }
+
+    #[test]
+    fn parse_preprocessor_if_bad_operator() {
+        let cases = [
+            ("-if(5.0 div 2 == 2).", BinaryOp::Div, "floats"),
+            ("-if(5.0 rem 2.0 == 1).", BinaryOp::Rem, "floats"),
+            ("-if(1 div 0 == 0).", BinaryOp::Div, "a zero divisor"),
+            ("-if(1.0 / 0.0 == 0).", BinaryOp::Divide, "a zero divisor"),
+        ];
+        for (cond, expected_op, expected_ty) in cases.iter() {
+            let src = format!("-module(foo).\n{}\nfoo() -> ok.\n-endif.\n", cond);
+            let errs =
+                parse_fail::<Module, &str>(ParseConfig::default(), Arc::new(CodeMap::new()), &src);
+            let found = errs.errors.iter().any(|err| match err {
+                ErrorOrWarning::Error(ParserError::Preprocessor {
+                    source: PreprocessorError::BadOperatorForType { op, ty, .. },
+                }) => op == expected_op && ty == expected_ty,
+                _ => false,
+            });
+            assert!(found, "expected bad operator error for {}", cond);
+        }
+    }
+
+    #[test]
+    fn parse_include_lib() {
+        use std::fs;
+
+        use crate::preprocessor::directives::DirectiveError;
+
+        let root = std::env::temp_dir().join(format!("eir_include_lib_{}", std::process::id()));
+        let app_include = root.join("lib").join("myapp-1.0").join("include");
+        let other_include = root.join("include").join("other");
+        fs::create_dir_all(&app_include).unwrap();
+        fs::create_dir_all(&other_include).unwrap();
+        fs::write(app_include.join("defs.hrl"), "-define(ANSWER, 42).\n").unwrap();
+        fs::write(other_include.join("defs.hrl"), "-define(OTHER, 43).\n").unwrap();
+
+        let mut config = ParseConfig::default();
+        config.code_paths.push_back(root.join("lib"));
+        config.include_paths.push_back(root.join("include"));
+
+        // Found in an application under the code paths, or else in the
+        // include paths
+        let _result: Module = parse(
+            config.clone(),
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+-include_lib(\"myapp/include/defs.hrl\").
+-include_lib(\"other/defs.hrl\").
+
+answer() -> ?ANSWER + ?OTHER.
+",
+        );
+
+        let mut errs = parse_fail::<Module, &str>(
+            config,
+            Arc::new(CodeMap::new()),
+            "-module(foo).
+-include_lib(\"missing/include/defs.hrl\").
+",
+        );
+        fs::remove_dir_all(&root).unwrap();
+
+        match errs.errors.pop() {
+            Some(ErrorOrWarning::Error(ParserError::Preprocessor {
+                source:
+                    PreprocessorError::BadDirective {
+                        source: DirectiveError::FileNotFound { searched, .. },
+                    },
+            })) => {
+                // Both the code path and the include path were searched
+                assert_eq!(searched.len(), 3);
+            }
+            Some(err) => panic!(
+                "expected file not found error, but got a different error instead: {:?}",
+                err
+            ),
+            None => panic!("expected file not found error, but didn't get any errors!"),
+        }
+    }
+
+    #[test]
+    fn parse_missing_module() {
+        let mut errs = parse_fail::<Module, &str>(
+            ParseConfig::default(),
+            Arc::new(CodeMap::default()),
+            "foo() -> ok.
+",
+        );
+        match errs.errors.pop() {
+            Some(ErrorOrWarning::Error(ParserError::ShowDiagnostic { diagnostic })) => {
+                assert_eq!(diagnostic.message, "missing module declaration");
+            }
+            Some(err) => panic!(
+                "expected missing module error, but got a different error instead: {:?}",
+                err
+            ),
+            None => panic!("expected missing module error, but didn't get any errors!"),
+        }
+    }
+
+    #[test]
+    fn parse_duplicate_module() {
+        let mut errs = parse_fail::<Module, &str>(
+            ParseConfig::default(),
+            Arc::new(CodeMap::default()),
+            "-module(foo).
+-module(bar).
+",
+        );
+        match errs.errors.pop() {
+            Some(ErrorOrWarning::Error(ParserError::ShowDiagnostic { diagnostic })) => {
+                assert_eq!(diagnostic.message, "multiple module declarations");
+                // Points at both declarations
+                assert_eq!(diagnostic.labels.len(), 2);
+            }
+            Some(err) => panic!(
+                "expected duplicate module error, but got a different error instead: {:?}",
+                err
+            ),
+            None => panic!("expected duplicate module error, but didn't get any errors!"),
+        }
+    }
+
     #[test]
     fn parse_spec() {
         let _result: Module = parse(
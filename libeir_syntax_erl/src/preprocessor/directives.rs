@@ -233,40 +233,69 @@ pub struct IncludeLib {
 }
 impl IncludeLib {
     /// Executes file inclusion.
-    pub fn include_lib(&self, code_paths: &VecDeque<PathBuf>) -> DirectiveResult<PathBuf> {
-        let mut path = substitute_path_variables(self.path.symbol().as_str().get()).context(
+    ///
+    /// The first path component names an application. The rest of the
+    /// path is looked up in that application's directory under each of
+    /// the code paths, either `<app>` or versioned as `<app>-<vsn>`. If
+    /// it is not found there, the whole path is looked up in the include
+    /// paths, like for `-include`.
+    pub fn include_lib(
+        &self,
+        include_paths: &VecDeque<PathBuf>,
+        code_paths: &VecDeque<PathBuf>,
+    ) -> DirectiveResult<PathBuf> {
+        let path = substitute_path_variables(self.path.symbol().as_str().get()).context(
             PathSubstitute {
                 span: self.path.span(),
             },
         )?;
 
-        let temp_path = path.clone();
-        let mut components = temp_path.components();
+        let mut searched = Vec::new();
+
+        let mut components = path.components();
         if let Some(Component::Normal(app_name)) = components.next() {
             let app_name = app_name
                 .to_str()
                 .expect("internal error: expected app name here");
-            let pattern = format!("{}", app_name);
-            'root: for root in code_paths.iter() {
-                let pattern = root.join(&pattern);
-                let pattern = pattern.to_str().unwrap();
-                if let Some(entry) = glob(pattern)
-                    .context(GlobPattern {
-                        span: self.path.span(),
-                    })?
-                    .nth(0)
-                {
-                    path = entry.context(Glob {
+            let rest = components.as_path();
+
+            for root in code_paths.iter() {
+                let patterns = [root.join(app_name), root.join(format!("{}-*", app_name))];
+                for pattern in patterns.iter() {
+                    searched.push(pattern.join(rest));
+
+                    let pattern = pattern.to_str().unwrap();
+                    let entries = glob(pattern).context(GlobPattern {
                         span: self.path.span(),
                     })?;
-                    for c in components {
-                        path.push(c.as_os_str());
+                    for entry in entries {
+                        let app_dir = entry.context(Glob {
+                            span: self.path.span(),
+                        })?;
+                        let tmp_path = app_dir.join(rest);
+                        if tmp_path.exists() {
+                            return Ok(tmp_path);
+                        }
                     }
-                    break 'root;
                 }
             }
         }
-        Ok(path)
+
+        for include_path in include_paths.iter() {
+            let tmp_path = include_path.join(&path);
+            if tmp_path.exists() {
+                return Ok(tmp_path);
+            }
+            searched.push(tmp_path);
+        }
+
+        Err(DirectiveError::FileNotFound {
+            span: self.span(),
+            searched: searched
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+        })
     }
 
     pub fn span(&self) -> SourceSpan {
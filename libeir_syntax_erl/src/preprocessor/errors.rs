@@ -7,6 +7,7 @@ use libeir_diagnostics::*;
 use libeir_util_parse::SourceError;
 
 use crate::lexer::{LexicalError, LexicalToken, TokenConvertError};
+use crate::parser::ast::BinaryOp;
 use crate::parser::ParserError;
 
 use super::directive::Directive;
@@ -36,6 +37,15 @@ pub enum PreprocessorError {
     #[snafu(display("invalid constant expression found in preprocessor directive"))]
     InvalidConstExpression { span: SourceSpan },
 
+    #[snafu(display("operator '{}' cannot be applied to {}", op, ty))]
+    BadOperatorForType {
+        /// The whole binary expression, `BinaryExpr` keeps no span for
+        /// the operator alone.
+        span: SourceSpan,
+        op: BinaryOp,
+        ty: &'static str,
+    },
+
     #[snafu(visibility(pub))]
     BadDirective { source: DirectiveError },
 
@@ -60,6 +70,9 @@ pub enum PreprocessorError {
     #[snafu(display("undefined macro"))]
     UndefinedMacro { call: MacroCall },
 
+    #[snafu(display("macro '{}' exceeded the expansion limit of {}", call.name(), limit))]
+    MacroRecursion { call: MacroCall, limit: usize },
+
     #[snafu(display("invalid macro invocation"))]
     BadMacroCall {
         call: MacroCall,
@@ -121,6 +134,13 @@ impl PreprocessorError {
                         Label::primary(span.source_id(), *span)
                             .with_message("expected valid constant expression (example: `?OTP_VERSION >= 21`)")
                     ]),
+            PreprocessorError::BadOperatorForType { span, op, ty } =>
+                Diagnostic::error()
+                    .with_message(self.to_string())
+                    .with_labels(vec![
+                        Label::primary(span.source_id(), *span)
+                            .with_message(format!("this expression applies '{}' to {}", op, ty))
+                    ]),
             PreprocessorError::BadDirective { source } => source.to_diagnostic(),
             PreprocessorError::InvalidConditional { span, .. } =>
                 Diagnostic::error()
@@ -168,6 +188,15 @@ impl PreprocessorError {
                         Label::primary(span.source_id(), span)
                     ])
             }
+            PreprocessorError::MacroRecursion { call, .. } => {
+                let span = call.span();
+                Diagnostic::error()
+                    .with_message(self.to_string())
+                    .with_labels(vec![
+                        Label::primary(span.source_id(), span)
+                            .with_message("this macro expands recursively")
+                    ])
+            }
             PreprocessorError::BadMacroCall { call, def: MacroDef::String(_), reason, .. } => {
                 let span = call.span();
                 Diagnostic::error()
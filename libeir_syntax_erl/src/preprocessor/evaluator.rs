@@ -25,6 +25,8 @@ use super::errors::PreprocessorError;
 /// - Bit shift operations on constants or expressions which evaluate to constants
 /// - Comparisons on constants or expressions which evaluate to constants
 /// - The use of `++` and `--` on constant lists, or expressions which evaluate to constant lists
+/// - The guard BIFs `length/1`, `is_list/1`, `is_integer/1`, `hd/1`, `tl/1`, `element/2` and
+///   `tuple_size/1` on constants
 pub fn eval(expr: Expr) -> Result<Expr, PreprocessorError> {
     let result = match expr {
         // Nothing to be done here
@@ -124,16 +126,21 @@ pub fn eval(expr: Expr) -> Result<Expr, PreprocessorError> {
             return Err(PreprocessorError::InvalidConstExpression { span });
         }
         Expr::Apply(Apply {
-            span, callee, args, ..
+            span,
+            id,
+            callee,
+            args,
         }) => {
-            let _args = eval_list(args)?;
+            let args = eval_list(args)?;
             match eval(*callee)? {
-                Expr::Literal(Literal::Atom(_, Ident { ref name, .. })) => match builtin(*name) {
-                    None => {
-                        return Err(PreprocessorError::InvalidConstExpression { span });
+                Expr::Literal(Literal::Atom(_, Ident { ref name, .. })) => {
+                    match builtin(*name, args.len()) {
+                        None => {
+                            return Err(PreprocessorError::InvalidConstExpression { span });
+                        }
+                        Some(fun) => return fun(span, id, args),
                     }
-                    Some(_) => unimplemented!(),
-                },
+                }
                 _ => return Err(PreprocessorError::InvalidConstExpression { span }),
             }
         }
@@ -318,6 +325,7 @@ fn eval_binary_op(
         | BinaryOp::Band
         | BinaryOp::Bsl
         | BinaryOp::Bsr => eval_shift(span, id, lhs, op, rhs),
+        BinaryOp::Append | BinaryOp::Remove => eval_list_op(span, id, lhs, op, rhs),
         _ => return Err(PreprocessorError::InvalidConstExpression { span }),
     }
 }
@@ -761,11 +769,14 @@ fn eval_op_int(
         BinaryOp::Add => Expr::Literal(Literal::Integer(span, id, x + y)),
         BinaryOp::Sub => Expr::Literal(Literal::Integer(span, id, x - y)),
         BinaryOp::Multiply => Expr::Literal(Literal::Integer(span, id, x * y)),
-        BinaryOp::Divide if *y == 0 => {
-            return Err(PreprocessorError::InvalidConstExpression { span })
+        BinaryOp::Divide | BinaryOp::Div | BinaryOp::Rem if *y == 0 => {
+            return Err(PreprocessorError::BadOperatorForType {
+                span,
+                op,
+                ty: "a zero divisor",
+            })
         }
         BinaryOp::Divide => Expr::Literal(Literal::Float(span, id, x.to_float() / y.to_float())),
-        BinaryOp::Div if *y == 0 => return Err(PreprocessorError::InvalidConstExpression { span }),
         BinaryOp::Div => Expr::Literal(Literal::Integer(span, id, x / y)),
         BinaryOp::Rem => Expr::Literal(Literal::Integer(span, id, x % y)),
         _ => unreachable!(),
@@ -784,12 +795,17 @@ fn eval_op_float(
         BinaryOp::Add => Ok(Expr::Literal(Literal::Float(span, id, x + y))),
         BinaryOp::Sub => Ok(Expr::Literal(Literal::Float(span, id, x - y))),
         BinaryOp::Multiply => Ok(Expr::Literal(Literal::Float(span, id, x * y))),
-        BinaryOp::Divide if y == 0.0 => {
-            return Err(PreprocessorError::InvalidConstExpression { span })
-        }
+        BinaryOp::Divide if y == 0.0 => Err(PreprocessorError::BadOperatorForType {
+            span,
+            op,
+            ty: "a zero divisor",
+        }),
         BinaryOp::Divide => Ok(Expr::Literal(Literal::Float(span, id, x / y))),
-        BinaryOp::Div => return Err(PreprocessorError::InvalidConstExpression { span }),
-        BinaryOp::Rem => return Err(PreprocessorError::InvalidConstExpression { span }),
+        BinaryOp::Div | BinaryOp::Rem => Err(PreprocessorError::BadOperatorForType {
+            span,
+            op,
+            ty: "floats",
+        }),
         _ => unreachable!(),
     }
 }
@@ -822,6 +838,59 @@ fn eval_shift(
     }
 }
 
+fn eval_list_op(
+    span: SourceSpan,
+    id: NodeId,
+    lhs: Expr,
+    op: BinaryOp,
+    rhs: Expr,
+) -> Result<Expr, PreprocessorError> {
+    let mut elements =
+        list_elements(lhs).ok_or(PreprocessorError::InvalidConstExpression { span })?;
+    let tail = match op {
+        // Like in Erlang, the right hand side becomes the tail as is
+        BinaryOp::Append => rhs,
+        // Removes the first occurrence of each element on the right
+        BinaryOp::Remove => {
+            let remove =
+                list_elements(rhs).ok_or(PreprocessorError::InvalidConstExpression { span })?;
+            for elem in remove.iter() {
+                if let Some(idx) = elements.iter().position(|e| e == elem) {
+                    elements.remove(idx);
+                }
+            }
+            Expr::Nil(Nil(span, id))
+        }
+        _ => unreachable!(),
+    };
+
+    let list = elements.drain(..).rev().fold(tail, |tail, head| {
+        Expr::Cons(Cons {
+            span,
+            id,
+            head: Box::new(head),
+            tail: Box::new(tail),
+        })
+    });
+    Ok(list)
+}
+
+/// The elements of a proper list of `Cons` cells ending in `Nil`.
+fn list_elements(expr: Expr) -> Option<Vec<Expr>> {
+    let mut elements = Vec::new();
+    let mut current = expr;
+    loop {
+        match current {
+            Expr::Nil(_) => return Some(elements),
+            Expr::Cons(Cons { head, tail, .. }) => {
+                elements.push(*head);
+                current = *tail;
+            }
+            _ => return None,
+        }
+    }
+}
+
 fn is_number(e: &Expr) -> bool {
     match *e {
         Expr::Literal(Literal::Integer(_, _, _)) => true,
@@ -849,6 +918,131 @@ fn is_true(e: &Expr) -> bool {
     }
 }
 
-fn builtin(_name: Symbol) -> Option<&'static fn(Vec<Expr>) -> Result<Expr, ()>> {
-    None
+type Builtin = fn(SourceSpan, NodeId, Vec<Expr>) -> Result<Expr, PreprocessorError>;
+
+/// Looks up a BIF that can be evaluated at preprocessing time.
+fn builtin(name: Symbol, arity: usize) -> Option<Builtin> {
+    let fun: Builtin = match (&*name.as_str(), arity) {
+        ("length", 1) => builtin_length,
+        ("is_list", 1) => builtin_is_list,
+        ("is_integer", 1) => builtin_is_integer,
+        ("hd", 1) => builtin_hd,
+        ("tl", 1) => builtin_tl,
+        ("element", 2) => builtin_element,
+        ("tuple_size", 1) => builtin_tuple_size,
+        _ => return None,
+    };
+    Some(fun)
+}
+
+fn bool_expr(span: SourceSpan, id: NodeId, value: bool) -> Expr {
+    let name = if value { symbols::True } else { symbols::False };
+    Expr::Literal(Literal::Atom(id, Ident { name, span }))
+}
+
+/// The number of elements of a proper constant list.
+fn list_length(expr: &Expr) -> Option<usize> {
+    let mut len = 0;
+    let mut current = expr;
+    loop {
+        match current {
+            Expr::Nil(_) => return Some(len),
+            Expr::Cons(Cons { tail, .. }) => {
+                len += 1;
+                current = &**tail;
+            }
+            Expr::Literal(Literal::String(_, Ident { name, .. })) => {
+                return Some(len + name.as_str().chars().count());
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn builtin_length(
+    span: SourceSpan,
+    id: NodeId,
+    args: Vec<Expr>,
+) -> Result<Expr, PreprocessorError> {
+    match list_length(&args[0]) {
+        Some(len) => Ok(Expr::Literal(Literal::Integer(span, id, len.into()))),
+        None => Err(PreprocessorError::InvalidConstExpression { span }),
+    }
+}
+
+fn builtin_is_list(
+    span: SourceSpan,
+    id: NodeId,
+    args: Vec<Expr>,
+) -> Result<Expr, PreprocessorError> {
+    let is_list = match args[0] {
+        Expr::Nil(_) | Expr::Cons(_) | Expr::Literal(Literal::String(_, _)) => true,
+        _ => false,
+    };
+    Ok(bool_expr(span, id, is_list))
+}
+
+fn builtin_is_integer(
+    span: SourceSpan,
+    id: NodeId,
+    args: Vec<Expr>,
+) -> Result<Expr, PreprocessorError> {
+    let is_integer = match args[0] {
+        Expr::Literal(Literal::Integer(_, _, _)) | Expr::Literal(Literal::Char(_, _, _)) => true,
+        _ => false,
+    };
+    Ok(bool_expr(span, id, is_integer))
+}
+
+fn builtin_hd(
+    span: SourceSpan,
+    _id: NodeId,
+    mut args: Vec<Expr>,
+) -> Result<Expr, PreprocessorError> {
+    match args.pop().unwrap() {
+        Expr::Cons(Cons { head, .. }) => Ok(*head),
+        _ => Err(PreprocessorError::InvalidConstExpression { span }),
+    }
+}
+
+fn builtin_tl(
+    span: SourceSpan,
+    _id: NodeId,
+    mut args: Vec<Expr>,
+) -> Result<Expr, PreprocessorError> {
+    match args.pop().unwrap() {
+        Expr::Cons(Cons { tail, .. }) => Ok(*tail),
+        _ => Err(PreprocessorError::InvalidConstExpression { span }),
+    }
+}
+
+fn builtin_element(
+    span: SourceSpan,
+    _id: NodeId,
+    mut args: Vec<Expr>,
+) -> Result<Expr, PreprocessorError> {
+    let tuple = args.pop().unwrap();
+    let index = args.pop().unwrap();
+    match (index, tuple) {
+        (
+            Expr::Literal(Literal::Integer(_, _, Integer::Small(n))),
+            Expr::Tuple(Tuple { mut elements, .. }),
+        ) if n >= 1 && (n as usize) <= elements.len() => Ok(elements.swap_remove(n as usize - 1)),
+        _ => Err(PreprocessorError::InvalidConstExpression { span }),
+    }
+}
+
+fn builtin_tuple_size(
+    span: SourceSpan,
+    id: NodeId,
+    args: Vec<Expr>,
+) -> Result<Expr, PreprocessorError> {
+    match &args[0] {
+        Expr::Tuple(Tuple { elements, .. }) => Ok(Expr::Literal(Literal::Integer(
+            span,
+            id,
+            elements.len().into(),
+        ))),
+        _ => Err(PreprocessorError::InvalidConstExpression { span }),
+    }
 }
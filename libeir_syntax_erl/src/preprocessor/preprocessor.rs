@@ -46,6 +46,8 @@ pub struct Preprocessor<'a, Reader: TokenReader> {
     expanded_tokens: VecDeque<LexicalToken>,
     warnings_as_errors: bool,
     no_warn: bool,
+    macro_expansion_limit: usize,
+    function: FunctionContext,
 }
 impl<'a, S> Preprocessor<'a, TokenStreamReader<S>>
 where
@@ -83,6 +85,8 @@ where
             expanded_tokens: VecDeque::new(),
             warnings_as_errors: parser.config.warnings_as_errors,
             no_warn: parser.config.no_warn,
+            macro_expansion_limit: parser.config.macro_expansion_limit,
+            function: FunctionContext::new(FunctionState::FormStart),
         }
     }
 }
@@ -107,6 +111,8 @@ where
             expanded_tokens: VecDeque::new(),
             warnings_as_errors: self.warnings_as_errors,
             no_warn: self.no_warn,
+            macro_expansion_limit: self.macro_expansion_limit,
+            function: FunctionContext::new(FunctionState::Other),
         }
     }
 
@@ -141,7 +147,7 @@ where
                     error_into!(self.errors, self.reader.try_read_macro_call(&self.macros))?
                 {
                     self.macro_calls.insert(m.span().start(), m.clone());
-                    self.expanded_tokens = error_into!(self.errors, self.expand_macro(m))?;
+                    self.expanded_tokens = error_into!(self.errors, self.expand_macro(m, 0))?;
                     continue;
                 }
             }
@@ -162,11 +168,19 @@ where
         Ok(None)
     }
 
-    fn expand_macro(&self, call: MacroCall) -> PResult<VecDeque<LexicalToken>> {
+    /// Expands a macro call, `depth` is the number of expansions the
+    /// call is nested within.
+    fn expand_macro(&self, call: MacroCall, depth: usize) -> PResult<VecDeque<LexicalToken>> {
+        if depth >= self.macro_expansion_limit {
+            return Err(PreprocessorError::MacroRecursion {
+                call,
+                limit: self.macro_expansion_limit,
+            });
+        }
         if let Some(expanded) = self.try_expand_predefined_macro(&call)? {
             Ok(vec![expanded].into())
         } else {
-            self.expand_userdefined_macro(call)
+            self.expand_userdefined_macro(call, depth)
         }
     }
 
@@ -202,7 +216,11 @@ where
         Ok(Some(expanded))
     }
 
-    fn expand_userdefined_macro(&self, call: MacroCall) -> PResult<VecDeque<LexicalToken>> {
+    fn expand_userdefined_macro(
+        &self,
+        call: MacroCall,
+        depth: usize,
+    ) -> PResult<VecDeque<LexicalToken>> {
         let definition = match self.macros.get(&call) {
             None => return Err(PreprocessorError::UndefinedMacro { call }),
             Some(def) => def,
@@ -247,7 +265,7 @@ where
                             .flat_map(|i| i.iter().map(|a| &a.tokens[..])),
                     )
                     .collect::<HashMap<_, _>>();
-                let expanded = self.expand_replacement(bindings, &def.replacement)?;
+                let expanded = self.expand_replacement(bindings, &def.replacement, depth)?;
                 Ok(expanded)
             }
             MacroDef::DelayedSubstitution(subst) => Ok(vec![LexicalToken(
@@ -263,6 +281,7 @@ where
         &self,
         bindings: HashMap<Symbol, &[LexicalToken]>,
         replacement: &[LexicalToken],
+        depth: usize,
     ) -> PResult<VecDeque<LexicalToken>> {
         let mut expanded = VecDeque::new();
         let replacement_tokens: VecDeque<_> = replacement.iter().map(|t| Ok(t.clone())).collect();
@@ -270,7 +289,7 @@ where
 
         loop {
             if let Some(call) = reader.try_read_macro_call(&self.macros)? {
-                let nested = self.expand_macro(call)?;
+                let nested = self.expand_macro(call, depth + 1)?;
                 for token in nested.into_iter().rev() {
                     reader.unread_token(token);
                 }
@@ -281,17 +300,18 @@ where
                     }
                     Some(tokens) => tokens,
                 };
-                let string = tokens.iter().map(|t| t.to_string()).collect::<String>();
-                let span = tokens[0].span();
-                let start = span.start();
-                let end = span.end();
-                let token = (start, Token::String(Symbol::intern(&string)), end);
+                let string = self.stringify_tokens(tokens);
+                let span = tokens
+                    .first()
+                    .map(|t| t.span())
+                    .unwrap_or_else(|| stringify.span());
+                let token = (span.start(), Token::String(Symbol::intern(&string)), span.end());
                 expanded.push_back(token.into());
             } else if let Some(token) = reader.try_read_token()? {
                 match IdentToken::try_from(token.clone()) {
                     Ok(ident) => match bindings.get(&ident.symbol()) {
                         Some(value) => {
-                            let nested = self.expand_replacement(HashMap::new(), value)?;
+                            let nested = self.expand_replacement(HashMap::new(), value, depth)?;
                             expanded.extend(nested);
                             continue;
                         }
@@ -307,6 +327,44 @@ where
         Ok(expanded)
     }
 
+    /// Produces the text of a macro argument for `??Arg`.
+    ///
+    /// When the argument tokens follow each other in a single source file,
+    /// separated by nothing but whitespace, the original source text is
+    /// used verbatim, so `?str(a + b)` yields `"a + b"`. Otherwise (e.g.
+    /// tokens from a nested expansion, or a comment between them), the
+    /// tokens are joined with single spaces so adjacent tokens never merge.
+    fn stringify_tokens(&self, tokens: &[LexicalToken]) -> String {
+        let (first, last) = match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return String::new(),
+        };
+
+        let adjacent = tokens.windows(2).all(|pair| {
+            let (prev, next) = (pair[0].span(), pair[1].span());
+            if prev.source_id() != next.source_id() || prev.end_index() > next.start_index() {
+                return false;
+            }
+            let gap = SourceSpan::new(prev.end(), next.start());
+            match self.codemap.source_slice(gap.source_id(), gap.as_span()) {
+                Some(Ok(text)) => text.trim().is_empty(),
+                _ => false,
+            }
+        });
+        if adjacent {
+            let span = SourceSpan::new(first.span().start(), last.span().end());
+            if let Some(Ok(slice)) = self.codemap.source_slice(span.source_id(), span.as_span()) {
+                return slice.to_string();
+            }
+        }
+
+        tokens
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     fn try_read_directive(&mut self) -> Result<Option<Directive>, ()> {
         let directive: Directive =
             if let Some(directive) = error_into!(self.errors, self.reader.try_read())? {
@@ -337,7 +395,7 @@ where
             Directive::IncludeLib(ref d) if !ignore => {
                 let path = error_into!(
                     self.errors,
-                    d.include_lib(&self.code_paths)
+                    d.include_lib(&self.include_paths, &self.code_paths)
                         .context(errors::BadDirective)
                 )?;
                 error_into!(self.errors, self.reader.inject_include(path))?;
@@ -518,8 +576,130 @@ where
         match self.next_token() {
             Err(()) => Some(Err(())),
             Ok(None) => None,
-            Ok(Some(token)) => Some(Ok(token.into())),
+            Ok(Some(token)) => {
+                let token = self.function.substitute(token);
+                self.function.observe(&token.1);
+                Some(Ok(token.into()))
+            }
+        }
+    }
+}
+
+/// Tracks the function whose tokens are being read, so that
+/// `?FUNCTION_NAME` and `?FUNCTION_ARITY` can be resolved while
+/// streaming. A function clause is a form starting with an atom and a
+/// parenthesized head, whose top level commas give the arity.
+#[derive(Debug)]
+struct FunctionContext {
+    state: FunctionState,
+    record: RecordAccess,
+}
+
+/// Where the tokens read are in a function clause.
+#[derive(Debug)]
+enum FunctionState {
+    /// At the start of a form.
+    FormStart,
+    /// Read the name of a function clause, the head should follow.
+    Name(Symbol),
+    /// Inside the head of a function clause.
+    Head {
+        name: Symbol,
+        depth: usize,
+        arity: usize,
+    },
+    /// Past the head, in the body of the function.
+    Body { name: Symbol, arity: usize },
+    /// Inside a form that is not a function.
+    Other,
+}
+
+/// Progress through `#name.`, whose dot is a record field access
+/// rather than the end of a form.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RecordAccess {
+    None,
+    Pound,
+    Name,
+}
+
+impl FunctionContext {
+    fn new(state: FunctionState) -> Self {
+        FunctionContext {
+            state,
+            record: RecordAccess::None,
+        }
+    }
+
+    fn substitute(&self, token: LexicalToken) -> LexicalToken {
+        let (name, arity) = match self.state {
+            FunctionState::Body { name, arity } => (name, arity),
+            _ => return token,
+        };
+        let LexicalToken(start, tok, end) = token;
+        let tok = match tok {
+            Token::DelayedSubstitution(DelayedSubstitution::FunctionName) => Token::Atom(name),
+            Token::DelayedSubstitution(DelayedSubstitution::FunctionArity) => {
+                Token::Integer((arity as i64).into())
+            }
+            tok => tok,
+        };
+        LexicalToken(start, tok, end)
+    }
+
+    fn observe(&mut self, token: &Token) {
+        let record = self.record;
+        self.record = match (record, token) {
+            (_, Token::Pound) => RecordAccess::Pound,
+            (RecordAccess::Pound, Token::Atom(_)) => RecordAccess::Name,
+            _ => RecordAccess::None,
+        };
+        if let Token::Dot = token {
+            if record != RecordAccess::Name {
+                self.state = FunctionState::FormStart;
+                return;
+            }
         }
+        self.state = match std::mem::replace(&mut self.state, FunctionState::Other) {
+            FunctionState::FormStart => match token {
+                Token::Atom(name) => FunctionState::Name(*name),
+                _ => FunctionState::Other,
+            },
+            FunctionState::Name(name) => match token {
+                Token::LParen => FunctionState::Head {
+                    name,
+                    depth: 1,
+                    arity: 0,
+                },
+                _ => FunctionState::Other,
+            },
+            FunctionState::Head {
+                name,
+                mut depth,
+                mut arity,
+            } => {
+                match token {
+                    Token::LParen | Token::LBrace | Token::LBracket | Token::BinaryStart => {
+                        depth += 1
+                    }
+                    Token::RParen | Token::RBrace | Token::RBracket | Token::BinaryEnd => {
+                        depth -= 1
+                    }
+                    Token::Comma if depth == 1 => arity += 1,
+                    _ => (),
+                }
+                if arity == 0 && depth > 0 {
+                    // The first token of the first parameter
+                    arity = 1;
+                }
+                if depth == 0 {
+                    FunctionState::Body { name, arity }
+                } else {
+                    FunctionState::Head { name, depth, arity }
+                }
+            }
+            other => other,
+        };
     }
 }
 
@@ -0,0 +1,67 @@
+use libeir_diagnostics::SourceSpan;
+use libeir_intern::{Ident, Symbol};
+use libeir_ir::{FunctionIdent, Module};
+
+use libeir_interpreter::{Term, VMState};
+
+#[test]
+fn test_call_flow_chain() {
+    let _ = env_logger::try_init();
+
+    let mut module = Module::new(Ident::from_str("woo"));
+    {
+        let fun_def = module.add_function(SourceSpan::UNKNOWN, Ident::from_str("woo"), 1);
+        let fun = fun_def.function_mut();
+        let mut b = fun.builder();
+
+        let (entry, entry_args) = b.create_entry(1);
+        let ret = entry_args[0];
+        let arg = entry_args[2];
+
+        let mut clauses = Vec::new();
+        for (pattern, result) in [(Some(1), "one"), (Some(2), "two"), (None, "other")].iter() {
+            let check = b.block_insert();
+            let check_val = b.block_arg_insert(check);
+            let check_body = b.block_arg_insert(check);
+            let check_next = b.block_arg_insert(check);
+
+            if let Some(pattern) = pattern {
+                let pattern_val = b.value(*pattern as i64);
+
+                let mut match_builder = b.op_match_build(SourceSpan::UNKNOWN);
+                let matched = match_builder.push_value(pattern_val, &mut b);
+                let no_match = match_builder.push_wildcard(SourceSpan::UNKNOWN, &mut b);
+                match_builder.finish(check, check_val, &mut b);
+
+                b.op_call_flow(matched, check_body, &[]);
+                b.op_call_flow(no_match, check_next, &[]);
+            } else {
+                b.op_call_flow(check, check_body, &[]);
+            }
+
+            let body = b.block_insert();
+            let result_val = b.value(Symbol::intern(result));
+            b.op_call_flow(body, ret, &[result_val]);
+
+            clauses.push((check, body));
+        }
+
+        let no_match = b.op_call_flow_chain(entry, arg, &clauses);
+        let no_match_val = b.value(Symbol::intern("no_match"));
+        b.op_call_flow(no_match, ret, &[no_match_val]);
+    }
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(module);
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("woo"),
+        arity: 1,
+    };
+
+    assert!(*vm.call(&fun, &[Term::new_i64(1)]).unwrap() == Term::new_atom("one"));
+    assert!(*vm.call(&fun, &[Term::new_i64(2)]).unwrap() == Term::new_atom("two"));
+    assert!(*vm.call(&fun, &[Term::new_i64(3)]).unwrap() == Term::new_atom("other"));
+}
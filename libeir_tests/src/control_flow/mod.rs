@@ -1,5 +1,7 @@
 mod fib;
 //mod nth_root;
 mod accumulate_list;
+mod call_flow_chain;
 mod get_values;
+mod nonterminating;
 mod shadowing;
@@ -0,0 +1,33 @@
+use libeir_intern::Symbol;
+use libeir_passes::PassManager;
+use libeir_syntax_erl::ParseConfig;
+
+use crate::lower;
+
+#[test]
+fn test_trivial_nonterminating() {
+    let _ = env_logger::try_init();
+
+    let mut eir_mod = lower(
+        "-module(woo).
+
+loop(X) -> loop(X).
+count(X) -> count(X + 1).
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    let looping = eir_mod.name_arity_index(Symbol::intern("loop"), 1).unwrap();
+    let fun = eir_mod[looping].function();
+    assert!(fun.detect_trivial_nonterminating().len() == 1);
+
+    let counting = eir_mod
+        .name_arity_index(Symbol::intern("count"), 1)
+        .unwrap();
+    let fun = eir_mod[counting].function();
+    assert!(fun.detect_trivial_nonterminating().is_empty());
+}
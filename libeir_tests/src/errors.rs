@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use super::lower;
 
 use libeir_intern::{Ident, Symbol};
@@ -5,7 +7,7 @@ use libeir_ir::FunctionIdent;
 use libeir_passes::PassManager;
 use libeir_syntax_erl::ParseConfig;
 
-use libeir_interpreter::{Term, VMState};
+use libeir_interpreter::{ErlEq, Term, VMState};
 
 #[test]
 fn test_basic_catch() {
@@ -88,3 +90,215 @@ end.
     );
     assert!(vm.call(&fun, &[1.into()]).is_err());
 }
+
+#[test]
+fn test_function_clause_mfa() {
+    let _ = env_logger::try_init();
+
+    let mut eir_mod = lower(
+        "
+-module(woo).
+
+woo(a, 1) -> one;
+woo(b, 2) -> two.
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("woo"),
+        arity: 2,
+    };
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(eir_mod);
+
+    let err = vm
+        .call(&fun, &[Term::new_atom("a"), Term::new_i64(2)])
+        .err()
+        .unwrap();
+    assert!(err.class.erl_eq(&Term::new_atom("error")));
+    assert!(err.reason.erl_eq(&Term::new_atom("function_clause")));
+
+    // The function that has no matching clause is the top of the
+    // stacktrace
+    let frame = Term::Tuple(vec![
+        Term::new_atom("woo").into(),
+        Term::new_atom("woo").into(),
+        Term::new_i64(2).into(),
+        Term::Nil.into(),
+    ]);
+    let trace = Term::as_list(&err.stacktrace).unwrap();
+    assert!(trace[0].erl_eq(&frame));
+}
+
+fn catch_class_vm() -> VMState {
+    let mut eir_mod = lower(
+        "
+-module(woo).
+
+raise(throw, R) -> throw(R);
+raise(error, R) -> erlang:error(R).
+
+bare(C) -> try raise(C, boom) catch
+    R -> {caught, R}
+end.
+
+any(C) -> try raise(C, boom) catch
+    _:R -> {caught, R}
+end.
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(eir_mod);
+    vm
+}
+
+#[test]
+fn test_catch_default_class() {
+    let _ = env_logger::try_init();
+
+    let mut vm = catch_class_vm();
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("bare"),
+        arity: 1,
+    };
+    let caught = Term::Tuple(vec![
+        Term::new_atom("caught").into(),
+        Term::new_atom("boom").into(),
+    ]);
+
+    let res = vm.call(&fun, &[Term::new_atom("throw")]).unwrap();
+    assert!(res.erl_eq(&caught));
+
+    let err = vm.call(&fun, &[Term::new_atom("error")]).err().unwrap();
+    assert!(err.class.erl_eq(&Term::new_atom("error")));
+    assert!(err.reason.erl_eq(&Term::new_atom("boom")));
+}
+
+#[test]
+fn test_catch_any_class() {
+    let _ = env_logger::try_init();
+
+    let mut vm = catch_class_vm();
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("any"),
+        arity: 1,
+    };
+    let caught = Term::Tuple(vec![
+        Term::new_atom("caught").into(),
+        Term::new_atom("boom").into(),
+    ]);
+
+    for class in &["throw", "error"] {
+        let res = vm.call(&fun, &[Term::new_atom(class)]).unwrap();
+        assert!(res.erl_eq(&caught));
+    }
+}
+
+#[test]
+fn test_catch_stacktrace() {
+    let _ = env_logger::try_init();
+
+    let mut eir_mod = lower(
+        "
+-module(woo).
+
+inner(X) ->
+    {ok, V} = X,
+    V.
+
+outer(X) ->
+    R = inner(X),
+    {R}.
+
+caught(X) -> catch outer(X).
+
+stack(X) ->
+    try outer(X) catch
+        error:_:Stack -> Stack
+    end.
+
+thrown() -> catch throw(foo).
+
+exited() -> catch exit(bye).
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(eir_mod);
+
+    let ident = |name: &str, arity: usize| FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str(name),
+        arity,
+    };
+    let frame = |name: &str, arity: i64| -> Rc<Term> {
+        Term::Tuple(vec![
+            Term::new_atom("woo").into(),
+            Term::new_atom(name).into(),
+            Term::new_i64(arity).into(),
+            Term::Nil.into(),
+        ])
+        .into()
+    };
+
+    // `error` is wrapped with the stack of the calls that were active.
+    let res = vm
+        .call(&ident("caught", 1), &[Term::new_atom("bad")])
+        .unwrap();
+    let reason = Term::Tuple(vec![
+        Term::new_atom("badmatch").into(),
+        Term::new_atom("bad").into(),
+    ]);
+    let stack = Term::slice_to_list(
+        &[frame("inner", 1), frame("outer", 1), frame("caught", 1)],
+        Term::Nil.into(),
+    );
+    let expected = Term::Tuple(vec![
+        Term::new_atom("EXIT").into(),
+        Term::Tuple(vec![reason.into(), stack]).into(),
+    ]);
+    assert!(res.erl_eq(&expected));
+
+    let res = vm
+        .call(&ident("stack", 1), &[Term::new_atom("bad")])
+        .unwrap();
+    let stack = Term::slice_to_list(
+        &[frame("inner", 1), frame("outer", 1), frame("stack", 1)],
+        Term::Nil.into(),
+    );
+    assert!(res.erl_eq(&*stack));
+
+    // `throw` is not wrapped, `exit` has no stack.
+    let res = vm.call(&ident("thrown", 0), &[]).unwrap();
+    assert!(res.erl_eq(&Term::new_atom("foo")));
+
+    let res = vm.call(&ident("exited", 0), &[]).unwrap();
+    let expected = Term::Tuple(vec![
+        Term::new_atom("EXIT").into(),
+        Term::new_atom("bye").into(),
+    ]);
+    assert!(res.erl_eq(&expected));
+}
@@ -0,0 +1,50 @@
+use crate::lower;
+
+use libeir_intern::Ident;
+use libeir_ir::FunctionIdent;
+use libeir_passes::PassManager;
+use libeir_syntax_erl::ParseConfig;
+
+use libeir_interpreter::{Term, VMState};
+
+use std::rc::Rc;
+
+fn string_list(string: &str) -> Rc<Term> {
+    let chars: Vec<Rc<Term>> = string
+        .chars()
+        .map(|c| Term::new_i64(c as i64).into())
+        .collect();
+    Term::slice_to_list(&chars, Term::Nil.into())
+}
+
+#[test]
+fn test_float_to_list_short() {
+    let _ = env_logger::try_init();
+
+    let mut eir_mod = lower(
+        "-module(woo).
+
+fmt(F) -> float_to_list(F, [short]).
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(eir_mod);
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("fmt"),
+        arity: 1,
+    };
+
+    for (num, expected) in &[(0.1, "0.1"), (1.0, "1.0"), (1.0e21, "1.0e21"), (-0.0, "-0.0")] {
+        let res = vm.call(&fun, &[Term::Float((*num).into())]).unwrap();
+        assert!(res == string_list(expected));
+    }
+}
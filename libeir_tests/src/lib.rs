@@ -2,21 +2,27 @@
 #![cfg(test)]
 
 use std::path::Path;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use libeir_diagnostics::*;
-use libeir_ir::{FunctionIdent, Module};
-use libeir_syntax_erl::lower_module;
+use libeir_ir::{Function, FunctionIdent, Module};
+use libeir_syntax_erl::ast;
+use libeir_syntax_erl::{lower_module, lower_module_lazy, LazyLowerer};
 use libeir_syntax_erl::{ErlangError, Parse, ParseConfig, Parser, ParserError};
 use libeir_util_parse::{error_tee, Errors};
 
 mod control_flow;
 mod ct_runner;
 mod errors;
+mod floats;
 mod list_comprehensions;
+mod lists;
 mod otp;
 mod patterns;
 mod records;
+mod text_round_trip;
+mod vm;
 
 fn lower_file<S>(path: S, config: ParseConfig) -> Result<Module, ()>
 where
@@ -54,6 +60,27 @@ where
     eir_res
 }
 
+/// Like `lower`, but the functions of the module are only lowered when
+/// first needed. `post_lower` is run on each function once it is lowered.
+pub fn lower_lazy<S, F>(input: S, config: ParseConfig, post_lower: F) -> Result<Module, ()>
+where
+    S: AsRef<str>,
+    F: Fn(&mut Function) + 'static,
+{
+    let mut errors: Errors<ErlangError, ErlangError> = Errors::new();
+    let codemap = Arc::new(CodeMap::new());
+    let ast_res: Result<ast::Module, ()> = error_tee(&mut errors, |mut errors| {
+        let parser = Parser::new(config, codemap.clone());
+        parser.parse_string(&mut errors.make_into_adapter(), input)
+    });
+
+    errors.print(&codemap);
+
+    let mut lowerer = LazyLowerer::new(codemap, ast_res?);
+    lowerer.set_post_lower(post_lower);
+    Ok(lower_module_lazy(Rc::new(lowerer)))
+}
+
 pub fn write_dot(module: &Module, ident: Option<FunctionIdent>) {
     if let Some(ident) = ident {
         let idx = module.ident_index(&ident).unwrap();
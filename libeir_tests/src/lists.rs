@@ -0,0 +1,327 @@
+use crate::lower;
+
+use libeir_intern::Ident;
+use libeir_ir::FunctionIdent;
+use libeir_passes::PassManager;
+use libeir_syntax_erl::ParseConfig;
+
+use libeir_interpreter::{Term, VMState};
+
+use std::rc::Rc;
+
+fn lists_vm(source: &str) -> VMState {
+    let mut eir_mod = lower(source, ParseConfig::default()).unwrap();
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(eir_mod);
+    vm
+}
+
+fn woo_fun(name: &str, arity: usize) -> FunctionIdent {
+    FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str(name),
+        arity,
+    }
+}
+
+fn int_list(items: &[i64]) -> Rc<Term> {
+    let items: Vec<Rc<Term>> = items.iter().map(|i| Term::new_i64(*i).into()).collect();
+    Term::slice_to_list(&items, Term::Nil.into())
+}
+
+fn atom_list(items: &[&str]) -> Rc<Term> {
+    let items: Vec<Rc<Term>> = items.iter().map(|i| Term::new_atom(i).into()).collect();
+    Term::slice_to_list(&items, Term::Nil.into())
+}
+
+#[test]
+fn test_zip_unzip() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+zip(A, B) -> lists:zip(A, B).
+unzip(L) -> lists:unzip(L).
+",
+    );
+
+    let zipped = vm
+        .call(
+            &woo_fun("zip", 2),
+            &[
+                (*int_list(&[1, 2, 3])).clone(),
+                (*atom_list(&["a", "b", "c"])).clone(),
+            ],
+        )
+        .unwrap();
+
+    let expected: Vec<Rc<Term>> = vec![
+        Term::Tuple(vec![Term::new_i64(1).into(), Term::new_atom("a").into()]).into(),
+        Term::Tuple(vec![Term::new_i64(2).into(), Term::new_atom("b").into()]).into(),
+        Term::Tuple(vec![Term::new_i64(3).into(), Term::new_atom("c").into()]).into(),
+    ];
+    assert!(zipped == Term::slice_to_list(&expected, Term::Nil.into()));
+
+    let unzipped = vm
+        .call(&woo_fun("unzip", 1), &[(*zipped).clone()])
+        .unwrap();
+    let expected = Term::Tuple(vec![int_list(&[1, 2, 3]), atom_list(&["a", "b", "c"])]);
+    assert!(*unzipped == expected);
+
+    assert!(vm
+        .call(
+            &woo_fun("zip", 2),
+            &[(*int_list(&[1, 2])).clone(), (*atom_list(&["a"])).clone()],
+        )
+        .is_err());
+}
+
+#[test]
+fn test_nth() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+nth(N, L) -> lists:nth(N, L).
+",
+    );
+
+    let res = vm
+        .call(
+            &woo_fun("nth", 2),
+            &[Term::new_i64(2), (*atom_list(&["a", "b", "c"])).clone()],
+        )
+        .unwrap();
+    assert!(*res == Term::new_atom("b"));
+
+    assert!(vm
+        .call(
+            &woo_fun("nth", 2),
+            &[Term::new_i64(4), (*atom_list(&["a", "b", "c"])).clone()],
+        )
+        .is_err());
+}
+
+#[test]
+fn test_filter() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+filter() -> lists:filter(fun(X) -> X > 1 end, [1, 2, 3]).
+",
+    );
+
+    let res = vm.call(&woo_fun("filter", 0), &[]).unwrap();
+    assert!(res == int_list(&[2, 3]));
+}
+
+#[test]
+fn test_all_any() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+all(L) -> lists:all(fun(X) -> X > 0 end, L).
+any(L) -> lists:any(fun(X) -> X > 2 end, L).
+",
+    );
+
+    let res = vm
+        .call(&woo_fun("all", 1), &[(*int_list(&[1, 2, 3])).clone()])
+        .unwrap();
+    assert!(res.as_boolean() == Some(true));
+
+    let res = vm
+        .call(&woo_fun("all", 1), &[(*int_list(&[1, 0])).clone()])
+        .unwrap();
+    assert!(res.as_boolean() == Some(false));
+
+    let res = vm.call(&woo_fun("all", 1), &[Term::Nil]).unwrap();
+    assert!(res.as_boolean() == Some(true));
+
+    let res = vm
+        .call(&woo_fun("any", 1), &[(*int_list(&[1, 2, 3])).clone()])
+        .unwrap();
+    assert!(res.as_boolean() == Some(true));
+
+    let res = vm.call(&woo_fun("any", 1), &[Term::Nil]).unwrap();
+    assert!(res.as_boolean() == Some(false));
+}
+
+#[test]
+fn test_foldr() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+foldr() -> lists:foldr(fun(X, Acc) -> [X | Acc] end, [], [1, 2, 3]).
+",
+    );
+
+    let res = vm.call(&woo_fun("foldr", 0), &[]).unwrap();
+    assert!(res == int_list(&[1, 2, 3]));
+}
+
+#[test]
+fn test_mapfoldl() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+mapfoldl() -> lists:mapfoldl(fun(X, Sum) -> {X + 1, Sum + X} end, 0, [1, 2, 3]).
+",
+    );
+
+    let res = vm.call(&woo_fun("mapfoldl", 0), &[]).unwrap();
+    let expected = Term::Tuple(vec![int_list(&[2, 3, 4]), Term::new_i64(6).into()]);
+    assert!(*res == expected);
+}
+
+#[test]
+fn test_partition() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+partition() -> lists:partition(fun(X) -> X > 2 end, [1, 3, 2, 4]).
+",
+    );
+
+    let res = vm.call(&woo_fun("partition", 0), &[]).unwrap();
+    let expected = Term::Tuple(vec![int_list(&[3, 4]), int_list(&[1, 2])]);
+    assert!(*res == expected);
+}
+
+fn pair(key: i64, val: &str) -> Rc<Term> {
+    Term::Tuple(vec![Term::new_i64(key).into(), Term::new_atom(val).into()]).into()
+}
+
+#[test]
+fn test_keysort() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+keysort() -> lists:keysort(1, [{3, c}, {1, a}, {2, b}, {1, z}]).
+",
+    );
+
+    let res = vm.call(&woo_fun("keysort", 0), &[]).unwrap();
+    let expected = [pair(1, "a"), pair(1, "z"), pair(2, "b"), pair(3, "c")];
+    assert!(res == Term::slice_to_list(&expected, Term::Nil.into()));
+}
+
+#[test]
+fn test_usort_umerge() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+usort() -> lists:usort([3, 1, 2, 1]).
+umerge() -> lists:umerge([1, 3, 5], [2, 3, 4, 6]).
+",
+    );
+
+    let res = vm.call(&woo_fun("usort", 0), &[]).unwrap();
+    assert!(res == int_list(&[1, 2, 3]));
+
+    let res = vm.call(&woo_fun("umerge", 0), &[]).unwrap();
+    assert!(res == int_list(&[1, 2, 3, 4, 5, 6]));
+}
+
+#[test]
+fn test_keyreplace_keydelete() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+replace(Key) -> lists:keyreplace(Key, 1, [{1, a}, {2, b}], {Key, new}).
+delete(Key) -> lists:keydelete(Key, 1, [{1, a}, {2, b}, {2, c}]).
+",
+    );
+
+    let res = vm
+        .call(&woo_fun("replace", 1), &[Term::new_i64(2)])
+        .unwrap();
+    let expected = [pair(1, "a"), pair(2, "new")];
+    assert!(res == Term::slice_to_list(&expected, Term::Nil.into()));
+
+    let res = vm
+        .call(&woo_fun("replace", 1), &[Term::new_i64(3)])
+        .unwrap();
+    let expected = [pair(1, "a"), pair(2, "b")];
+    assert!(res == Term::slice_to_list(&expected, Term::Nil.into()));
+
+    let res = vm
+        .call(&woo_fun("delete", 1), &[Term::new_i64(2)])
+        .unwrap();
+    let expected = [pair(1, "a"), pair(2, "c")];
+    assert!(res == Term::slice_to_list(&expected, Term::Nil.into()));
+}
+
+#[test]
+fn test_duplicate_last_droplast() {
+    let _ = env_logger::try_init();
+
+    let mut vm = lists_vm(
+        "-module(woo).
+
+duplicate(N, X) -> lists:duplicate(N, X).
+last(L) -> lists:last(L).
+droplast(L) -> lists:droplast(L).
+",
+    );
+
+    let res = vm
+        .call(
+            &woo_fun("duplicate", 2),
+            &[Term::new_i64(3), Term::new_atom("x")],
+        )
+        .unwrap();
+    assert!(res == atom_list(&["x", "x", "x"]));
+
+    assert!(vm
+        .call(
+            &woo_fun("duplicate", 2),
+            &[Term::new_i64(-1), Term::new_atom("x")],
+        )
+        .is_err());
+
+    let res = vm
+        .call(&woo_fun("last", 1), &[(*int_list(&[1, 2, 3])).clone()])
+        .unwrap();
+    assert!(*res == Term::new_i64(3));
+
+    let exception = vm.call(&woo_fun("last", 1), &[Term::Nil]).unwrap_err();
+    assert!(*exception.reason == Term::new_atom("function_clause"));
+
+    let res = vm
+        .call(&woo_fun("droplast", 1), &[(*int_list(&[1, 2, 3])).clone()])
+        .unwrap();
+    assert!(res == int_list(&[1, 2]));
+
+    let exception = vm.call(&woo_fun("droplast", 1), &[Term::Nil]).unwrap_err();
+    assert!(*exception.reason == Term::new_atom("function_clause"));
+
+    let improper = Term::slice_to_list(&[Term::new_i64(1).into()], Term::new_i64(2).into());
+    let exception = vm
+        .call(&woo_fun("droplast", 1), &[(*improper).clone()])
+        .unwrap_err();
+    assert!(*exception.reason == Term::new_atom("function_clause"));
+}
@@ -200,9 +200,9 @@ woo(def) -> 2.
 
     {
         let res = vm.call(&fun, &[Term::Nil.into()]).err().unwrap();
-        assert!(res.0.erl_eq(&Term::Atom(Symbol::intern("error")).into()));
+        assert!(res.class.erl_eq(&Term::Atom(Symbol::intern("error")).into()));
         assert!(res
-            .1
+            .reason
             .erl_eq(&Term::Atom(Symbol::intern("function_clause")).into()));
     }
 }
@@ -256,8 +256,8 @@ end.
             .call(&fun, &[Term::Atom(Symbol::intern("aaa")).into()])
             .err()
             .unwrap();
-        assert!(res.0.erl_eq(&Term::Atom(Symbol::intern("error")).into()));
-        assert!(res.1.erl_eq(&Term::Tuple(vec![
+        assert!(res.class.erl_eq(&Term::Atom(Symbol::intern("error")).into()));
+        assert!(res.reason.erl_eq(&Term::Tuple(vec![
             Term::Atom(Symbol::intern("case_clause")).into(),
             Term::Atom(Symbol::intern("aaa")).into(),
         ])));
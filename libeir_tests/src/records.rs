@@ -163,3 +163,62 @@ create_1(A) -> #person{name=A}.
     vm.add_builtin_modules();
     vm.add_erlang_module(eir_mod);
 }
+
+#[test]
+fn record_typed_default() {
+    let _ = env_logger::try_init();
+
+    let mut eir_mod = lower(
+        "
+-module(woo).
+
+-record(state, {count = 0 :: integer(), name}).
+
+new(Name) -> #state{name=Name}.
+bump(S) -> S#state{count = S#state.count + 1}.
+read(S) -> {S#state.count, S#state.name}.
+
+run(Name) -> read(bump(new(Name))).
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(eir_mod);
+
+    {
+        let fun = FunctionIdent {
+            module: Ident::from_str("woo"),
+            name: Ident::from_str("new"),
+            arity: 1,
+        };
+
+        let out = Term::Tuple(vec![
+            Term::Atom(Symbol::intern("state")).into(),
+            Term::Integer(0.into()).into(),
+            Term::Atom(Symbol::intern("foo")).into(),
+        ]);
+        let res = vm.call(&fun, &[Term::new_atom("foo")]).unwrap();
+        assert!(res.erl_eq(&out));
+    }
+
+    {
+        let fun = FunctionIdent {
+            module: Ident::from_str("woo"),
+            name: Ident::from_str("run"),
+            arity: 1,
+        };
+
+        let out = Term::Tuple(vec![
+            Term::Integer(1.into()).into(),
+            Term::Atom(Symbol::intern("foo")).into(),
+        ]);
+        let res = vm.call(&fun, &[Term::new_atom("foo")]).unwrap();
+        assert!(res.erl_eq(&out));
+    }
+}
@@ -0,0 +1,182 @@
+//! Checks that every function lowered from Erlang survives printing to
+//! text and parsing back, optionally after the default passes have run,
+//! and how the patterns of `case` operations are printed.
+
+use std::sync::Arc;
+
+use libeir_diagnostics::CodeMap;
+use libeir_ir::{Function, Module};
+use libeir_passes::PassManager;
+use libeir_syntax_erl::ParseConfig;
+
+use super::lower;
+
+fn assert_function_round_trip(fun: &Function) {
+    let ident = fun.ident();
+    let text = format!(
+        "a'{}':a'{}'/{} {{\n{}}}\n",
+        ident.module,
+        ident.name,
+        ident.arity,
+        fun.to_text_standard()
+    );
+
+    let codemap = Arc::new(CodeMap::new());
+    let parsed = match libeir_ir::text::parser::function_codemap(&text, codemap.clone()) {
+        (Ok(parsed), _errors) => parsed,
+        (Err(()), errors) => {
+            errors.print(&codemap);
+            panic!("failed to parse printed function {}:\n{}", ident, text);
+        }
+    };
+
+    if let Err(err) = fun.graph_eq(fun.block_entry(), &parsed, parsed.block_entry()) {
+        panic!("round trip mismatch for {} ({:?}):\n{}", ident, err, text);
+    }
+}
+
+fn assert_module_round_trip(module: &Module) {
+    for fun_def in module.function_iter() {
+        assert_function_round_trip(fun_def.function());
+    }
+}
+
+#[test]
+fn round_trip_simple_module() {
+    let _ = env_logger::try_init();
+
+    let eir_mod = lower(
+        "
+-module(woo).
+
+identity(A) -> A.
+
+swap({A, B}) -> {B, A}.
+
+cons(H, T) -> [H | T].
+
+call(A) -> erlang:element(1, A).
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    assert_module_round_trip(&eir_mod);
+}
+
+#[test]
+fn round_trip_case_patterns() {
+    let _ = env_logger::try_init();
+
+    let eir_mod = lower(
+        "
+-module(woo).
+
+kind(ok) -> atom;
+kind(1) -> one;
+kind({A, _}) -> A;
+kind([H | _]) -> H;
+kind([]) -> nil;
+kind(_) -> other.
+
+same(A, A) -> true;
+same(_, _) -> false.
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    assert_module_round_trip(&eir_mod);
+}
+
+#[test]
+fn round_trip_arithmetic_maps_type_checks() {
+    let _ = env_logger::try_init();
+
+    let mut eir_mod = lower(
+        "
+-module(woo).
+
+area({square, S}) when is_integer(S), S > 0 -> S * S;
+area({rect, W, H}) when is_number(W) andalso is_number(H) -> W * H;
+area(_) -> 0.
+
+sum(A, B) -> A + B - 1.
+
+classify(X) when 0 < X, X =< 10 -> small;
+classify(X) when is_float(X); is_list(X) -> other;
+classify(X) when X >= 10, X /= 20, X =/= 30 -> big;
+classify(_) -> none.
+
+new(K, V) -> #{K => V, size => 1}.
+
+update(M, V) -> M#{size := V}.
+
+size_of(#{size := S}, K) -> {S, K}.
+
+double(L) -> [X * 2 || X <- L, is_integer(X)].
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    assert_module_round_trip(&eir_mod);
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    assert_module_round_trip(&eir_mod);
+}
+
+#[test]
+fn print_case_patterns() {
+    let _ = env_logger::try_init();
+
+    let eir_mod = lower(
+        "
+-module(woo).
+
+kind(A) ->
+    case A of
+        {ok, B} -> B;
+        [H | _] -> H;
+        #{key := V} -> V;
+        <<X:8, _/binary>> -> X;
+        <<S:8, D:S/binary>> -> {S, D};
+        <<\"abc\">> -> abc;
+        _ -> none
+    end.
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    assert_module_round_trip(&eir_mod);
+
+    let fun = eir_mod
+        .function_iter()
+        .map(|fun_def| fun_def.function())
+        .find(|fun| fun.ident().name.as_str().get() == "kind")
+        .unwrap();
+    let text = fun.to_text_standard();
+
+    let patterns: Vec<&str> = text
+        .lines()
+        .filter_map(|line| line.find(" guard ").map(|idx| line[..idx].trim()))
+        .collect();
+    let expected = [
+        "{a'ok', bind0@_}",
+        "[bind0@_ | _]",
+        "%{value a'key' => bind0@_}",
+        "binary integer(unsigned,big,1) value 8 (bind0@_ | binary bytes(8) (_ | binary[]))",
+        "binary integer(unsigned,big,1) value 8 (bind0@_ | \
+         binary bytes(8) bind0 (bind1@_ | binary[]))",
+        "binary[97, 98, 99]",
+        "_",
+    ];
+    assert!(
+        patterns.windows(expected.len()).any(|w| w == expected),
+        "{}",
+        text
+    );
+}
@@ -0,0 +1,1362 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::{lower, lower_lazy};
+
+use libeir_diagnostics::SourceSpan;
+use libeir_intern::Ident;
+use libeir_ir::{FunctionIdent, Module};
+use libeir_passes::PassManager;
+use libeir_syntax_erl::ParseConfig;
+use libeir_util_datastructures::pooled_entity_set::{EntitySet, EntitySetPool};
+
+use libeir_interpreter::{RustTerm, Term, VMState};
+
+fn lower_pass(source: &str) -> Module {
+    let mut eir_mod = lower(source, ParseConfig::default()).unwrap();
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut eir_mod);
+
+    eir_mod
+}
+
+/// A VM with the builtin modules and the module in `source`, lowered
+/// with `lower_pass`.
+fn woo_vm(source: &str) -> VMState {
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(source));
+    vm
+}
+
+fn woo_fun(name: &str, arity: usize) -> FunctionIdent {
+    FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str(name),
+        arity,
+    }
+}
+
+#[test]
+fn test_replace_module() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+woo() -> 1.
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("woo"),
+        arity: 0,
+    };
+
+    assert!(vm.call(&fun, &[]).unwrap().as_i64() == Some(1));
+
+    vm.replace_module(lower_pass(
+        "-module(woo).
+
+woo() -> 2.
+",
+    ));
+
+    assert!(vm.call(&fun, &[]).unwrap().as_i64() == Some(2));
+}
+
+#[test]
+fn test_tail_call_loop() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+count(0, Acc) -> Acc;
+count(N, Acc) -> count(N - 1, Acc + 1).
+
+depth(0) ->
+    try throw(bottom) catch
+        throw:bottom:Stack -> length(Stack)
+    end;
+depth(N) -> depth(N - 1).
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("count"),
+        arity: 2,
+    };
+
+    let res = vm.call(&fun, &[Term::new_i64(100_000), Term::new_i64(0)]);
+    assert!(res.unwrap().as_i64() == Some(100_000));
+
+    // Tail calls reuse the frame of the caller, the stack stays one
+    // frame deep.
+    let depth = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("depth"),
+        arity: 1,
+    };
+    let res = vm.call(&depth, &[Term::new_i64(10_000)]);
+    assert!(res.unwrap().as_i64() == Some(1));
+}
+
+#[test]
+fn test_trap_exit() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+crash(b) -> ok.
+
+trapping() ->
+    process_flag(trap_exit, true),
+    Child = spawn(fun() -> crash(a) end),
+    link(Child),
+    receive
+        {'EXIT', Child, Reason} -> {exited, Reason}
+    end.
+
+not_trapping() ->
+    Child = spawn(fun() -> crash(a) end),
+    link(Child),
+    receive
+        never -> ok
+    end.
+",
+    ));
+
+    // The exit reason carries the stacktrace, with the frame of crash/1
+    let frame = Term::Tuple(vec![
+        Term::new_atom("woo").into(),
+        Term::new_atom("crash").into(),
+        Term::new_i64(1).into(),
+        Term::Nil.into(),
+    ]);
+    let reason = Term::Tuple(vec![
+        Term::new_atom("function_clause").into(),
+        Term::slice_to_list(&[frame.into()], Term::Nil.into()),
+    ]);
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("trapping"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(*res == Term::Tuple(vec![Term::new_atom("exited").into(), reason.clone().into()]));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("not_trapping"),
+        arity: 0,
+    };
+    let exception = vm.call(&fun, &[]).unwrap_err();
+    assert!(*exception.class == Term::new_atom("exit"));
+    assert!(*exception.reason == reason);
+}
+
+#[test]
+fn test_spawn_link() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+trapping() ->
+    process_flag(trap_exit, true),
+    Child = spawn_link(fun() -> exit(boom) end),
+    receive
+        {'EXIT', Child, Reason} -> {exited, Reason}
+    end.
+
+not_trapping() ->
+    spawn_link(fun() -> exit(boom) end),
+    receive
+        never -> ok
+    end.
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("trapping"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(
+        *res == Term::Tuple(vec![
+            Term::new_atom("exited").into(),
+            Term::new_atom("boom").into()
+        ])
+    );
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("not_trapping"),
+        arity: 0,
+    };
+    let exception = vm.call(&fun, &[]).unwrap_err();
+    assert!(*exception.class == Term::new_atom("exit"));
+    assert!(*exception.reason == Term::new_atom("boom"));
+}
+
+#[test]
+fn test_blocked_receive() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+stuck() ->
+    receive
+        X -> X
+    end.
+
+in_callback() ->
+    try lists:map(fun(_) -> receive X -> X end end, [1])
+    catch error:Reason -> Reason
+    end.
+
+spawner() ->
+    spawn(fun() -> ok end),
+    spawn(fun() -> exit(boom) end),
+    receive
+        never -> ok
+    after 10 -> done
+    end.
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("stuck"),
+        arity: 0,
+    };
+    let exception = vm.call(&fun, &[]).unwrap_err();
+    assert!(*exception.class == Term::new_atom("exit"));
+    assert!(*exception.reason == Term::new_atom("deadlock"));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("in_callback"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(*res == Term::new_atom("receive_in_native_callback"));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("spawner"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(*res == Term::new_atom("done"));
+
+    // Every process exited and was reaped
+    assert!(vm.processes.borrow().is_empty());
+}
+
+#[test]
+fn test_send_after() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+delayed() ->
+    erlang:send_after(100, self(), ping),
+    Early = receive
+        ping -> early
+    after 0 -> none
+    end,
+    Late = receive
+        ping -> late
+    end,
+    {Early, Late}.
+
+cancelled() ->
+    Ref = erlang:send_after(100, self(), ping),
+    Left = erlang:cancel_timer(Ref),
+    Again = erlang:cancel_timer(Ref),
+    Received = receive
+        ping -> received
+    after 1000 -> timeout
+    end,
+    {Left, Again, Received}.
+
+raced() ->
+    erlang:send_after(2000, self(), ping),
+    receive
+        ping -> ping
+    after 100 -> timeout
+    end.
+",
+    ));
+
+    vm.advance_clock(50);
+    assert!(vm.now() == 50);
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("delayed"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(
+        *res == Term::Tuple(vec![
+            Term::new_atom("none").into(),
+            Term::new_atom("late").into()
+        ])
+    );
+    assert!(vm.now() == 150);
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("cancelled"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(
+        *res == Term::Tuple(vec![
+            Term::new_i64(100).into(),
+            Term::new_bool(false).into(),
+            Term::new_atom("timeout").into()
+        ])
+    );
+    assert!(vm.now() == 1150);
+
+    // The receive timeout is due before the timer, which was started
+    // first.
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("raced"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(*res == Term::new_atom("timeout"));
+    assert!(vm.now() == 1250);
+}
+
+#[test]
+fn test_outline_region() {
+    let _ = env_logger::try_init();
+
+    let (mut fun, map) = libeir_ir::parse_function_map_unwrap(
+        "
+a'woo':a'pick'/2 {
+    entry(%ret, %thr, %a, %b):
+        b1(%a);
+    b1(%x):
+        if_bool %x b2 b3;
+    b2():
+        %ret(%b);
+    b3():
+        %ret(a'none');
+}
+",
+    );
+
+    let mut pool = EntitySetPool::new();
+    let mut region = EntitySet::new();
+    for name in &["b1", "b2", "b3"] {
+        region.insert(map.get_block(name), &mut pool);
+    }
+    let (outlined, passed) = fun
+        .outline_region(&region.bind(&pool), map.get_block("b1"))
+        .unwrap();
+    assert!(passed == vec![map.get_value("b")]);
+
+    let ident = *fun.ident();
+    let outlined_ident = *outlined.ident();
+    let mut module = Module::new(ident.module);
+    *module
+        .add_function(SourceSpan::UNKNOWN, ident.name, ident.arity)
+        .function_mut() = fun;
+    *module
+        .add_function(
+            SourceSpan::UNKNOWN,
+            outlined_ident.name,
+            outlined_ident.arity,
+        )
+        .function_mut() = outlined;
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(module);
+
+    // The outlined function runs on its own, and through the original
+    for ident in &[outlined_ident, ident] {
+        let res = vm
+            .call(ident, &[Term::new_bool(true), Term::new_i64(5)])
+            .unwrap();
+        assert!(*res == Term::new_i64(5));
+        let res = vm
+            .call(ident, &[Term::new_bool(false), Term::new_i64(5)])
+            .unwrap();
+        assert!(*res == Term::new_atom("none"));
+    }
+}
+
+#[test]
+fn test_copy_function_case() {
+    let _ = env_logger::try_init();
+
+    let mut module = lower(
+        "-module(woo).
+
+classify(X) ->
+    case X of
+        {ok, V} when V > 0 -> V;
+        [H | _] -> H;
+        _ -> none
+    end.
+",
+        ParseConfig::default(),
+    )
+    .unwrap();
+
+    let ident = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("classify"),
+        arity: 1,
+    };
+    let copy_ident = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("classify_copy"),
+        arity: 1,
+    };
+
+    // Copy before any pass has compiled the `case` away
+    let idx = module.ident_index(&ident).unwrap();
+    let source = module[idx].function().clone();
+    let copy = module
+        .add_function(SourceSpan::UNKNOWN, copy_ident.name, copy_ident.arity)
+        .function_mut();
+    libeir_ir::copy_function(&source, copy, copy_ident);
+
+    let mut pass_manager = PassManager::default();
+    pass_manager.run(&mut module);
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(module);
+
+    let ok = Term::Tuple(vec![Term::new_atom("ok").into(), Term::new_i64(3).into()]);
+    let list = Term::slice_to_list(&[Term::new_i64(7).into()], Term::Nil.into());
+    for ident in &[ident, copy_ident] {
+        let res = vm.call(ident, &[ok.clone()]).unwrap();
+        assert!(*res == Term::new_i64(3));
+        let res = vm.call(ident, &[(*list).clone()]).unwrap();
+        assert!(*res == Term::new_i64(7));
+        let res = vm.call(ident, &[Term::new_atom("other")]).unwrap();
+        assert!(*res == Term::new_atom("none"));
+    }
+}
+
+#[test]
+fn test_register() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+echo() ->
+    receive
+        {From, Msg} -> From ! {echo, Msg}
+    end.
+
+run() ->
+    Pid = spawn(fun() -> echo() end),
+    true = register(echo, Pid),
+    Pid = whereis(echo),
+    echo ! {self(), hello},
+    receive
+        {echo, Msg} -> {Msg, whereis(echo)}
+    end.
+
+duplicate() ->
+    true = register(dup, self()),
+    try register(dup, spawn(fun() -> ok end)) of
+        _ -> registered
+    catch
+        error:badarg -> badarg
+    end.
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("run"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(
+        *res == Term::Tuple(vec![
+            Term::new_atom("hello").into(),
+            Term::new_atom("undefined").into()
+        ])
+    );
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("duplicate"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(*res == Term::new_atom("badarg"));
+}
+
+#[test]
+fn test_process_info() {
+    let _ = env_logger::try_init();
+
+    let mut vm = woo_vm(
+        "-module(woo).
+
+run() ->
+    Pid = spawn(fun() -> receive X -> X end end),
+    Pid ! hello,
+    Pid ! world,
+    {process_info(Pid, message_queue_len),
+     process_info(Pid, status),
+     process_info(Pid, no_such_item)}.
+
+self_info() ->
+    process_info(self(), current_function).
+",
+    );
+
+    let atom = |name: &str| RustTerm::Atom(name.to_string());
+
+    let res = vm.call(&woo_fun("run", 0), &[]).unwrap();
+    assert_eq!(
+        res.to_rust(),
+        Some(RustTerm::Tuple(vec![
+            RustTerm::Tuple(vec![atom("message_queue_len"), RustTerm::Int(2.into())]),
+            RustTerm::Tuple(vec![atom("status"), atom("runnable")]),
+            atom("undefined"),
+        ]))
+    );
+
+    let res = vm.call(&woo_fun("self_info", 0), &[]).unwrap();
+    assert_eq!(
+        res.to_rust(),
+        Some(RustTerm::Tuple(vec![
+            atom("current_function"),
+            RustTerm::Tuple(vec![
+                atom("erlang"),
+                atom("process_info"),
+                RustTerm::Int(2.into())
+            ]),
+        ]))
+    );
+}
+
+#[test]
+fn test_apply_mfa() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+add(A, B) -> A + B.
+
+call(M, F) -> M:F(1, 2).
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("call"),
+        arity: 2,
+    };
+
+    let res = vm.call(&fun, &[Term::new_atom("woo"), Term::new_atom("add")]);
+    assert!(res.unwrap().as_i64() == Some(3));
+
+    let exception = vm
+        .call(&fun, &[Term::new_atom("woo"), Term::new_atom("nope")])
+        .unwrap_err();
+    assert!(*exception.class == Term::new_atom("error"));
+    assert!(*exception.reason == Term::new_atom("undef"));
+
+    let exception = vm
+        .call(&fun, &[Term::new_i64(1), Term::new_atom("add")])
+        .unwrap_err();
+    assert!(*exception.class == Term::new_atom("error"));
+    assert!(*exception.reason == Term::new_atom("badarg"));
+}
+
+#[test]
+fn test_binary_append_loop() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+build(0, Acc) -> Acc;
+build(N, Acc) -> build(N - 1, <<Acc/binary, 7>>).
+
+count(<<7, Rest/binary>>, N) -> count(Rest, N + 1);
+count(_, N) -> N.
+
+run() -> count(build(10000, <<>>), 0).
+
+lookup() ->
+    #{<<7, 7, 7>> := V} = #{build(3, <<>>) => found},
+    V.
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("run"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]);
+    assert!(res.unwrap().as_i64() == Some(10_000));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("lookup"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]);
+    assert!(*res.unwrap() == Term::new_atom("found"));
+}
+
+#[test]
+fn test_binary_match_dynamic_size() {
+    let _ = env_logger::try_init();
+
+    let mut vm = woo_vm(
+        "-module(woo).
+
+decode(<<Len:8, Data:Len/binary, Rest/binary>>) -> {Data, Rest};
+decode(_) -> error.
+
+unaligned_big(<<_:4, X:16, _:4>>) -> X.
+
+unaligned_little(<<_:4, X:16/little, _:4>>) -> X.
+",
+    );
+
+    let binary = |bytes: &[u8]| (*Term::from_rust(&RustTerm::Binary(bytes.to_vec()))).clone();
+
+    let res = vm
+        .call(&woo_fun("decode", 1), &[binary(&[3, 1, 2, 3, 4, 5])])
+        .unwrap();
+    assert!(
+        res.to_rust()
+            == Some(RustTerm::Tuple(vec![
+                RustTerm::Binary(vec![1, 2, 3]),
+                RustTerm::Binary(vec![4, 5]),
+            ]))
+    );
+
+    let res = vm
+        .call(&woo_fun("decode", 1), &[binary(&[4, 1, 2])])
+        .unwrap();
+    assert!(*res == Term::new_atom("error"));
+
+    let bytes = [0xa1, 0x23, 0x4b];
+    let res = vm.call(&woo_fun("unaligned_big", 1), &[binary(&bytes)]);
+    assert!(res.unwrap().as_i64() == Some(0x1234));
+    let res = vm.call(&woo_fun("unaligned_little", 1), &[binary(&bytes)]);
+    assert!(res.unwrap().as_i64() == Some(0x3412));
+}
+
+#[test]
+fn test_pid_printing() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+idle() -> ok.
+
+own_pid() -> pid_to_list(self()).
+",
+    ));
+
+    let idle = Term::CapturedFunction {
+        ident: FunctionIdent {
+            module: Ident::from_str("woo"),
+            name: Ident::from_str("idle"),
+            arity: 0,
+        },
+    };
+    let first = vm.spawn(idle.clone().into(), &[]);
+    let second = vm.spawn(idle.into(), &[]);
+    assert!(first != second);
+    assert!(first.to_string() == "<0.0.0>");
+    assert!(second.to_string() == "<0.1.0>");
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("own_pid"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    let printed: String = Term::as_list(&res)
+        .unwrap()
+        .iter()
+        .map(|c| c.as_i64().unwrap() as u8 as char)
+        .collect();
+    assert!(printed == "<0.2.0>");
+}
+
+#[test]
+fn test_badarg_argument_span() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+first(Tup) -> element(1, Tup).
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("first"),
+        arity: 1,
+    };
+    let exception = vm.call(&fun, &[Term::new_atom("foo")]).unwrap_err();
+    assert!(*exception.class == Term::new_atom("error"));
+    assert!(*exception.reason == Term::new_atom("badarg"));
+
+    // The span points at `Tup`, the second argument of `element/2`.
+    let trace = Term::as_list(&exception.stacktrace).unwrap();
+    match &*trace[0] {
+        Term::Tuple(entry) => {
+            assert!(*entry[0] == Term::new_atom("argument"));
+            assert!(entry[1].as_i64() == Some(1));
+            match &*entry[2] {
+                Term::Tuple(span) => {
+                    let start = span[0].as_i64().unwrap();
+                    let end = span[1].as_i64().unwrap();
+                    assert!(end - start == 3);
+                }
+                other => panic!("expected span tuple, got {:?}", other),
+            }
+        }
+        other => panic!("expected trace entry, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_tuple_arity_match() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+-record(pair, {value}).
+
+is_pair({_, _}) -> true;
+is_pair(_) -> false.
+
+pair_value(P) -> P#pair.value.
+",
+    ));
+
+    let is_pair = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("is_pair"),
+        arity: 1,
+    };
+    let two = Term::Tuple(vec![Term::new_i64(1).into(), Term::new_i64(2).into()]);
+    let three = Term::Tuple(vec![
+        Term::new_i64(1).into(),
+        Term::new_i64(2).into(),
+        Term::new_i64(3).into(),
+    ]);
+    let res = vm.call(&is_pair, &[two]).unwrap();
+    assert!(res.as_boolean() == Some(true));
+    let res = vm.call(&is_pair, &[three]).unwrap();
+    assert!(res.as_boolean() == Some(false));
+
+    // Record access checks the tuple arity before unpacking.
+    let pair_value = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("pair_value"),
+        arity: 1,
+    };
+    let rec = Term::Tuple(vec![Term::new_atom("pair").into(), Term::new_i64(5).into()]);
+    let res = vm.call(&pair_value, &[rec]).unwrap();
+    assert!(res.as_i64() == Some(5));
+    let bad_rec = Term::Tuple(vec![
+        Term::new_atom("pair").into(),
+        Term::new_i64(5).into(),
+        Term::new_i64(6).into(),
+    ]);
+    assert!(vm.call(&pair_value, &[bad_rec]).is_err());
+}
+
+#[test]
+fn test_call_exception() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+raise(Kind) -> erlang:Kind(oops).
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("raise"),
+        arity: 1,
+    };
+    for kind in &["error", "exit"] {
+        let exception = vm.call(&fun, &[Term::new_atom(kind)]).unwrap_err();
+        assert!(*exception.class == Term::new_atom(kind));
+        assert!(*exception.reason == Term::new_atom("oops"));
+    }
+}
+
+#[test]
+fn test_guard_exception() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+single(X) when X div 0 == 1 -> a;
+single(_) -> b.
+
+sequence(X) when X div 0 == 1; X > 0 -> a;
+sequence(_) -> b.
+",
+    ));
+
+    // An exception in a guard fails the clause instead of crashing.
+    let single = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("single"),
+        arity: 1,
+    };
+    let res = vm.call(&single, &[Term::new_i64(1)]).unwrap();
+    assert!(*res == Term::new_atom("b"));
+
+    // Only the raising guard fails, the rest of the sequence is still tried.
+    let sequence = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("sequence"),
+        arity: 1,
+    };
+    let res = vm.call(&sequence, &[Term::new_i64(1)]).unwrap();
+    assert!(*res == Term::new_atom("a"));
+    let res = vm.call(&sequence, &[Term::new_i64(-1)]).unwrap();
+    assert!(*res == Term::new_atom("b"));
+}
+
+#[test]
+fn test_guard_compare_chain() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+range(X) when 0 < X, X < 10 -> in;
+range(_) -> out.
+",
+    ));
+
+    let range = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("range"),
+        arity: 1,
+    };
+    let res = vm.call(&range, &[Term::new_i64(5)]).unwrap();
+    assert!(*res == Term::new_atom("in"));
+    let res = vm.call(&range, &[Term::new_i64(20)]).unwrap();
+    assert!(*res == Term::new_atom("out"));
+    let res = vm.call(&range, &[Term::new_i64(0)]).unwrap();
+    assert!(*res == Term::new_atom("out"));
+}
+
+#[test]
+fn test_error_with_args() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+fail() -> error(badarg, [1, 2]).
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("fail"),
+        arity: 0,
+    };
+    let exception = vm.call(&fun, &[]).unwrap_err();
+    assert!(*exception.class == Term::new_atom("error"));
+    assert!(*exception.reason == Term::new_atom("badarg"));
+
+    let trace = Term::as_list(&exception.stacktrace).unwrap();
+    let args = Term::slice_to_list(
+        &[Term::new_i64(1).into(), Term::new_i64(2).into()],
+        Term::Nil.into(),
+    );
+    let frame = Term::Tuple(vec![
+        Term::new_atom("woo").into(),
+        Term::new_atom("fail").into(),
+        args,
+        Term::Nil.into(),
+    ]);
+    assert!(*trace[0] == frame);
+}
+
+#[test]
+fn test_process_dictionary() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+run() ->
+    undefined = put(key, 1),
+    1 = get(key),
+    Old = put(key, 2),
+    [{key, 2}] = get(),
+    2 = erase(key),
+    undefined = get(key),
+    {Old, get()}.
+",
+    ));
+
+    let run = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("run"),
+        arity: 0,
+    };
+    let res = vm.call(&run, &[]).unwrap();
+    // The second put returned the value of the first.
+    assert!(*res == Term::Tuple(vec![Term::new_i64(1).into(), Term::Nil.into()]));
+}
+
+#[test]
+fn test_apply_fun() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+run(X, Y) ->
+    F = fun(A, B) -> A - B end,
+    apply(F, lists:reverse([X, Y])).
+
+bad_arity(X) ->
+    apply(fun(A, B) -> A - B end, [X]).
+",
+    ));
+
+    let run = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("run"),
+        arity: 2,
+    };
+    let res = vm
+        .call(&run, &[Term::new_i64(1), Term::new_i64(5)])
+        .unwrap();
+    assert!(res.as_i64() == Some(4));
+
+    let bad_arity = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("bad_arity"),
+        arity: 1,
+    };
+    let err = vm.call(&bad_arity, &[Term::new_i64(1)]).err().unwrap();
+    match &*err.reason {
+        Term::Tuple(elems) => assert!(*elems[0] == Term::new_atom("badarity")),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_lazy_lowering() {
+    let _ = env_logger::try_init();
+
+    let lowered = Rc::new(Cell::new(0));
+    let pass_manager = RefCell::new(PassManager::default());
+    let counter = lowered.clone();
+    let module = lower_lazy(
+        "-module(woo).
+
+first() -> second().
+second() -> ok.
+unused() -> ok.
+",
+        ParseConfig::default(),
+        move |fun| {
+            counter.set(counter.get() + 1);
+            pass_manager.borrow_mut().run_function(fun);
+        },
+    )
+    .unwrap();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(module);
+    assert!(lowered.get() == 0);
+
+    let first = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("first"),
+        arity: 0,
+    };
+    let res = vm.call(&first, &[]).unwrap();
+    assert!(*res == Term::new_atom("ok"));
+    // `first` and `second` are lowered when called, `unused` never is.
+    assert!(lowered.get() == 2);
+
+    // Lowered functions are cached.
+    vm.call(&first, &[]).unwrap();
+    assert!(lowered.get() == 2);
+}
+
+#[test]
+fn test_lazy_lowering_failure() {
+    let _ = env_logger::try_init();
+
+    let pass_manager = RefCell::new(PassManager::default());
+    let module = lower_lazy(
+        "-module(woo).
+
+good() -> ok.
+bad() -> X.
+",
+        ParseConfig::default(),
+        move |fun| pass_manager.borrow_mut().run_function(fun),
+    )
+    .unwrap();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(module);
+
+    let bad = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("bad"),
+        arity: 0,
+    };
+    let reason = Term::Tuple(vec![
+        Term::new_atom("lower_failed").into(),
+        Term::Tuple(vec![
+            Term::new_atom("woo").into(),
+            Term::new_atom("bad").into(),
+            Term::new_i64(0).into(),
+        ])
+        .into(),
+    ]);
+    let exception = vm.call(&bad, &[]).unwrap_err();
+    assert!(*exception.class == Term::new_atom("error"));
+    assert!(*exception.reason == reason);
+
+    // The failure is cached, the function is not lowered again.
+    let exception = vm.call(&bad, &[]).unwrap_err();
+    assert!(*exception.reason == reason);
+
+    let good = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("good"),
+        arity: 0,
+    };
+    let res = vm.call(&good, &[]).unwrap();
+    assert!(*res == Term::new_atom("ok"));
+}
+
+#[test]
+fn test_io_format() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+print() ->
+    io:format(\"~p~n\", [{a,1}]),
+    io:format(\"~w ~p ~s ~b~~\", [\"ab\", \"ab\", \"cd\", 12]),
+    io:format(\"~n\").
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("print"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(*res == Term::new_atom("ok"));
+    assert!(vm.take_output() == "{a,1}\n[97,98] \"ab\" cd 12~\n");
+    assert!(vm.take_output() == "");
+}
+
+#[test]
+fn test_reorder_blocks_for_layout() {
+    let _ = env_logger::try_init();
+
+    let mut eir_mod = lower_pass(
+        "-module(woo).
+
+run(X) when X < 2 -> X;
+run(X) ->
+    case X rem 2 of
+        0 -> run(X - 1) + run(X - 2);
+        _ -> run(X - 2) + run(X - 1)
+    end.
+",
+    );
+    for fun_def in eir_mod.function_iter_mut() {
+        let fun = fun_def.function_mut();
+        fun.builder().reorder_blocks_for_layout();
+        fun.graph_validate_global();
+    }
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(eir_mod);
+
+    let run = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("run"),
+        arity: 1,
+    };
+    let res = vm.call(&run, &[Term::new_i64(10).into()]).unwrap();
+    assert!(*res == Term::new_i64(55));
+}
+
+#[test]
+fn test_binary_part() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+sizes() ->
+    {byte_size(<<1, 2, 3>>), bit_size(<<1, 2, 3>>)}.
+
+parts() ->
+    Bin = <<1, 2, 3, 4>>,
+    true = binary_part(Bin, {1, 2}) =:= <<2, 3>>,
+    true = binary_part(Bin, 4, -2) =:= <<3, 4>>,
+    ok.
+
+out_of_range() ->
+    try binary_part(<<1, 2, 3>>, 2, 5)
+    catch error:badarg -> caught
+    end.
+",
+    ));
+
+    let sizes = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("sizes"),
+        arity: 0,
+    };
+    let res = vm.call(&sizes, &[]).unwrap();
+    assert!(*res == Term::Tuple(vec![Term::new_i64(3).into(), Term::new_i64(24).into()]));
+
+    let parts = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("parts"),
+        arity: 0,
+    };
+    let res = vm.call(&parts, &[]).unwrap();
+    assert!(*res == Term::new_atom("ok"));
+
+    let out_of_range = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("out_of_range"),
+        arity: 0,
+    };
+    let res = vm.call(&out_of_range, &[]).unwrap();
+    assert!(*res == Term::new_atom("caught"));
+}
+
+#[test]
+fn test_rust_term_round_trip() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+make() ->
+    {ok, [1, 2], #{a => 1}}.
+
+check({ok, [1, 2], #{a := 1}}) -> matched;
+check(_) -> no_match.
+",
+    ));
+
+    let expected = RustTerm::Tuple(vec![
+        RustTerm::Atom("ok".to_string()),
+        RustTerm::List(vec![RustTerm::Int(1.into()), RustTerm::Int(2.into())]),
+        RustTerm::Map(vec![(
+            RustTerm::Atom("a".to_string()),
+            RustTerm::Int(1.into()),
+        )]),
+    ]);
+
+    let make = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("make"),
+        arity: 0,
+    };
+    let res = vm.call(&make, &[]).unwrap();
+    assert!(res.to_rust() == Some(expected.clone()));
+
+    let arg = Term::from_rust(&expected);
+    assert!(*arg == *res);
+
+    let check = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("check"),
+        arity: 1,
+    };
+    let res = vm.call(&check, &[(*arg).clone()]).unwrap();
+    assert!(*res == Term::new_atom("matched"));
+}
+
+#[test]
+fn test_display() {
+    let _ = env_logger::try_init();
+
+    let mut vm = VMState::new();
+    vm.add_builtin_modules();
+    vm.add_erlang_module(lower_pass(
+        "-module(woo).
+
+print() ->
+    erlang:display({a, [1,2], #{}}).
+",
+    ));
+
+    let fun = FunctionIdent {
+        module: Ident::from_str("woo"),
+        name: Ident::from_str("print"),
+        arity: 0,
+    };
+    let res = vm.call(&fun, &[]).unwrap();
+    assert!(*res == Term::new_atom("true"));
+    assert!(vm.take_output() == "{a,[1,2],#{}}\n");
+}
+
+#[test]
+fn test_native_lists() {
+    let _ = env_logger::try_init();
+
+    let mut vm = woo_vm(
+        "-module(woo).
+
+my_reverse([], Acc) -> Acc;
+my_reverse([H | T], Acc) -> my_reverse(T, [H | Acc]).
+
+my_map(_F, []) -> [];
+my_map(F, [H | T]) -> [F(H) | my_map(F, T)].
+
+my_foldl(_F, Acc, []) -> Acc;
+my_foldl(F, Acc, [H | T]) -> my_foldl(F, F(H, Acc), T).
+
+my_member(_X, []) -> false;
+my_member(X, [X | _]) -> true;
+my_member(X, [_ | T]) -> my_member(X, T).
+
+my_seq(From, To) when From > To -> [];
+my_seq(From, To) -> [From | my_seq(From + 1, To)].
+
+pairs() ->
+    L = [3, 1, {a, b}, 2],
+    Double = fun(X) when is_integer(X) -> X * 2; (X) -> X end,
+    Cons = fun(X, Acc) -> [X | Acc] end,
+    [
+     {lists:reverse(L), my_reverse(L, [])},
+     {lists:map(Double, L), my_map(Double, L)},
+     {lists:foldl(Cons, [], L), my_foldl(Cons, [], L)},
+     {lists:member({a, b}, L), my_member({a, b}, L)},
+     {lists:member(1.0, L), my_member(1.0, L)},
+     {lists:seq(1, 5), my_seq(1, 5)},
+     {lists:seq(1, 0), my_seq(1, 0)}
+    ].
+
+map_throws() ->
+    lists:map(fun(X) -> throw(X) end, [woops]).
+",
+    );
+
+    let res = vm.call(&woo_fun("pairs", 0), &[]).unwrap();
+    let pairs = Term::as_list(&res).unwrap();
+    assert!(pairs.len() == 7);
+    for pair in pairs.iter() {
+        match pair.as_tuple() {
+            Some([native, erlang]) => assert!(native == erlang),
+            _ => panic!(),
+        }
+    }
+
+    let err = vm.call(&woo_fun("map_throws", 0), &[]).err().unwrap();
+    assert!(*err.class == Term::new_atom("throw"));
+    assert!(*err.reason == Term::new_atom("woops"));
+
+    // The trace of the throw in the fun is passed on by lists:map
+    let trace = Term::as_list(&err.stacktrace).unwrap();
+    assert!(trace.iter().any(|frame| match frame.as_tuple() {
+        Some([_module, name, ..]) => match &**name {
+            Term::Atom(name) => name.as_str().contains("-fun-"),
+            _ => false,
+        },
+        _ => false,
+    }));
+}
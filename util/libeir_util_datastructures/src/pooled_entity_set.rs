@@ -183,7 +183,8 @@ where
         }
     }
 
-    pub fn union(&mut self, other: &EntitySet<K>, pool: &mut EntitySetPool<K>) {
+    /// Adds every element of `other` to this set.
+    pub fn union_with(&mut self, other: &EntitySet<K>, pool: &mut EntitySetPool<K>) {
         let other_list = &other.list;
         let other_pages_len = other_list.len(&pool.pool);
         self.grow_to_pages(other_pages_len, pool);
@@ -196,6 +197,38 @@ where
         }
     }
 
+    /// Removes every element not in `other` from this set. Never allocates
+    /// in the pool.
+    pub fn intersect_with(&mut self, other: &EntitySet<K>, pool: &mut EntitySetPool<K>) {
+        let self_pages_len = self.list.len(&pool.pool);
+        let other_pages_len = other.list.len(&pool.pool);
+        for n in 0..self_pages_len {
+            let other_val = if n < other_pages_len {
+                other.list.get(n, &pool.pool).unwrap().0
+            } else {
+                0
+            };
+            let val_mut = self.list.get_mut(n, &mut pool.pool).unwrap();
+            debug_assert!(val_mut.0 != std::u64::MAX);
+            debug_assert!(other_val != std::u64::MAX);
+            val_mut.0 &= other_val;
+        }
+    }
+
+    /// Removes every element of `other` from this set. Never allocates in
+    /// the pool.
+    pub fn difference_with(&mut self, other: &EntitySet<K>, pool: &mut EntitySetPool<K>) {
+        let self_pages_len = self.list.len(&pool.pool);
+        let other_pages_len = other.list.len(&pool.pool);
+        for n in 0..self_pages_len.min(other_pages_len) {
+            let other_val = other.list.get(n, &pool.pool).unwrap();
+            let val_mut = self.list.get_mut(n, &mut pool.pool).unwrap();
+            debug_assert!(val_mut.0 != std::u64::MAX);
+            debug_assert!(other_val.0 != std::u64::MAX);
+            val_mut.0 &= !other_val.0;
+        }
+    }
+
     pub fn iter<'a>(&self, pool: &'a EntitySetPool<K>) -> EntitySetIter<'a, K> {
         EntitySetIter {
             pool: &pool.pool,
@@ -507,4 +540,65 @@ mod test {
         assert!(iter.next() == None);
         assert!(iter.next() == None);
     }
+
+    fn make_set(items: &[u32], pool: &mut EntitySetPool<TestEntity>) -> EntitySet<TestEntity> {
+        let mut set = EntitySet::new();
+        for item in items {
+            set.insert(TestEntity(*item), pool);
+        }
+        set
+    }
+
+    fn items(set: &EntitySet<TestEntity>, pool: &EntitySetPool<TestEntity>) -> Vec<u32> {
+        set.iter(pool).map(|e| e.0).collect()
+    }
+
+    #[test]
+    fn test_union_with() {
+        let mut pool = EntitySetPool::new();
+        let mut set1 = make_set(&[1, 3, 70], &mut pool);
+        let set2 = make_set(&[2, 3, 200], &mut pool);
+
+        set1.union_with(&set2, &mut pool);
+        assert!(items(&set1, &pool) == vec![1, 2, 3, 70, 200]);
+        assert!(items(&set2, &pool) == vec![2, 3, 200]);
+    }
+
+    #[test]
+    fn test_intersect_with() {
+        let mut pool = EntitySetPool::new();
+        let mut set1 = make_set(&[1, 3, 70, 200], &mut pool);
+        let set2 = make_set(&[3, 4, 70], &mut pool);
+
+        set1.intersect_with(&set2, &mut pool);
+        assert!(items(&set1, &pool) == vec![3, 70]);
+
+        // Intersecting with a larger set does not grow the smaller one
+        let mut small = make_set(&[3], &mut pool);
+        let large = make_set(&[3, 500], &mut pool);
+        small.intersect_with(&large, &mut pool);
+        assert!(items(&small, &pool) == vec![3]);
+        assert!(small.list.len(&pool.pool) == 1);
+
+        let empty = EntitySet::new();
+        set1.intersect_with(&empty, &mut pool);
+        assert!(set1.size(&pool) == 0);
+        assert!(set1.eq(&empty, &pool));
+    }
+
+    #[test]
+    fn test_difference_with() {
+        let mut pool = EntitySetPool::new();
+        let mut set1 = make_set(&[1, 3, 70, 200], &mut pool);
+        let set2 = make_set(&[3, 4, 200, 500], &mut pool);
+
+        set1.difference_with(&set2, &mut pool);
+        assert!(items(&set1, &pool) == vec![1, 70]);
+        assert!(set1.list.len(&pool.pool) == 4);
+
+        let mut small = make_set(&[3], &mut pool);
+        small.difference_with(&set2, &mut pool);
+        assert!(small.size(&pool) == 0);
+        assert!(small.list.len(&pool.pool) == 1);
+    }
 }
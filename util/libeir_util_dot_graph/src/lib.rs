@@ -164,6 +164,20 @@ impl<O: Write> GraphPrinter<O> {
     }
 
     pub fn node<I>(&mut self, id: I, label: &str)
+    where
+        I: NodeId,
+    {
+        self.node_inner(id, label, None)
+    }
+
+    pub fn node_filled<I>(&mut self, id: I, label: &str, fill_color: &str)
+    where
+        I: NodeId,
+    {
+        self.node_inner(id, label, Some(fill_color))
+    }
+
+    fn node_inner<I>(&mut self, id: I, label: &str, fill_color: Option<&str>)
     where
         I: NodeId,
     {
@@ -179,7 +193,11 @@ impl<O: Write> GraphPrinter<O> {
         id_buf.clear();
         format_label(label, &mut id_buf);
         self.w(|w| {
-            write!(w, "{}> ];\n", &id_buf)?;
+            write!(w, "{}>", &id_buf)?;
+            if let Some(fill_color) = fill_color {
+                write!(w, ", style=filled, fillcolor=\"{}\"", fill_color)?;
+            }
+            write!(w, " ];\n")?;
             Ok(())
         });
 
@@ -187,6 +205,22 @@ impl<O: Write> GraphPrinter<O> {
     }
 
     pub fn edge<I1, I2>(&mut self, from: I1, to: I2, label: &str)
+    where
+        I1: NodeId,
+        I2: NodeId,
+    {
+        self.edge_inner(from, to, label, None)
+    }
+
+    pub fn edge_colored<I1, I2>(&mut self, from: I1, to: I2, label: &str, color: &str)
+    where
+        I1: NodeId,
+        I2: NodeId,
+    {
+        self.edge_inner(from, to, label, Some(color))
+    }
+
+    fn edge_inner<I1, I2>(&mut self, from: I1, to: I2, label: &str, color: Option<&str>)
     where
         I1: NodeId,
         I2: NodeId,
@@ -210,7 +244,11 @@ impl<O: Write> GraphPrinter<O> {
         id_buf.clear();
         format_label(label, &mut id_buf);
         self.w(|w| {
-            write!(w, "[ label=<{}> ];\n", id_buf)?;
+            write!(w, "[ label=<{}>", id_buf)?;
+            if let Some(color) = color {
+                write!(w, ", color=\"{}\"", color)?;
+            }
+            write!(w, " ];\n")?;
             Ok(())
         });
 
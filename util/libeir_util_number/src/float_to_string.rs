@@ -0,0 +1,80 @@
+/// Formats a float the same way Erlang does for `~p` and
+/// `float_to_list(F, [short])`.
+///
+/// The digits are the shortest that round trip to the same float. They
+/// are laid out either in plain decimal or in exponent notation,
+/// whichever is shorter, and there is always at least one digit after
+/// the decimal point.
+pub fn float_to_string(num: f64) -> String {
+    if !num.is_finite() {
+        return format!("{}", num);
+    }
+
+    let sign = if num.is_sign_negative() { "-" } else { "" };
+    if num == 0.0 {
+        return format!("{}0.0", sign);
+    }
+
+    // Rust already produces the shortest round trip digits, we only
+    // need to lay them out differently.
+    let sci = format!("{:e}", num.abs());
+    let (mantissa, exp) = sci.split_at(sci.find('e').unwrap());
+    let exp: i64 = exp[1..].parse().unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+
+    format!("{}{}", sign, insert_decimal(exp + 1, &digits))
+}
+
+/// Places the decimal point in `digits`, where the value is
+/// `0.<digits> * 10^place`.
+fn insert_decimal(place: i64, digits: &str) -> String {
+    let len = digits.len() as i64;
+
+    if place > 0 && place < len {
+        let (int, frac) = digits.split_at(place as usize);
+        return format!("{}.{}", int, frac);
+    }
+    if place == 0 {
+        return format!("0.{}", digits);
+    }
+
+    let exp = (place - 1).to_string();
+    let exp_dot = if len == 1 { 2 } else { 1 };
+    let exp_cost = exp.len() as i64 + 1 + exp_dot;
+
+    if place < 0 {
+        if 2 - place <= exp_cost {
+            return format!("0.{}{}", "0".repeat(-place as usize), digits);
+        }
+    } else if place - len + 2 <= exp_cost {
+        return format!("{}{}.0", digits, "0".repeat((place - len) as usize));
+    }
+
+    let (first, rest) = digits.split_at(1);
+    if rest.is_empty() {
+        format!("{}.0e{}", first, exp)
+    } else {
+        format!("{}.{}e{}", first, rest, exp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::float_to_string;
+
+    #[test]
+    fn test_float_to_string() {
+        assert_eq!(float_to_string(0.1), "0.1");
+        assert_eq!(float_to_string(1.0), "1.0");
+        assert_eq!(float_to_string(1.0e21), "1.0e21");
+        assert_eq!(float_to_string(-0.0), "-0.0");
+
+        assert_eq!(float_to_string(0.0), "0.0");
+        assert_eq!(float_to_string(100.0), "100.0");
+        assert_eq!(float_to_string(1000.0), "1.0e3");
+        assert_eq!(float_to_string(123.456), "123.456");
+        assert_eq!(float_to_string(0.01), "0.01");
+        assert_eq!(float_to_string(1.5e-10), "1.5e-10");
+        assert_eq!(float_to_string(-2.5), "-2.5");
+    }
+}
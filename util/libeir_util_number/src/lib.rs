@@ -4,6 +4,9 @@ pub use bigint_to_float::bigint_to_double;
 mod integer;
 pub use integer::Integer;
 
+mod float_to_string;
+pub use float_to_string::float_to_string;
+
 pub use num_bigint as bigint;
 pub use num_bigint::BigInt;
 pub use num_traits as traits;